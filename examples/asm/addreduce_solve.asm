@@ -0,0 +1,9 @@
+def main(a:i8, b:i8, c:i8, d:i8, e:i8, f:i8, g:i8, h:i8, en:bool) -> (y:i8) {
+    y:i8 = laddrega_i8(t10, t11, en) @lut(x, y0%2);
+    t10:i8 = laddrega_i8(t4, t5, en) @lut(x, y0+1);
+    t11:i8 = laddrega_i8(t6, t7, en) @lut(x, y0-1);
+    t4:i8 = laddrega_i8(a, b, en) @lut(x+2, 0..3);
+    t5:i8 = laddrega_i8(c, d, en) @lut(x+2, 4..7);
+    t6:i8 = laddrega_i8(e, f, en) @lut(x*2, ??);
+    t7:i8 = laddrega_i8(g, h, en) @lut(??, ??);
+}
@@ -0,0 +1,5 @@
+def main(a:bool, b:bool, c:bool) -> (y:bool, z:bool) {
+    y:bool = land_bool(t0, c) @lut(??, ??);
+    (t0:bool, t1:bool) = landeq_bool(a, b) @lut(??, ??);
+    z:bool = leq_bool(t1, c) @lut(??, ??);
+}
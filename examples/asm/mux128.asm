@@ -47,5 +47,5 @@ def main(c:bool, a:i128, b:i128) -> (y:i128) {
     t45:i8 = ext[120, 127](a);
     t46:i8 = ext[120, 127](b);
     t47:i8 = lmux_i8(c, t45, t46) @lut(??, ??);
-    y:i128 = cat(t2, t5, t8, t11, t14, t17, t20, t23, t26, t29, t32, t35, t38, t41, t44, t47);
+    y:i128 = cat(t47, t44, t41, t38, t35, t32, t29, t26, t23, t20, t17, t14, t11, t8, t5, t2);
 }
\ No newline at end of file
@@ -0,0 +1,54 @@
+module main (
+    input wire clock,
+    input wire reset,
+    input wire a,
+    input wire b,
+    input wire c,
+    input wire d,
+    output wire y,
+    output wire z,
+    output wire w,
+    output wire v,
+    output wire u
+);
+    wire gnd;
+    wire vcc;
+    GND _gnd (
+        .G(gnd)
+    );
+    VCC _vcc (
+        .P(vcc)
+    );
+    (*LOC = "SLICE_X1Y2"*)
+    LUT6_2 # (
+        .INIT(64'h88888888c33cc33c)
+    ) __y (
+        .I0(a),
+        .I1(b),
+        .I2(c),
+        .I3(d),
+        .I4(gnd),
+        .I5(vcc),
+        .O5(z),
+        .O6(y)
+    );
+    (*LOC = "SLICE_X3Y4"*)
+    LUT6_2 # (
+        .INIT(64'h6666666688888888)
+    ) __w (
+        .I0(a),
+        .I1(b),
+        .I2(gnd),
+        .I3(gnd),
+        .I4(gnd),
+        .I5(vcc),
+        .O5(u),
+        .O6(w)
+    );
+    LUT1 # (
+        .INIT(2'h1)
+    ) __v (
+        .I0(c),
+        .O(v)
+    );
+endmodule
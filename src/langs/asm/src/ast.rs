@@ -11,10 +11,17 @@ pub type Expr = ir::Expr;
 pub type OpWire = ir::OpWire;
 pub type InstrWire = ir::InstrWire;
 pub type Sig = ir::Sig;
+pub type Span = ir::Span;
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
 pub enum OpCoord {
     Add,
+    Sub,
+    Mul,
+    // lo..hi is any coordinate from lo to hi
+    Range,
+    // a%k is a, which must be a multiple of k
+    Align,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
@@ -43,6 +50,8 @@ pub struct InstrAsm {
     pub dst: Expr,
     pub arg: Expr,
     pub loc: Loc,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
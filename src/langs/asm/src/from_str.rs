@@ -35,6 +35,10 @@ impl FromStr for OpCoord {
         let err = format!("Error: {} is not valid coordinate operation", input);
         match input {
             "+" => Ok(OpCoord::Add),
+            "-" => Ok(OpCoord::Sub),
+            "*" => Ok(OpCoord::Mul),
+            ".." => Ok(OpCoord::Range),
+            "%" => Ok(OpCoord::Align),
             _ => Err(Error::new_conv_error(&err)),
         }
     }
@@ -1,4 +1,5 @@
 use crate::ast::*;
+use std::rc::Rc;
 
 impl Loc {
     pub fn prim(&self) -> &Prim {
@@ -30,6 +31,69 @@ impl Loc {
     }
 }
 
+impl OpCoord {
+    // ranges bind the least, alignments as tightly as products
+    pub fn prec(&self) -> u8 {
+        match self {
+            OpCoord::Range => 0,
+            OpCoord::Add | OpCoord::Sub => 1,
+            OpCoord::Mul | OpCoord::Align => 2,
+        }
+    }
+}
+
+impl ExprCoord {
+    pub fn op(&self) -> Option<&OpCoord> {
+        match self {
+            ExprCoord::Bin(op, _, _) => Some(op),
+            _ => None,
+        }
+    }
+    pub fn is_any(&self) -> bool {
+        matches!(self, ExprCoord::Any)
+    }
+    // names of the variables in the expression
+    pub fn vars(&self) -> Vec<Id> {
+        match self {
+            ExprCoord::Var(n) => vec![n.to_string()],
+            ExprCoord::Bin(_, lhs, rhs) => {
+                let mut var = lhs.vars();
+                var.extend(rhs.vars());
+                var
+            }
+            _ => Vec::new(),
+        }
+    }
+    // Left-associative expression from operands and the operators between
+    // them, following operator precedence
+    pub fn from_ops(expr: Vec<ExprCoord>, op: Vec<OpCoord>) -> ExprCoord {
+        let mut expr = expr.into_iter();
+        let mut out: Vec<ExprCoord> = expr.next().into_iter().collect();
+        let mut stack: Vec<OpCoord> = Vec::new();
+        let reduce = |out: &mut Vec<ExprCoord>, op: OpCoord| {
+            if let (Some(rhs), Some(lhs)) = (out.pop(), out.pop()) {
+                out.push(ExprCoord::Bin(op, Rc::new(lhs), Rc::new(rhs)));
+            }
+        };
+        for (op, rhs) in op.into_iter().zip(expr) {
+            while let Some(top) = stack.pop() {
+                if top.prec() >= op.prec() {
+                    reduce(&mut out, top);
+                } else {
+                    stack.push(top);
+                    break;
+                }
+            }
+            stack.push(op);
+            out.push(rhs);
+        }
+        while let Some(top) = stack.pop() {
+            reduce(&mut out, top);
+        }
+        out.pop().unwrap_or_default()
+    }
+}
+
 impl OpAsm {
     pub fn name(&self) -> String {
         match self {
@@ -69,6 +133,12 @@ impl InstrAsm {
     pub fn set_arg(&mut self, arg: Expr) {
         self.arg = arg;
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl Instr {
@@ -96,6 +166,18 @@ impl Instr {
             Instr::Wire(instr) => instr.set_arg(arg),
         }
     }
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Instr::Asm(instr) => instr.span(),
+            Instr::Wire(instr) => instr.span(),
+        }
+    }
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            Instr::Asm(instr) => instr.set_span(span),
+            Instr::Wire(instr) => instr.set_span(span),
+        }
+    }
 }
 
 impl Prog {
@@ -117,4 +199,14 @@ impl Prog {
     pub fn body_mut(&mut self) -> &mut Vec<Instr> {
         &mut self.body
     }
+    // name of the source file in the spans of sig and body
+    pub fn set_file(&mut self, file: &str) {
+        self.sig.span.set_file(file);
+        for instr in self.body.iter_mut() {
+            if let Some(mut span) = instr.span().cloned() {
+                span.set_file(file);
+                instr.set_span(span);
+            }
+        }
+    }
 }
@@ -6,7 +6,6 @@ use pest_consume::match_nodes;
 use pest_consume::Error as PestError;
 use pest_consume::Parser as PestParser;
 use std::path::Path;
-use std::rc::Rc;
 use std::str::FromStr;
 
 pub type ParseResult<T> = std::result::Result<T, PestError<Rule>>;
@@ -86,11 +85,16 @@ impl Parser {
     }
 
     fn expr_coord(input: Node) -> ParseResult<ExprCoord> {
-        Ok(match_nodes!(
-            input.into_children();
-            [coord(coord)] => coord,
-            [coord(lhs), op_coord(op), coord(rhs)] => ExprCoord::Bin(op, Rc::new(lhs), Rc::new(rhs)),
-        ))
+        let mut expr: Vec<ExprCoord> = Vec::new();
+        let mut op: Vec<OpCoord> = Vec::new();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::op_coord => op.push(Parser::op_coord(node)?),
+                Rule::expr_coord => expr.push(Parser::expr_coord(node)?),
+                _ => expr.push(Parser::coord(node)?),
+            }
+        }
+        Ok(ExprCoord::from_ops(expr, op))
     }
 
     fn prim(input: Node) -> ParseResult<Prim> {
@@ -164,33 +168,40 @@ impl Parser {
     }
 
     fn instr(input: Node) -> ParseResult<Instr> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut instr = match_nodes!(
             input.into_children();
             [io(dst), op_wire(op), tup_val(attr)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::default(),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), tup_val(attr), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_asm(op), io(arg), loc(loc)] => Instr::from(InstrAsm {
                 op,
                 dst,
                 arg,
                 loc,
+                span: Span::default(),
             })
-        ))
+        );
+        instr.set_span(span);
+        Ok(instr)
     }
 
     fn body(input: Node) -> ParseResult<Vec<Instr>> {
@@ -201,24 +212,30 @@ impl Parser {
     }
 
     fn sig(input: Node) -> ParseResult<Sig> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut sig = match_nodes!(
             input.into_children();
             [id(id)] => Sig {
                 id,
                 input: Expr::default(),
                 output: Expr::default(),
+                span: Span::default(),
             },
             [id(id), io(output)] => Sig {
                 id,
                 input: Expr::default(),
                 output,
+                span: Span::default(),
             },
             [id(id), io(input), io(output)] => Sig {
                 id,
                 input,
                 output,
+                span: Span::default(),
             },
-        ))
+        );
+        sig.set_span(span);
+        Ok(sig)
     }
 
     fn prog(input: Node) -> ParseResult<Prog> {
@@ -251,7 +268,9 @@ impl Parser {
         Ok(infer_type_try_from_prog(&prog))
     }
     pub fn parse_from_file<P: AsRef<Path>>(path: P) -> Result<Prog, Error> {
-        let content = read_to_string(path);
-        Parser::parse_from_str(&content)
+        let content = read_to_string(&path);
+        let mut res = Parser::parse_from_str(&content)?;
+        res.set_file(&path.as_ref().to_string_lossy());
+        Ok(res)
     }
 }
@@ -6,6 +6,10 @@ impl PrettyPrint for OpCoord {
     fn to_doc(&self) -> RcDoc<()> {
         match self {
             OpCoord::Add => RcDoc::text("+"),
+            OpCoord::Sub => RcDoc::text("-"),
+            OpCoord::Mul => RcDoc::text("*"),
+            OpCoord::Range => RcDoc::text(".."),
+            OpCoord::Align => RcDoc::text("%"),
         }
     }
 }
@@ -16,7 +20,19 @@ impl PrettyPrint for ExprCoord {
             ExprCoord::Any => RcDoc::text("??"),
             ExprCoord::Val(n) => RcDoc::as_string(n),
             ExprCoord::Var(n) => RcDoc::as_string(n),
-            ExprCoord::Bin(op, lhs, rhs) => lhs.to_doc().append(op.to_doc()).append(rhs.to_doc()),
+            ExprCoord::Bin(op, lhs, rhs) => {
+                // operands binding less tightly than op need parentheses,
+                // and so does a right operand binding as tightly
+                let lhs = match lhs.op() {
+                    Some(l) if l.prec() < op.prec() => lhs.to_doc().parens(),
+                    _ => lhs.to_doc(),
+                };
+                let rhs = match rhs.op() {
+                    Some(r) if r.prec() <= op.prec() => rhs.to_doc().parens(),
+                    _ => rhs.to_doc(),
+                };
+                lhs.append(op.to_doc()).append(rhs)
+            }
         }
     }
 }
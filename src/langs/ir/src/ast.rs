@@ -6,6 +6,7 @@ pub type Id = String;
 pub type InstrMap = HashMap<Id, Instr>;
 pub type TermMap = HashMap<Id, ExprTerm>;
 pub type ExprSet = HashSet<Expr>;
+pub type Span = crate::span::Span;
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
 pub enum Ty {
@@ -89,6 +90,8 @@ pub struct InstrWire {
     pub dst: Expr,
     pub attr: Expr,
     pub arg: Expr,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
@@ -98,6 +101,8 @@ pub struct InstrPrim {
     pub attr: Expr,
     pub arg: Expr,
     pub prim: Prim,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
@@ -105,6 +110,8 @@ pub struct InstrCall {
     pub op: OpCall,
     pub dst: Expr,
     pub arg: Expr,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
@@ -119,6 +126,8 @@ pub struct Sig {
     pub id: Id,
     pub input: Expr,
     pub output: Expr,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash, Default)]
@@ -0,0 +1,280 @@
+use crate::ast::*;
+use crate::elab::check_call;
+use crate::errors::Error;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// Type checker, every problem found in a def is reported with the
+// instruction it comes from.
+#[derive(Clone, Debug, Default)]
+struct Checker {
+    def: Id,
+    span: Option<Span>,
+    env: HashMap<Id, Ty>,
+    diag: Vec<String>,
+}
+
+fn terms(expr: &Expr) -> Vec<ExprTerm> {
+    expr.clone().into()
+}
+
+fn is_bool(ty: &Ty) -> bool {
+    match ty {
+        Ty::Bool => true,
+        _ => false,
+    }
+}
+
+impl Checker {
+    fn new(def: &Def) -> Self {
+        Checker {
+            def: def.id(),
+            span: def.sig().span().cloned(),
+            ..Default::default()
+        }
+    }
+    // problems without an instruction are reported at the signature
+    fn report(&mut self, instr: Option<&Instr>, msg: &str) {
+        let msg = format!("{}: {}", self.def, msg);
+        let span = instr
+            .and_then(|instr| instr.span())
+            .or(self.span.as_ref());
+        let diag = match (instr, span) {
+            (_, Some(span)) => span.diagnostic(&msg),
+            (Some(instr), None) => format!("{}\n    in `{}`", msg, instr),
+            (None, None) => msg,
+        };
+        self.diag.push(diag);
+    }
+    fn define(&mut self, instr: Option<&Instr>, term: &ExprTerm) {
+        if let (Some(id), Some(ty)) = (term.id(), term.ty()) {
+            let defined = match self.env.entry(id.clone()) {
+                Entry::Occupied(_) => true,
+                Entry::Vacant(entry) => {
+                    entry.insert(ty.clone());
+                    false
+                }
+            };
+            if defined {
+                let msg = format!("{} is defined more than once", id);
+                self.report(instr, &msg);
+            }
+        }
+    }
+    // type of arguments, undefined ones are reported and left out
+    fn args(&mut self, instr: &Instr) -> Option<Vec<Ty>> {
+        let mut res: Vec<Ty> = Vec::new();
+        for term in terms(instr.arg()) {
+            if let Some(id) = term.id() {
+                match self.env.get(&id) {
+                    Some(ty) => res.push(ty.clone()),
+                    None => {
+                        let msg = format!("{} is not defined", id);
+                        self.report(Some(instr), &msg);
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(res)
+    }
+    fn arity(&mut self, instr: &Instr, arg: &[Ty], num: usize) -> bool {
+        if arg.len() == num {
+            true
+        } else {
+            let msg = format!("expects {} arguments, found {}", num, arg.len());
+            self.report(Some(instr), &msg);
+            false
+        }
+    }
+    fn same(&mut self, instr: &Instr, what: &str, found: &Ty, exp: &Ty) {
+        if found != exp {
+            let msg = format!("{} is {}, expected {}", what, found, exp);
+            self.report(Some(instr), &msg);
+        }
+    }
+    fn boolean(&mut self, instr: &Instr, what: &str, ty: &Ty) {
+        if !is_bool(ty) {
+            let msg = format!("{} is {}, expected bool", what, ty);
+            self.report(Some(instr), &msg);
+        }
+    }
+    fn check_wire(&mut self, instr: &Instr, wire: &InstrWire, dst: &Ty) {
+        let arg = match self.args(instr) {
+            Some(arg) => arg,
+            None => return,
+        };
+        match wire.op() {
+            OpWire::Con => {
+                self.arity(instr, &arg, 0);
+            }
+            OpWire::Id | OpWire::Sll | OpWire::Srl | OpWire::Sra => {
+                if self.arity(instr, &arg, 1) {
+                    self.same(instr, "operand", &arg[0], dst);
+                }
+            }
+            OpWire::Ext => {
+                if !self.arity(instr, &arg, 1) {
+                    return;
+                }
+                let width = arg[0].width().unwrap_or(0) as i64;
+                match (wire.attr().get_val(0), wire.attr().get_val(1)) {
+                    (Ok(lo), Ok(hi)) if lo < 0 || lo > hi || hi >= width => {
+                        let msg = format!("slice [{},{}] is out of range for {}", lo, hi, arg[0]);
+                        self.report(Some(instr), &msg);
+                    }
+                    (Ok(lo), Ok(hi)) => {
+                        let slice = (hi - lo + 1) as u64;
+                        if dst.width() != Some(slice) {
+                            let msg = format!("slice of {} bits assigned to {}", slice, dst);
+                            self.report(Some(instr), &msg);
+                        }
+                    }
+                    _ => self.report(Some(instr), "expects a slice [lo,hi]"),
+                }
+            }
+            OpWire::Cat => {
+                let sum: u64 = arg.iter().map(|ty| ty.width().unwrap_or(0)).sum();
+                if arg.iter().any(|ty| ty.is_vector()) || dst.is_vector() {
+                    self.report(Some(instr), "cat only supports scalar values");
+                } else if dst.width() != Some(sum) {
+                    let msg = format!("concatenation of {} bits assigned to {}", sum, dst);
+                    self.report(Some(instr), &msg);
+                }
+            }
+        }
+    }
+    fn check_prim(&mut self, instr: &Instr, prim: &InstrPrim, dst: &Ty) {
+        let arg = match self.args(instr) {
+            Some(arg) => arg,
+            None => return,
+        };
+        match prim.op() {
+            OpPrim::Reg => {
+                if self.arity(instr, &arg, 2) {
+                    self.same(instr, "data", &arg[0], dst);
+                    self.boolean(instr, "enable", &arg[1]);
+                }
+            }
+            OpPrim::Rom | OpPrim::Srom => {
+                self.arity(instr, &arg, 1);
+            }
+            OpPrim::Ram | OpPrim::Sram => {
+                if self.arity(instr, &arg, 3) {
+                    self.same(instr, "data", &arg[1], dst);
+                    self.boolean(instr, "write enable", &arg[2]);
+                }
+            }
+            OpPrim::Not => {
+                if self.arity(instr, &arg, 1) {
+                    self.same(instr, "operand", &arg[0], dst);
+                }
+            }
+            OpPrim::Add | OpPrim::Sub | OpPrim::Mul | OpPrim::And | OpPrim::Or | OpPrim::Xor => {
+                if self.arity(instr, &arg, 2) {
+                    self.same(instr, "left operand", &arg[0], dst);
+                    self.same(instr, "right operand", &arg[1], dst);
+                }
+            }
+            OpPrim::Mux => {
+                if self.arity(instr, &arg, 3) {
+                    self.boolean(instr, "select", &arg[0]);
+                    self.same(instr, "true operand", &arg[1], dst);
+                    self.same(instr, "false operand", &arg[2], dst);
+                }
+            }
+            OpPrim::Eql | OpPrim::Neql | OpPrim::Gt | OpPrim::Lt | OpPrim::Ge | OpPrim::Le => {
+                if self.arity(instr, &arg, 2) {
+                    self.same(instr, "right operand", &arg[1], &arg[0]);
+                    if dst.width() != Some(1) || dst.length() != arg[0].length() {
+                        let msg = format!("comparison result assigned to {}", dst);
+                        self.report(Some(instr), &msg);
+                    }
+                }
+            }
+        }
+    }
+    fn check_call(&mut self, instr: &Instr, call: &InstrCall, prog: &Prog) {
+        if self.args(instr).is_none() {
+            return;
+        }
+        let op = call.op().op();
+        match prog.get(&op) {
+            Some(callee) => {
+                if let Err(e) = check_call(call, callee) {
+                    self.report(Some(instr), &e.to_string());
+                }
+            }
+            None => {
+                let msg = format!("{} is not defined", op);
+                self.report(Some(instr), &msg);
+            }
+        }
+    }
+    fn check_def(&mut self, def: &Def, prog: &Prog) {
+        for term in terms(def.input()) {
+            self.define(None, &term);
+        }
+        // values can be used before they are defined, e.g. reg feedback
+        for instr in def.body() {
+            for term in terms(instr.dst()) {
+                self.define(Some(instr), &term);
+            }
+        }
+        for instr in def.body() {
+            let dst = match instr.dst().get_term(0).and_then(|t| t.get_ty()) {
+                Ok(ty) => ty.clone(),
+                Err(_) => Ty::Any,
+            };
+            match instr {
+                Instr::Wire(wire) => self.check_wire(instr, wire, &dst),
+                Instr::Prim(prim) => self.check_prim(instr, prim, &dst),
+                Instr::Call(call) => self.check_call(instr, call, prog),
+            }
+        }
+        for term in terms(def.output()) {
+            if let (Some(id), Some(ty)) = (term.id(), term.ty()) {
+                match self.env.get(&id) {
+                    Some(found) if found != ty => {
+                        let msg = format!("output {} is {}, expected {}", id, found, ty);
+                        self.report(None, &msg);
+                    }
+                    Some(_) => (),
+                    None => {
+                        let msg = format!("output {} is never assigned", id);
+                        self.report(None, &msg);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn check_def(def: &Def, prog: &Prog) -> Result<(), Error> {
+    let mut checker = Checker::new(def);
+    checker.check_def(def, prog);
+    if checker.diag.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new_type_error(&checker.diag.join("\n")))
+    }
+}
+
+// check every def and report all the problems found at once
+pub fn check_prog(prog: &Prog) -> Result<(), Error> {
+    let mut names: Vec<&Id> = prog.def().keys().collect();
+    names.sort();
+    let mut diag: Vec<String> = Vec::new();
+    for name in names {
+        if let Some(def) = prog.get(name) {
+            if let Err(e) = check_def(def, prog) {
+                diag.push(e.to_string());
+            }
+        }
+    }
+    if diag.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new_type_error(&diag.join("\n")))
+    }
+}
@@ -0,0 +1,317 @@
+use crate::ast::*;
+use crate::errors::Error;
+use crate::span::diagnostic;
+use std::collections::{HashMap, HashSet};
+
+pub fn calls_from_def(def: &Def) -> Vec<&InstrCall> {
+    let mut calls: Vec<&InstrCall> = Vec::new();
+    for instr in def.body() {
+        if let Instr::Call(call) = instr {
+            calls.push(call);
+        }
+    }
+    calls
+}
+
+fn check_terms(op: &str, kind: &str, found: &[ExprTerm], exp: &[ExprTerm]) -> Result<(), Error> {
+    if found.len() != exp.len() {
+        let err = format!(
+            "{} expects {} {}s, found {}",
+            op,
+            exp.len(),
+            kind,
+            found.len()
+        );
+        return Err(Error::new_type_error(&err));
+    }
+    for (index, (f, e)) in found.iter().zip(exp.iter()).enumerate() {
+        if f.ty() != e.ty() {
+            let err = format!(
+                "{} {} {} is {}, expected {}",
+                op,
+                kind,
+                index,
+                f,
+                e.get_ty()?
+            );
+            return Err(Error::new_type_error(&err));
+        }
+    }
+    Ok(())
+}
+
+// argument and result types must match the signature of the callee
+pub fn check_call(call: &InstrCall, callee: &Def) -> Result<(), Error> {
+    let op = call.op().op();
+    let arg: Vec<ExprTerm> = call.arg().clone().into();
+    let dst: Vec<ExprTerm> = call.dst().clone().into();
+    let input: Vec<ExprTerm> = callee.input().clone().into();
+    let output: Vec<ExprTerm> = callee.output().clone().into();
+    check_terms(&op, "argument", &arg, &input)?;
+    check_terms(&op, "result", &dst, &output)?;
+    Ok(())
+}
+
+fn located(span: Option<&Span>, err: Error) -> Error {
+    match err {
+        Error::Type(msg) => Error::new_type_error(&diagnostic(span, &msg)),
+        Error::Elab(msg) => Error::new_elab_error(&diagnostic(span, &msg)),
+        err => err,
+    }
+}
+
+fn visit(prog: &Prog, id: &str, stack: &mut Vec<Id>, order: &mut Vec<Id>) -> Result<(), Error> {
+    if order.iter().any(|d| d == id) {
+        return Ok(());
+    }
+    let def = match prog.get(id) {
+        Some(def) => def,
+        None => {
+            let err = format!("{} is not defined", id);
+            return Err(Error::new_elab_error(&err));
+        }
+    };
+    stack.push(id.to_string());
+    for call in calls_from_def(def) {
+        let op = call.op().op();
+        if stack.iter().any(|d| d == &op) {
+            let err = format!("recursive call to {} in {}", op, stack.join(" -> "));
+            return Err(located(call.span(), Error::new_elab_error(&err)));
+        }
+        match prog.get(&op) {
+            Some(callee) => check_call(call, callee).map_err(|e| located(call.span(), e))?,
+            None => {
+                let err = format!("{} is not defined", op);
+                return Err(located(call.span(), Error::new_elab_error(&err)));
+            }
+        }
+        visit(prog, &op, stack, order)?;
+    }
+    stack.pop();
+    order.push(id.to_string());
+    Ok(())
+}
+
+// def names ordered so callees come before their callers, it fails on
+// undefined or recursive calls and on calls that do not match the callee
+pub fn call_order(prog: &Prog) -> Result<Vec<Id>, Error> {
+    let mut names: Vec<&Id> = prog.def().keys().collect();
+    names.sort();
+    let mut order: Vec<Id> = Vec::new();
+    for name in names {
+        visit(prog, name, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+fn rename_expr(expr: &Expr, map: &TermMap, prefix: &str) -> Expr {
+    let rename = |term: &ExprTerm| match term {
+        ExprTerm::Var(id, ty) => match map.get(id) {
+            Some(term) => term.clone(),
+            None => ExprTerm::Var(format!("{}{}", prefix, id), ty.clone()),
+        },
+        _ => term.clone(),
+    };
+    match expr {
+        Expr::Term(term) => Expr::from(rename(term)),
+        Expr::Tup(tup) => Expr::from(ExprTup::from(
+            tup.term().iter().map(rename).collect::<Vec<ExprTerm>>(),
+        )),
+    }
+}
+
+// Call instance each inlined value comes from, as the prefix of the
+// innermost instance. Values of the def itself are not in the map.
+pub type InstanceMap = HashMap<Id, Id>;
+
+// instrs of callee with inputs and outputs bound to the call, other values
+// get the prefix so they do not clash with the ones in the caller. The
+// values that get the prefix are recorded in instance, inner holds the
+// instances of the callee.
+fn inline_call(
+    call: &InstrCall,
+    callee: &Def,
+    prefix: &str,
+    inner: Option<&InstanceMap>,
+    instance: &mut InstanceMap,
+) -> Result<Vec<Instr>, Error> {
+    let mut map = TermMap::new();
+    let mut body: Vec<Instr> = Vec::new();
+    let arg: Vec<ExprTerm> = call.arg().clone().into();
+    let dst: Vec<ExprTerm> = call.dst().clone().into();
+    let input: Vec<ExprTerm> = callee.input().clone().into();
+    let output: Vec<ExprTerm> = callee.output().clone().into();
+    for (i, a) in input.iter().zip(arg.iter()) {
+        map.insert(i.get_id()?, a.clone());
+    }
+    for (o, d) in output.iter().zip(dst.iter()) {
+        let id = o.get_id()?;
+        // outputs that are inputs of the callee become wires
+        if let Some(a) = map.get(&id) {
+            body.push(Instr::from(InstrWire {
+                op: OpWire::Id,
+                dst: Expr::from(d.clone()),
+                attr: Expr::default(),
+                arg: Expr::from(ExprTup::from(vec![a.clone()])),
+                span: Span::default(),
+            }));
+        } else {
+            map.insert(id, d.clone());
+        }
+    }
+    for instr in callee.body() {
+        let dst: Vec<ExprTerm> = instr.dst().clone().into();
+        for id in dst.iter().filter_map(|t| t.id()) {
+            if !map.contains_key(&id) {
+                let inner = inner.and_then(|m| m.get(&id)).map_or("", |p| p.as_str());
+                instance.insert(format!("{}{}", prefix, id), format!("{}{}", prefix, inner));
+            }
+        }
+        let mut instr = instr.clone();
+        instr.set_dst(rename_expr(instr.dst(), &map, prefix));
+        instr.set_arg(rename_expr(instr.arg(), &map, prefix));
+        body.push(instr);
+    }
+    Ok(body)
+}
+
+fn contains_id(terms: &[ExprTerm], id: &str) -> bool {
+    terms.iter().any(|t| t.id().map_or(false, |i| i == id))
+}
+
+// def without its calls, so it can be compiled on its own. Call results become
+// inputs and the values passed to calls become outputs, the backend connects
+// them to an instance of the callee.
+pub fn detach_calls(def: &Def) -> Result<Def, Error> {
+    let mut input: Vec<ExprTerm> = def.input().clone().into();
+    let mut output: Vec<ExprTerm> = Vec::new();
+    let mut body: Vec<Instr> = Vec::new();
+    let mut call_dst: Vec<ExprTerm> = Vec::new();
+    let mut call_arg: Vec<ExprTerm> = Vec::new();
+    for instr in def.body() {
+        if let Instr::Call(call) = instr {
+            let dst: Vec<ExprTerm> = call.dst().clone().into();
+            let arg: Vec<ExprTerm> = call.arg().clone().into();
+            call_dst.extend(dst);
+            call_arg.extend(arg);
+        } else {
+            body.push(instr.clone());
+        }
+    }
+    let def_output: Vec<ExprTerm> = def.output().clone().into();
+    for term in def_output {
+        if !contains_id(&call_dst, &term.get_id()?) {
+            output.push(term);
+        }
+    }
+    for term in call_arg {
+        let id = term.get_id()?;
+        if !contains_id(&input, &id) && !contains_id(&call_dst, &id) && !contains_id(&output, &id) {
+            output.push(term);
+        }
+    }
+    input.extend(call_dst);
+    let mut res = def.clone();
+    res.set_sig(Sig {
+        id: def.id(),
+        input: Expr::from(ExprTup::from(input)),
+        output: Expr::from(ExprTup::from(output)),
+        span: Span::default(),
+    });
+    res.set_body(body);
+    Ok(res)
+}
+
+fn def_ids(def: &Def) -> HashSet<Id> {
+    let mut ids: HashSet<Id> = HashSet::new();
+    let input: Vec<ExprTerm> = def.input().clone().into();
+    for term in input {
+        ids.extend(term.id());
+    }
+    for instr in def.body() {
+        let dst: Vec<ExprTerm> = instr.dst().clone().into();
+        for term in dst {
+            ids.extend(term.id());
+        }
+    }
+    ids
+}
+
+// prefix given to the values of each call when it is inlined, in the order
+// of the calls in the body
+pub fn call_prefixes(def: &Def) -> Vec<Id> {
+    let ids = def_ids(def);
+    let mut count = 0;
+    let mut prefixes: Vec<Id> = Vec::new();
+    for call in calls_from_def(def) {
+        let op = call.op().op();
+        let mut prefix = format!("{}_{}_", op, count);
+        while ids.iter().any(|id| id.starts_with(&prefix)) {
+            count += 1;
+            prefix = format!("{}_{}_", op, count);
+        }
+        count += 1;
+        prefixes.push(prefix);
+    }
+    prefixes
+}
+
+fn flat_try_from_def(
+    def: &Def,
+    flat: &HashMap<Id, (Def, InstanceMap)>,
+) -> Result<(Def, InstanceMap), Error> {
+    let mut prefixes = call_prefixes(def).into_iter();
+    let mut body: Vec<Instr> = Vec::new();
+    let mut instance = InstanceMap::new();
+    for instr in def.body() {
+        if let Instr::Call(call) = instr {
+            let op = call.op().op();
+            let (callee, inner) = match flat.get(&op) {
+                Some(callee) => callee,
+                None => {
+                    let err = format!("{} is not defined", op);
+                    return Err(Error::new_elab_error(&err));
+                }
+            };
+            let prefix = prefixes.next().unwrap_or_default();
+            body.extend(inline_call(
+                call,
+                callee,
+                &prefix,
+                Some(inner),
+                &mut instance,
+            )?);
+        } else {
+            body.push(instr.clone());
+        }
+    }
+    let mut res = def.clone();
+    res.set_body(body);
+    Ok((res, instance))
+}
+
+// inline every call, so each def in the result is flat, along with the call
+// instance of the inlined values of each def
+pub fn flat_try_from_prog_with_instances(
+    prog: &Prog,
+) -> Result<(Prog, HashMap<Id, InstanceMap>), Error> {
+    let mut flat: HashMap<Id, (Def, InstanceMap)> = HashMap::new();
+    for id in call_order(prog)? {
+        if let Some(def) = prog.get(&id) {
+            let def = flat_try_from_def(def, &flat)?;
+            flat.insert(id, def);
+        }
+    }
+    let mut res = Prog::default();
+    let mut instances: HashMap<Id, InstanceMap> = HashMap::new();
+    for (id, (def, instance)) in flat {
+        res.insert(&id, def);
+        instances.insert(id, instance);
+    }
+    Ok((res, instances))
+}
+
+// inline every call, so each def in the result is flat
+pub fn flat_try_from_prog(prog: &Prog) -> Result<Prog, Error> {
+    Ok(flat_try_from_prog_with_instances(prog)?.0)
+}
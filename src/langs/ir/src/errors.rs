@@ -8,6 +8,7 @@ pub enum Error {
     Parser(pest_consume::Error<parser::Rule>),
     Conversion(String),
     Type(String),
+    Elab(String),
     TryFromInt(TryFromIntError),
     ParseInt(ParseIntError),
 }
@@ -17,7 +18,10 @@ impl Error {
         Error::Conversion(msg.to_string())
     }
     pub fn new_type_error(msg: &str) -> Self {
-        Error::Conversion(msg.to_string())
+        Error::Type(msg.to_string())
+    }
+    pub fn new_elab_error(msg: &str) -> Self {
+        Error::Elab(msg.to_string())
     }
 }
 
@@ -46,6 +50,7 @@ impl fmt::Display for Error {
             Error::ParseInt(msg) => write!(f, "{}", msg),
             Error::Conversion(msg) => write!(f, "{}", msg),
             Error::Type(msg) => write!(f, "{}", msg),
+            Error::Elab(msg) => write!(f, "{}", msg),
             Error::TryFromInt(msg) => write!(f, "{}", msg),
         }
     }
@@ -214,6 +214,12 @@ impl InstrCall {
     pub fn set_arg(&mut self, arg: Expr) {
         self.arg = arg;
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl InstrPrim {
@@ -244,6 +250,12 @@ impl InstrPrim {
     pub fn set_arg(&mut self, arg: Expr) {
         self.arg = arg;
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl InstrWire {
@@ -265,6 +277,12 @@ impl InstrWire {
     pub fn set_arg(&mut self, arg: Expr) {
         self.arg = arg;
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl Instr {
@@ -311,6 +329,20 @@ impl Instr {
             Instr::Call(instr) => instr.set_arg(arg),
         }
     }
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Instr::Prim(instr) => instr.span(),
+            Instr::Wire(instr) => instr.span(),
+            Instr::Call(instr) => instr.span(),
+        }
+    }
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            Instr::Prim(instr) => instr.set_span(span),
+            Instr::Wire(instr) => instr.set_span(span),
+            Instr::Call(instr) => instr.set_span(span),
+        }
+    }
 }
 
 impl Sig {
@@ -326,6 +358,12 @@ impl Sig {
     pub fn set_id(&mut self, id: &str) {
         self.id = id.to_string();
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 fn term_is_ready(env: &HashSet<Id>, term: &ExprTerm) -> bool {
@@ -422,6 +460,16 @@ impl Def {
             Err(Error::new_conv_error("Sorting"))
         }
     }
+    // name of the source file in the spans of sig and body
+    pub fn set_file(&mut self, file: &str) {
+        self.sig.span.set_file(file);
+        for instr in self.body.iter_mut() {
+            if let Some(mut span) = instr.span().cloned() {
+                span.set_file(file);
+                instr.set_span(span);
+            }
+        }
+    }
 }
 
 impl Prog {
@@ -443,4 +491,9 @@ impl Prog {
         }
         Ok(())
     }
+    pub fn set_file(&mut self, file: &str) {
+        for (_, def) in self.def.iter_mut() {
+            def.set_file(file);
+        }
+    }
 }
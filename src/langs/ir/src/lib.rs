@@ -1,6 +1,8 @@
 pub mod ast;
+pub mod check;
 pub mod default;
 pub mod display;
+pub mod elab;
 pub mod errors;
 pub mod from;
 pub mod from_str;
@@ -8,4 +10,5 @@ pub mod helpers;
 pub mod infer;
 pub mod parser;
 pub mod pretty_print;
+pub mod span;
 pub mod try_from;
@@ -131,7 +131,8 @@ impl Parser {
     }
 
     fn instr(input: Node) -> ParseResult<Instr> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut instr = match_nodes!(
             input.into_children();
             [io(dst), op_prim(op), io(arg)] => Instr::from(InstrPrim {
                 op,
@@ -139,6 +140,7 @@ impl Parser {
                 attr: Expr::default(),
                 arg,
                 prim: Prim::Any,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), tup_val(attr), io(arg)] => Instr::from(InstrPrim {
                 op,
@@ -146,6 +148,7 @@ impl Parser {
                 attr: Expr::from(attr),
                 arg,
                 prim: Prim::Any,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), io(arg), prim(prim)] => Instr::from(InstrPrim {
                 op,
@@ -153,6 +156,7 @@ impl Parser {
                 attr: Expr::default(),
                 arg,
                 prim,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), tup_val(attr), io(arg), prim(prim)] => Instr::from(InstrPrim {
                 op,
@@ -160,36 +164,44 @@ impl Parser {
                 attr: Expr::from(attr),
                 arg,
                 prim,
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), tup_val(attr)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::default(),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), tup_val(attr), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_call(op)] => Instr::from(InstrCall {
                 op,
                 dst,
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_call(op), io(arg)] => Instr::from(InstrCall {
                 op,
                 dst,
                 arg,
+                span: Span::default(),
             }),
-        ))
+        );
+        instr.set_span(span);
+        Ok(instr)
     }
 
     fn body(input: Node) -> ParseResult<Vec<Instr>> {
@@ -200,24 +212,30 @@ impl Parser {
     }
 
     fn sig(input: Node) -> ParseResult<Sig> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut sig = match_nodes!(
             input.into_children();
             [id(id)] => Sig {
                 id,
                 input: Expr::default(),
                 output: Expr::default(),
+                span: Span::default(),
             },
             [id(id), io(output)] => Sig {
                 id,
                 input: Expr::default(),
                 output,
+                span: Span::default(),
             },
             [id(id), io(input), io(output)] => Sig {
                 id,
                 input,
                 output,
+                span: Span::default(),
             },
-        ))
+        );
+        sig.set_span(span);
+        Ok(sig)
     }
 
     fn def(input: Node) -> ParseResult<Def> {
@@ -264,7 +282,9 @@ impl Parser {
         Ok(infer::type_try_from_prog(&prog))
     }
     pub fn parse_from_file<P: AsRef<Path>>(path: P) -> Result<Prog, Error> {
-        let content = read_to_string(path);
-        Parser::parse_from_str(&content)
+        let content = read_to_string(&path);
+        let mut res = Parser::parse_from_str(&content)?;
+        res.set_file(&path.as_ref().to_string_lossy());
+        Ok(res)
     }
 }
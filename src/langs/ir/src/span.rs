@@ -0,0 +1,90 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Position of an instruction or signature in its source, line zero when
+// unknown. Spans are not serialized and do not take part in comparisons, so
+// a parsed program is equal to the same program built by hand.
+#[derive(Clone, Debug, Default)]
+pub struct Span {
+    pub file: Option<String>,
+    pub line: usize,
+    pub col: usize,
+    pub text: String,
+}
+
+impl Span {
+    pub fn file(&self) -> Option<&String> {
+        self.file.as_ref()
+    }
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn col(&self) -> usize {
+        self.col
+    }
+    pub fn text(&self) -> &String {
+        &self.text
+    }
+    pub fn known(&self) -> Option<&Span> {
+        if self.line > 0 {
+            Some(self)
+        } else {
+            None
+        }
+    }
+    pub fn set_file(&mut self, file: &str) {
+        if self.line > 0 {
+            self.file = Some(file.to_string());
+        }
+    }
+    // message followed by the location and the source line
+    pub fn diagnostic(&self, msg: &str) -> String {
+        let pad = " ".repeat(self.line.to_string().len());
+        let caret = " ".repeat(self.col.saturating_sub(1));
+        format!(
+            "{}\n{}--> {}\n{} |\n{} | {}\n{} | {}^",
+            msg, pad, self, pad, self.line, self.text, pad, caret
+        )
+    }
+}
+
+pub fn diagnostic(span: Option<&Span>, msg: &str) -> String {
+    match span {
+        Some(span) => span.diagnostic(msg),
+        None => msg.to_string(),
+    }
+}
+
+impl<'i> From<pest::Span<'i>> for Span {
+    fn from(span: pest::Span<'i>) -> Self {
+        let pos = span.start_pos();
+        let (line, col) = pos.line_col();
+        Span {
+            file: None,
+            line,
+            col,
+            text: pos.line_of().trim_end().to_string(),
+        }
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.line, self.col),
+            None => write!(f, "{}:{}", self.line, self.col),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use ir::check::check_prog;
+use ir::parser::Parser;
+
+fn check(name: &str) {
+    let file = format!("../../../examples/ir/{}.ir", name);
+    let prog = Parser::parse_from_file(file).unwrap();
+    assert!(check_prog(&prog).is_ok());
+}
+
+fn check_err(input: &str) -> String {
+    let prog = Parser::parse_from_str(input).unwrap();
+    check_prog(&prog).unwrap_err().to_string()
+}
+
+#[test]
+fn add() {
+    check("add");
+}
+
+#[test]
+fn aes_encrypt() {
+    check("aes_encrypt");
+}
+
+#[test]
+fn call() {
+    check("call");
+}
+
+#[test]
+fn fsm_3() {
+    check("fsm_3");
+}
+
+#[test]
+fn tdot() {
+    check("tdot");
+}
+
+#[test]
+fn operand_mismatch() {
+    let err = check_err(
+        "def main(a:i8, b:u8) -> (y:i8) {
+            y:i8 = add(a, b);
+        }",
+    );
+    assert_eq!(
+        err,
+        "main: right operand is u8, expected i8
+ --> 2:13
+  |
+2 |             y:i8 = add(a, b);
+  |             ^"
+    );
+}
+
+#[test]
+fn mux_select() {
+    let err = check_err(
+        "def main(s:i8, a:i8, b:i8) -> (y:i8) {
+            y:i8 = mux(s, a, b);
+        }",
+    );
+    assert!(err.contains("select is i8, expected bool"));
+}
+
+#[test]
+fn reg_enable() {
+    let err = check_err(
+        "def main(a:i8, en:u2) -> (y:i8) {
+            y:i8 = reg[0](a, en);
+        }",
+    );
+    assert!(err.contains("enable is u2, expected bool"));
+}
+
+#[test]
+fn ext_range() {
+    let err = check_err(
+        "def main(a:i8) -> (y:i4, z:i2) {
+            y:i4 = ext[6,9](a);
+            z:i2 = ext[0,2](a);
+        }",
+    );
+    assert!(err.contains("slice [6,9] is out of range for i8"));
+    assert!(err.contains("slice of 3 bits assigned to i2"));
+}
+
+#[test]
+fn cat_width() {
+    let err = check_err(
+        "def main(a:i8, b:i4) -> (y:i16) {
+            y:i16 = cat(a, b);
+        }",
+    );
+    assert!(err.contains("concatenation of 12 bits assigned to i16"));
+}
+
+#[test]
+fn undefined() {
+    let err = check_err(
+        "def main(a:i8) -> (y:i8) {
+            y:i8 = add(a, b);
+        }",
+    );
+    assert!(err.contains("b is not defined"));
+}
+
+#[test]
+fn defined_twice() {
+    let err = check_err(
+        "def main(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b);
+            y:i8 = sub(a, b);
+        }",
+    );
+    assert!(err.contains("y is defined more than once"));
+}
+
+#[test]
+fn unassigned_output() {
+    let err = check_err(
+        "def main(a:i8, b:i8) -> (y:i8, z:i8) {
+            y:i8 = add(a, b);
+        }",
+    );
+    assert!(err.starts_with("main: output z is never assigned\n --> 1:1"));
+}
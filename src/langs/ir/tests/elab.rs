@@ -0,0 +1,123 @@
+use ir::elab::{call_order, detach_calls, flat_try_from_prog};
+use ir::parser::Parser;
+
+#[test]
+fn flat_call() {
+    let prog = Parser::parse_from_file("../../../examples/ir/call.ir").unwrap();
+    let res = flat_try_from_prog(&prog).unwrap();
+    let exp = Parser::parse_from_str(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8) {
+            addreg_0_t0:i8 = add(a, b);
+            t0:i8 = reg[0](addreg_0_t0, en);
+            addreg_1_t0:i8 = add(t0, c);
+            y:i8 = reg[0](addreg_1_t0, en);
+        }",
+    )
+    .unwrap();
+    assert_eq!(res.get("main"), exp.get("main"));
+    assert_eq!(res.get("addreg"), prog.get("addreg"));
+}
+
+#[test]
+fn flat_nested() {
+    let prog = Parser::parse_from_str(
+        "def pass(a:i8) -> (a:i8) {}
+        def inc(a:i8) -> (y:i8) {
+            t0:i8 = pass(a);
+            t1:i8 = const[1];
+            y:i8 = add(t0, t1);
+        }
+        def main(a:i8) -> (y:i8) {
+            y:i8 = inc(a);
+        }",
+    )
+    .unwrap();
+    let res = flat_try_from_prog(&prog).unwrap();
+    let exp = Parser::parse_from_str(
+        "def main(a:i8) -> (y:i8) {
+            inc_0_t0:i8 = id(a);
+            inc_0_t1:i8 = const[1];
+            y:i8 = add(inc_0_t0, inc_0_t1);
+        }",
+    )
+    .unwrap();
+    assert_eq!(res.get("main"), exp.get("main"));
+    assert_eq!(call_order(&prog).unwrap(), vec!["pass", "inc", "main"]);
+}
+
+#[test]
+fn recursion() {
+    let prog = Parser::parse_from_str(
+        "def foo(a:i8) -> (y:i8) {
+            y:i8 = bar(a);
+        }
+        def bar(a:i8) -> (y:i8) {
+            y:i8 = foo(a);
+        }",
+    )
+    .unwrap();
+    assert!(flat_try_from_prog(&prog).is_err());
+}
+
+#[test]
+fn undefined() {
+    let prog = Parser::parse_from_str(
+        "def main(a:i8) -> (y:i8) {
+            y:i8 = foo(a);
+        }",
+    )
+    .unwrap();
+    assert!(flat_try_from_prog(&prog).is_err());
+}
+
+#[test]
+fn mismatch() {
+    let prog = Parser::parse_from_str(
+        "def inc(a:i8) -> (y:i8) {
+            t0:i8 = const[1];
+            y:i8 = add(a, t0);
+        }
+        def main(a:i4) -> (y:i8) {
+            y:i8 = inc(a);
+        }",
+    )
+    .unwrap();
+    assert!(flat_try_from_prog(&prog).is_err());
+    let prog = Parser::parse_from_str(
+        "def inc(a:i8) -> (y:i8) {
+            t0:i8 = const[1];
+            y:i8 = add(a, t0);
+        }
+        def main(a:i8) -> (y:u8) {
+            y:u8 = inc(a);
+        }",
+    )
+    .unwrap();
+    assert!(flat_try_from_prog(&prog).is_err());
+}
+
+#[test]
+fn detach() {
+    let prog = Parser::parse_from_str(
+        "def inc(a:i8) -> (y:i8) {
+            t0:i8 = const[1];
+            y:i8 = add(a, t0);
+        }
+        def main(a:i8, b:i8, en:bool) -> (y:i8, z:i8) {
+            t0:i8 = add(a, b);
+            t1:i8 = inc(t0);
+            z:i8 = inc(a);
+            y:i8 = reg[0](t1, en);
+        }",
+    )
+    .unwrap();
+    let res = detach_calls(prog.get("main").unwrap()).unwrap();
+    let exp = Parser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool, t1:i8, z:i8) -> (y:i8, t0:i8) {
+            t0:i8 = add(a, b);
+            y:i8 = reg[0](t1, en);
+        }",
+    )
+    .unwrap();
+    assert_eq!(&res, exp.get("main").unwrap());
+}
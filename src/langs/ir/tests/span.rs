@@ -0,0 +1,36 @@
+use ir::parser::Parser;
+
+#[test]
+fn parse_span() {
+    let prog = Parser::parse_from_file("../../../examples/ir/add.ir").unwrap();
+    let def = prog.get("main").unwrap();
+    let span = def.body()[1].span().unwrap();
+    assert_eq!(span.line(), 3);
+    assert_eq!(span.col(), 5);
+    assert_eq!(span.text(), "    y:i8 = reg[0](t0, en);");
+    assert_eq!(span.to_string(), "../../../examples/ir/add.ir:3:5");
+    assert_eq!(def.sig().span().unwrap().line(), 1);
+}
+
+#[test]
+fn diagnostic() {
+    let prog = Parser::parse_from_str(
+        "def main(a:i8, b:i8) -> (y:i8) {
+    y:i8 = add(a, b);
+}",
+    )
+    .unwrap();
+    let instr = &prog.get("main").unwrap().body()[0];
+    let res = instr.span().unwrap().diagnostic("error");
+    assert_eq!(
+        res,
+        "error\n --> 2:5\n  |\n2 |     y:i8 = add(a, b);\n  |     ^"
+    );
+}
+
+#[test]
+fn span_eq() {
+    let a = Parser::parse_from_str("def main(a:i8) -> (y:i8) {\n y:i8 = id(a);\n}").unwrap();
+    let b = Parser::parse_from_str("def main(a:i8) -> (y:i8) {\n\n    y:i8 = id(a);\n}").unwrap();
+    assert_eq!(a, b);
+}
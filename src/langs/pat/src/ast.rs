@@ -13,6 +13,11 @@ pub type OpPrim = ir::OpPrim;
 pub type InstrWire = ir::InstrWire;
 pub type InstrPrim = ir::InstrPrim;
 pub type Sig = ir::Sig;
+pub type Index = generic::Index;
+pub type Io = generic::Io;
+pub type Param = generic::Param;
+pub type Stmt = generic::Stmt<GenericInstr>;
+pub type Span = ir::Span;
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
 pub enum Instr {
@@ -26,7 +31,39 @@ pub struct Pat {
     pub body: Vec<Instr>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum GenericOp {
+    Wire(OpWire),
+    Prim(OpPrim),
+}
+
+// Instruction of a generic pattern, its ids, types and attributes may have
+// placeholders such as a{i} or i{N}
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct GenericInstr {
+    pub op: GenericOp,
+    pub dst: Io,
+    pub attr: Vec<Index>,
+    pub arg: Io,
+    pub prim: Prim,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+// Pattern generic in its width, instantiated as id_iN
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct GenericPat {
+    pub id: Id,
+    pub param: Param,
+    pub input: Io,
+    pub output: Io,
+    pub body: Vec<Stmt>,
+    #[serde(skip)]
+    pub span: Span,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Default)]
 pub struct Target {
     pub pat: HashMap<Id, Pat>,
+    pub generic: HashMap<Id, Vec<GenericPat>>,
 }
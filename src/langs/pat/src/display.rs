@@ -15,3 +15,49 @@ macro_rules! display {
 display!(Instr);
 display!(Pat);
 display!(Target);
+
+impl fmt::Display for GenericOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenericOp::Wire(op) => write!(f, "{}", op),
+            GenericOp::Prim(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+impl fmt::Display for GenericInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.dst(), self.op())?;
+        if !self.attr().is_empty() {
+            let attr: Vec<String> = self
+                .attr()
+                .iter()
+                .map(|index| match index {
+                    Index::Val(val) => val.to_string(),
+                    _ => format!("{{{}}}", index),
+                })
+                .collect();
+            write!(f, "[{}]", attr.join(", "))?;
+        }
+        write!(f, "{}", self.arg())?;
+        match self.op() {
+            GenericOp::Prim(_) => write!(f, " @{}", self.prim()),
+            GenericOp::Wire(_) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for GenericPat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "pat {}{}{} -> {} {{",
+            self.id(),
+            self.param(),
+            self.input(),
+            self.output()
+        )?;
+        generic::fmt_body(f, self.body(), 4)?;
+        write!(f, "}}")
+    }
+}
@@ -1,5 +1,9 @@
 use crate::ast::*;
+use crate::errors::Error;
+use crate::infer::infer_type_try_from_pat;
 use bincode::{deserialize_from, serialize_into};
+use generic::errors::Error as GenericError;
+use generic::{instance_name, unroll, width_from_instance, Env};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
@@ -42,6 +46,18 @@ impl Instr {
             Instr::Wire(instr) => instr.set_arg(arg),
         }
     }
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Instr::Prim(instr) => instr.span(),
+            Instr::Wire(instr) => instr.span(),
+        }
+    }
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            Instr::Prim(instr) => instr.set_span(span),
+            Instr::Wire(instr) => instr.set_span(span),
+        }
+    }
 }
 
 impl Pat {
@@ -69,6 +85,119 @@ impl Pat {
     pub fn body_mut(&mut self) -> &mut Vec<Instr> {
         &mut self.body
     }
+    // name of the source file in the spans of sig and body
+    pub fn set_file(&mut self, file: &str) {
+        self.sig.span.set_file(file);
+        for instr in self.body.iter_mut() {
+            if let Some(mut span) = instr.span().cloned() {
+                span.set_file(file);
+                instr.set_span(span);
+            }
+        }
+    }
+}
+
+impl GenericInstr {
+    pub fn op(&self) -> &GenericOp {
+        &self.op
+    }
+    pub fn dst(&self) -> &Io {
+        &self.dst
+    }
+    pub fn attr(&self) -> &Vec<Index> {
+        &self.attr
+    }
+    pub fn arg(&self) -> &Io {
+        &self.arg
+    }
+    pub fn prim(&self) -> &Prim {
+        &self.prim
+    }
+    // concrete instruction with the values of the width and repeat variables
+    pub fn eval(&self, env: &Env) -> Result<Instr, Error> {
+        let attr = if self.attr.is_empty() {
+            Expr::default()
+        } else {
+            let mut tup = ExprTup::default();
+            for index in self.attr.iter() {
+                tup.add_term(ExprTerm::Val(index.eval(env)?));
+            }
+            Expr::from(tup)
+        };
+        let dst = self.dst.eval(env)?;
+        let arg = self.arg.eval(env)?;
+        let mut instr = match &self.op {
+            GenericOp::Wire(op) => Instr::from(InstrWire {
+                op: op.clone(),
+                dst,
+                attr,
+                arg,
+                span: Span::default(),
+            }),
+            GenericOp::Prim(op) => Instr::from(InstrPrim {
+                op: op.clone(),
+                dst,
+                attr,
+                arg,
+                prim: self.prim.clone(),
+                span: Span::default(),
+            }),
+        };
+        instr.set_span(self.span.clone());
+        Ok(instr)
+    }
+}
+
+fn set_body_file(body: &mut [Stmt], file: &str) {
+    for stmt in body.iter_mut() {
+        match stmt {
+            Stmt::Instr(instr) => instr.span.set_file(file),
+            Stmt::Repeat(_, _, inner) => set_body_file(inner, file),
+        }
+    }
+}
+
+impl GenericPat {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+    pub fn param(&self) -> &Param {
+        &self.param
+    }
+    pub fn input(&self) -> &Io {
+        &self.input
+    }
+    pub fn output(&self) -> &Io {
+        &self.output
+    }
+    pub fn body(&self) -> &Vec<Stmt> {
+        &self.body
+    }
+    pub fn is_within(&self, width: u64) -> bool {
+        self.param.is_within(width)
+    }
+    pub fn set_file(&mut self, file: &str) {
+        self.span.set_file(file);
+        set_body_file(&mut self.body, file);
+    }
+    // concrete pat named id_iN at a width
+    pub fn instance(&self, width: u64) -> Result<Pat, Error> {
+        if !self.is_within(width) {
+            let msg = format!("{} is not defined for width {}", self.id, width);
+            return Err(Error::from(GenericError::new_generic_error(&msg)));
+        }
+        let env = self.param.env(width);
+        let sig = Sig {
+            id: instance_name(&self.id, width),
+            input: self.input.eval(&env)?,
+            output: self.output.eval(&env)?,
+            span: self.span.clone(),
+        };
+        let body = unroll(&self.body, &env, &mut |instr: &GenericInstr, env: &Env| {
+            instr.eval(env)
+        })?;
+        Ok(infer_type_try_from_pat(&Pat { sig, body }))
+    }
 }
 
 impl Target {
@@ -96,5 +225,43 @@ impl Target {
     }
     pub fn extend(&mut self, target: Target) {
         self.pat.extend(target.pat().clone());
+        for (_, generic) in target.generic {
+            for g in generic {
+                self.add_generic(g);
+            }
+        }
+    }
+    pub fn generic(&self) -> &HashMap<Id, Vec<GenericPat>> {
+        &self.generic
+    }
+    pub fn add_generic(&mut self, generic: GenericPat) {
+        self.generic
+            .entry(generic.id().clone())
+            .or_default()
+            .push(generic);
+    }
+    // a concrete pat, or the instance of a generic one for names like add_i12
+    pub fn instance(&self, name: &str) -> Result<Option<Pat>, Error> {
+        if let Some(pat) = self.get(name) {
+            return Ok(Some(pat.clone()));
+        }
+        for (id, generic) in self.generic.iter() {
+            if let Some(width) = width_from_instance(id, name) {
+                if let Some(g) = generic.iter().find(|g| g.is_within(width)) {
+                    return Ok(Some(g.instance(width)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+    pub fn set_file(&mut self, file: &str) {
+        for (_, pat) in self.pat.iter_mut() {
+            pat.set_file(file);
+        }
+        for (_, generic) in self.generic.iter_mut() {
+            for g in generic.iter_mut() {
+                g.set_file(file);
+            }
+        }
     }
 }
@@ -26,7 +26,7 @@ fn build_env(pat: &Pat) -> HashMap<String, Ty> {
     env
 }
 
-fn infer_type_try_from_pat(pat: &Pat) -> Pat {
+pub fn infer_type_try_from_pat(pat: &Pat) -> Pat {
     let env = build_env(pat);
     let mut pat = pat.clone();
     // solve instr arg types with environment
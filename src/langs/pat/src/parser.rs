@@ -1,6 +1,7 @@
 use crate::ast::*;
 use crate::errors::Error;
 use crate::infer::infer_type_try_from_target;
+use generic::{IndexOp, Name, Part, Range, Term};
 use io::read_to_string;
 use pest_consume::match_nodes;
 use pest_consume::Error as PestError;
@@ -123,7 +124,8 @@ impl Parser {
     }
 
     fn instr(input: Node) -> ParseResult<Instr> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut instr = match_nodes!(
             input.into_children();
             [io(dst), op_prim(op), io(arg)] => Instr::from(InstrPrim {
                 op,
@@ -131,6 +133,7 @@ impl Parser {
                 attr: Expr::default(),
                 arg,
                 prim: Prim::Any,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), tup_val(attr), io(arg)] => Instr::from(InstrPrim {
                 op,
@@ -138,6 +141,7 @@ impl Parser {
                 attr: Expr::from(attr),
                 arg,
                 prim: Prim::Any,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), io(arg), prim(prim)] => Instr::from(InstrPrim {
                 op,
@@ -145,6 +149,7 @@ impl Parser {
                 attr: Expr::default(),
                 arg,
                 prim,
+                span: Span::default(),
             }),
             [io(dst), op_prim(op), tup_val(attr), io(arg), prim(prim)] => Instr::from(InstrPrim {
                 op,
@@ -152,26 +157,32 @@ impl Parser {
                 attr: Expr::from(attr),
                 arg,
                 prim,
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), tup_val(attr)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::default(),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_wire(op), tup_val(attr), io(arg)] => Instr::from(InstrWire {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg,
+                span: Span::default(),
             }),
-        ))
+        );
+        instr.set_span(span);
+        Ok(instr)
     }
 
     fn body(input: Node) -> ParseResult<Vec<Instr>> {
@@ -182,24 +193,30 @@ impl Parser {
     }
 
     fn sig(input: Node) -> ParseResult<Sig> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut sig = match_nodes!(
             input.into_children();
             [id(id)] => Sig {
                 id,
                 input: Expr::default(),
                 output: Expr::default(),
+                span: Span::default(),
             },
             [id(id), io(output)] => Sig {
                 id,
                 input: Expr::default(),
                 output,
+                span: Span::default(),
             },
             [id(id), io(input), io(output)] => Sig {
                 id,
                 input,
                 output,
+                span: Span::default(),
             },
-        ))
+        );
+        sig.set_span(span);
+        Ok(sig)
     }
 
     fn pat(input: Node) -> ParseResult<Pat> {
@@ -216,20 +233,247 @@ impl Parser {
         ))
     }
 
-    fn target(input: Node) -> ParseResult<Target> {
+    fn index_var(input: Node) -> ParseResult<Id> {
+        Ok(input.as_str().to_string())
+    }
+
+    fn index_val(input: Node) -> ParseResult<i64> {
+        input.as_str().parse::<i64>().map_err(|e| input.error(e))
+    }
+
+    fn index_op(input: Node) -> ParseResult<IndexOp> {
+        match input.as_str() {
+            "+" => Ok(IndexOp::Add),
+            "-" => Ok(IndexOp::Sub),
+            "*" => Ok(IndexOp::Mul),
+            "/" => Ok(IndexOp::Div),
+            "%" => Ok(IndexOp::Rem),
+            op => Err(input.error(format!("{} is not an index op", op))),
+        }
+    }
+
+    fn index(input: Node) -> ParseResult<Index> {
+        let mut operand: Vec<Index> = Vec::new();
+        let mut op: Vec<IndexOp> = Vec::new();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::index_op => op.push(Parser::index_op(node)?),
+                Rule::index_val => operand.push(Index::Val(Parser::index_val(node)?)),
+                Rule::index_var => operand.push(Index::Var(Parser::index_var(node)?)),
+                _ => operand.push(Parser::index(node)?),
+            }
+        }
+        Ok(Index::from_ops(operand, op))
+    }
+
+    fn range_incl(_input: Node) -> ParseResult<bool> {
+        Ok(true)
+    }
+
+    fn range_excl(_input: Node) -> ParseResult<bool> {
+        Ok(false)
+    }
+
+    fn range(input: Node) -> ParseResult<Range> {
         Ok(match_nodes!(
             input.into_children();
-            [pat(pat)..] => {
-                let mut target = Target::default();
-                let pats: Vec<Pat> = pat.collect();
-                for p in pats {
-                    target.insert(&p.id(), p.clone());
-                }
-                target
+            [index(lo), range_incl(inclusive), index(hi)] => Range { lo, hi, inclusive },
+            [index(lo), range_excl(inclusive), index(hi)] => Range { lo, hi, inclusive },
+        ))
+    }
+
+    fn placeholder(input: Node) -> ParseResult<Index> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index(index)] => index,
+        ))
+    }
+
+    fn name_str(input: Node) -> ParseResult<String> {
+        Ok(input.as_str().to_string())
+    }
+
+    fn name(input: Node) -> ParseResult<Name> {
+        let mut part: Vec<Part> = Vec::new();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::name_str => part.push(Part::Str(Parser::name_str(node)?)),
+                _ => part.push(Part::Index(Parser::placeholder(node)?)),
             }
+        }
+        Ok(Name { part })
+    }
+
+    fn list(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [name_str(id), range(range)] => Term::List(id, range),
+        ))
+    }
+
+    fn gvar(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [name(id), name(ty)] => Term::Var(id, Some(ty)),
+            [name(id)] => Term::Var(id, None),
+        ))
+    }
+
+    fn gterm(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [list(term)] => term,
+            [gvar(term)] => term,
         ))
     }
 
+    fn gtup(input: Node) -> ParseResult<Vec<Term>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gterm(terms)..] => terms.collect(),
+        ))
+    }
+
+    fn gio(input: Node) -> ParseResult<Io> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gterm(term)] => Io::Term(term),
+            [gtup(tup)] => Io::Tup(tup),
+        ))
+    }
+
+    fn gval(input: Node) -> ParseResult<Index> {
+        let node = input.clone();
+        match_nodes!(
+            input.into_children();
+            [val(val)] => val.get_val().map(Index::Val).map_err(|e| node.error(e)),
+            [placeholder(index)] => Ok(index),
+        )
+    }
+
+    fn gtup_val(input: Node) -> ParseResult<Vec<Index>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gval(vals)..] => vals.collect(),
+        ))
+    }
+
+    fn gop(input: Node) -> ParseResult<GenericOp> {
+        Ok(match_nodes!(
+            input.into_children();
+            [op_prim(op)] => GenericOp::Prim(op),
+            [op_wire(op)] => GenericOp::Wire(op),
+        ))
+    }
+
+    fn ginstr(input: Node) -> ParseResult<GenericInstr> {
+        let span = Span::from(input.as_span());
+        let mut io: Vec<Io> = Vec::new();
+        let mut op: Option<GenericOp> = None;
+        let mut attr: Vec<Index> = Vec::new();
+        let mut prim = Prim::Any;
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::gio => io.push(Parser::gio(node)?),
+                Rule::gop => op = Some(Parser::gop(node)?),
+                Rule::gtup_val => attr = Parser::gtup_val(node)?,
+                _ => prim = Parser::prim(node)?,
+            }
+        }
+        let mut io = io.into_iter();
+        match (op, io.next()) {
+            (Some(op), Some(dst)) => Ok(GenericInstr {
+                op,
+                dst,
+                attr,
+                arg: io.next().unwrap_or_else(|| Io::Tup(Vec::new())),
+                prim,
+                span,
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    fn repeat(input: Node) -> ParseResult<Stmt> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index_var(var), range(range), gstmt(body)..] => Stmt::Repeat(var, range, body.collect()),
+        ))
+    }
+
+    fn gstmt(input: Node) -> ParseResult<Stmt> {
+        Ok(match_nodes!(
+            input.into_children();
+            [repeat(stmt)] => stmt,
+            [ginstr(instr)] => Stmt::Instr(instr),
+        ))
+    }
+
+    fn param_lo(input: Node) -> ParseResult<u64> {
+        input.as_str().parse::<u64>().map_err(|e| input.error(e))
+    }
+
+    // the largest width, hi is exclusive unless it comes after =
+    fn param_hi(input: Node) -> ParseResult<u64> {
+        let hi = input.as_str();
+        if hi.starts_with('=') {
+            hi[1..].parse::<u64>().map_err(|e| input.error(e))
+        } else {
+            match hi.parse::<u64>() {
+                Ok(hi) if hi > 0 => Ok(hi - 1),
+                _ => Err(input.error(format!("{} is not a width bound", hi))),
+            }
+        }
+    }
+
+    fn param(input: Node) -> ParseResult<Param> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index_var(id)] => Param { id, lo: 1, hi: None },
+            [index_var(id), param_lo(lo)] => Param { id, lo, hi: None },
+            [index_var(id), param_lo(lo), param_hi(hi)] => Param { id, lo, hi: Some(hi) },
+        ))
+    }
+
+    fn gsig(input: Node) -> ParseResult<GenericPat> {
+        let span = Span::from(input.as_span());
+        Ok(match_nodes!(
+            input.into_children();
+            [id(id), param(param), gio(input), gio(output)] => GenericPat {
+                id,
+                param,
+                input,
+                output,
+                body: Vec::new(),
+                span,
+            },
+        ))
+    }
+
+    fn generic(input: Node) -> ParseResult<GenericPat> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gsig(generic), gstmt(body)..] => GenericPat {
+                body: body.collect(),
+                ..generic
+            },
+        ))
+    }
+
+    fn target(input: Node) -> ParseResult<Target> {
+        let mut target = Target::default();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::generic => target.add_generic(Parser::generic(node)?),
+                _ => {
+                    let pat = Parser::pat(node)?;
+                    target.insert(&pat.id(), pat);
+                }
+            }
+        }
+        Ok(target)
+    }
+
     fn file(input: Node) -> ParseResult<Target> {
         Ok(match_nodes!(
             input.into_children();
@@ -243,10 +487,18 @@ impl Parser {
         let inputs = Parser::parse(Rule::file, input_str)?;
         let input = inputs.single()?;
         let target = Parser::file(input)?;
-        Ok(infer_type_try_from_target(&target))
+        let mut res = infer_type_try_from_target(&target);
+        for generic in target.generic().values() {
+            for g in generic {
+                res.add_generic(g.clone());
+            }
+        }
+        Ok(res)
     }
     pub fn parse_from_file<P: AsRef<Path>>(path: P) -> Result<Target, Error> {
-        let content = read_to_string(path);
-        Parser::parse_from_str(&content)
+        let content = read_to_string(&path);
+        let mut res = Parser::parse_from_str(&content)?;
+        res.set_file(&path.as_ref().to_string_lossy());
+        Ok(res)
     }
 }
@@ -26,11 +26,17 @@ impl PrettyPrint for Pat {
 
 impl PrettyPrint for Target {
     fn to_doc(&self) -> RcDoc<()> {
+        let generic = self
+            .generic()
+            .iter()
+            .sorted_by_key(|(id, _)| (*id).clone())
+            .flat_map(|(_, g)| g.iter().map(RcDoc::as_string));
         intersperse(
             self.pat()
                 .iter()
                 .sorted_by_key(|(id, _)| (*id).clone())
-                .map(|(_, pat)| pat.to_doc()),
+                .map(|(_, pat)| pat.to_doc())
+                .chain(generic),
             RcDoc::hardline(),
         )
     }
@@ -12,3 +12,19 @@ fn simple_target() {
     exp.insert(&pat.id(), pat);
     assert_eq!(res, exp);
 }
+
+#[test]
+fn generic_target() {
+    let res = Parser::parse_from_str(
+        "pat ladd<N>(a:i{N}, b:i{N}) -> (y:i{N}) {
+            y:i{N} = add(a, b) @lut;
+        }",
+    )
+    .unwrap();
+    let pat = res.instance("ladd_i12").unwrap().unwrap();
+    assert_eq!(pat.id(), "ladd_i12");
+    assert_eq!(pat.output().get_ty(0).unwrap().width(), Some(12));
+    assert!(res.instance("lsub_i12").unwrap().is_none());
+    let again = Parser::parse_from_str(&res.to_string()).unwrap();
+    assert_eq!(again.instance("ladd_i12").unwrap().unwrap(), pat);
+}
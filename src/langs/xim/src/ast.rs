@@ -23,6 +23,21 @@ pub type Loc = xir::Loc;
 pub type InstrMach = xir::InstrMach;
 pub type InstrBasc = xir::InstrBasc;
 pub type Instr = xir::Instr;
+pub type Index = generic::Index;
+pub type Name = generic::Name;
+pub type Io = generic::Io;
+pub type Param = generic::Param;
+pub type Stmt = generic::Stmt<GenericInstr>;
+pub type Span = xir::Span;
+
+// Narrower implementations a wide one is legalized onto, either bit by bit
+// or chaining a carry from one slice to the next
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Split {
+    None,
+    Bitwise(Vec<Id>),
+    Chain(Vec<Id>),
+}
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
 pub struct Sig {
@@ -31,6 +46,9 @@ pub struct Sig {
     pub output: Expr,
     pub area: u64,
     pub perf: u64,
+    pub split: Split,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash, Default)]
@@ -39,7 +57,51 @@ pub struct Imp {
     pub body: Vec<Instr>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum GenericOp {
+    Basc(OpBasc),
+    Mach(OpMach),
+}
+
+// Location with a bel such as {i}6, where a leading placeholder is a bel
+// letter, so {i}6 is the i-th LUT6 of a slice
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct GenericLoc {
+    pub bel: Name,
+    pub x: ExprCoord,
+    pub y: ExprCoord,
+}
+
+// Instruction of a generic implementation, its ids, types, attributes and
+// bels may have placeholders such as a{i} or i{N}
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct GenericInstr {
+    pub op: GenericOp,
+    pub dst: Io,
+    pub attr: Vec<Index>,
+    pub arg: Io,
+    pub loc: Option<GenericLoc>,
+    #[serde(skip)]
+    pub span: Span,
+}
+
+// Implementation generic in its width, instantiated as id_iN
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct GenericImp {
+    pub id: Id,
+    pub param: Param,
+    pub area: Index,
+    pub perf: Index,
+    pub input: Io,
+    pub output: Io,
+    pub split: Split,
+    pub body: Vec<Stmt>,
+    #[serde(skip)]
+    pub span: Span,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Default)]
 pub struct Target {
     pub imp: HashMap<Id, Imp>,
+    pub generic: HashMap<Id, Vec<GenericImp>>,
 }
@@ -8,6 +8,14 @@ impl Default for Sig {
             output: Expr::Tup(ExprTup::default()),
             area: 0,
             perf: 0,
+            split: Split::default(),
+            span: Span::default(),
         }
     }
 }
+
+impl Default for Split {
+    fn default() -> Self {
+        Split::None
+    }
+}
@@ -12,6 +12,66 @@ macro_rules! display {
     };
 }
 
+display!(Split);
 display!(Sig);
 display!(Imp);
 display!(Target);
+
+// attribute and cost values, with braces around placeholders
+fn index_val(index: &Index) -> String {
+    match index {
+        Index::Val(val) => val.to_string(),
+        _ => format!("{{{}}}", index),
+    }
+}
+
+impl fmt::Display for GenericOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenericOp::Basc(op) => write!(f, "{}", op),
+            GenericOp::Mach(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+impl fmt::Display for GenericLoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({}, {})", self.bel(), self.x(), self.y())
+    }
+}
+
+impl fmt::Display for GenericInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.dst(), self.op())?;
+        if !self.attr().is_empty() {
+            let attr: Vec<String> = self.attr().iter().map(index_val).collect();
+            write!(f, "[{}]", attr.join(", "))?;
+        }
+        write!(f, "{}", self.arg())?;
+        match self.loc() {
+            Some(loc) => write!(f, " @{}", loc),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for GenericImp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "imp {}{}[{}, {}]{} -> {}",
+            self.id(),
+            self.param(),
+            index_val(self.area()),
+            index_val(self.perf()),
+            self.input(),
+            self.output()
+        )?;
+        if !self.split().is_none() {
+            write!(f, " {}", self.split())?;
+        }
+        writeln!(f, " {{")?;
+        generic::fmt_body(f, self.body(), 4)?;
+        write!(f, "}}")
+    }
+}
@@ -1,4 +1,5 @@
 use crate::parser;
+use generic::errors::Error as GenericError;
 use std::fmt;
 use std::num::ParseIntError;
 use std::num::TryFromIntError;
@@ -9,6 +10,7 @@ pub enum Error {
     Type(String),
     TryFromInt(TryFromIntError),
     ParseInt(ParseIntError),
+    Generic(GenericError),
 }
 
 impl From<pest_consume::Error<parser::Rule>> for Error {
@@ -29,6 +31,12 @@ impl From<ParseIntError> for Error {
     }
 }
 
+impl From<GenericError> for Error {
+    fn from(e: GenericError) -> Self {
+        Error::Generic(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -36,6 +44,7 @@ impl fmt::Display for Error {
             Error::ParseInt(msg) => write!(f, "{}", msg),
             Error::Type(msg) => write!(f, "{}", msg),
             Error::TryFromInt(msg) => write!(f, "{}", msg),
+            Error::Generic(msg) => write!(f, "{}", msg),
         }
     }
 }
@@ -1,9 +1,32 @@
 use crate::ast::*;
+use crate::errors::Error;
+use crate::infer::infer_type_try_from_imp;
 use bincode::{deserialize_from, serialize_into};
+use generic::errors::Error as GenericError;
+use generic::{instance_name, unroll, width_from_instance, Env, Part};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
+use std::str::FromStr;
+
+impl Split {
+    pub fn is_none(&self) -> bool {
+        matches!(self, Split::None)
+    }
+    pub fn is_chain(&self) -> bool {
+        matches!(self, Split::Chain(_))
+    }
+    // slice implementations, empty when not split
+    pub fn slice(&self) -> &[Id] {
+        match self {
+            Split::None => &[],
+            Split::Bitwise(slice) => slice,
+            Split::Chain(slice) => slice,
+        }
+    }
+}
 
 impl Sig {
     pub fn id(&self) -> String {
@@ -21,6 +44,9 @@ impl Sig {
     pub fn perf(&self) -> u64 {
         self.perf
     }
+    pub fn split(&self) -> &Split {
+        &self.split
+    }
     pub fn set_id(&mut self, id: &str) {
         self.id = id.to_string();
     }
@@ -30,6 +56,15 @@ impl Sig {
     pub fn set_perf(&mut self, perf: u64) {
         self.perf = perf;
     }
+    pub fn set_split(&mut self, split: Split) {
+        self.split = split;
+    }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl Imp {
@@ -45,6 +80,9 @@ impl Imp {
     pub fn perf(&self) -> u64 {
         self.sig.perf
     }
+    pub fn split(&self) -> &Split {
+        self.sig.split()
+    }
     pub fn input(&self) -> &Expr {
         self.sig.input()
     }
@@ -63,6 +101,189 @@ impl Imp {
     pub fn body_mut(&mut self) -> &mut Vec<Instr> {
         &mut self.body
     }
+    // name of the source file in the spans of sig and body
+    pub fn set_file(&mut self, file: &str) {
+        self.sig.span.set_file(file);
+        for instr in self.body.iter_mut() {
+            if let Some(mut span) = instr.span().cloned() {
+                span.set_file(file);
+                instr.set_span(span);
+            }
+        }
+    }
+}
+
+impl GenericLoc {
+    pub fn bel(&self) -> &Name {
+        &self.bel
+    }
+    pub fn x(&self) -> &ExprCoord {
+        &self.x
+    }
+    pub fn y(&self) -> &ExprCoord {
+        &self.y
+    }
+    // concrete location of an instruction, the bel kind follows the op
+    pub fn eval(&self, env: &Env, op: &OpMach) -> Result<Loc, Error> {
+        let mut name = String::new();
+        for (i, part) in self.bel.part().iter().enumerate() {
+            match part {
+                Part::Str(s) => name.push_str(s),
+                Part::Index(index) if i == 0 => {
+                    let val = index.eval(env)?;
+                    if val < 0 || val >= 26 {
+                        let msg = format!("{} is not a bel letter", val);
+                        return Err(Error::from(GenericError::new_generic_error(&msg)));
+                    }
+                    name.push((b'a' + val as u8) as char);
+                }
+                Part::Index(index) => name.push_str(&index.eval(env)?.to_string()),
+            }
+        }
+        let bel = match op {
+            OpMach::Lut1
+            | OpMach::Lut2
+            | OpMach::Lut3
+            | OpMach::Lut4
+            | OpMach::Lut5
+            | OpMach::Lut6 => BelLut::from_str(&name).map(Bel::from),
+            OpMach::Fdre | OpMach::Fdse => BelReg::from_str(&name).map(Bel::from),
+            OpMach::CarryAdd | OpMach::CarrySub => BelCarry::from_str(&name).map(Bel::from),
+            OpMach::Lram | OpMach::Lrom => BelLum::from_str(&name).map(Bel::from),
+            OpMach::Bram | OpMach::Brom => BelBlock::from_str(&name).map(Bel::from),
+            _ => BelDsp::from_str(&name).map(Bel::from),
+        };
+        match bel {
+            Ok(bel) => Ok(Loc {
+                bel,
+                x: self.x.clone(),
+                y: self.y.clone(),
+            }),
+            Err(_) => {
+                let msg = format!("{} is not a valid bel for {}", name, op);
+                Err(Error::from(GenericError::new_generic_error(&msg)))
+            }
+        }
+    }
+}
+
+impl GenericInstr {
+    pub fn op(&self) -> &GenericOp {
+        &self.op
+    }
+    pub fn dst(&self) -> &Io {
+        &self.dst
+    }
+    pub fn attr(&self) -> &Vec<Index> {
+        &self.attr
+    }
+    pub fn arg(&self) -> &Io {
+        &self.arg
+    }
+    pub fn loc(&self) -> Option<&GenericLoc> {
+        self.loc.as_ref()
+    }
+    // concrete instruction with the values of the width and repeat variables
+    pub fn eval(&self, env: &Env) -> Result<Instr, Error> {
+        let attr = if self.attr.is_empty() {
+            Expr::default()
+        } else {
+            let mut tup = ExprTup::default();
+            for index in self.attr.iter() {
+                tup.add_term(ExprTerm::Val(index.eval(env)?));
+            }
+            Expr::from(tup)
+        };
+        let dst = self.dst.eval(env)?;
+        let arg = self.arg.eval(env)?;
+        let mut instr = match &self.op {
+            GenericOp::Basc(op) => Instr::from(InstrBasc {
+                op: op.clone(),
+                dst,
+                attr,
+                arg,
+                span: Span::default(),
+            }),
+            GenericOp::Mach(op) => Instr::from(InstrMach {
+                op: op.clone(),
+                attr,
+                dst,
+                arg,
+                loc: match &self.loc {
+                    Some(loc) => Some(loc.eval(env, op)?),
+                    None => None,
+                },
+                mem: None,
+                span: Span::default(),
+            }),
+        };
+        instr.set_span(self.span.clone());
+        Ok(instr)
+    }
+}
+
+fn set_body_file(body: &mut [Stmt], file: &str) {
+    for stmt in body.iter_mut() {
+        match stmt {
+            Stmt::Instr(instr) => instr.span.set_file(file),
+            Stmt::Repeat(_, _, inner) => set_body_file(inner, file),
+        }
+    }
+}
+
+impl GenericImp {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+    pub fn param(&self) -> &Param {
+        &self.param
+    }
+    pub fn area(&self) -> &Index {
+        &self.area
+    }
+    pub fn perf(&self) -> &Index {
+        &self.perf
+    }
+    pub fn input(&self) -> &Io {
+        &self.input
+    }
+    pub fn output(&self) -> &Io {
+        &self.output
+    }
+    pub fn split(&self) -> &Split {
+        &self.split
+    }
+    pub fn body(&self) -> &Vec<Stmt> {
+        &self.body
+    }
+    pub fn is_within(&self, width: u64) -> bool {
+        self.param.is_within(width)
+    }
+    pub fn set_file(&mut self, file: &str) {
+        self.span.set_file(file);
+        set_body_file(&mut self.body, file);
+    }
+    // concrete imp named id_iN at a width
+    pub fn instance(&self, width: u64) -> Result<Imp, Error> {
+        if !self.is_within(width) {
+            let msg = format!("{} is not defined for width {}", self.id, width);
+            return Err(Error::from(GenericError::new_generic_error(&msg)));
+        }
+        let env = self.param.env(width);
+        let sig = Sig {
+            id: instance_name(&self.id, width),
+            input: self.input.eval(&env)?,
+            output: self.output.eval(&env)?,
+            area: u64::try_from(self.area.eval(&env)?)?,
+            perf: u64::try_from(self.perf.eval(&env)?)?,
+            split: self.split.clone(),
+            span: self.span.clone(),
+        };
+        let body = unroll(&self.body, &env, &mut |instr: &GenericInstr, env: &Env| {
+            instr.eval(env)
+        })?;
+        Ok(infer_type_try_from_imp(&Imp { sig, body }))
+    }
 }
 
 impl Target {
@@ -90,5 +311,43 @@ impl Target {
     }
     pub fn extend(&mut self, target: Target) {
         self.imp.extend(target.imp().clone());
+        for (_, generic) in target.generic {
+            for g in generic {
+                self.add_generic(g);
+            }
+        }
+    }
+    pub fn generic(&self) -> &HashMap<Id, Vec<GenericImp>> {
+        &self.generic
+    }
+    pub fn add_generic(&mut self, generic: GenericImp) {
+        self.generic
+            .entry(generic.id().clone())
+            .or_default()
+            .push(generic);
+    }
+    // a concrete imp, or the instance of a generic one for names like add_i12
+    pub fn instance(&self, name: &str) -> Result<Option<Imp>, Error> {
+        if let Some(imp) = self.get(name) {
+            return Ok(Some(imp.clone()));
+        }
+        for (id, generic) in self.generic.iter() {
+            if let Some(width) = width_from_instance(id, name) {
+                if let Some(g) = generic.iter().find(|g| g.is_within(width)) {
+                    return Ok(Some(g.instance(width)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+    pub fn set_file(&mut self, file: &str) {
+        for (_, imp) in self.imp.iter_mut() {
+            imp.set_file(file);
+        }
+        for (_, generic) in self.generic.iter_mut() {
+            for g in generic.iter_mut() {
+                g.set_file(file);
+            }
+        }
     }
 }
@@ -26,7 +26,7 @@ fn build_env(imp: &Imp) -> HashMap<String, Ty> {
     env
 }
 
-fn infer_type_try_from_imp(imp: &Imp) -> Imp {
+pub fn infer_type_try_from_imp(imp: &Imp) -> Imp {
     let env = build_env(imp);
     let mut imp = imp.clone();
     // solve instr arg types with environment
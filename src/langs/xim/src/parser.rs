@@ -1,6 +1,7 @@
 use crate::ast::*;
 use crate::errors::Error;
 use crate::infer::infer_type_try_from_target;
+use generic::{IndexOp, Name, Part, Range, Term};
 use io::read_to_string;
 use pest_consume::match_nodes;
 use pest_consume::Error as PestError;
@@ -312,6 +313,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_block(op), io(arg), loc_block(loc)] => Instr::from(InstrMach {
                 op,
@@ -320,6 +322,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -334,6 +337,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_dsp(op), io(arg), loc_dsp(loc)] => Instr::from(InstrMach {
                 op,
@@ -342,6 +346,25 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
+            }),
+            [io(dst), op_dsp(op), tup_val(attr), io(arg)] => Instr::from(InstrMach {
+                op,
+                attr: Expr::from(attr),
+                dst,
+                arg,
+                loc: None,
+                mem: None,
+                span: Span::default(),
+            }),
+            [io(dst), op_dsp(op), tup_val(attr), io(arg), loc_dsp(loc)] => Instr::from(InstrMach {
+                op,
+                attr: Expr::from(attr),
+                dst,
+                arg,
+                loc: Some(loc),
+                mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -356,6 +379,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_reg(op), io(arg), loc_reg(loc)] => Instr::from(InstrMach {
                 op,
@@ -364,6 +388,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -378,6 +403,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_carry(op), io(arg), loc_carry(loc)] => Instr::from(InstrMach {
                 op,
@@ -386,6 +412,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -400,6 +427,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), io(arg), loc_lut(loc)] => Instr::from(InstrMach {
                 op,
@@ -408,6 +436,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), tup_val(attr), io(arg)] => Instr::from(InstrMach {
                 op,
@@ -416,6 +445,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), tup_val(attr), io(arg), loc_lut(loc)] => Instr::from(InstrMach {
                 op,
@@ -424,6 +454,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -438,6 +469,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lum(op), io(arg), loc_lum(loc)] => Instr::from(InstrMach {
                 op,
@@ -446,6 +478,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -458,24 +491,28 @@ impl Parser {
                 dst,
                 attr: Expr::from(attr),
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_basc(op), io(arg)] => Instr::from(InstrBasc {
                 op,
                 dst,
                 attr: Expr::default(),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_basc(op), tup_val(attr), io(arg)] => Instr::from(InstrBasc {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg,
+                span: Span::default(),
             })
         ))
     }
 
     fn instr(input: Node) -> ParseResult<Instr> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut instr = match_nodes!(
             input.into_children();
             [instr_basc(instr)] => instr,
             [instr_reg(instr)] => instr,
@@ -484,7 +521,9 @@ impl Parser {
             [instr_block(instr)] => instr,
             [instr_lut(instr)] => instr,
             [instr_lum(instr)] => instr,
-        ))
+        );
+        instr.set_span(span);
+        Ok(instr)
     }
 
     fn body(input: Node) -> ParseResult<Vec<Instr>> {
@@ -494,8 +533,31 @@ impl Parser {
         ))
     }
 
-    fn sig(input: Node) -> ParseResult<Sig> {
+    fn split_bitwise(input: Node) -> ParseResult<Split> {
+        Ok(match_nodes!(
+            input.into_children();
+            [id(ids)..] => Split::Bitwise(ids.collect()),
+        ))
+    }
+
+    fn split_chain(input: Node) -> ParseResult<Split> {
+        Ok(match_nodes!(
+            input.into_children();
+            [id(ids)..] => Split::Chain(ids.collect()),
+        ))
+    }
+
+    fn split(input: Node) -> ParseResult<Split> {
         Ok(match_nodes!(
+            input.into_children();
+            [split_bitwise(split)] => split,
+            [split_chain(split)] => split,
+        ))
+    }
+
+    fn sig(input: Node) -> ParseResult<Sig> {
+        let span = Span::from(input.as_span());
+        let mut sig = match_nodes!(
             input.into_children();
             [id(id), cost(area), cost(perf)] => Sig {
                 id,
@@ -503,6 +565,17 @@ impl Parser {
                 output: Expr::default(),
                 area,
                 perf,
+                split: Split::None,
+                span: Span::default(),
+            },
+            [id(id), cost(area), cost(perf), split(split)] => Sig {
+                id,
+                input: Expr::default(),
+                output: Expr::default(),
+                area,
+                perf,
+                split,
+                span: Span::default(),
             },
             [id(id), cost(area), cost(perf), io(output)] => Sig {
                 id,
@@ -510,6 +583,17 @@ impl Parser {
                 output,
                 area,
                 perf,
+                split: Split::None,
+                span: Span::default(),
+            },
+            [id(id), cost(area), cost(perf), io(output), split(split)] => Sig {
+                id,
+                input: Expr::default(),
+                output,
+                area,
+                perf,
+                split,
+                span: Span::default(),
             },
             [id(id), cost(area), cost(perf), io(input), io(output)] => Sig {
                 id,
@@ -517,8 +601,21 @@ impl Parser {
                 output,
                 area,
                 perf,
+                split: Split::None,
+                span: Span::default(),
             },
-        ))
+            [id(id), cost(area), cost(perf), io(input), io(output), split(split)] => Sig {
+                id,
+                input,
+                output,
+                area,
+                perf,
+                split,
+                span: Span::default(),
+            },
+        );
+        sig.set_span(span);
+        Ok(sig)
     }
 
     fn imp(input: Node) -> ParseResult<Imp> {
@@ -535,20 +632,281 @@ impl Parser {
         ))
     }
 
-    fn target(input: Node) -> ParseResult<Target> {
+    fn index_var(input: Node) -> ParseResult<Id> {
+        Ok(input.as_str().to_string())
+    }
+
+    fn index_val(input: Node) -> ParseResult<i64> {
+        input.as_str().parse::<i64>().map_err(|e| input.error(e))
+    }
+
+    fn index_op(input: Node) -> ParseResult<IndexOp> {
+        match input.as_str() {
+            "+" => Ok(IndexOp::Add),
+            "-" => Ok(IndexOp::Sub),
+            "*" => Ok(IndexOp::Mul),
+            "/" => Ok(IndexOp::Div),
+            "%" => Ok(IndexOp::Rem),
+            op => Err(input.error(format!("{} is not an index op", op))),
+        }
+    }
+
+    fn index(input: Node) -> ParseResult<Index> {
+        let mut operand: Vec<Index> = Vec::new();
+        let mut op: Vec<IndexOp> = Vec::new();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::index_op => op.push(Parser::index_op(node)?),
+                Rule::index_val => operand.push(Index::Val(Parser::index_val(node)?)),
+                Rule::index_var => operand.push(Index::Var(Parser::index_var(node)?)),
+                _ => operand.push(Parser::index(node)?),
+            }
+        }
+        Ok(Index::from_ops(operand, op))
+    }
+
+    fn range_incl(_input: Node) -> ParseResult<bool> {
+        Ok(true)
+    }
+
+    fn range_excl(_input: Node) -> ParseResult<bool> {
+        Ok(false)
+    }
+
+    fn range(input: Node) -> ParseResult<Range> {
         Ok(match_nodes!(
             input.into_children();
-            [imp(imp)..] => {
-                let mut target = Target::default();
-                let imps: Vec<Imp> = imp.collect();
-                for i in imps {
-                    target.insert(&i.id(), i.clone());
-                }
-                target
+            [index(lo), range_incl(inclusive), index(hi)] => Range { lo, hi, inclusive },
+            [index(lo), range_excl(inclusive), index(hi)] => Range { lo, hi, inclusive },
+        ))
+    }
+
+    fn placeholder(input: Node) -> ParseResult<Index> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index(index)] => index,
+        ))
+    }
+
+    fn name_str(input: Node) -> ParseResult<String> {
+        Ok(input.as_str().to_string())
+    }
+
+    fn name(input: Node) -> ParseResult<Name> {
+        let mut part: Vec<Part> = Vec::new();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::name_str => part.push(Part::Str(Parser::name_str(node)?)),
+                _ => part.push(Part::Index(Parser::placeholder(node)?)),
+            }
+        }
+        Ok(Name { part })
+    }
+
+    fn list(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [name_str(id), range(range)] => Term::List(id, range),
+        ))
+    }
+
+    fn gvar(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [name(id), name(ty)] => Term::Var(id, Some(ty)),
+            [name(id)] => Term::Var(id, None),
+        ))
+    }
+
+    fn gterm(input: Node) -> ParseResult<Term> {
+        Ok(match_nodes!(
+            input.into_children();
+            [list(term)] => term,
+            [gvar(term)] => term,
+        ))
+    }
+
+    fn gtup(input: Node) -> ParseResult<Vec<Term>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gterm(terms)..] => terms.collect(),
+        ))
+    }
+
+    fn gio(input: Node) -> ParseResult<Io> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gterm(term)] => Io::Term(term),
+            [gtup(tup)] => Io::Tup(tup),
+        ))
+    }
+
+    fn gval(input: Node) -> ParseResult<Index> {
+        let node = input.clone();
+        match_nodes!(
+            input.into_children();
+            [val(val)] => val.get_val().map(Index::Val).map_err(|e| node.error(e)),
+            [placeholder(index)] => Ok(index),
+        )
+    }
+
+    fn gtup_val(input: Node) -> ParseResult<Vec<Index>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gval(vals)..] => vals.collect(),
+        ))
+    }
+
+    fn gop(input: Node) -> ParseResult<GenericOp> {
+        Ok(match_nodes!(
+            input.into_children();
+            [op_basc(op)] => GenericOp::Basc(op),
+            [op_lut(op)] => GenericOp::Mach(op),
+            [op_lum(op)] => GenericOp::Mach(op),
+            [op_carry(op)] => GenericOp::Mach(op),
+            [op_reg(op)] => GenericOp::Mach(op),
+            [op_block(op)] => GenericOp::Mach(op),
+            [op_dsp(op)] => GenericOp::Mach(op),
+        ))
+    }
+
+    fn gloc(input: Node) -> ParseResult<GenericLoc> {
+        Ok(match_nodes!(
+            input.into_children();
+            [name(bel), expr_coord(x), expr_coord(y)] => GenericLoc { bel, x, y },
+        ))
+    }
+
+    fn ginstr(input: Node) -> ParseResult<GenericInstr> {
+        let span = Span::from(input.as_span());
+        let mut io: Vec<Io> = Vec::new();
+        let mut op: Option<GenericOp> = None;
+        let mut attr: Vec<Index> = Vec::new();
+        let mut loc: Option<GenericLoc> = None;
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::gio => io.push(Parser::gio(node)?),
+                Rule::gop => op = Some(Parser::gop(node)?),
+                Rule::gtup_val => attr = Parser::gtup_val(node)?,
+                _ => loc = Some(Parser::gloc(node)?),
             }
+        }
+        let mut io = io.into_iter();
+        match (op, io.next()) {
+            (Some(op), Some(dst)) => Ok(GenericInstr {
+                op,
+                dst,
+                attr,
+                arg: io.next().unwrap_or_else(|| Io::Tup(Vec::new())),
+                loc,
+                span,
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    fn repeat(input: Node) -> ParseResult<Stmt> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index_var(var), range(range), gstmt(body)..] => Stmt::Repeat(var, range, body.collect()),
+        ))
+    }
+
+    fn gstmt(input: Node) -> ParseResult<Stmt> {
+        Ok(match_nodes!(
+            input.into_children();
+            [repeat(stmt)] => stmt,
+            [ginstr(instr)] => Stmt::Instr(instr),
         ))
     }
 
+    fn param_lo(input: Node) -> ParseResult<u64> {
+        input.as_str().parse::<u64>().map_err(|e| input.error(e))
+    }
+
+    // the largest width, hi is exclusive unless it comes after =
+    fn param_hi(input: Node) -> ParseResult<u64> {
+        let hi = input.as_str();
+        if hi.starts_with('=') {
+            hi[1..].parse::<u64>().map_err(|e| input.error(e))
+        } else {
+            match hi.parse::<u64>() {
+                Ok(hi) if hi > 0 => Ok(hi - 1),
+                _ => Err(input.error(format!("{} is not a width bound", hi))),
+            }
+        }
+    }
+
+    fn param(input: Node) -> ParseResult<Param> {
+        Ok(match_nodes!(
+            input.into_children();
+            [index_var(id)] => Param { id, lo: 1, hi: None },
+            [index_var(id), param_lo(lo)] => Param { id, lo, hi: None },
+            [index_var(id), param_lo(lo), param_hi(hi)] => Param { id, lo, hi: Some(hi) },
+        ))
+    }
+
+    fn gcost(input: Node) -> ParseResult<Index> {
+        Ok(match_nodes!(
+            input.into_children();
+            [cost(cost)] => Index::Val(cost as i64),
+            [placeholder(index)] => index,
+        ))
+    }
+
+    fn gsig(input: Node) -> ParseResult<GenericImp> {
+        let span = Span::from(input.as_span());
+        Ok(match_nodes!(
+            input.into_children();
+            [id(id), param(param), gcost(area), gcost(perf), gio(input), gio(output)] => GenericImp {
+                id,
+                param,
+                area,
+                perf,
+                input,
+                output,
+                split: Split::None,
+                body: Vec::new(),
+                span,
+            },
+            [id(id), param(param), gcost(area), gcost(perf), gio(input), gio(output), split(split)] => GenericImp {
+                id,
+                param,
+                area,
+                perf,
+                input,
+                output,
+                split,
+                body: Vec::new(),
+                span,
+            },
+        ))
+    }
+
+    fn generic(input: Node) -> ParseResult<GenericImp> {
+        Ok(match_nodes!(
+            input.into_children();
+            [gsig(generic), gstmt(body)..] => GenericImp {
+                body: body.collect(),
+                ..generic
+            },
+        ))
+    }
+
+    fn target(input: Node) -> ParseResult<Target> {
+        let mut target = Target::default();
+        for node in input.into_children() {
+            match node.as_rule() {
+                Rule::generic => target.add_generic(Parser::generic(node)?),
+                _ => {
+                    let imp = Parser::imp(node)?;
+                    target.insert(&imp.id(), imp);
+                }
+            }
+        }
+        Ok(target)
+    }
+
     fn file(input: Node) -> ParseResult<Target> {
         Ok(match_nodes!(
             input.into_children();
@@ -562,10 +920,18 @@ impl Parser {
         let inputs = Parser::parse(Rule::file, input_str)?;
         let input = inputs.single()?;
         let target = Parser::file(input)?;
-        Ok(infer_type_try_from_target(&target))
+        let mut res = infer_type_try_from_target(&target);
+        for generic in target.generic().values() {
+            for g in generic {
+                res.add_generic(g.clone());
+            }
+        }
+        Ok(res)
     }
     pub fn parse_from_file<P: AsRef<Path>>(path: P) -> Result<Target, Error> {
-        let content = read_to_string(path);
-        Parser::parse_from_str(&content)
+        let content = read_to_string(&path);
+        let mut res = Parser::parse_from_str(&content)?;
+        res.set_file(&path.as_ref().to_string_lossy());
+        Ok(res)
     }
 }
@@ -2,6 +2,21 @@ use crate::ast::*;
 use itertools::Itertools;
 use prettyprint::{block_with_braces, intersperse, PrettyHelper, PrettyPrint, RcDoc};
 
+impl PrettyPrint for Split {
+    fn to_doc(&self) -> RcDoc<()> {
+        let name = match self {
+            Split::None => return RcDoc::nil(),
+            Split::Bitwise(_) => "bitwise",
+            Split::Chain(_) => "chain",
+        };
+        let slice = intersperse(
+            self.slice().iter().map(RcDoc::as_string),
+            RcDoc::text(",").append(RcDoc::space()),
+        );
+        RcDoc::text(name).append(slice.parens())
+    }
+}
+
 impl PrettyPrint for Sig {
     fn to_doc(&self) -> RcDoc<()> {
         let attr_val = vec![RcDoc::as_string(self.area()), RcDoc::as_string(self.perf())];
@@ -10,6 +25,11 @@ impl PrettyPrint for Sig {
             RcDoc::text(",").append(RcDoc::space()),
         )
         .brackets();
+        let split = if self.split().is_none() {
+            RcDoc::nil()
+        } else {
+            RcDoc::space().append(self.split().to_doc())
+        };
         RcDoc::text("imp")
             .append(RcDoc::space())
             .append(RcDoc::as_string(self.id()))
@@ -19,6 +39,7 @@ impl PrettyPrint for Sig {
             .append(RcDoc::text("->"))
             .append(RcDoc::space())
             .append(self.output().to_doc())
+            .append(split)
     }
 }
 
@@ -37,11 +58,17 @@ impl PrettyPrint for Imp {
 
 impl PrettyPrint for Target {
     fn to_doc(&self) -> RcDoc<()> {
+        let generic = self
+            .generic()
+            .iter()
+            .sorted_by_key(|(id, _)| (*id).clone())
+            .flat_map(|(_, g)| g.iter().map(RcDoc::as_string));
         intersperse(
             self.imp()
                 .iter()
                 .sorted_by_key(|(id, _)| (*id).clone())
-                .map(|(_, imp)| imp.to_doc()),
+                .map(|(_, imp)| imp.to_doc())
+                .chain(generic),
             RcDoc::hardline(),
         )
     }
@@ -1,4 +1,4 @@
-use xim::ast::{Imp, Sig, Target};
+use xim::ast::{Imp, Sig, Split, Target};
 use xim::parser::Parser;
 
 #[test]
@@ -14,3 +14,47 @@ fn simple_target() {
     exp.insert(&imp.id(), imp);
     assert_eq!(res, exp);
 }
+
+#[test]
+fn split_target() {
+    let res = Parser::parse_from_str(
+        "imp lxor_i20[3, 2](a:i20, b:i20) -> (y:i20) bitwise(lxor_i8, lxor_i4) {}
+        imp ladd_i16[2, 2](a:i16, b:i16) -> (y:i16) chain(laddc_i8) {}",
+    )
+    .unwrap();
+    let slice = vec!["lxor_i8".to_string(), "lxor_i4".to_string()];
+    assert_eq!(res.get("lxor_i20").unwrap().split(), &Split::Bitwise(slice));
+    assert!(res.get("ladd_i16").unwrap().split().is_chain());
+    let again = Parser::parse_from_str(&res.to_string()).unwrap();
+    assert_eq!(
+        again.get("ladd_i16").unwrap().split(),
+        res.get("ladd_i16").unwrap().split()
+    );
+}
+
+#[test]
+fn generic_target() {
+    let res = Parser::parse_from_str(
+        "imp lxor<N: 1..=8>[1, 2](a:i{N}, b:i{N}) -> (y:i{N}) {
+            repeat i in 0..N {
+                a{i}:bool = ext[{i}](a);
+                b{i}:bool = ext[{i}](b);
+                x{i}:bool = lut2[6](a{i}, b{i}) @{i}6(??, ??);
+            }
+            y:i{N} = cat(x{0..N});
+        }",
+    )
+    .unwrap();
+    let imp = res.instance("lxor_i3").unwrap().unwrap();
+    assert_eq!(imp.body().len(), 10);
+    assert_eq!(imp.input().get_ty(0).unwrap().width(), Some(3));
+    assert!(res.instance("lxor_i9").unwrap().is_none());
+    let again = Parser::parse_from_str(&res.to_string()).unwrap();
+    assert_eq!(again.instance("lxor_i3").unwrap().unwrap(), imp);
+}
+
+#[test]
+fn coord_target() {
+    let imp = "imp lnot_bool[1, 2](a:bool) -> (y:bool) { y:bool = lut1[1](a) @a6(x, y%2); }";
+    assert!(Parser::parse_from_str(imp).is_err());
+}
@@ -9,6 +9,7 @@ pub type OpCoord = asm::OpCoord;
 pub type ExprTerm = ir::ExprTerm;
 pub type ExprTup = ir::ExprTup;
 pub type Expr = ir::Expr;
+pub type Span = ir::Span;
 pub type ExprCoord = asm::ExprCoord;
 pub type Sig = ir::Sig;
 pub type Mem = mmap::Mem;
@@ -36,6 +37,9 @@ pub enum OpMach {
     CarryAdd,
     CarrySub,
     VecAddRegA,
+    VecAddRegACi,
+    VecAddRegACo,
+    VecAddRegACio,
     VecAdd,
     VecSub,
     VecMul,
@@ -136,6 +140,8 @@ pub struct InstrBasc {
     pub attr: Expr,
     pub dst: Expr,
     pub arg: Expr,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
@@ -146,6 +152,8 @@ pub struct InstrMach {
     pub arg: Expr,
     pub loc: Option<Loc>,
     pub mem: Option<Mem>,
+    #[serde(skip)]
+    pub span: Span,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
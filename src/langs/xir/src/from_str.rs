@@ -33,6 +33,9 @@ impl FromStr for OpMach {
             "carryadd" => Ok(OpMach::CarryAdd),
             "carrysub" => Ok(OpMach::CarrySub),
             "vaddrega" => Ok(OpMach::VecAddRegA),
+            "vaddregaci" => Ok(OpMach::VecAddRegACi),
+            "vaddregaco" => Ok(OpMach::VecAddRegACo),
+            "vaddregacio" => Ok(OpMach::VecAddRegACio),
             "vadd" => Ok(OpMach::VecAdd),
             "vsub" => Ok(OpMach::VecSub),
             "vmul" => Ok(OpMach::VecMul),
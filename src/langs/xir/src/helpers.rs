@@ -1,10 +1,14 @@
 use crate::ast::*;
+use crate::errors::Error;
 
 impl OpMach {
     pub fn is_dsp(&self) -> bool {
         matches!(
             self,
             OpMach::VecAddRegA
+                | OpMach::VecAddRegACi
+                | OpMach::VecAddRegACo
+                | OpMach::VecAddRegACio
                 | OpMach::VecAdd
                 | OpMach::VecSub
                 | OpMach::VecMul
@@ -16,6 +20,92 @@ impl OpMach {
                 | OpMach::MulAddRegACio
         )
     }
+    // argument driven by the cascade output of the dsp right below
+    pub fn cascade_arg(&self) -> Option<usize> {
+        match self {
+            OpMach::VecAddRegACi | OpMach::VecAddRegACio => Some(1),
+            OpMach::MulAddRegACi | OpMach::MulAddRegACio => Some(2),
+            _ => None,
+        }
+    }
+    // the cascade input skips the registers the argument goes through
+    // otherwise, so a cascade takes fewer cycles than the plain instructions
+    pub fn is_cascade_unregistered(&self) -> bool {
+        matches!(self, OpMach::VecAddRegACi | OpMach::VecAddRegACio)
+    }
+    pub fn is_cascade_out(&self) -> bool {
+        matches!(
+            self,
+            OpMach::VecAddRegACo
+                | OpMach::VecAddRegACio
+                | OpMach::MulAddRegACo
+                | OpMach::MulAddRegACio
+        )
+    }
+    // number of inputs of a lut
+    pub fn lut_inputs(&self) -> Option<usize> {
+        match self {
+            OpMach::Lut1 => Some(1),
+            OpMach::Lut2 => Some(2),
+            OpMach::Lut3 => Some(3),
+            OpMach::Lut4 => Some(4),
+            OpMach::Lut5 => Some(5),
+            OpMach::Lut6 => Some(6),
+            _ => None,
+        }
+    }
+    pub fn lut(inputs: usize) -> Option<OpMach> {
+        match inputs {
+            1 => Some(OpMach::Lut1),
+            2 => Some(OpMach::Lut2),
+            3 => Some(OpMach::Lut3),
+            4 => Some(OpMach::Lut4),
+            5 => Some(OpMach::Lut5),
+            6 => Some(OpMach::Lut6),
+            _ => None,
+        }
+    }
+}
+
+// The 5 and 6 bels of a letter are the two outputs of the same lut
+impl BelLut {
+    pub fn is_lut5(&self) -> bool {
+        matches!(
+            self,
+            BelLut::A5
+                | BelLut::B5
+                | BelLut::C5
+                | BelLut::D5
+                | BelLut::E5
+                | BelLut::F5
+                | BelLut::G5
+                | BelLut::H5
+        )
+    }
+    pub fn lut5(&self) -> BelLut {
+        match self {
+            BelLut::A5 | BelLut::A6 => BelLut::A5,
+            BelLut::B5 | BelLut::B6 => BelLut::B5,
+            BelLut::C5 | BelLut::C6 => BelLut::C5,
+            BelLut::D5 | BelLut::D6 => BelLut::D5,
+            BelLut::E5 | BelLut::E6 => BelLut::E5,
+            BelLut::F5 | BelLut::F6 => BelLut::F5,
+            BelLut::G5 | BelLut::G6 => BelLut::G5,
+            BelLut::H5 | BelLut::H6 => BelLut::H5,
+        }
+    }
+    pub fn lut6(&self) -> BelLut {
+        match self {
+            BelLut::A5 | BelLut::A6 => BelLut::A6,
+            BelLut::B5 | BelLut::B6 => BelLut::B6,
+            BelLut::C5 | BelLut::C6 => BelLut::C6,
+            BelLut::D5 | BelLut::D6 => BelLut::D6,
+            BelLut::E5 | BelLut::E6 => BelLut::E6,
+            BelLut::F5 | BelLut::F6 => BelLut::F6,
+            BelLut::G5 | BelLut::G6 => BelLut::G6,
+            BelLut::H5 | BelLut::H6 => BelLut::H6,
+        }
+    }
 }
 
 impl Loc {
@@ -61,6 +151,12 @@ impl InstrBasc {
     pub fn set_arg(&mut self, arg: Expr) {
         self.arg = arg;
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl InstrMach {
@@ -94,6 +190,29 @@ impl InstrMach {
     pub fn set_mem(&mut self, mem: Mem) {
         self.mem = Some(mem);
     }
+    pub fn span(&self) -> Option<&Span> {
+        self.span.known()
+    }
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+    // registers in the a, b, m and p stages of a dsp, one per stage when
+    // the instruction has no attributes
+    pub fn dsp_reg(&self) -> Result<Vec<i64>, Error> {
+        let attr: Vec<ExprTerm> = self.attr().clone().into();
+        if attr.is_empty() {
+            Ok(vec![1; 4])
+        } else {
+            attr.iter()
+                .map(|t| {
+                    t.get_val().map_err(|_| {
+                        let err = format!("{} register stage {} is not a literal", self.op(), t);
+                        Error::new_conv_error(&err)
+                    })
+                })
+                .collect()
+        }
+    }
 }
 
 impl Instr {
@@ -133,6 +252,18 @@ impl Instr {
             Instr::Mach(instr) => instr.set_arg(arg),
         }
     }
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Instr::Basc(instr) => instr.span(),
+            Instr::Mach(instr) => instr.span(),
+        }
+    }
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            Instr::Basc(instr) => instr.set_span(span),
+            Instr::Mach(instr) => instr.set_span(span),
+        }
+    }
 }
 
 impl Prog {
@@ -154,4 +285,14 @@ impl Prog {
     pub fn body_mut(&mut self) -> &mut Vec<Instr> {
         &mut self.body
     }
+    // name of the source file in the spans of sig and body
+    pub fn set_file(&mut self, file: &str) {
+        self.sig.span.set_file(file);
+        for instr in self.body.iter_mut() {
+            if let Some(mut span) = instr.span().cloned() {
+                span.set_file(file);
+                instr.set_span(span);
+            }
+        }
+    }
 }
@@ -304,6 +304,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_block(op), io(arg), loc_block(loc)] => Instr::from(InstrMach {
                 op,
@@ -312,6 +313,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -326,6 +328,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_dsp(op), io(arg), loc_dsp(loc)] => Instr::from(InstrMach {
                 op,
@@ -334,6 +337,25 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
+            }),
+            [io(dst), op_dsp(op), tup_val(attr), io(arg)] => Instr::from(InstrMach {
+                op,
+                attr: Expr::from(attr),
+                dst,
+                arg,
+                loc: None,
+                mem: None,
+                span: Span::default(),
+            }),
+            [io(dst), op_dsp(op), tup_val(attr), io(arg), loc_dsp(loc)] => Instr::from(InstrMach {
+                op,
+                attr: Expr::from(attr),
+                dst,
+                arg,
+                loc: Some(loc),
+                mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -348,6 +370,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_reg(op), io(arg), loc_reg(loc)] => Instr::from(InstrMach {
                 op,
@@ -356,6 +379,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -370,6 +394,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_carry(op), io(arg), loc_carry(loc)] => Instr::from(InstrMach {
                 op,
@@ -378,6 +403,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -392,6 +418,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), io(arg), loc_lut(loc)] => Instr::from(InstrMach {
                 op,
@@ -400,6 +427,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), tup_val(attr), io(arg)] => Instr::from(InstrMach {
                 op,
@@ -408,6 +436,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lut(op), tup_val(attr), io(arg), loc_lut(loc)] => Instr::from(InstrMach {
                 op,
@@ -416,6 +445,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -430,6 +460,7 @@ impl Parser {
                 arg,
                 loc: None,
                 mem: None,
+                span: Span::default(),
             }),
             [io(dst), op_lum(op), io(arg), loc_lum(loc)] => Instr::from(InstrMach {
                 op,
@@ -438,6 +469,7 @@ impl Parser {
                 arg,
                 loc: Some(loc),
                 mem: None,
+                span: Span::default(),
             })
         ))
     }
@@ -450,24 +482,28 @@ impl Parser {
                 dst,
                 attr: Expr::from(attr),
                 arg: Expr::default(),
+                span: Span::default(),
             }),
             [io(dst), op_basc(op), io(arg)] => Instr::from(InstrBasc {
                 op,
                 dst,
                 attr: Expr::default(),
                 arg,
+                span: Span::default(),
             }),
             [io(dst), op_basc(op), tup_val(attr), io(arg)] => Instr::from(InstrBasc {
                 op,
                 dst,
                 attr: Expr::from(attr),
                 arg,
+                span: Span::default(),
             })
         ))
     }
 
     fn instr(input: Node) -> ParseResult<Instr> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut instr = match_nodes!(
             input.into_children();
             [instr_basc(instr)] => instr,
             [instr_reg(instr)] => instr,
@@ -476,7 +512,9 @@ impl Parser {
             [instr_block(instr)] => instr,
             [instr_lut(instr)] => instr,
             [instr_lum(instr)] => instr,
-        ))
+        );
+        instr.set_span(span);
+        Ok(instr)
     }
 
     fn body(input: Node) -> ParseResult<Vec<Instr>> {
@@ -487,24 +525,30 @@ impl Parser {
     }
 
     fn sig(input: Node) -> ParseResult<Sig> {
-        Ok(match_nodes!(
+        let span = Span::from(input.as_span());
+        let mut sig = match_nodes!(
             input.into_children();
             [id(id)] => Sig {
                 id,
                 input: Expr::default(),
                 output: Expr::default(),
+                span: Span::default(),
             },
             [id(id), io(output)] => Sig {
                 id,
                 input: Expr::default(),
                 output,
+                span: Span::default(),
             },
             [id(id), io(input), io(output)] => Sig {
                 id,
                 input,
                 output,
+                span: Span::default(),
             },
-        ))
+        );
+        sig.set_span(span);
+        Ok(sig)
     }
 
     fn prog(input: Node) -> ParseResult<Prog> {
@@ -537,7 +581,9 @@ impl Parser {
         Ok(infer_type_try_from_prog(&prog))
     }
     pub fn parse_from_file<P: AsRef<Path>>(path: P) -> Result<Prog, Error> {
-        let content = read_to_string(path);
-        Parser::parse_from_str(&content)
+        let content = read_to_string(&path);
+        let mut res = Parser::parse_from_str(&content)?;
+        res.set_file(&path.as_ref().to_string_lossy());
+        Ok(res)
     }
 }
@@ -28,6 +28,9 @@ impl PrettyPrint for OpMach {
             OpMach::CarryAdd => RcDoc::text("carryadd"),
             OpMach::CarrySub => RcDoc::text("carrysub"),
             OpMach::VecAddRegA => RcDoc::text("vaddrega"),
+            OpMach::VecAddRegACi => RcDoc::text("vaddregaci"),
+            OpMach::VecAddRegACo => RcDoc::text("vaddregaco"),
+            OpMach::VecAddRegACio => RcDoc::text("vaddregacio"),
             OpMach::VecAdd => RcDoc::text("vadd"),
             OpMach::VecSub => RcDoc::text("vsub"),
             OpMach::VecMul => RcDoc::text("vmul"),
@@ -8,3 +8,11 @@ fn simple_prog() {
     exp.set_id("main");
     assert_eq!(res, exp);
 }
+
+#[test]
+fn coord_prog() {
+    let prog = "def main (a:bool) -> (y:bool) { y:bool = lut1[1](a) @a6(x, y+1); }";
+    assert!(Parser::parse_from_str(prog).is_ok());
+    let prog = "def main (a:bool) -> (y:bool) { y:bool = lut1[1](a) @a6(x*2, y); }";
+    assert!(Parser::parse_from_str(prog).is_err());
+}
@@ -1,6 +1,7 @@
 use asm::errors::Error as AsmError;
 use ir::errors::Error as IrError;
 use std::fmt;
+use xim::errors::Error as XimError;
 use xir::errors::Error as XirError;
 
 #[derive(Debug)]
@@ -8,6 +9,7 @@ pub enum Error {
     Ir(IrError),
     Asm(AsmError),
     Xir(XirError),
+    Xim(XimError),
     Bler(String),
 }
 
@@ -29,6 +31,12 @@ impl From<XirError> for Error {
     }
 }
 
+impl From<XimError> for Error {
+    fn from(e: XimError) -> Self {
+        Error::Xim(e)
+    }
+}
+
 impl From<IrError> for Error {
     fn from(e: IrError) -> Self {
         Error::Ir(e)
@@ -41,6 +49,7 @@ impl fmt::Display for Error {
             Error::Ir(msg) => write!(f, "{}", msg),
             Error::Asm(msg) => write!(f, "{}", msg),
             Error::Xir(msg) => write!(f, "{}", msg),
+            Error::Xim(msg) => write!(f, "{}", msg),
             Error::Bler(msg) => write!(f, "{}", msg),
         }
     }
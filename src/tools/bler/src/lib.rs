@@ -1,6 +1,7 @@
 pub mod errors;
 
 use crate::errors::Error;
+use ::ir::span::diagnostic;
 use asm::ast as asm;
 use mmap::Mmap;
 use std::collections::HashMap;
@@ -77,8 +78,9 @@ impl Assembler {
     pub fn get_var(&self, key: &str) -> Option<&String> {
         self.map.get(key)
     }
-    pub fn get_target_imp(&self, key: &str) -> Option<&xim::Imp> {
-        self.target.get(key)
+    // generic implementations are instantiated on demand
+    pub fn get_target_imp(&self, key: &str) -> Result<Option<xim::Imp>, Error> {
+        Ok(self.target.instance(key)?)
     }
     pub fn set_target(&mut self, target: xim::Target) {
         self.target = target;
@@ -150,18 +152,23 @@ impl Assembler {
             attr: xir::Expr::default(),
             dst,
             arg,
+            span: xir::Span::default(),
         };
         self.add_instr(xir::Instr::from(instr));
         Ok(())
     }
     pub fn expand_instr_cat(&mut self, instr: &asm::InstrWire) -> Result<(), Error> {
-        let arg = self.rename_expr(instr.arg())?;
+        // asm concatenates most significant first and xir least significant first
+        let mut term: Vec<xir::ExprTerm> = self.rename_expr(instr.arg())?.into();
+        term.reverse();
+        let arg = xir::Expr::from(xir::ExprTup { term });
         let dst = self.rename_expr(instr.dst())?;
         let instr = xir::InstrBasc {
             op: xir::OpBasc::Cat,
             attr: xir::Expr::default(),
             dst,
             arg,
+            span: xir::Span::default(),
         };
         self.add_instr(xir::Instr::from(instr));
         Ok(())
@@ -175,6 +182,7 @@ impl Assembler {
             attr,
             dst,
             arg,
+            span: xir::Span::default(),
         };
         self.add_instr(xir::Instr::from(instr));
         Ok(())
@@ -186,7 +194,8 @@ impl Assembler {
         let mut arg_tup = xir::ExprTup::default();
         if let Some(width) = dst_term.width() {
             for i in 0..width {
-                let lsb = value >> i;
+                // constants wider than the value are sign extended
+                let lsb = value >> i.min(63);
                 let mask = lsb & 1;
                 let op = if mask == 1 {
                     xir::OpBasc::Vcc
@@ -212,6 +221,7 @@ impl Assembler {
                     attr: xir::Expr::default(),
                     dst,
                     arg: xir::Expr::default(),
+                    span: xir::Span::default(),
                 };
                 self.add_instr(xir::Instr::from(instr_basc));
             }
@@ -230,6 +240,7 @@ impl Assembler {
                     attr: xir::Expr::default(),
                     dst,
                     arg,
+                    span: xir::Span::default(),
                 };
                 self.add_instr(xir::Instr::from(cat));
             }
@@ -239,10 +250,10 @@ impl Assembler {
     pub fn expand_instr_asm(&mut self, input: &asm::InstrAsm) -> Result<(), Error> {
         let op = input.op().to_string();
         let instr = self.rename_instr_asm(input)?;
-        if let Some(imp) = self.get_target_imp(&op) {
-            let mut scope = scope_from_expr(&imp.output(), instr.dst());
-            scope.extend(scope_from_expr(&imp.input(), instr.arg()));
-            for i in imp.clone().body() {
+        if let Some(imp) = self.get_target_imp(&op)? {
+            let mut scope = scope_from_expr(imp.output(), instr.dst());
+            scope.extend(scope_from_expr(imp.input(), instr.arg()));
+            for i in imp.body() {
                 let arg: Vec<xir::ExprTerm> = i.arg().clone().into();
                 let mut arg_tup = xir::ExprTup::default();
                 for a in arg {
@@ -277,7 +288,7 @@ impl Assembler {
                 match i {
                     xir::Instr::Mach(mach) => {
                         if let Some(loc) = mach.loc() {
-                            if *mach.op() == xir::OpMach::Brom {
+                            if *mach.op() == xir::OpMach::Brom || *mach.op() == xir::OpMach::Lrom {
                                 let old = input.dst().get_id(0)?;
                                 let new = dst_expr.get_id(0)?;
                                 self.replace_mem(&old, &new);
@@ -325,14 +336,22 @@ pub fn deserialize_target() -> xim::Target {
 pub fn try_from_asm_prog(
     input: &asm::Prog,
     mem: Option<&Mmap>,
+) -> Result<(xir::Prog, Option<Mmap>), Error> {
+    try_from_asm_prog_with_target(input, mem, &deserialize_target())
+}
+
+pub fn try_from_asm_prog_with_target(
+    input: &asm::Prog,
+    mem: Option<&Mmap>,
+    target: &xim::Target,
 ) -> Result<(xir::Prog, Option<Mmap>), Error> {
     let mut assembler = Assembler::new(input.sig().clone());
-    let target = deserialize_target();
-    assembler.set_target(target);
+    assembler.set_target(target.clone());
     if let Some(m) = mem {
         assembler.set_mem(m.clone());
     }
     for instr in input.body() {
+        let start = assembler.body().len();
         match instr {
             asm::Instr::Wire(instr) if instr.op() == &asm::OpWire::Con => {
                 assembler.expand_instr_const(instr)?;
@@ -348,10 +367,14 @@ pub fn try_from_asm_prog(
             }
             asm::Instr::Asm(instr) => assembler.expand_instr_asm(instr)?,
             _ => {
-                return Err(Error::new_bler_error(&format!(
-                    "{} instruction not supported",
-                    instr
-                )))
+                let msg = format!("{} instruction not supported", instr);
+                return Err(Error::new_bler_error(&diagnostic(instr.span(), &msg)));
+            }
+        }
+        // expanded instructions keep the location of the asm instruction
+        if let Some(span) = instr.span() {
+            for new in assembler.body[start..].iter_mut() {
+                new.set_span(span.clone());
             }
         }
     }
@@ -2,7 +2,9 @@ pub mod errors;
 
 use crate::errors::Error;
 use ir::ast as ir;
+use ::ir::elab::flat_try_from_prog;
 use itertools::izip;
+use mmap::Mmap;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::convert::TryInto;
@@ -11,6 +13,9 @@ use verilog::ast as vl;
 const CLOCK: &str = "clock";
 const RESET: &str = "reset";
 
+// roms are read through a mux per address bit, so the tree stays small
+const MAX_ROM_ADDR_WIDTH: u64 = 10;
+
 pub fn vec_expr_try_from_term(term: &ir::ExprTerm) -> Result<Vec<vl::Expr>, Error> {
     match term {
         ir::ExprTerm::Any => Ok(vec![vl::Expr::new_ref("")]),
@@ -155,7 +160,96 @@ pub fn vec_decl_try_from_instr(instr: &ir::Instr) -> Result<Vec<vl::Decl>, Error
     }
 }
 
-fn vec_stmt_try_from_instr_prim(instr: &ir::InstrPrim) -> Result<Vec<vl::Stmt>, Error> {
+fn binop_try_from_instr_prim(
+    instr: &ir::InstrPrim,
+    op: fn(vl::Expr, vl::Expr) -> vl::Expr,
+) -> Result<Vec<vl::Stmt>, Error> {
+    let term_y = instr.dst().get_term(0)?;
+    let term_a = instr.arg().get_term(0)?;
+    let term_b = instr.arg().get_term(1)?;
+    let expr_y: Vec<vl::Expr> = vec_expr_try_from_term(term_y)?;
+    let expr_a: Vec<vl::Expr> = vec_expr_try_from_term(term_a)?;
+    let expr_b: Vec<vl::Expr> = vec_expr_try_from_term(term_b)?;
+    let mut stmt: Vec<vl::Stmt> = Vec::new();
+    for (y, a, b) in izip!(expr_y, expr_a, expr_b) {
+        stmt.push(vl::Stmt::from(vl::Parallel::Assign(y, op(a, b))));
+    }
+    Ok(stmt)
+}
+
+fn mask(width: u64) -> u128 {
+    if width >= 128 {
+        u128::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+fn ulit_from_bits(width: u64, bits: u128) -> Result<vl::Expr, Error> {
+    let width = u32::try_from(width)?;
+    Ok(vl::Expr::new_ulit_hex(width, &format!("{:x}", bits)))
+}
+
+// words of a memory, as many bytes per word as its width takes, least
+// significant byte first
+fn rom_words(id: &str, width: u64, depth: usize, mmap: Option<&Mmap>) -> Result<Vec<u128>, Error> {
+    let mut words: Vec<u128> = vec![0; depth];
+    let bytes = ((width + 7) / 8) as usize;
+    if let Some(mem) = mmap.and_then(|m| m.get(id)) {
+        for (index, chunk) in mem.values().chunks(bytes).enumerate() {
+            let addr = mem.offset() as usize + index;
+            if let Some(word) = words.get_mut(addr) {
+                *word = chunk
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, b| (acc << 8) | u128::from(*b));
+            }
+        }
+        Ok(words)
+    } else {
+        let err = format!("missing memory contents for {}", id);
+        Err(Error::new_bline_error(&err))
+    }
+}
+
+// read of words at addr, a mux on the most significant address bit first
+fn rom_tree(addr: &str, bit: i32, words: &[u128], width: u64) -> Result<vl::Expr, Error> {
+    if bit < 0 {
+        ulit_from_bits(width, words[0] & mask(width))
+    } else {
+        let half = 1 << bit;
+        let hi = rom_tree(addr, bit - 1, &words[half..], width)?;
+        let lo = rom_tree(addr, bit - 1, &words[..half], width)?;
+        Ok(vl::Expr::new_mux(
+            vl::Expr::new_index_bit(addr, bit),
+            hi,
+            lo,
+        ))
+    }
+}
+
+fn rom_try_from_instr_prim(instr: &ir::InstrPrim, mmap: Option<&Mmap>) -> Result<vl::Expr, Error> {
+    let term_y = instr.dst().get_term(0)?;
+    let term_a = instr.arg().get_term(0)?;
+    if term_y.is_vector() || term_a.is_vector() {
+        return Err(Error::new_bline_error("vector memories not supported yet"));
+    }
+    let id: vl::Id = term_y.clone().try_into()?;
+    let addr: vl::Id = term_a.clone().try_into()?;
+    let width = term_y.width().unwrap_or(0);
+    let addr_width = term_a.width().unwrap_or(0);
+    if addr_width > MAX_ROM_ADDR_WIDTH {
+        let err = format!("{} address is wider than {} bits", id, MAX_ROM_ADDR_WIDTH);
+        return Err(Error::new_bline_error(&err));
+    }
+    let words = rom_words(&id, width, 1 << addr_width, mmap)?;
+    rom_tree(&addr, addr_width as i32 - 1, &words, width)
+}
+
+fn vec_stmt_try_from_instr_prim(
+    instr: &ir::InstrPrim,
+    mmap: Option<&Mmap>,
+) -> Result<Vec<vl::Stmt>, Error> {
     match instr.op() {
         ir::OpPrim::Reg => {
             let attr: Vec<i32> = instr.attr().clone().try_into()?;
@@ -289,6 +383,41 @@ fn vec_stmt_try_from_instr_prim(instr: &ir::InstrPrim) -> Result<Vec<vl::Stmt>,
                 Err(Error::new_bline_error("mux sel do not have right type"))
             }
         }
+        ir::OpPrim::Xor => binop_try_from_instr_prim(instr, vl::Expr::new_bit_xor),
+        // not is xor with all ones
+        ir::OpPrim::Not => {
+            let term_y = instr.dst().get_term(0)?;
+            let term_a = instr.arg().get_term(0)?;
+            let width = term_y.width().unwrap_or(0);
+            let ones = ulit_from_bits(width, mask(width))?;
+            let expr_y: Vec<vl::Expr> = vec_expr_try_from_term(term_y)?;
+            let expr_a: Vec<vl::Expr> = vec_expr_try_from_term(term_a)?;
+            let mut stmt: Vec<vl::Stmt> = Vec::new();
+            for (y, a) in izip!(expr_y, expr_a) {
+                let not = vl::Expr::new_bit_xor(a, ones.clone());
+                stmt.push(vl::Stmt::from(vl::Parallel::Assign(y, not)));
+            }
+            Ok(stmt)
+        }
+        ir::OpPrim::Rom => {
+            let y = vl::Expr::new_ref(&instr.dst().get_id(0)?);
+            let read = rom_try_from_instr_prim(instr, mmap)?;
+            Ok(vec![vl::Stmt::from(vl::Parallel::Assign(y, read))])
+        }
+        // synchronous read, cleared on reset like a reg
+        ir::OpPrim::Srom => {
+            let y = vl::Expr::new_ref(&instr.dst().get_id(0)?);
+            let read = rom_try_from_instr_prim(instr, mmap)?;
+            let mut always = vl::ParallelProcess::new_always();
+            let s0 = vl::Sequential::new_nonblk_assign(y.clone(), vl::Expr::new_int(0));
+            let s1 = vl::Sequential::new_nonblk_assign(y, read);
+            let mut i0 = vl::SequentialIfElse::new(vl::Expr::new_ref(RESET));
+            i0.add_seq(s0);
+            i0.set_else(s1);
+            always.set_event(vl::Sequential::new_posedge(CLOCK));
+            always.add_seq(i0.into());
+            Ok(vec![vl::Stmt::from(always)])
+        }
         _ => Err(Error::new_bline_error("comp op not implemented yet")),
     }
 }
@@ -328,19 +457,53 @@ fn vec_stmt_try_from_instr_wire(instr: &ir::InstrWire) -> Result<Vec<vl::Stmt>,
             }
             Ok(stmt)
         }
+        ir::OpWire::Ext => {
+            let term_y = instr.dst().get_term(0)?;
+            let term_a = instr.arg().get_term(0)?;
+            if term_y.is_vector() || term_a.is_vector() {
+                return Err(Error::new_bline_error("vector ext not supported yet"));
+            }
+            let attr: Vec<i32> = instr.attr().clone().try_into()?;
+            let (lo, hi) = match attr.as_slice() {
+                [lo, hi] => (*lo, *hi),
+                _ => return Err(Error::new_bline_error("ext instr must have two attrs")),
+            };
+            let y = vl::Expr::new_ref(&instr.dst().get_id(0)?);
+            let a: vl::Id = term_a.clone().try_into()?;
+            let slice = vl::Expr::new_slice(&a, vl::Expr::new_int(hi), vl::Expr::new_int(lo));
+            Ok(vec![vl::Stmt::from(vl::Parallel::Assign(y, slice))])
+        }
+        // the first argument of cat is the most significant one, and the first
+        // expression of a Verilog concatenation the least significant one
+        ir::OpWire::Cat => {
+            let y = vl::Expr::new_ref(&instr.dst().get_id(0)?);
+            let arg: Vec<ir::ExprTerm> = instr.arg().clone().into();
+            let mut cat = vl::ExprConcat::default();
+            for term in arg.iter().rev() {
+                if term.is_vector() {
+                    return Err(Error::new_bline_error("vector cat not supported yet"));
+                }
+                let id: vl::Id = term.clone().try_into()?;
+                cat.add_expr(vl::Expr::new_ref(&id));
+            }
+            Ok(vec![vl::Stmt::from(vl::Parallel::Assign(
+                y,
+                vl::Expr::from(cat),
+            ))])
+        }
         _ => Err(Error::new_bline_error("wire op not implemented yet")),
     }
 }
 
-fn vec_stmt_try_from_instr(instr: &ir::Instr) -> Result<Vec<vl::Stmt>, Error> {
+fn vec_stmt_try_from_instr(instr: &ir::Instr, mmap: Option<&Mmap>) -> Result<Vec<vl::Stmt>, Error> {
     match instr {
-        ir::Instr::Prim(instr) => Ok(vec_stmt_try_from_instr_prim(instr)?),
+        ir::Instr::Prim(instr) => Ok(vec_stmt_try_from_instr_prim(instr, mmap)?),
         ir::Instr::Wire(instr) => Ok(vec_stmt_try_from_instr_wire(instr)?),
         ir::Instr::Call(_) => Err(Error::new_bline_error("call instr not implemented yet")),
     }
 }
 
-pub fn behav_try_from_ir_def(def: &ir::Def) -> Result<vl::Module, Error> {
+pub fn behav_try_from_ir_def(def: &ir::Def, mmap: Option<&Mmap>) -> Result<vl::Module, Error> {
     let id = def.sig().id();
     let mut module = vl::Module::new(&id);
     let input = input_try_from_sig(def.sig())?;
@@ -362,7 +525,7 @@ pub fn behav_try_from_ir_def(def: &ir::Def) -> Result<vl::Module, Error> {
                 }
             }
         }
-        let s: Vec<vl::Stmt> = vec_stmt_try_from_instr(instr)?;
+        let s: Vec<vl::Stmt> = vec_stmt_try_from_instr(instr, mmap)?;
         stmt.extend(s);
     }
     for s in stmt {
@@ -372,8 +535,17 @@ pub fn behav_try_from_ir_def(def: &ir::Def) -> Result<vl::Module, Error> {
 }
 
 pub fn try_from_ir_prog(prog: &ir::Prog) -> Result<vl::Module, Error> {
+    try_from_ir_prog_with_mmap(prog, None)
+}
+
+// memory contents of roms are given by mmap, keyed by the name of the rom
+pub fn try_from_ir_prog_with_mmap(
+    prog: &ir::Prog,
+    mmap: Option<&Mmap>,
+) -> Result<vl::Module, Error> {
+    let prog = flat_try_from_prog(prog)?;
     if let Some(def) = prog.get("main") {
-        Ok(behav_try_from_ir_def(def)?)
+        Ok(behav_try_from_ir_def(def, mmap)?)
     } else {
         Err(Error::new_bline_error("main not found"))
     }
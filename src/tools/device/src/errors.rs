@@ -0,0 +1,25 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(String),
+    Io(String),
+}
+
+impl Error {
+    pub fn new_parse_error(msg: &str) -> Self {
+        Error::Parse(msg.to_string())
+    }
+    pub fn new_io_error(msg: &str) -> Self {
+        Error::Io(msg.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(msg) => write!(f, "{}", msg),
+            Error::Io(msg) => write!(f, "{}", msg),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+pub mod errors;
+
+use crate::errors::Error;
+use ir::ast::Prim;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Site {
+    pub prim: Prim,
+    pub x: u64,
+    pub y: u64,
+    pub raw: String,
+}
+
+// Neighbors of a site, cascades run up from a site to the one above it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// Sites of a part, read from a csv file with x, y, prim and raw columns such
+// as dev/xczu3eg-sbva484.csv, where raw is the vendor site name
+#[derive(Clone, Debug, Default)]
+pub struct Device {
+    pub name: String,
+    pub site: Vec<Site>,
+    pub loc: HashMap<Prim, BTreeMap<(u64, u64), usize>>,
+    pub raw: HashMap<String, usize>,
+}
+
+// lut rams are placed on slices, like luts
+pub fn site_prim(prim: &Prim) -> Prim {
+    match prim {
+        Prim::Lram => Prim::Lut,
+        _ => prim.clone(),
+    }
+}
+
+fn prim_from_str(input: &str) -> Result<Prim, Error> {
+    match input {
+        "LUT" => Ok(Prim::Lut),
+        "DSP" => Ok(Prim::Dsp),
+        "BRAM" => Ok(Prim::Bram),
+        "URAM" => Ok(Prim::Uram),
+        _ => Err(Error::new_parse_error(&format!(
+            "Unsupported site prim {}",
+            input
+        ))),
+    }
+}
+
+impl Site {
+    pub fn prim(&self) -> &Prim {
+        &self.prim
+    }
+    pub fn x(&self) -> u64 {
+        self.x
+    }
+    pub fn y(&self) -> u64 {
+        self.y
+    }
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl Device {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Device, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::new_io_error(&format!("Failed to read device {}: {}", path.display(), e))
+        })?;
+        let mut device = Device::from_str(&content)?;
+        if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+            device.name = name.to_string();
+        }
+        Ok(device)
+    }
+    // part file in a directory, such as dev and xczu3eg-sbva484
+    pub fn from_part<P: AsRef<Path>>(dir: P, part: &str) -> Result<Device, Error> {
+        let mut path = dir.as_ref().join(part);
+        path.set_extension("csv");
+        Device::from_file(path)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn add_site(&mut self, site: Site) -> Result<(), Error> {
+        let index = self.site.len();
+        let loc = self.loc.entry(site.prim.clone()).or_default();
+        if loc.insert((site.x, site.y), index).is_some() {
+            let msg = format!("Site ({}, {}) is listed twice", site.x, site.y);
+            return Err(Error::new_parse_error(&msg));
+        }
+        if !site.raw.is_empty() {
+            self.raw.insert(site.raw.clone(), index);
+        }
+        self.site.push(site);
+        Ok(())
+    }
+    // sites of a prim ordered by x and then y
+    pub fn sites(&self, prim: &Prim) -> Vec<&Site> {
+        match self.loc.get(&site_prim(prim)) {
+            Some(loc) => loc.values().map(|i| &self.site[*i]).collect(),
+            None => Vec::new(),
+        }
+    }
+    pub fn site(&self, prim: &Prim, x: u64, y: u64) -> Option<&Site> {
+        self.loc
+            .get(&site_prim(prim))
+            .and_then(|loc| loc.get(&(x, y)))
+            .map(|i| &self.site[*i])
+    }
+    pub fn contains(&self, prim: &Prim, x: u64, y: u64) -> bool {
+        self.site(prim, x, y).is_some()
+    }
+    // site with a vendor name such as SLICE_X0Y179
+    pub fn site_from_raw(&self, raw: &str) -> Option<&Site> {
+        self.raw.get(raw).map(|i| &self.site[*i])
+    }
+    pub fn raw(&self, prim: &Prim, x: u64, y: u64) -> Option<&str> {
+        self.site(prim, x, y).map(|s| s.raw())
+    }
+    pub fn capacity(&self, prim: &Prim) -> usize {
+        self.loc.get(&site_prim(prim)).map_or(0, |loc| loc.len())
+    }
+    pub fn prims(&self) -> Vec<Prim> {
+        let mut prim: Vec<Prim> = self.loc.keys().cloned().collect();
+        prim.sort_by_key(|p| p.to_string());
+        prim
+    }
+    // x of every column with sites of a prim
+    pub fn columns(&self, prim: &Prim) -> Vec<u64> {
+        let mut x: Vec<u64> = self.sites(prim).iter().map(|s| s.x).collect();
+        x.dedup();
+        x
+    }
+    // y of every row with sites of a prim
+    pub fn rows(&self, prim: &Prim) -> Vec<u64> {
+        let mut y: Vec<u64> = self.sites(prim).iter().map(|s| s.y).collect();
+        y.sort_unstable();
+        y.dedup();
+        y
+    }
+    // lowest and highest y of a column
+    pub fn column_range(&self, prim: &Prim, x: u64) -> Option<RangeInclusive<u64>> {
+        let loc = self.loc.get(&site_prim(prim))?;
+        let mut col = loc.range((x, 0)..=(x, std::u64::MAX)).map(|((_, y), _)| *y);
+        let lo = col.next()?;
+        let hi = col.next_back().unwrap_or(lo);
+        Some(lo..=hi)
+    }
+    pub fn column_ranges(&self, prim: &Prim) -> Vec<(u64, RangeInclusive<u64>)> {
+        self.columns(prim)
+            .into_iter()
+            .filter_map(|x| self.column_range(prim, x).map(|r| (x, r)))
+            .collect()
+    }
+    // largest coordinate of any site
+    pub fn extent(&self) -> (u64, u64) {
+        self.site
+            .iter()
+            .fold((0, 0), |(x, y), s| (x.max(s.x), y.max(s.y)))
+    }
+    // Next site of the same prim in a direction. Up and down are the sites
+    // right above and below, as a cascade needs, while left and right skip
+    // the columns without sites of the prim.
+    pub fn neighbor(&self, prim: &Prim, x: u64, y: u64, dir: Direction) -> Option<&Site> {
+        match dir {
+            Direction::Up => self.site(prim, x, y.checked_add(1)?),
+            Direction::Down => self.site(prim, x, y.checked_sub(1)?),
+            Direction::Left => {
+                let col = self.columns(prim);
+                let x = col.into_iter().rev().find(|c| *c < x)?;
+                self.site(prim, x, y)
+            }
+            Direction::Right => {
+                let col = self.columns(prim);
+                let x = col.into_iter().find(|c| *c > x)?;
+                self.site(prim, x, y)
+            }
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.site.is_empty()
+    }
+}
+
+impl FromStr for Device {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut lines = input.lines().filter(|l| !l.trim().is_empty());
+        let header: Vec<&str> = match lines.next() {
+            Some(line) => line.split(',').map(|c| c.trim()).collect(),
+            None => return Err(Error::new_parse_error("Device file is empty")),
+        };
+        let column = |name: &str| {
+            header.iter().position(|c| *c == name).ok_or_else(|| {
+                Error::new_parse_error(&format!("Device file has no {} column", name))
+            })
+        };
+        let (cx, cy, cprim) = (column("x")?, column("y")?, column("prim")?);
+        let craw = column("raw").ok();
+        let mut device = Device::default();
+        for line in lines {
+            let row: Vec<&str> = line.split(',').map(|c| c.trim()).collect();
+            let field = |index: usize| {
+                row.get(index).cloned().ok_or_else(|| {
+                    Error::new_parse_error(&format!("Missing column in device row: {}", line))
+                })
+            };
+            let parse = |index: usize| -> Result<u64, Error> {
+                field(index)?.parse::<u64>().map_err(|_| {
+                    Error::new_parse_error(&format!("Invalid coordinate in device row: {}", line))
+                })
+            };
+            device.add_site(Site {
+                prim: prim_from_str(field(cprim)?)?,
+                x: parse(cx)?,
+                y: parse(cy)?,
+                raw: match craw {
+                    Some(c) => field(c)?.to_string(),
+                    None => String::new(),
+                },
+            })?;
+        }
+        Ok(device)
+    }
+}
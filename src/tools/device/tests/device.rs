@@ -0,0 +1,67 @@
+use device::errors::Error;
+use device::{Device, Direction};
+use ir::ast::Prim;
+use std::str::FromStr;
+
+fn device() -> Result<Device, Error> {
+    Device::from_part("../../../dev", "xczu3eg-sbva484")
+}
+
+#[test]
+fn capacity() -> Result<(), Error> {
+    let device = device()?;
+    assert_eq!(device.name(), "xczu3eg-sbva484");
+    assert_eq!(device.capacity(&Prim::Lut), 5220);
+    assert_eq!(device.capacity(&Prim::Lram), 5220);
+    assert_eq!(device.capacity(&Prim::Dsp), 360);
+    assert_eq!(device.capacity(&Prim::Bram), 0);
+    assert_eq!(device.prims(), vec![Prim::Dsp, Prim::Lut]);
+    Ok(())
+}
+
+#[test]
+fn raw() -> Result<(), Error> {
+    let device = device()?;
+    assert_eq!(device.raw(&Prim::Lut, 0, 179), Some("SLICE_X0Y179"));
+    let site = device.site_from_raw("DSP48E2_X1Y3").unwrap();
+    assert_eq!((site.prim(), site.x(), site.y()), (&Prim::Dsp, 1, 3));
+    assert!(device.site_from_raw("RAMB36_X0Y0").is_none());
+    Ok(())
+}
+
+#[test]
+fn columns() -> Result<(), Error> {
+    let device = device()?;
+    assert_eq!(device.columns(&Prim::Dsp), vec![0, 1, 2, 3, 4]);
+    assert_eq!(device.columns(&Prim::Lut)[..4], [0, 2, 3, 6]);
+    assert_eq!(device.rows(&Prim::Dsp).len(), 72);
+    assert_eq!(device.column_range(&Prim::Dsp, 0), Some(0..=71));
+    assert_eq!(device.column_range(&Prim::Lut, 1), None);
+    assert_eq!(device.column_ranges(&Prim::Dsp).len(), 5);
+    Ok(())
+}
+
+#[test]
+fn neighbor() -> Result<(), Error> {
+    let device = device()?;
+    let up = device.neighbor(&Prim::Dsp, 0, 70, Direction::Up).unwrap();
+    assert_eq!(up.raw(), "DSP48E2_X0Y71");
+    assert!(device.neighbor(&Prim::Dsp, 0, 71, Direction::Up).is_none());
+    assert!(device.neighbor(&Prim::Dsp, 0, 0, Direction::Down).is_none());
+    let right = device.neighbor(&Prim::Lut, 0, 5, Direction::Right).unwrap();
+    assert_eq!((right.x(), right.y()), (2, 5));
+    assert!(device.neighbor(&Prim::Lut, 0, 5, Direction::Left).is_none());
+    Ok(())
+}
+
+#[test]
+fn error() {
+    assert!(Device::from_str("").is_err());
+    assert!(Device::from_str("x,y,raw\n0,0,SLICE_X0Y0").is_err());
+    assert!(Device::from_str("x,y,prim,raw\n0,0,IOB,IOB_X0Y0").is_err());
+    assert!(Device::from_str("x,y,prim,raw\n0,a,LUT,SLICE_X0Y0").is_err());
+    assert!(Device::from_str("x,y,prim,raw\n0,0,LUT,SLICE_X0Y0\n0,0,LUT,SLICE_X0Y0").is_err());
+    assert!(Device::from_part("../../../dev", "missing").is_err());
+    let device = Device::from_str("x,y,prim\n0,0,BRAM\n").unwrap();
+    assert_eq!(device.capacity(&Prim::Bram), 1);
+}
@@ -0,0 +1,625 @@
+use crate::cell::Cell;
+use crate::errors::Error;
+use ir::ast as ir;
+use sim::bits::*;
+use sim::trace::{lane_names, term_width, Stimulus, Trace};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use verilog::ast as vl;
+
+pub const CLOCK: &str = "clock";
+pub const RESET: &str = "reset";
+
+// bits lo to hi of a declared wire or reg
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub net: usize,
+    pub lo: u64,
+    pub hi: u64,
+}
+
+// part of a wiring expression, the first one is the least significant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seg {
+    Range(Range),
+    Const(u128, u64),
+}
+
+// Right-hand side of a continuous assignment or an input port. Wiring, that
+// is wires, bits, slices, literals and concatenations of them, is resolved
+// into segments once, anything else is evaluated as an expression.
+#[derive(Clone, Debug)]
+pub enum Source {
+    Wiring(Vec<Seg>),
+    Expr(vl::Expr),
+}
+
+// continuous assignment or primitive instance, kept in module order. A cell
+// has a source for every input port and a target for every output port,
+// which is none when the port is left open.
+#[derive(Clone, Debug)]
+pub enum Node {
+    Assign(Range, Source),
+    Cell(Cell, Vec<Source>, Vec<Option<Range>>),
+}
+
+// Evaluator for the behavioral modules produced by bline and the structural
+// modules produced by xpand. It walks the module and covers the subset both
+// emit: wire/reg declarations, continuous assignments, posedge always blocks
+// with if/else and non-blocking assignments, and instances of the primitives
+// modeled in cell.rs. Declarations are unsigned, as in Verilog-2005 modules
+// without signed nets, and $signed marks signed operands.
+#[derive(Clone, Debug, Default)]
+pub struct Behav {
+    pub name: String,
+    pub net: HashMap<String, usize>,
+    pub width: Vec<u64>,
+    pub value: Vec<u128>,
+    pub input: Vec<String>,
+    pub output: Vec<String>,
+    pub node: Vec<Node>,
+    pub always: Vec<Vec<vl::Sequential>>,
+}
+
+fn unsupported(what: &str, node: &impl std::fmt::Debug) -> Error {
+    let err = format!("{} {:?} is not supported", what, node);
+    Error::new_behav_error(&err)
+}
+
+fn check_width(width: u64) -> Result<u64, Error> {
+    if width > MAX_WIDTH {
+        let err = format!("width {} is wider than {} bits", width, MAX_WIDTH);
+        Err(Error::new_behav_error(&err))
+    } else {
+        Ok(width)
+    }
+}
+
+fn width_from_range(hi: u64, lo: u64) -> Result<u64, Error> {
+    check_width(if hi >= lo { hi - lo + 1 } else { lo - hi + 1 })
+}
+
+// always blocks only assign whole registers
+fn target(expr: &vl::Expr) -> Result<String, Error> {
+    match expr {
+        vl::Expr::Ref(id) => Ok(id.clone()),
+        _ => Err(unsupported("assignment to", expr)),
+    }
+}
+
+// an output port left open is connected to the empty name
+fn is_open(expr: &vl::Expr) -> bool {
+    matches!(expr, vl::Expr::Ref(id) if id.is_empty())
+}
+
+fn seg_width(seg: &[Seg]) -> u64 {
+    seg.iter()
+        .map(|s| match s {
+            Seg::Range(range) => range.hi - range.lo + 1,
+            Seg::Const(_, width) => *width,
+        })
+        .sum()
+}
+
+fn extend(bits: u128, from: u64, width: u64, signed: bool) -> u128 {
+    if signed {
+        trunc(sign_extend(bits, from) as u128, width)
+    } else {
+        trunc(bits, width)
+    }
+}
+
+fn ulit(width: u32, radix: &vl::Radix, val: &str) -> Result<u128, Error> {
+    let radix = match radix {
+        vl::Radix::Dec => 10,
+        vl::Radix::Bin => 2,
+        vl::Radix::Hex => 16,
+    };
+    match u128::from_str_radix(&val.replace('_', ""), radix) {
+        Ok(bits) => Ok(trunc(bits, u64::from(width))),
+        Err(_) => {
+            let err = format!("{} is not a valid literal", val);
+            Err(Error::new_behav_error(&err))
+        }
+    }
+}
+
+impl Behav {
+    pub fn try_from_module(module: &vl::Module) -> Result<Behav, Error> {
+        let mut behav = Behav {
+            name: module.name.clone(),
+            ..Behav::default()
+        };
+        for port in &module.ports {
+            match port {
+                vl::Port::Input(decl) => {
+                    let id = behav.add_decl(decl)?;
+                    behav.input.push(id);
+                }
+                vl::Port::Output(decl) => {
+                    let id = behav.add_decl(decl)?;
+                    behav.output.push(id);
+                }
+            }
+        }
+        for stmt in &module.body {
+            if let vl::Stmt::Decl(decl) = stmt {
+                behav.add_decl(decl)?;
+            }
+        }
+        // wires may be used before they are declared
+        for stmt in &module.body {
+            match stmt {
+                vl::Stmt::Decl(_) => (),
+                vl::Stmt::Parallel(vl::Parallel::Assign(lhs, rhs)) => {
+                    let node = Node::Assign(behav.range(lhs)?, behav.source(rhs)?);
+                    behav.node.push(node);
+                }
+                vl::Stmt::Parallel(vl::Parallel::Inst(instance)) => {
+                    let cell = Cell::try_from_instance(instance)?;
+                    let mut source = Vec::new();
+                    for (_, expr) in cell.input() {
+                        source.push(behav.source(expr)?);
+                    }
+                    let mut target = Vec::new();
+                    for (_, expr) in cell.output() {
+                        if is_open(expr) {
+                            target.push(None);
+                        } else {
+                            target.push(Some(behav.range(expr)?));
+                        }
+                    }
+                    behav.node.push(Node::Cell(cell, source, target));
+                }
+                vl::Stmt::Parallel(vl::Parallel::ParProcess(process)) => {
+                    behav.add_process(process)?;
+                }
+                _ => return Err(unsupported("statement", stmt)),
+            }
+        }
+        Ok(behav)
+    }
+    fn add_decl(&mut self, decl: &vl::Decl) -> Result<String, Error> {
+        match decl {
+            vl::Decl::Wire(id, vl::Ty::Width(width)) | vl::Decl::Reg(id, vl::Ty::Width(width)) => {
+                let width = check_width(*width)?;
+                match self.net.get(id) {
+                    Some(net) => self.width[*net] = width,
+                    None => {
+                        self.net.insert(id.clone(), self.width.len());
+                        self.width.push(width);
+                        self.value.push(0);
+                    }
+                }
+                Ok(id.clone())
+            }
+            _ => Err(unsupported("declaration", decl)),
+        }
+    }
+    fn add_process(&mut self, process: &vl::ParallelProcess) -> Result<(), Error> {
+        match &process.event {
+            Some(vl::Sequential::Event(vl::EventTy::Posedge, vl::Expr::Ref(id))) if id == CLOCK => {
+                self.always.push(process.body.clone());
+                Ok(())
+            }
+            _ => {
+                let err = format!("always blocks must be clocked by posedge {}", CLOCK);
+                Err(Error::new_behav_error(&err))
+            }
+        }
+    }
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    fn net(&self, id: &str) -> Result<usize, Error> {
+        match self.net.get(id) {
+            Some(net) => Ok(*net),
+            None => {
+                let err = format!("{} is not declared", id);
+                Err(Error::new_behav_error(&err))
+            }
+        }
+    }
+    pub fn width(&self, id: &str) -> Result<u64, Error> {
+        Ok(self.width[self.net(id)?])
+    }
+    pub fn get(&self, id: &str) -> Result<u128, Error> {
+        Ok(self.value[self.net(id)?])
+    }
+    pub fn set(&mut self, id: &str, bits: u128) -> Result<(), Error> {
+        let net = self.net(id)?;
+        self.value[net] = trunc(bits, self.width[net]);
+        Ok(())
+    }
+    // slice bounds and the like, which must not depend on any signal
+    fn constant(&self, expr: &vl::Expr) -> Result<u64, Error> {
+        match expr {
+            vl::Expr::Int(val) if *val >= 0 => Ok(*val as u64),
+            vl::Expr::ULit(width, radix, val) => Ok(ulit(*width, radix, val)? as u64),
+            _ => Err(unsupported("non-constant bound", expr)),
+        }
+    }
+    // self-determined width, as defined by the Verilog standard
+    fn expr_width(&self, expr: &vl::Expr) -> Result<u64, Error> {
+        match expr {
+            vl::Expr::Ref(id) => self.width(id),
+            vl::Expr::Int(_) => Ok(32),
+            vl::Expr::ULit(width, _, _) => Ok(u64::from(*width)),
+            vl::Expr::Signed(e) => self.expr_width(e),
+            vl::Expr::Binop(vl::Binop::Equal, _, _)
+            | vl::Expr::Binop(vl::Binop::NotEqual, _, _)
+            | vl::Expr::Binop(vl::Binop::IndexBit, _, _) => Ok(1),
+            vl::Expr::Binop(_, a, b) | vl::Expr::Ternop(vl::Ternop::Mux, _, a, b) => {
+                Ok(self.expr_width(a)?.max(self.expr_width(b)?))
+            }
+            vl::Expr::Ternop(vl::Ternop::Slice, _, hi, lo) => {
+                width_from_range(self.constant(hi)?, self.constant(lo)?)
+            }
+            vl::Expr::Concat(concat) => {
+                let mut width = 0;
+                for e in &concat.exprs {
+                    width += self.expr_width(e)?;
+                }
+                check_width(width)
+            }
+            _ => Err(unsupported("expression", expr)),
+        }
+    }
+    fn expr_signed(&self, expr: &vl::Expr) -> Result<bool, Error> {
+        match expr {
+            vl::Expr::Int(_) | vl::Expr::Signed(_) => Ok(true),
+            vl::Expr::Binop(vl::Binop::Add, a, b)
+            | vl::Expr::Binop(vl::Binop::Sub, a, b)
+            | vl::Expr::Binop(vl::Binop::Mul, a, b)
+            | vl::Expr::Binop(vl::Binop::BitAnd, a, b)
+            | vl::Expr::Binop(vl::Binop::BitOr, a, b)
+            | vl::Expr::Binop(vl::Binop::BitXor, a, b)
+            | vl::Expr::Ternop(vl::Ternop::Mux, _, a, b) => {
+                Ok(self.expr_signed(a)? && self.expr_signed(b)?)
+            }
+            _ => Ok(false),
+        }
+    }
+    fn eval_self(&self, expr: &vl::Expr) -> Result<u128, Error> {
+        let width = self.expr_width(expr)?;
+        let signed = self.expr_signed(expr)?;
+        self.eval(expr, width, signed)
+    }
+    fn eval_compare(&self, a: &vl::Expr, b: &vl::Expr) -> Result<Ordering, Error> {
+        let width = self.expr_width(a)?.max(self.expr_width(b)?);
+        let signed = self.expr_signed(a)? && self.expr_signed(b)?;
+        let va = self.eval(a, width, signed)?;
+        let vb = self.eval(b, width, signed)?;
+        if signed {
+            Ok(sign_extend(va, width).cmp(&sign_extend(vb, width)))
+        } else {
+            Ok(va.cmp(&vb))
+        }
+    }
+    // evaluate expr in a context of the given width and signedness
+    fn eval(&self, expr: &vl::Expr, width: u64, signed: bool) -> Result<u128, Error> {
+        check_width(width)?;
+        let res = match expr {
+            vl::Expr::Ref(id) => extend(self.get(id)?, self.width(id)?, width, signed),
+            vl::Expr::Int(val) => extend(trunc(*val as u128, 32), 32, width, signed),
+            vl::Expr::ULit(lit, radix, val) => ulit(*lit, radix, val)?,
+            vl::Expr::Signed(e) => {
+                let from = self.expr_width(e)?;
+                extend(self.eval(e, from, false)?, from, width, signed)
+            }
+            vl::Expr::Binop(vl::Binop::Equal, a, b) => {
+                (self.eval_compare(a, b)? == Ordering::Equal) as u128
+            }
+            vl::Expr::Binop(vl::Binop::NotEqual, a, b) => {
+                (self.eval_compare(a, b)? != Ordering::Equal) as u128
+            }
+            vl::Expr::Binop(vl::Binop::IndexBit, e, index) => {
+                let val = self.eval_self(e)?;
+                let index = self.eval_self(index)?;
+                if index >= u128::from(self.expr_width(e)?) {
+                    0
+                } else {
+                    extract(val, index as u64, index as u64)
+                }
+            }
+            vl::Expr::Binop(op, a, b) => {
+                let va = self.eval(a, width, signed)?;
+                let vb = self.eval(b, width, signed)?;
+                match op {
+                    vl::Binop::Add => va.wrapping_add(vb),
+                    vl::Binop::Sub => va.wrapping_sub(vb),
+                    vl::Binop::Mul => va.wrapping_mul(vb),
+                    vl::Binop::BitAnd => va & vb,
+                    vl::Binop::BitOr => va | vb,
+                    vl::Binop::BitXor => va ^ vb,
+                    _ => return Err(unsupported("expression", expr)),
+                }
+            }
+            vl::Expr::Ternop(vl::Ternop::Mux, cond, a, b) => {
+                if self.eval_self(cond)? != 0 {
+                    self.eval(a, width, signed)?
+                } else {
+                    self.eval(b, width, signed)?
+                }
+            }
+            vl::Expr::Ternop(vl::Ternop::Slice, e, hi, lo) => {
+                let (hi, lo) = (self.constant(hi)?, self.constant(lo)?);
+                extract(self.eval_self(e)?, lo.min(hi), lo.max(hi))
+            }
+            // the first expression of a concatenation is the least significant
+            vl::Expr::Concat(concat) => {
+                let mut res: u128 = 0;
+                let mut offset: u64 = 0;
+                for e in &concat.exprs {
+                    let width = self.expr_width(e)?;
+                    res |= shift_left(self.eval_self(e)?, offset, MAX_WIDTH);
+                    offset += width;
+                }
+                res
+            }
+            _ => return Err(unsupported("expression", expr)),
+        };
+        Ok(trunc(res, width))
+    }
+    fn eval_assign(&self, id: &str, expr: &vl::Expr) -> Result<u128, Error> {
+        let width = self.width(id)?.max(self.expr_width(expr)?);
+        let signed = self.expr_signed(expr)?;
+        Ok(trunc(self.eval(expr, width, signed)?, self.width(id)?))
+    }
+    // bits driven by a continuous assignment or an output port, which may be
+    // a whole wire, a bit or a slice
+    fn range(&self, target: &vl::Expr) -> Result<Range, Error> {
+        let (id, lo, hi) = match target {
+            vl::Expr::Ref(id) => (id, 0, self.width(id)? - 1),
+            vl::Expr::Binop(vl::Binop::IndexBit, e, index) => match e.as_ref() {
+                vl::Expr::Ref(id) => {
+                    let index = self.constant(index)?;
+                    (id, index, index)
+                }
+                _ => return Err(unsupported("assignment to", target)),
+            },
+            vl::Expr::Ternop(vl::Ternop::Slice, e, hi, lo) => match e.as_ref() {
+                vl::Expr::Ref(id) => {
+                    let (hi, lo) = (self.constant(hi)?, self.constant(lo)?);
+                    (id, lo.min(hi), lo.max(hi))
+                }
+                _ => return Err(unsupported("assignment to", target)),
+            },
+            _ => return Err(unsupported("assignment to", target)),
+        };
+        let net = self.net(id)?;
+        if hi >= self.width[net] {
+            let err = format!("{} is out of range", id);
+            return Err(Error::new_behav_error(&err));
+        }
+        Ok(Range { net, lo, hi })
+    }
+    // segments of a wiring expression, false when expr is not wiring
+    fn wiring(&self, expr: &vl::Expr, seg: &mut Vec<Seg>) -> Result<bool, Error> {
+        let next = match expr {
+            vl::Expr::Ref(_)
+            | vl::Expr::Binop(vl::Binop::IndexBit, _, _)
+            | vl::Expr::Ternop(vl::Ternop::Slice, _, _, _) => match self.range(expr) {
+                Ok(range) => Seg::Range(range),
+                Err(_) => return Ok(false),
+            },
+            vl::Expr::ULit(width, radix, val) => {
+                Seg::Const(ulit(*width, radix, val)?, u64::from(*width))
+            }
+            vl::Expr::Concat(concat) => {
+                for e in &concat.exprs {
+                    if !self.wiring(e, seg)? {
+                        return Ok(false);
+                    }
+                }
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+        // adjacent bits of a wire are gathered at once
+        match (seg.last_mut(), next) {
+            (Some(Seg::Range(last)), Seg::Range(range))
+                if last.net == range.net && last.hi + 1 == range.lo =>
+            {
+                last.hi = range.hi;
+            }
+            _ => seg.push(next),
+        }
+        Ok(true)
+    }
+    fn source(&self, expr: &vl::Expr) -> Result<Source, Error> {
+        let mut seg = Vec::new();
+        if self.wiring(expr, &mut seg)? && seg_width(&seg) <= MAX_WIDTH {
+            Ok(Source::Wiring(seg))
+        } else {
+            Ok(Source::Expr(expr.clone()))
+        }
+    }
+    fn gather(&self, seg: &[Seg]) -> u128 {
+        let mut res: u128 = 0;
+        let mut offset: u64 = 0;
+        for s in seg {
+            let (bits, width) = match s {
+                Seg::Range(range) => (
+                    extract(self.value[range.net], range.lo, range.hi),
+                    range.hi - range.lo + 1,
+                ),
+                Seg::Const(bits, width) => (*bits, *width),
+            };
+            res |= shift_left(bits, offset, MAX_WIDTH);
+            offset += width;
+        }
+        res
+    }
+    fn eval_source(&self, source: &Source) -> Result<u128, Error> {
+        match source {
+            Source::Wiring(seg) => Ok(self.gather(seg)),
+            Source::Expr(expr) => self.eval_self(expr),
+        }
+    }
+    // drive the range with bits, true when its value changed
+    fn drive(&mut self, range: Range, bits: u128) -> bool {
+        let cur = self.value[range.net];
+        let mask = shift_left(mask(range.hi - range.lo + 1), range.lo, MAX_WIDTH);
+        let next = (cur & !mask) | (shift_left(bits, range.lo, MAX_WIDTH) & mask);
+        self.value[range.net] = next;
+        next != cur
+    }
+    fn port_value(&self, source: &[Source]) -> Result<Vec<u128>, Error> {
+        let mut value = Vec::new();
+        for source in source {
+            value.push(self.eval_source(source)?);
+        }
+        Ok(value)
+    }
+    fn eval_node(&self, node: &Node, drive: &mut Vec<(Range, u128)>) -> Result<(), Error> {
+        match node {
+            Node::Assign(range, Source::Wiring(seg)) => drive.push((*range, self.gather(seg))),
+            Node::Assign(range, Source::Expr(rhs)) => {
+                let width = (range.hi - range.lo + 1).max(self.expr_width(rhs)?);
+                let bits = self.eval(rhs, width, self.expr_signed(rhs)?)?;
+                drive.push((*range, bits));
+            }
+            Node::Cell(cell, source, target) => {
+                let res = cell.eval(&self.port_value(source)?)?;
+                for ((port, _), target) in cell.output().iter().zip(target) {
+                    if let Some(range) = target {
+                        match res.iter().find(|(p, _)| p == port) {
+                            Some((_, bits)) => drive.push((*range, *bits)),
+                            None => {
+                                let err = format!("{} output {} is not modeled", cell.prim(), port);
+                                return Err(Error::new_behav_error(&err));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn eval_seq(
+        &self,
+        seq: &vl::Sequential,
+        update: &mut Vec<(String, u128)>,
+    ) -> Result<(), Error> {
+        match seq {
+            vl::Sequential::Assign(lhs, rhs, vl::AssignTy::NonBlocking) => {
+                let id = target(lhs)?;
+                let bits = self.eval_assign(&id, rhs)?;
+                update.push((id, bits));
+            }
+            // a branch without a condition is a plain else
+            vl::Sequential::If(branch) => {
+                let taken = match &branch.cond {
+                    Some(cond) => self.eval_self(cond)? != 0,
+                    None => true,
+                };
+                if taken {
+                    for seq in &branch.body {
+                        self.eval_seq(seq, update)?;
+                    }
+                } else if let Some(other) = &branch.else_br {
+                    self.eval_seq(other, update)?;
+                }
+            }
+            _ => return Err(unsupported("sequential statement", seq)),
+        }
+        Ok(())
+    }
+    // evaluate continuous assignments and cells until every wire is stable
+    pub fn settle(&mut self) -> Result<(), Error> {
+        let mut drive = Vec::new();
+        for _ in 0..=self.node.len() {
+            let mut changed = false;
+            for index in 0..self.node.len() {
+                self.eval_node(&self.node[index], &mut drive)?;
+                for (range, bits) in drive.drain(..) {
+                    changed |= self.drive(range, bits);
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+        let err = format!("{} has a combinational loop", self.name);
+        Err(Error::new_behav_error(&err))
+    }
+    // positive clock edge, all non-blocking assignments update together
+    pub fn tick(&mut self) -> Result<(), Error> {
+        let mut update: Vec<(String, u128)> = Vec::new();
+        for body in &self.always {
+            for seq in body {
+                self.eval_seq(seq, &mut update)?;
+            }
+        }
+        let mut input: Vec<Vec<u128>> = Vec::new();
+        for node in &self.node {
+            if let Node::Cell(_, source, _) = node {
+                input.push(self.port_value(source)?);
+            }
+        }
+        let cell = self.node.iter_mut().filter_map(|node| match node {
+            Node::Cell(cell, _, _) => Some(cell),
+            _ => None,
+        });
+        for (cell, input) in cell.zip(input.iter()) {
+            cell.tick(input)?;
+        }
+        for (id, bits) in update {
+            self.set(&id, bits)?;
+        }
+        Ok(())
+    }
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if self.net.contains_key(RESET) {
+            self.set(RESET, 1)?;
+            self.settle()?;
+            self.tick()?;
+            self.set(RESET, 0)?;
+        }
+        Ok(())
+    }
+    pub fn run(&mut self, sig: &ir::Sig, stim: &Stimulus) -> Result<Trace, Error> {
+        let input: Vec<ir::ExprTerm> = sig.input().clone().into();
+        let output: Vec<ir::ExprTerm> = sig.output().clone().into();
+        let mut signal: Vec<String> = Vec::new();
+        for term in &output {
+            signal.extend(lane_names(term));
+        }
+        let mut trace = Trace::new(&signal);
+        self.reset()?;
+        for cycle in 0..stim.len() {
+            for term in &input {
+                for name in lane_names(term) {
+                    match stim.get(cycle, &name) {
+                        Some(val) => self.set(&name, val as u128)?,
+                        None => {
+                            let err = format!("stimulus is missing {} at cycle {}", name, cycle);
+                            return Err(Error::new_behav_error(&err));
+                        }
+                    }
+                }
+            }
+            self.settle()?;
+            let mut row: Vec<i128> = Vec::new();
+            for term in &output {
+                let width = term_width(term)?;
+                let signed = term.get_ty()?.is_signed();
+                for name in lane_names(term) {
+                    row.push(bits_to_value(self.get(&name)?, width, signed));
+                }
+            }
+            trace.push(row)?;
+            self.tick()?;
+        }
+        Ok(trace)
+    }
+}
+
+pub fn run_behav_module(
+    module: &vl::Module,
+    sig: &ir::Sig,
+    stim: &Stimulus,
+) -> Result<Trace, Error> {
+    Behav::try_from_module(module)?.run(sig, stim)
+}
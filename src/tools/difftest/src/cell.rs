@@ -0,0 +1,668 @@
+use crate::behav::CLOCK;
+use crate::errors::Error;
+use sim::bits::*;
+use std::collections::HashMap;
+use verilog::ast as vl;
+
+// value of every input port of a cell, ports left out are low
+pub type PortValue<'a> = HashMap<&'a str, u128>;
+
+// Models of the UltraScale primitives that xpand instantiates, as far as the
+// structural modules use them. Each one is built from the parameters of the
+// instance and is evaluated by the behavioral evaluator, which drives the
+// input ports and writes the output ports back to the module.
+#[derive(Clone, Debug)]
+pub enum Model {
+    Gnd,
+    Vcc,
+    Lut(u64, Vec<bool>),
+    Lut62(Vec<bool>),
+    Carry,
+    Fdre(u128),
+    Fdse(u128),
+    Dsp(Box<Dsp>),
+    Lram(Vec<Vec<bool>>),
+    Lram256(Vec<bool>),
+    Bram(Bram),
+}
+
+// DSP48E2 with direct A and B inputs, an A:B, M, C, P and PCIN datapath and
+// unregistered control inputs
+#[derive(Clone, Debug, Default)]
+pub struct Dsp {
+    pub areg: u64,
+    pub breg: u64,
+    pub creg: u64,
+    pub mreg: u64,
+    pub preg: u64,
+    pub mult: bool,
+    pub lanes: u64,
+    pub rnd: u128,
+    pub a1: u128,
+    pub a2: u128,
+    pub b1: u128,
+    pub b2: u128,
+    pub c: u128,
+    pub m: u128,
+    pub p: u128,
+}
+
+// RAMB18E2 port A read with a data latch and an optional output register
+#[derive(Clone, Debug, Default)]
+pub struct Bram {
+    pub width: u64,
+    pub shift: u64,
+    pub reg: bool,
+    pub init: Vec<bool>,
+    pub latch: u128,
+    pub out: u128,
+}
+
+#[derive(Clone, Debug)]
+pub struct Cell {
+    pub name: String,
+    pub prim: String,
+    pub model: Model,
+    pub input: Vec<(String, vl::Expr)>,
+    pub output: Vec<(String, vl::Expr)>,
+}
+
+const DSP_WIDTH: u64 = 48;
+
+fn unsupported(prim: &str, what: &str) -> Error {
+    let err = format!("{} {} is not supported", prim, what);
+    Error::new_behav_error(&err)
+}
+
+fn output_port(prim: &str) -> Vec<&'static str> {
+    match prim {
+        "GND" => vec!["G"],
+        "VCC" => vec!["P"],
+        "LUT1" | "LUT2" | "LUT3" | "LUT4" | "LUT5" | "LUT6" => vec!["O"],
+        "LUT6_2" => vec!["O5", "O6"],
+        "CARRY8" => vec!["O", "CO"],
+        "FDRE" | "FDSE" => vec!["Q"],
+        "DSP48E2" => vec![
+            "ACOUT",
+            "BCOUT",
+            "CARRYCASCOUT",
+            "MULTSIGNOUT",
+            "PCOUT",
+            "OVERFLOW",
+            "PATTERNBDETECT",
+            "PATTERNDETECT",
+            "UNDERFLOW",
+            "CARRYOUT",
+            "P",
+            "XOROUT",
+        ],
+        "RAM64M8" => vec!["DOA", "DOB", "DOC", "DOD", "DOE", "DOF", "DOG", "DOH"],
+        "RAM256X1S" => vec!["O"],
+        "RAMB18E2" => vec![
+            "CASDOUTA",
+            "CASDOUTB",
+            "CASDOUTPA",
+            "CASDOUTPB",
+            "DOUTADOUT",
+            "DOUTPADOUTP",
+            "DOUTBDOUT",
+            "DOUTPBDOUTP",
+        ],
+        _ => vec![],
+    }
+}
+
+fn clock_port(prim: &str) -> Option<&'static str> {
+    match prim {
+        "FDRE" | "FDSE" => Some("C"),
+        "DSP48E2" => Some("CLK"),
+        "RAM64M8" | "RAM256X1S" => Some("WCLK"),
+        "RAMB18E2" => Some("CLKARDCLK"),
+        _ => None,
+    }
+}
+
+// literal as a vector of bits, least significant first, so parameters wider
+// than 128 bits such as the block ram contents can be read
+pub fn bits_from_expr(expr: &vl::Expr) -> Result<Vec<bool>, Error> {
+    let (width, digit, val) = match expr {
+        vl::Expr::Int(val) => return Ok((0..32).map(|i| (val >> i) & 1 == 1).collect()),
+        vl::Expr::ULit(width, vl::Radix::Bin, val) => (*width, 1, val.clone()),
+        vl::Expr::ULit(width, vl::Radix::Hex, val) => (*width, 4, val.clone()),
+        vl::Expr::ULit(width, vl::Radix::Dec, val) => match val.replace('_', "").parse::<u128>() {
+            Ok(num) => (*width, 4, format!("{:x}", num)),
+            Err(_) => return Err(unsupported("literal", val)),
+        },
+        _ => return Err(unsupported("parameter", &format!("{:?}", expr))),
+    };
+    let mut bits: Vec<bool> = Vec::new();
+    for c in val.chars().rev().filter(|c| *c != '_') {
+        match c.to_digit(1 << digit) {
+            Some(d) => bits.extend((0..digit).map(|i| (d >> i) & 1 == 1)),
+            None => return Err(unsupported("literal", &val)),
+        }
+    }
+    bits.resize(width as usize, false);
+    Ok(bits)
+}
+
+fn bits_to_u128(bits: &[bool]) -> u128 {
+    bits.iter()
+        .take(MAX_WIDTH as usize)
+        .enumerate()
+        .fold(0, |acc, (i, b)| acc | (u128::from(*b) << i))
+}
+
+fn bit(bits: &[bool], index: u128) -> u128 {
+    u128::from(bits.get(index as usize).cloned().unwrap_or(false))
+}
+
+fn port_value<'a>(input: &'a [(String, vl::Expr)], value: &[u128]) -> PortValue<'a> {
+    input
+        .iter()
+        .zip(value)
+        .map(|((name, _), bits)| (name.as_str(), *bits))
+        .collect()
+}
+
+fn port(input: &PortValue, name: &str) -> u128 {
+    input.get(name).cloned().unwrap_or(0)
+}
+
+fn is_set(input: &PortValue, name: &str) -> bool {
+    port(input, name) & 1 == 1
+}
+
+struct Param<'a> {
+    prim: &'a str,
+    map: HashMap<String, vl::Expr>,
+}
+
+impl<'a> Param<'a> {
+    fn get(&self, name: &str) -> Result<&vl::Expr, Error> {
+        match self.map.get(name) {
+            Some(expr) => Ok(expr),
+            None => Err(unsupported(self.prim, &format!("without {}", name))),
+        }
+    }
+    fn bits(&self, name: &str) -> Result<Vec<bool>, Error> {
+        bits_from_expr(self.get(name)?)
+    }
+    fn num(&self, name: &str) -> Result<u64, Error> {
+        Ok(bits_to_u128(&self.bits(name)?) as u64)
+    }
+    fn string(&self, name: &str) -> Result<String, Error> {
+        match self.get(name)? {
+            vl::Expr::Str(val) => Ok(val.clone()),
+            expr => Err(unsupported(self.prim, &format!("{} {:?}", name, expr))),
+        }
+    }
+    fn expect(&self, name: &str, val: &str) -> Result<(), Error> {
+        if self.string(name)? == val {
+            Ok(())
+        } else {
+            Err(unsupported(self.prim, &format!("without {} {}", name, val)))
+        }
+    }
+    // the models do not invert any pin
+    fn check_inverted(&self) -> Result<(), Error> {
+        for (name, expr) in self.map.iter() {
+            let inverted = name.starts_with("IS_") && name.ends_with("_INVERTED");
+            if inverted && bits_from_expr(expr)?.iter().any(|b| *b) {
+                return Err(unsupported(self.prim, name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dsp {
+    fn try_from_param(param: &Param) -> Result<Dsp, Error> {
+        param.expect("A_INPUT", "DIRECT")?;
+        param.expect("B_INPUT", "DIRECT")?;
+        param.expect("AMULTSEL", "A")?;
+        param.expect("BMULTSEL", "B")?;
+        for name in &["ALUMODEREG", "CARRYINSELREG", "INMODEREG", "OPMODEREG"] {
+            if param.num(name)? != 0 {
+                return Err(unsupported(param.prim, name));
+            }
+        }
+        let mult = match param.string("USE_MULT")?.as_str() {
+            "MULTIPLY" => true,
+            "NONE" => false,
+            other => return Err(unsupported(param.prim, other)),
+        };
+        let lanes = match param.string("USE_SIMD")?.as_str() {
+            "ONE48" => 1,
+            "TWO24" => 2,
+            "FOUR12" => 4,
+            other => return Err(unsupported(param.prim, other)),
+        };
+        Ok(Dsp {
+            areg: param.num("AREG")?,
+            breg: param.num("BREG")?,
+            creg: param.num("CREG")?,
+            mreg: param.num("MREG")?,
+            preg: param.num("PREG")?,
+            mult,
+            lanes,
+            rnd: bits_to_u128(&param.bits("RND")?),
+            ..Dsp::default()
+        })
+    }
+    fn a(&self, input: &PortValue) -> u128 {
+        if self.areg == 0 {
+            trunc(port(input, "A"), 30)
+        } else {
+            self.a2
+        }
+    }
+    fn b(&self, input: &PortValue) -> u128 {
+        if self.breg == 0 {
+            trunc(port(input, "B"), 18)
+        } else {
+            self.b2
+        }
+    }
+    fn c(&self, input: &PortValue) -> u128 {
+        if self.creg == 0 {
+            trunc(port(input, "C"), DSP_WIDTH)
+        } else {
+            self.c
+        }
+    }
+    // 27 by 18 signed multiplier of the A and B registers
+    fn mul(&self, input: &PortValue) -> u128 {
+        let a = sign_extend(trunc(self.a(input), 27), 27);
+        let b = sign_extend(self.b(input), 18);
+        trunc(a.wrapping_mul(b) as u128, DSP_WIDTH)
+    }
+    fn m(&self, input: &PortValue) -> u128 {
+        if self.mreg == 0 {
+            self.mul(input)
+        } else {
+            self.m
+        }
+    }
+    fn p(&self) -> Result<u128, Error> {
+        if self.preg == 0 {
+            Err(unsupported("DSP48E2", "P feedback without PREG"))
+        } else {
+            Ok(self.p)
+        }
+    }
+    // the X, Y, Z and W multiplexers followed by the simd adder
+    fn alu(&self, input: &PortValue) -> Result<u128, Error> {
+        let opmode = port(input, "OPMODE");
+        let (x, y, z, w) = (
+            extract(opmode, 0, 1),
+            extract(opmode, 2, 3),
+            extract(opmode, 4, 6),
+            extract(opmode, 7, 8),
+        );
+        let pcin = trunc(port(input, "PCIN"), DSP_WIDTH);
+        // both partial products of the multiplier go through X and Y
+        let (x, y) = match (x, y) {
+            (1, 1) if self.mult => (self.m(input), 0),
+            (1, _) | (_, 1) => return Err(unsupported("DSP48E2", "OPMODE")),
+            (x, y) => {
+                let x = match x {
+                    0 => 0,
+                    2 => self.p()?,
+                    _ => (self.a(input) << 18) | self.b(input),
+                };
+                let y = match y {
+                    0 => 0,
+                    2 => mask(DSP_WIDTH),
+                    _ => self.c(input),
+                };
+                (x, y)
+            }
+        };
+        let shift = |v: u128| trunc((sign_extend(v, DSP_WIDTH) >> 17) as u128, DSP_WIDTH);
+        let z = match z {
+            0 => 0,
+            1 => pcin,
+            2 | 4 => self.p()?,
+            3 => self.c(input),
+            5 => shift(pcin),
+            6 => shift(self.p()?),
+            _ => return Err(unsupported("DSP48E2", "OPMODE")),
+        };
+        let w = match w {
+            0 => 0,
+            1 => self.p()?,
+            2 => self.rnd,
+            _ => self.c(input),
+        };
+        if port(input, "CARRYINSEL") != 0 {
+            return Err(unsupported("DSP48E2", "CARRYINSEL"));
+        }
+        let sub = match port(input, "ALUMODE") {
+            0 => false,
+            3 => true,
+            _ => return Err(unsupported("DSP48E2", "ALUMODE")),
+        };
+        let width = DSP_WIDTH / self.lanes;
+        let mut res: u128 = 0;
+        for lane in 0..self.lanes {
+            let (lo, hi) = (lane * width, lane * width + width - 1);
+            let cin = if lane == 0 {
+                port(input, "CARRYIN") & 1
+            } else {
+                0
+            };
+            let sum = extract(w, lo, hi) + extract(x, lo, hi) + extract(y, lo, hi) + cin;
+            let val = if sub {
+                extract(z, lo, hi).wrapping_sub(sum)
+            } else {
+                extract(z, lo, hi) + sum
+            };
+            res |= trunc(val, width) << lo;
+        }
+        Ok(res)
+    }
+    fn eval(&self, input: &PortValue) -> Result<Vec<(&'static str, u128)>, Error> {
+        let p = if self.preg == 0 {
+            self.alu(input)?
+        } else {
+            self.p
+        };
+        Ok(vec![("P", p), ("PCOUT", p)])
+    }
+    fn tick(&mut self, input: &PortValue) -> Result<(), Error> {
+        let p = self.alu(input)?;
+        let m = self.mul(input);
+        let (a1, b1) = (self.a1, self.b1);
+        if is_set(input, "RSTA") {
+            self.a1 = 0;
+            self.a2 = 0;
+        } else {
+            let a = trunc(port(input, "A"), 30);
+            if is_set(input, "CEA1") {
+                self.a1 = a;
+            }
+            if is_set(input, "CEA2") {
+                self.a2 = if self.areg == 2 { a1 } else { a };
+            }
+        }
+        if is_set(input, "RSTB") {
+            self.b1 = 0;
+            self.b2 = 0;
+        } else {
+            let b = trunc(port(input, "B"), 18);
+            if is_set(input, "CEB1") {
+                self.b1 = b;
+            }
+            if is_set(input, "CEB2") {
+                self.b2 = if self.breg == 2 { b1 } else { b };
+            }
+        }
+        if is_set(input, "RSTC") {
+            self.c = 0;
+        } else if is_set(input, "CEC") {
+            self.c = trunc(port(input, "C"), DSP_WIDTH);
+        }
+        if is_set(input, "RSTM") {
+            self.m = 0;
+        } else if is_set(input, "CEM") {
+            self.m = m;
+        }
+        if is_set(input, "RSTP") {
+            self.p = 0;
+        } else if is_set(input, "CEP") {
+            self.p = p;
+        }
+        Ok(())
+    }
+}
+
+impl Bram {
+    fn try_from_param(param: &Param) -> Result<Bram, Error> {
+        // data bits of a read and the address bits below the word address
+        let (width, shift) = match param.num("READ_WIDTH_A")? {
+            0 | 1 => (1, 0),
+            2 => (2, 1),
+            4 => (4, 2),
+            9 => (8, 3),
+            18 => (16, 4),
+            _ => return Err(unsupported(param.prim, "READ_WIDTH_A")),
+        };
+        let reg = match param.num("DOA_REG")? {
+            0 => false,
+            1 => true,
+            _ => return Err(unsupported(param.prim, "DOA_REG")),
+        };
+        let mut init: Vec<bool> = Vec::new();
+        for i in 0..64 {
+            let mut bits = param.bits(&format!("INIT_{:02X}", i))?;
+            bits.resize(256, false);
+            init.extend(bits);
+        }
+        Ok(Bram {
+            width,
+            shift,
+            reg,
+            init,
+            ..Bram::default()
+        })
+    }
+    fn read(&self, addr: u128) -> u128 {
+        let word = trunc(addr, 14) >> self.shift;
+        let lo = word * u128::from(self.width);
+        (0..self.width).fold(0, |acc, i| acc | (bit(&self.init, lo + u128::from(i)) << i))
+    }
+    fn eval(&self) -> Vec<(&'static str, u128)> {
+        let out = if self.reg { self.out } else { self.latch };
+        vec![("DOUTADOUT", out)]
+    }
+    fn tick(&mut self, input: &PortValue) -> Result<(), Error> {
+        let latch = self.latch;
+        if is_set(input, "ENARDEN") {
+            if port(input, "WEA") != 0 {
+                return Err(unsupported("RAMB18E2", "write"));
+            }
+            self.latch = if is_set(input, "RSTRAMARSTRAM") {
+                0
+            } else {
+                self.read(port(input, "ADDRARDADDR"))
+            };
+        }
+        if self.reg && is_set(input, "REGCEAREGCE") {
+            self.out = if is_set(input, "RSTREGARSTREG") {
+                0
+            } else {
+                latch
+            };
+        }
+        Ok(())
+    }
+}
+
+const LUT_PORT: [&str; 6] = ["I0", "I1", "I2", "I3", "I4", "I5"];
+
+const LRAM_PORT: [char; 8] = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H'];
+
+impl Model {
+    fn try_from_param(param: &Param) -> Result<Model, Error> {
+        param.check_inverted()?;
+        let model = match param.prim {
+            "GND" => Model::Gnd,
+            "VCC" => Model::Vcc,
+            "LUT1" | "LUT2" | "LUT3" | "LUT4" | "LUT5" | "LUT6" => {
+                let inputs = param.prim[3..].parse::<u64>().unwrap_or(0);
+                Model::Lut(inputs, param.bits("INIT")?)
+            }
+            "LUT6_2" => Model::Lut62(param.bits("INIT")?),
+            "CARRY8" => {
+                param.expect("CARRY_TYPE", "SINGLE_CY8")?;
+                Model::Carry
+            }
+            "FDRE" => Model::Fdre(bits_to_u128(&param.bits("INIT")?) & 1),
+            "FDSE" => Model::Fdse(bits_to_u128(&param.bits("INIT")?) & 1),
+            "DSP48E2" => Model::Dsp(Box::new(Dsp::try_from_param(param)?)),
+            "RAM64M8" => {
+                let mut init = Vec::new();
+                for p in LRAM_PORT.iter() {
+                    let mut bits = param.bits(&format!("INIT_{}", p))?;
+                    bits.resize(64, false);
+                    init.push(bits);
+                }
+                Model::Lram(init)
+            }
+            "RAM256X1S" => {
+                let mut init = param.bits("INIT")?;
+                init.resize(256, false);
+                Model::Lram256(init)
+            }
+            "RAMB18E2" => Model::Bram(Bram::try_from_param(param)?),
+            _ => return Err(unsupported("primitive", param.prim)),
+        };
+        Ok(model)
+    }
+}
+
+impl Cell {
+    pub fn try_from_instance(instance: &vl::Instance) -> Result<Cell, Error> {
+        let prim = instance.prim.clone();
+        let param = Param {
+            prim: &prim,
+            map: instance
+                .params
+                .iter()
+                .map(|(name, expr)| (name.clone(), expr.clone()))
+                .collect(),
+        };
+        let model = Model::try_from_param(&param)?;
+        let output_port = output_port(&prim);
+        let mut input: Vec<(String, vl::Expr)> = Vec::new();
+        let mut output: Vec<(String, vl::Expr)> = Vec::new();
+        for (name, expr) in instance.ports.iter() {
+            if output_port.contains(&name.as_str()) {
+                output.push((name.clone(), expr.clone()));
+            } else {
+                input.push((name.clone(), expr.clone()));
+            }
+        }
+        // a single clock domain, the one of the module
+        if let Some(clock) = clock_port(&prim) {
+            let clocked = input
+                .iter()
+                .any(|(name, expr)| name == clock && *expr == vl::Expr::new_ref(CLOCK));
+            if !clocked {
+                let err = format!("{} {} is not clocked by {}", prim, instance.id, CLOCK);
+                return Err(Error::new_behav_error(&err));
+            }
+        }
+        Ok(Cell {
+            name: instance.id.clone(),
+            prim,
+            model,
+            input,
+            output,
+        })
+    }
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn prim(&self) -> &String {
+        &self.prim
+    }
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+    pub fn input(&self) -> &Vec<(String, vl::Expr)> {
+        &self.input
+    }
+    pub fn output(&self) -> &Vec<(String, vl::Expr)> {
+        &self.output
+    }
+    // outputs as a function of the inputs, given in the order of input(), and
+    // the state of the cell
+    pub fn eval(&self, value: &[u128]) -> Result<Vec<(&'static str, u128)>, Error> {
+        let input = &port_value(&self.input, value);
+        let res = match &self.model {
+            Model::Gnd => vec![("G", 0)],
+            Model::Vcc => vec![("P", 1)],
+            Model::Lut(inputs, init) => {
+                let index = LUT_PORT[..*inputs as usize]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, p)| acc | ((port(input, p) & 1) << i));
+                vec![("O", bit(init, index))]
+            }
+            Model::Lut62(init) => {
+                let index = LUT_PORT
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, p)| acc | ((port(input, p) & 1) << i));
+                vec![("O6", bit(init, index)), ("O5", bit(init, index & 31))]
+            }
+            Model::Carry => {
+                let (di, s) = (port(input, "DI"), port(input, "S"));
+                let mut carry = port(input, "CI") & 1;
+                let (mut o, mut co) = (0, 0);
+                for i in 0..8 {
+                    let (di, s) = ((di >> i) & 1, (s >> i) & 1);
+                    o |= (s ^ carry) << i;
+                    carry = if s == 1 { carry } else { di };
+                    co |= carry << i;
+                }
+                vec![("O", o), ("CO", co)]
+            }
+            Model::Fdre(q) | Model::Fdse(q) => vec![("Q", *q)],
+            Model::Dsp(dsp) => dsp.eval(input)?,
+            Model::Lram(init) => {
+                let mut res = Vec::new();
+                let names = ["DOA", "DOB", "DOC", "DOD", "DOE", "DOF", "DOG", "DOH"];
+                for ((p, name), init) in LRAM_PORT.iter().zip(names.iter()).zip(init) {
+                    let addr = trunc(port(input, &format!("ADDR{}", p)), 6);
+                    res.push((*name, bit(init, addr)));
+                }
+                res
+            }
+            Model::Lram256(init) => vec![("O", bit(init, trunc(port(input, "A"), 8)))],
+            Model::Bram(bram) => bram.eval(),
+        };
+        Ok(res)
+    }
+    // positive clock edge
+    pub fn tick(&mut self, value: &[u128]) -> Result<(), Error> {
+        let input = &port_value(&self.input, value);
+        match &mut self.model {
+            Model::Fdre(q) => {
+                if is_set(input, "R") {
+                    *q = 0;
+                } else if is_set(input, "CE") {
+                    *q = port(input, "D") & 1;
+                }
+            }
+            Model::Fdse(q) => {
+                if is_set(input, "S") {
+                    *q = 1;
+                } else if is_set(input, "CE") {
+                    *q = port(input, "D") & 1;
+                }
+            }
+            Model::Dsp(dsp) => dsp.tick(input)?,
+            // all eight ports write at the address of port H
+            Model::Lram(init) => {
+                if is_set(input, "WE") {
+                    let addr = trunc(port(input, "ADDRH"), 6) as usize;
+                    for (p, init) in LRAM_PORT.iter().zip(init.iter_mut()) {
+                        init[addr] = is_set(input, &format!("DI{}", p));
+                    }
+                }
+            }
+            Model::Lram256(init) => {
+                if is_set(input, "WE") {
+                    let addr = trunc(port(input, "A"), 8) as usize;
+                    init[addr] = is_set(input, "D");
+                }
+            }
+            Model::Bram(bram) => bram.tick(input)?,
+            _ => (),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use bler::errors::Error as BlerError;
+use bline::errors::Error as BlineError;
+use ir::errors::Error as IrError;
+use isel::errors::Error as ISelError;
+use sim::errors::Error as SimError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Ir(IrError),
+    ISel(ISelError),
+    Bler(BlerError),
+    Bline(BlineError),
+    Sim(SimError),
+    Behav(String),
+}
+
+impl Error {
+    pub fn new_behav_error(msg: &str) -> Self {
+        Error::Behav(msg.to_string())
+    }
+}
+
+impl From<IrError> for Error {
+    fn from(e: IrError) -> Self {
+        Error::Ir(e)
+    }
+}
+
+impl From<ISelError> for Error {
+    fn from(e: ISelError) -> Self {
+        Error::ISel(e)
+    }
+}
+
+impl From<BlerError> for Error {
+    fn from(e: BlerError) -> Self {
+        Error::Bler(e)
+    }
+}
+
+impl From<BlineError> for Error {
+    fn from(e: BlineError) -> Self {
+        Error::Bline(e)
+    }
+}
+
+impl From<SimError> for Error {
+    fn from(e: SimError) -> Self {
+        Error::Sim(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Ir(msg) => write!(f, "{}", msg),
+            Error::ISel(msg) => write!(f, "{}", msg),
+            Error::Bler(msg) => write!(f, "{}", msg),
+            Error::Bline(msg) => write!(f, "{}", msg),
+            Error::Sim(msg) => write!(f, "{}", msg),
+            Error::Behav(msg) => write!(f, "{}", msg),
+        }
+    }
+}
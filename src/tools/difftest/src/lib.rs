@@ -0,0 +1,198 @@
+pub mod behav;
+pub mod cell;
+pub mod errors;
+
+use crate::behav::run_behav_module;
+use crate::errors::Error;
+use bler::try_from_asm_prog_with_target as asm_try_into_xir;
+use bline::try_from_ir_prog_with_mmap as ir_try_into_behav;
+use ir::ast as ir;
+use isel::cost::Cost;
+use isel::library::Library;
+use isel::try_from_ir_prog_with_cost as ir_try_into_asm;
+use mmap::Mmap;
+use sim::interp::{run_ir_prog, top_def};
+use sim::trace::{signal_from_expr, Stimulus, Trace};
+use sim::xsim::run_xir_prog;
+use std::fmt;
+use xpand::try_from_xir_prog as xir_try_into_struct;
+
+// Number of cycles of random stimulus when none is given
+pub const CYCLES: usize = 100;
+
+// Seed of the random stimulus, so a program always gets the same one
+pub const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub cycle: usize,
+    pub signal: String,
+    pub expected: Option<i128>,
+    pub found: Option<i128>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    Pass,
+    Fail(Mismatch),
+    Skip(String),
+}
+
+// Result of comparing every backend against the IR interpreter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub xir: Check,
+    pub xpand: Check,
+    pub behav: Check,
+}
+
+// first cycle and signal where res differs from exp
+pub fn first_mismatch(exp: &Trace, res: &Trace) -> Option<Mismatch> {
+    let cycles = exp.len().max(res.len());
+    let mut signal: Vec<&String> = exp.signal().iter().collect();
+    signal.extend(res.signal().iter().filter(|s| exp.index(s).is_none()));
+    for cycle in 0..cycles {
+        for s in &signal {
+            let expected = exp.get(cycle, s);
+            let found = res.get(cycle, s);
+            if expected != found {
+                return Some(Mismatch {
+                    cycle,
+                    signal: s.to_string(),
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    None
+}
+
+pub fn stimulus_from_sig(sig: &ir::Sig, cycles: usize) -> Stimulus {
+    Stimulus::random(&signal_from_expr(sig.input()), cycles, SEED)
+}
+
+pub fn stimulus_from_ir_prog(prog: &ir::Prog, cycles: usize) -> Result<Stimulus, Error> {
+    Ok(stimulus_from_sig(top_def(prog)?.sig(), cycles))
+}
+
+impl Check {
+    pub fn from_traces(exp: &Trace, res: &Trace) -> Self {
+        match first_mismatch(exp, res) {
+            Some(mismatch) => Check::Fail(mismatch),
+            None => Check::Pass,
+        }
+    }
+    pub fn is_pass(&self) -> bool {
+        matches!(self, Check::Pass)
+    }
+    pub fn is_fail(&self) -> bool {
+        matches!(self, Check::Fail(_))
+    }
+    pub fn is_skip(&self) -> bool {
+        matches!(self, Check::Skip(_))
+    }
+}
+
+impl Report {
+    pub fn xir(&self) -> &Check {
+        &self.xir
+    }
+    pub fn xpand(&self) -> &Check {
+        &self.xpand
+    }
+    pub fn behav(&self) -> &Check {
+        &self.behav
+    }
+    // every backend ran and matched the interpreter
+    pub fn is_pass(&self) -> bool {
+        self.xir.is_pass() && self.xpand.is_pass() && self.behav.is_pass()
+    }
+    // backends that do not support the program are skipped, not failed
+    pub fn is_pass_or_skip(&self) -> bool {
+        !self.xir.is_fail() && !self.xpand.is_fail() && !self.behav.is_fail()
+    }
+}
+
+// Run prog through the interpreter, the xir simulator (isel, tile and bler),
+// the structural module of that xir (xpand) and the behavioral module (bline),
+// comparing all of them against the interpreter. The structural and
+// behavioral checks are skipped when xpand or bline do not support the
+// program, or when it uses something the evaluator does not model.
+pub fn run_diff_test(
+    prog: &ir::Prog,
+    stim: &Stimulus,
+    mmap: Option<&Mmap>,
+) -> Result<Report, Error> {
+    run_diff_test_with_library(prog, stim, mmap, &Library::builtin(), &Cost::default())
+}
+
+pub fn run_diff_test_with_library(
+    prog: &ir::Prog,
+    stim: &Stimulus,
+    mmap: Option<&Mmap>,
+    lib: &Library,
+    cost: &Cost,
+) -> Result<Report, Error> {
+    let exp = run_ir_prog(prog, stim, mmap)?;
+    let asm = ir_try_into_asm(prog, lib, cost)?;
+    let (xir, xir_mmap) = asm_try_into_xir(&asm, mmap, &lib.imp())?;
+    let res = run_xir_prog(&xir, stim, xir_mmap.as_ref())?;
+    let sig = top_def(prog)?.sig();
+    let xpand = match xir_try_into_struct(&xir, xir_mmap.as_ref()) {
+        Ok(module) => match run_behav_module(&module, sig, stim) {
+            Ok(res) => Check::from_traces(&exp, &res),
+            Err(e) => Check::Skip(e.to_string()),
+        },
+        Err(e) => Check::Skip(e.to_string()),
+    };
+    let xir = Check::from_traces(&exp, &res);
+    let behav = match ir_try_into_behav(prog, mmap) {
+        Ok(module) => match run_behav_module(&module, sig, stim) {
+            Ok(res) => Check::from_traces(&exp, &res),
+            Err(e) => Check::Skip(e.to_string()),
+        },
+        Err(e) => Check::Skip(e.to_string()),
+    };
+    Ok(Report { xir, xpand, behav })
+}
+
+fn value_to_string(value: Option<i128>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "nothing".to_string(),
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle {}, signal {}: expected {}, found {}",
+            self.cycle,
+            self.signal,
+            value_to_string(self.expected),
+            value_to_string(self.found)
+        )
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Pass => write!(f, "pass"),
+            Check::Fail(mismatch) => write!(f, "fail at {}", mismatch),
+            Check::Skip(reason) => write!(f, "skip ({})", reason),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "xir: {}\nxpand: {}\nbehav: {}",
+            self.xir, self.xpand, self.behav
+        )
+    }
+}
@@ -0,0 +1,164 @@
+use difftest::behav::Behav;
+use difftest::{run_diff_test, run_diff_test_with_library, stimulus_from_ir_prog, Check, CYCLES};
+use ir::parser::Parser as IrParser;
+use isel::cost::{Cost, Select};
+use isel::library::Library;
+use mmap::Mmap;
+use sim::interp::run_ir_prog;
+use sim::trace::{Stimulus, Trace};
+use std::fs;
+use std::str::FromStr;
+use verilog::ast as vl;
+
+// Memory contents of the programs with memories
+const MMAP: [(&str, &str); 3] = [
+    ("aes_encrypt", "aes.json"),
+    ("rom_8x8", "lrom_8x8.json"),
+    ("srom_8x256", "brom_8x256.json"),
+];
+
+fn mmap_file<'a>(name: &str) -> Option<&'a str> {
+    MMAP.iter().find(|(n, _)| *n == name).map(|(_, f)| *f)
+}
+
+#[test]
+fn examples() {
+    let mut name: Vec<String> = fs::read_dir("../../../examples/ir")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |e| e == "ir"))
+        .map(|path| path.file_stem().unwrap().to_string_lossy().to_string())
+        .collect();
+    name.sort();
+    for name in &name {
+        let file = format!("../../../examples/ir/{}.ir", name);
+        let prog = IrParser::parse_from_file(file).unwrap();
+        let stim = stimulus_from_ir_prog(&prog, CYCLES).unwrap();
+        let mmap =
+            mmap_file(name).map(|file| Mmap::from_file(format!("../../../examples/mmap/{}", file)));
+        let report = run_diff_test(&prog, &stim, mmap.as_ref()).unwrap();
+        assert!(report.is_pass(), "{} {}", name, report);
+    }
+}
+
+#[test]
+fn behav_add() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/add.ir").unwrap();
+    let module = bline::try_from_ir_prog(&prog).unwrap();
+    let mut behav = Behav::try_from_module(&module).unwrap();
+    let stim = Stimulus::from_str("a b en\n1 2 1\n3 4 1\n-1 -1 0\n127 1 1\n0 0 0").unwrap();
+    let exp = run_ir_prog(&prog, &stim, None).unwrap();
+    let res = behav.run(prog.get("main").unwrap().sig(), &stim).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn behav_expr() {
+    let mut module = vl::Module::new("main");
+    for (id, width) in &[("clock", 1), ("reset", 1), ("a", 8), ("b", 8)] {
+        module.add_port(vl::Port::Input(vl::Decl::new_wire(id, *width)));
+    }
+    for (id, width) in &[("y0", 8), ("y1", 8), ("y2", 1), ("y3", 4), ("y4", 1)] {
+        module.add_port(vl::Port::Output(vl::Decl::new_wire(id, *width)));
+    }
+    let assign = vec![
+        (
+            "y0",
+            vl::Expr::new_mul(vl::Expr::new_signed_ref("a"), vl::Expr::new_signed_ref("b")),
+        ),
+        (
+            "y1",
+            vl::Expr::new_mux(
+                vl::Expr::new_ref("y2"),
+                vl::Expr::new_ref("b"),
+                vl::Expr::new_ulit_dec(8, "5"),
+            ),
+        ),
+        (
+            "y2",
+            vl::Expr::new_eq(vl::Expr::new_ref("a"), vl::Expr::new_ref("b")),
+        ),
+        (
+            "y3",
+            vl::Expr::new_bit_and(
+                vl::Expr::new_slice("a", vl::Expr::new_int(7), vl::Expr::new_int(4)),
+                vl::Expr::new_slice("b", vl::Expr::new_int(3), vl::Expr::new_int(0)),
+            ),
+        ),
+        ("y4", vl::Expr::new_index_bit("a", 0)),
+    ];
+    for (id, expr) in assign {
+        let stmt = vl::Parallel::Assign(vl::Expr::new_ref(id), expr);
+        module.add_stmt(vl::Stmt::from(stmt));
+    }
+    let mut behav = Behav::try_from_module(&module).unwrap();
+    let prog = IrParser::parse_from_str(
+        "def main(a:i8, b:i8) -> (y0:i8, y1:u8, y2:bool, y3:u4, y4:bool) {
+            y0:i8 = mul(a, b);
+        }",
+    )
+    .unwrap();
+    let stim = Stimulus::from_str("a b\n-3 5\n0x12 0x12").unwrap();
+    let exp = Trace::from_str("y0 y1 y2 y3 y4\n-15 5 0 5 1\n68 0x12 1 0 0").unwrap();
+    let res = behav.run(prog.get("main").unwrap().sig(), &stim).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn select() {
+    let lib = Library::builtin();
+    for name in &["fsm_3", "mux128", "tadd_64", "tdot_5_3"] {
+        let file = format!("../../../examples/ir/{}.ir", name);
+        let prog = IrParser::parse_from_file(file).unwrap();
+        let stim = stimulus_from_ir_prog(&prog, CYCLES).unwrap();
+        for select in &[Select::Dp, Select::Exact] {
+            let mut cost = Cost::default();
+            cost.set_select(select.clone());
+            let report = run_diff_test_with_library(&prog, &stim, None, &lib, &cost).unwrap();
+            assert_eq!(report.xir(), &Check::Pass, "{} {}", name, select);
+        }
+    }
+}
+
+// and and eq of the same inputs are selected as one lut with two outputs,
+// blasted by bler into the o6 and o5 luts that xsim runs
+#[test]
+fn multi_output() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/landeq.ir").unwrap();
+    let asm = isel::try_from_ir_prog(&prog).unwrap();
+    assert!(asm
+        .to_string()
+        .contains("(t0:bool, t1:bool) = landeq_bool(a, b)"));
+    let stim = stimulus_from_ir_prog(&prog, CYCLES).unwrap();
+    let report = run_diff_test(&prog, &stim, None).unwrap();
+    assert!(report.is_pass(), "{}", report);
+}
+
+#[test]
+fn split() {
+    let prog = IrParser::parse_from_str(
+        "def main(a:i16, b:i16, c:i32, d:i32) -> (y:i16, z:i32) {
+            y:i16 = add(a, b) @lut;
+            z:i32 = xor(c, d) @lut;
+        }",
+    )
+    .unwrap();
+    let stim = stimulus_from_ir_prog(&prog, CYCLES).unwrap();
+    let report = run_diff_test(&prog, &stim, None).unwrap();
+    assert_eq!(report.xir(), &Check::Pass, "{}", report);
+}
+
+#[test]
+fn generic() {
+    let prog = IrParser::parse_from_str(
+        "def main(a:i3, b:i3, s:bool, c:i12, d:i12) -> (y:i3, z:i12) {
+            t0:i3 = mux(s, a, b) @lut;
+            y:i3 = reg[0](t0, s) @lut;
+            z:i12 = add(c, d) @lut;
+        }",
+    )
+    .unwrap();
+    let stim = stimulus_from_ir_prog(&prog, CYCLES).unwrap();
+    let report = run_diff_test(&prog, &stim, None).unwrap();
+    assert_eq!(report.xir(), &Check::Pass, "{}", report);
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+// A literal is a node index times two, plus one when negated. Node 0 is the
+// constant false, so literal 0 is false and literal 1 is true.
+pub type Lit = u32;
+
+// Bits of a value, the least significant one first
+pub type Word = Vec<Lit>;
+
+pub const FALSE: Lit = 0;
+pub const TRUE: Lit = 1;
+
+pub fn neg(a: Lit) -> Lit {
+    a ^ 1
+}
+
+pub fn var(a: Lit) -> usize {
+    (a >> 1) as usize
+}
+
+pub fn is_neg(a: Lit) -> bool {
+    a & 1 == 1
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Const,
+    Input,
+    And(Lit, Lit),
+}
+
+// And-inverter graph with structural hashing, so both sides of a check share
+// every node they build the same way
+#[derive(Clone, Debug)]
+pub struct Aig {
+    pub node: Vec<Node>,
+    pub hash: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Aig {
+            node: vec![Node::Const],
+            hash: HashMap::new(),
+        }
+    }
+}
+
+impl Aig {
+    pub fn node(&self) -> &Vec<Node> {
+        &self.node
+    }
+    pub fn len(&self) -> usize {
+        self.node.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.node.len() == 1
+    }
+    pub fn input(&mut self) -> Lit {
+        let lit = (self.node.len() as Lit) << 1;
+        self.node.push(Node::Input);
+        lit
+    }
+    pub fn word(&mut self, width: u64) -> Word {
+        (0..width).map(|_| self.input()).collect()
+    }
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        if a == FALSE || a == neg(b) {
+            return FALSE;
+        }
+        if a == TRUE || a == b {
+            return b;
+        }
+        if let Some(lit) = self.hash.get(&(a, b)) {
+            return *lit;
+        }
+        let lit = (self.node.len() as Lit) << 1;
+        self.node.push(Node::And(a, b));
+        self.hash.insert((a, b), lit);
+        lit
+    }
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        neg(self.and(neg(a), neg(b)))
+    }
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let x = self.and(a, neg(b));
+        let y = self.and(neg(a), b);
+        self.or(x, y)
+    }
+    pub fn mux(&mut self, sel: Lit, a: Lit, b: Lit) -> Lit {
+        let x = self.and(sel, a);
+        let y = self.and(neg(sel), b);
+        self.or(x, y)
+    }
+    pub fn maj(&mut self, a: Lit, b: Lit, c: Lit) -> Lit {
+        let x = self.and(a, b);
+        let y = self.or(a, b);
+        let z = self.and(c, y);
+        self.or(x, z)
+    }
+    pub fn const_word(&self, bits: u128, width: u64) -> Word {
+        (0..width)
+            .map(|i| {
+                if i < 128 && (bits >> i) & 1 == 1 {
+                    TRUE
+                } else {
+                    FALSE
+                }
+            })
+            .collect()
+    }
+    // truncated or extended to width, with the sign bit if signed
+    pub fn resize(&self, a: &[Lit], width: u64, signed: bool) -> Word {
+        let fill = match a.last() {
+            Some(msb) if signed => *msb,
+            _ => FALSE,
+        };
+        (0..width as usize)
+            .map(|i| a.get(i).cloned().unwrap_or(fill))
+            .collect()
+    }
+    pub fn not_word(&mut self, a: &[Lit]) -> Word {
+        a.iter().map(|x| neg(*x)).collect()
+    }
+    pub fn and_word(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| self.and(*x, *y))
+            .collect()
+    }
+    pub fn or_word(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| self.or(*x, *y))
+            .collect()
+    }
+    pub fn xor_word(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| self.xor(*x, *y))
+            .collect()
+    }
+    pub fn mux_word(&mut self, sel: Lit, a: &[Lit], b: &[Lit]) -> Word {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| self.mux(sel, *x, *y))
+            .collect()
+    }
+    // ripple-carry sum and carry out
+    pub fn add_word(&mut self, a: &[Lit], b: &[Lit], ci: Lit) -> (Word, Lit) {
+        let mut carry = ci;
+        let mut sum: Word = Vec::new();
+        for (x, y) in a.iter().zip(b.iter()) {
+            let s = self.xor(*x, *y);
+            sum.push(self.xor(s, carry));
+            carry = self.maj(*x, *y, carry);
+        }
+        (sum, carry)
+    }
+    pub fn sub_word(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        let b = self.not_word(b);
+        self.add_word(a, &b, TRUE).0
+    }
+    // product truncated to the width of a, shift and add
+    pub fn mul_word(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        let width = a.len();
+        let mut acc = self.const_word(0, width as u64);
+        for (i, y) in b.iter().enumerate().take(width) {
+            let mut part = self.const_word(0, i as u64);
+            for x in a.iter().take(width - i) {
+                part.push(self.and(*x, *y));
+            }
+            acc = self.add_word(&acc, &part, FALSE).0;
+        }
+        acc
+    }
+    pub fn eq_word(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let mut res = TRUE;
+        for (x, y) in a.iter().zip(b.iter()) {
+            let d = self.xor(*x, *y);
+            res = self.and(res, neg(d));
+        }
+        res
+    }
+    // a < b, the borrow out of a - b
+    pub fn lt_word(&mut self, a: &[Lit], b: &[Lit], signed: bool) -> Lit {
+        let (mut a, mut b) = (a.to_vec(), b.to_vec());
+        // flipping the sign bits turns a signed compare into an unsigned one
+        if signed {
+            if let (Some(x), Some(y)) = (a.last_mut(), b.last_mut()) {
+                *x = neg(*x);
+                *y = neg(*y);
+            }
+        }
+        let nb = self.not_word(&b);
+        let (_, carry) = self.add_word(&a, &nb, TRUE);
+        neg(carry)
+    }
+    // lut output as a tree of muxes over INIT, the first input being the lsb
+    pub fn lut(&mut self, init: u64, input: &[Lit]) -> Lit {
+        self.lut_from(init, 0, input)
+    }
+    fn lut_from(&mut self, init: u64, index: u64, input: &[Lit]) -> Lit {
+        match input.split_last() {
+            None => {
+                if (init >> index) & 1 == 1 {
+                    TRUE
+                } else {
+                    FALSE
+                }
+            }
+            Some((last, rest)) => {
+                let bit = 1 << rest.len();
+                let hi = self.lut_from(init, index | bit, rest);
+                let lo = self.lut_from(init, index, rest);
+                self.mux(*last, hi, lo)
+            }
+        }
+    }
+    // clauses of the cone of root with root asserted, one variable per node
+    pub fn cnf(&self, root: Lit) -> Vec<Vec<Lit>> {
+        let mut clause: Vec<Vec<Lit>> = vec![vec![TRUE], vec![root]];
+        let mut seen = vec![false; self.node.len()];
+        let mut stack = vec![var(root)];
+        while let Some(n) = stack.pop() {
+            if seen[n] {
+                continue;
+            }
+            seen[n] = true;
+            if let Node::And(a, b) = self.node[n] {
+                let out = (n as Lit) << 1;
+                clause.push(vec![neg(out), a]);
+                clause.push(vec![neg(out), b]);
+                clause.push(vec![out, neg(a), neg(b)]);
+                stack.push(var(a));
+                stack.push(var(b));
+            }
+        }
+        clause
+    }
+}
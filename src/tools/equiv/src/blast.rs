@@ -0,0 +1,554 @@
+use crate::aig::{Aig, Lit, Word, FALSE, TRUE};
+use crate::errors::Error;
+use ir::ast as ir;
+use sim::interp::top_def;
+use sim::trace::{lanes, term_width};
+use sim::xsim::{dsp_reg, is_seq, sort_body};
+use std::collections::HashMap;
+use xir::ast as xir;
+
+// Symbolic values of a cycle, one word per lane
+pub type Env = HashMap<ir::Id, Vec<Word>>;
+
+fn arg<'a>(env: &'a Env, expr: &ir::Expr, index: usize) -> Result<&'a Vec<Word>, Error> {
+    let id = expr.get_id(index)?;
+    match env.get(&id) {
+        Some(word) => Ok(word),
+        None => {
+            let err = format!("{} is not defined", id);
+            Err(Error::new_equiv_error(&err))
+        }
+    }
+}
+
+fn arg_signed(expr: &ir::Expr, index: usize) -> Result<bool, Error> {
+    Ok(expr.get_ty(index)?.is_signed())
+}
+
+// lanes of every output term, in the order of the signature
+fn sample(env: &Env, output: &ir::Expr) -> Result<Vec<Word>, Error> {
+    let term: Vec<ir::ExprTerm> = output.clone().into();
+    let mut res: Vec<Word> = Vec::new();
+    for t in term {
+        let id = t.get_id()?;
+        match env.get(&id) {
+            Some(word) => res.extend(word.iter().cloned()),
+            None => {
+                let err = format!("{} is never assigned", id);
+                return Err(Error::new_equiv_error(&err));
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn unsupported(op: &str) -> Error {
+    let err = format!("{} is not supported by the equivalence checker", op);
+    Error::new_equiv_error(&err)
+}
+
+fn eval_ir_wire(aig: &mut Aig, env: &Env, instr: &ir::InstrWire) -> Result<Vec<Word>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let width = term_width(dst)?;
+    let len = lanes(dst.get_ty()?);
+    match instr.op() {
+        ir::OpWire::Id => {
+            let a = arg(env, instr.arg(), 0)?;
+            Ok(a.iter().map(|x| aig.resize(x, width, false)).collect())
+        }
+        ir::OpWire::Con => {
+            let val = instr.attr().get_val(0)?;
+            let word = aig.const_word(i128::from(val) as u128, width);
+            Ok(vec![word; len])
+        }
+        ir::OpWire::Sll | ir::OpWire::Srl | ir::OpWire::Sra => {
+            let amount = instr.attr().get_val(0)?.max(0) as usize;
+            let signed = matches!(instr.op(), ir::OpWire::Sra);
+            let a = arg(env, instr.arg(), 0)?;
+            let mut res: Vec<Word> = Vec::new();
+            for x in a {
+                let word: Word = match instr.op() {
+                    ir::OpWire::Sll => (0..width as usize)
+                        .map(|i| {
+                            if i < amount {
+                                FALSE
+                            } else {
+                                x.get(i - amount).cloned().unwrap_or(FALSE)
+                            }
+                        })
+                        .collect(),
+                    _ => {
+                        let fill = if signed {
+                            x.last().cloned().unwrap_or(FALSE)
+                        } else {
+                            FALSE
+                        };
+                        (0..width as usize)
+                            .map(|i| x.get(i + amount).cloned().unwrap_or(fill))
+                            .collect()
+                    }
+                };
+                res.push(word);
+            }
+            Ok(res)
+        }
+        ir::OpWire::Ext => {
+            let lo = instr.attr().get_val(0)? as usize;
+            let a = arg(env, instr.arg(), 0)?;
+            let res = a.iter().map(|x| {
+                let part: Word = x.iter().skip(lo).cloned().collect();
+                aig.resize(&part, width, false)
+            });
+            Ok(res.collect())
+        }
+        // the first argument ends up in the most significant bits
+        ir::OpWire::Cat => {
+            let term: Vec<ir::ExprTerm> = instr.arg().clone().into();
+            let mut acc: Word = Vec::new();
+            for index in (0..term.len()).rev() {
+                let a = arg(env, instr.arg(), index)?;
+                acc.extend(a[0].iter().cloned());
+            }
+            Ok(vec![aig.resize(&acc, width, false)])
+        }
+    }
+}
+
+fn eval_ir_prim(
+    aig: &mut Aig,
+    env: &Env,
+    state: &Env,
+    instr: &ir::InstrPrim,
+) -> Result<Vec<Word>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let id = dst.get_id()?;
+    let width = term_width(dst)?;
+    match instr.op() {
+        ir::OpPrim::Reg => match state.get(&id) {
+            Some(word) => Ok(word.clone()),
+            None => {
+                let err = format!("{} does not have a state", id);
+                Err(Error::new_equiv_error(&err))
+            }
+        },
+        ir::OpPrim::Not => {
+            let a = arg(env, instr.arg(), 0)?;
+            Ok(a.iter()
+                .map(|x| aig.not_word(&aig.resize(x, width, false)))
+                .collect())
+        }
+        ir::OpPrim::Add
+        | ir::OpPrim::Sub
+        | ir::OpPrim::Mul
+        | ir::OpPrim::And
+        | ir::OpPrim::Or
+        | ir::OpPrim::Xor => {
+            let a = arg(env, instr.arg(), 0)?;
+            let b = arg(env, instr.arg(), 1)?;
+            let sa = arg_signed(instr.arg(), 0)?;
+            let sb = arg_signed(instr.arg(), 1)?;
+            let mut res: Vec<Word> = Vec::new();
+            for (x, y) in a.iter().zip(b.iter()) {
+                let x = aig.resize(x, width, sa);
+                let y = aig.resize(y, width, sb);
+                let word = match instr.op() {
+                    ir::OpPrim::Add => aig.add_word(&x, &y, FALSE).0,
+                    ir::OpPrim::Sub => aig.sub_word(&x, &y),
+                    ir::OpPrim::Mul => aig.mul_word(&x, &y),
+                    ir::OpPrim::And => aig.and_word(&x, &y),
+                    ir::OpPrim::Or => aig.or_word(&x, &y),
+                    _ => aig.xor_word(&x, &y),
+                };
+                res.push(word);
+            }
+            Ok(res)
+        }
+        ir::OpPrim::Mux => {
+            let sel = arg(env, instr.arg(), 0)?[0][0];
+            let a = arg(env, instr.arg(), 1)?;
+            let b = arg(env, instr.arg(), 2)?;
+            let res = a.iter().zip(b.iter()).map(|(x, y)| {
+                let x = aig.resize(x, width, false);
+                let y = aig.resize(y, width, false);
+                aig.mux_word(sel, &x, &y)
+            });
+            Ok(res.collect())
+        }
+        ir::OpPrim::Eql
+        | ir::OpPrim::Neql
+        | ir::OpPrim::Gt
+        | ir::OpPrim::Lt
+        | ir::OpPrim::Ge
+        | ir::OpPrim::Le => {
+            let a = arg(env, instr.arg(), 0)?;
+            let b = arg(env, instr.arg(), 1)?;
+            // the signedness of the first argument applies to both, as in the interpreter
+            let signed = arg_signed(instr.arg(), 0)?;
+            let mut res: Vec<Word> = Vec::new();
+            for (x, y) in a.iter().zip(b.iter()) {
+                let w = x.len().max(y.len()) as u64;
+                let x = aig.resize(x, w, signed);
+                let y = aig.resize(y, w, signed);
+                let bit = match instr.op() {
+                    ir::OpPrim::Eql => aig.eq_word(&x, &y),
+                    ir::OpPrim::Neql => aig.eq_word(&x, &y) ^ 1,
+                    ir::OpPrim::Lt => aig.lt_word(&x, &y, signed),
+                    ir::OpPrim::Gt => aig.lt_word(&y, &x, signed),
+                    ir::OpPrim::Ge => aig.lt_word(&x, &y, signed) ^ 1,
+                    _ => aig.lt_word(&y, &x, signed) ^ 1,
+                };
+                res.push(vec![bit]);
+            }
+            Ok(res)
+        }
+        op => Err(unsupported(&op.to_string())),
+    }
+}
+
+// Outputs of every cycle of an ir program, starting from the reset state
+pub fn blast_ir_prog(
+    aig: &mut Aig,
+    prog: &ir::Prog,
+    input: &[Env],
+) -> Result<Vec<Vec<Word>>, Error> {
+    let mut def = top_def(prog)?.clone();
+    def.sort_body()?;
+    let mut state = Env::new();
+    for instr in def.body() {
+        if let ir::Instr::Prim(instr) = instr {
+            if instr.is_reg() {
+                let dst = instr.dst().get_term(0)?;
+                let init = instr.attr().get_val(0)?;
+                let word = aig.const_word(i128::from(init) as u128, term_width(dst)?);
+                state.insert(dst.get_id()?, vec![word; lanes(dst.get_ty()?)]);
+            }
+        }
+    }
+    let mut output: Vec<Vec<Word>> = Vec::new();
+    for cycle in input {
+        let mut env = cycle.clone();
+        for instr in def.body() {
+            let word = match instr {
+                ir::Instr::Wire(instr) => eval_ir_wire(aig, &env, instr)?,
+                ir::Instr::Prim(instr) => eval_ir_prim(aig, &env, &state, instr)?,
+                ir::Instr::Call(instr) => return Err(unsupported(&instr.op().to_string())),
+            };
+            env.insert(instr.dst().get_id(0)?, word);
+        }
+        output.push(sample(&env, def.output())?);
+        let mut next = Env::new();
+        for instr in def.body() {
+            if let ir::Instr::Prim(instr) = instr {
+                if instr.is_reg() {
+                    let id = instr.dst().get_id(0)?;
+                    let d = arg(&env, instr.arg(), 0)?;
+                    let en = arg(&env, instr.arg(), 1)?[0][0];
+                    let q = &state[&id];
+                    let word = d.iter().zip(q.iter()).map(|(d, q)| aig.mux_word(en, d, q));
+                    next.insert(id, word.collect());
+                }
+            }
+        }
+        state.extend(next);
+    }
+    Ok(output)
+}
+
+// pipeline registers of a dsp, one word per lane
+#[derive(Clone, Debug, Default)]
+struct Dsp {
+    a1: Vec<Word>,
+    b1: Vec<Word>,
+    a: Vec<Word>,
+    b: Vec<Word>,
+    m: Vec<Word>,
+    p: Vec<Word>,
+}
+
+fn eval_xir_basc(aig: &mut Aig, env: &Env, instr: &xir::InstrBasc) -> Result<Vec<Word>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let width = term_width(dst)?;
+    let len = lanes(dst.get_ty()?);
+    match instr.op() {
+        xir::OpBasc::Id => {
+            let a = arg(env, instr.arg(), 0)?;
+            Ok(a.iter().map(|x| aig.resize(x, width, false)).collect())
+        }
+        xir::OpBasc::Gnd => Ok(vec![aig.const_word(0, width); len]),
+        xir::OpBasc::Vcc => Ok(vec![vec![TRUE; width as usize]; len]),
+        xir::OpBasc::Ext => {
+            let lo = instr.attr().get_val(0)? as usize;
+            let a = arg(env, instr.arg(), 0)?;
+            let res = a.iter().map(|x| {
+                let part: Word = x.iter().skip(lo).cloned().collect();
+                aig.resize(&part, width, false)
+            });
+            Ok(res.collect())
+        }
+        // unlike ir, the first argument ends up in the least significant bits
+        xir::OpBasc::Cat => {
+            let term: Vec<ir::ExprTerm> = instr.arg().clone().into();
+            let mut acc: Word = Vec::new();
+            for index in 0..term.len() {
+                let a = arg(env, instr.arg(), index)?;
+                acc.extend(a[0].iter().cloned());
+            }
+            Ok(vec![aig.resize(&acc, width, false)])
+        }
+    }
+}
+
+fn eval_xir_mach(
+    aig: &mut Aig,
+    env: &Env,
+    state: &Env,
+    dsp: &HashMap<ir::Id, Dsp>,
+    instr: &xir::InstrMach,
+) -> Result<Vec<Word>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let id = dst.get_id()?;
+    let width = term_width(dst)?;
+    if let Some(inputs) = instr.op().lut_inputs() {
+        let init = instr.attr().get_val(0)? as u64;
+        let mut input: Vec<Lit> = Vec::new();
+        for index in 0..inputs {
+            input.push(arg(env, instr.arg(), index)?[0][0]);
+        }
+        return Ok(vec![vec![aig.lut(init, &input)]]);
+    }
+    match instr.op() {
+        xir::OpMach::Fdre | xir::OpMach::Fdse => match state.get(&id) {
+            Some(word) => Ok(word.clone()),
+            None => {
+                let err = format!("{} does not have a state", id);
+                Err(Error::new_equiv_error(&err))
+            }
+        },
+        op if is_seq(op) => match dsp.get(&id) {
+            Some(dsp) => Ok(dsp.p.clone()),
+            None => {
+                let err = format!("{} does not have a state", id);
+                Err(Error::new_equiv_error(&err))
+            }
+        },
+        // carry chain where DI is the first argument and S the second one
+        xir::OpMach::CarryAdd | xir::OpMach::CarrySub => {
+            let di = &arg(env, instr.arg(), 0)?[0];
+            let s = &arg(env, instr.arg(), 1)?[0];
+            let mut carry = if matches!(instr.op(), xir::OpMach::CarrySub) {
+                TRUE
+            } else {
+                FALSE
+            };
+            let mut res: Word = Vec::new();
+            for bit in 0..width as usize {
+                let s = s.get(bit).cloned().unwrap_or(FALSE);
+                let di = di.get(bit).cloned().unwrap_or(FALSE);
+                res.push(aig.xor(s, carry));
+                carry = aig.mux(s, carry, di);
+            }
+            Ok(vec![res])
+        }
+        xir::OpMach::VecAdd
+        | xir::OpMach::VecSub
+        | xir::OpMach::VecMul
+        | xir::OpMach::Mul
+        | xir::OpMach::MulAdd => {
+            let a = arg(env, instr.arg(), 0)?;
+            let b = arg(env, instr.arg(), 1)?;
+            let mut res: Vec<Word> = Vec::new();
+            for (lane, x) in a.iter().enumerate() {
+                let x = aig.resize(x, width, false);
+                let y = aig.resize(&b[lane], width, false);
+                let word = match instr.op() {
+                    xir::OpMach::VecAdd => aig.add_word(&x, &y, FALSE).0,
+                    xir::OpMach::VecSub => aig.sub_word(&x, &y),
+                    xir::OpMach::MulAdd => {
+                        let c = arg(env, instr.arg(), 2)?;
+                        let z = aig.resize(&c[lane], width, false);
+                        let m = aig.mul_word(&x, &y);
+                        aig.add_word(&m, &z, FALSE).0
+                    }
+                    _ => aig.mul_word(&x, &y),
+                };
+                res.push(word);
+            }
+            Ok(res)
+        }
+        op => Err(unsupported(&op.to_string())),
+    }
+}
+
+fn commit_dsp(
+    aig: &mut Aig,
+    env: &Env,
+    instr: &xir::InstrMach,
+    dsp: &Dsp,
+    width: u64,
+) -> Result<Dsp, Error> {
+    let mut next = dsp.clone();
+    let en = |index: usize| -> Result<Lit, Error> { Ok(arg(env, instr.arg(), index)?[0][0]) };
+    let a = arg(env, instr.arg(), 0)?;
+    let b = arg(env, instr.arg(), 1)?;
+    let lane = |aig: &mut Aig, sel: Lit, d: &[Word], q: &[Word]| -> Vec<Word> {
+        d.iter()
+            .zip(q.iter())
+            .map(|(d, q)| {
+                let d = aig.resize(d, width, false);
+                aig.mux_word(sel, &d, q)
+            })
+            .collect()
+    };
+    if matches!(
+        instr.op(),
+        xir::OpMach::VecAddRegA
+            | xir::OpMach::VecAddRegACi
+            | xir::OpMach::VecAddRegACo
+            | xir::OpMach::VecAddRegACio
+    ) {
+        // the cascade input bypasses the b register
+        let y: Vec<Word> = if instr.op().is_cascade_unregistered() {
+            b.iter().map(|w| aig.resize(w, width, false)).collect()
+        } else {
+            dsp.b.clone()
+        };
+        let sum: Vec<Word> = dsp
+            .a
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| aig.add_word(x, y, FALSE).0)
+            .collect();
+        next.p = lane(aig, en(4)?, &sum, &dsp.p);
+        next.a = lane(aig, en(2)?, a, &dsp.a);
+        next.b = lane(aig, en(3)?, b, &dsp.b);
+    } else {
+        // the cascade variants compute the same value, only the routing changes
+        let reg = dsp_reg(instr)?;
+        let c = arg(env, instr.arg(), 2)?;
+        let resize = |aig: &mut Aig, w: &[Word]| -> Vec<Word> {
+            w.iter().map(|w| aig.resize(w, width, false)).collect()
+        };
+        // stages without registers pass their input through
+        let x = if reg[0] == 0 {
+            resize(aig, a)
+        } else {
+            dsp.a.clone()
+        };
+        let y = if reg[1] == 0 {
+            resize(aig, b)
+        } else {
+            dsp.b.clone()
+        };
+        let mul: Vec<Word> = x
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| aig.mul_word(x, y))
+            .collect();
+        let m = if reg[2] == 0 { &mul } else { &dsp.m };
+        let sum: Vec<Word> = m
+            .iter()
+            .zip(c.iter())
+            .map(|(x, y)| {
+                let y = aig.resize(y, width, false);
+                aig.add_word(x, &y, FALSE).0
+            })
+            .collect();
+        next.p = lane(aig, en(6)?, &sum, &dsp.p);
+        if reg[2] == 1 {
+            next.m = lane(aig, en(5)?, &mul, &dsp.m);
+        }
+        next.a1 = lane(aig, en(3)?, a, &dsp.a1);
+        next.b1 = lane(aig, en(4)?, b, &dsp.b1);
+        next.a = match reg[0] {
+            2 => lane(aig, en(3)?, &dsp.a1, &dsp.a),
+            _ => lane(aig, en(3)?, a, &dsp.a),
+        };
+        next.b = match reg[1] {
+            2 => lane(aig, en(4)?, &dsp.b1, &dsp.b),
+            _ => lane(aig, en(4)?, b, &dsp.b),
+        };
+    }
+    Ok(next)
+}
+
+// Outputs of every cycle of an xir program, starting from the reset state
+pub fn blast_xir_prog(
+    aig: &mut Aig,
+    prog: &xir::Prog,
+    input: &[Env],
+) -> Result<Vec<Vec<Word>>, Error> {
+    let body = sort_body(prog)?;
+    let mut state = Env::new();
+    let mut dsp: HashMap<ir::Id, Dsp> = HashMap::new();
+    for instr in body.iter() {
+        if let xir::Instr::Mach(instr) = instr {
+            let dst = instr.dst().get_term(0)?;
+            let id = dst.get_id()?;
+            let width = term_width(dst)?;
+            let len = lanes(dst.get_ty()?);
+            match instr.op() {
+                // the state after reset, fdse is set and fdre is cleared
+                xir::OpMach::Fdre => {
+                    state.insert(id, vec![aig.const_word(0, width); len]);
+                }
+                xir::OpMach::Fdse => {
+                    state.insert(id, vec![vec![TRUE; width as usize]; len]);
+                }
+                op if is_seq(op) => {
+                    if matches!(op, xir::OpMach::Bram | xir::OpMach::Brom) {
+                        return Err(unsupported(&op.to_string()));
+                    }
+                    let zero = vec![aig.const_word(0, width); len];
+                    let reg = Dsp {
+                        a1: zero.clone(),
+                        b1: zero.clone(),
+                        a: zero.clone(),
+                        b: zero.clone(),
+                        m: zero.clone(),
+                        p: zero,
+                    };
+                    dsp.insert(id, reg);
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut output: Vec<Vec<Word>> = Vec::new();
+    for cycle in input {
+        let mut env = cycle.clone();
+        for instr in body.iter() {
+            let word = match instr {
+                xir::Instr::Basc(instr) => eval_xir_basc(aig, &env, instr)?,
+                xir::Instr::Mach(instr) => eval_xir_mach(aig, &env, &state, &dsp, instr)?,
+            };
+            env.insert(instr.dst().get_id(0)?, word);
+        }
+        output.push(sample(&env, prog.sig().output())?);
+        let mut next = Env::new();
+        let mut next_dsp: HashMap<ir::Id, Dsp> = HashMap::new();
+        for instr in body.iter() {
+            if let xir::Instr::Mach(instr) = instr {
+                let dst = instr.dst().get_term(0)?;
+                let id = dst.get_id()?;
+                match instr.op() {
+                    xir::OpMach::Fdre | xir::OpMach::Fdse => {
+                        let d = arg(&env, instr.arg(), 0)?;
+                        let ce = arg(&env, instr.arg(), 1)?[0][0];
+                        let q = &state[&id];
+                        let word = d.iter().zip(q.iter()).map(|(d, q)| aig.mux_word(ce, d, q));
+                        next.insert(id, word.collect());
+                    }
+                    op if is_seq(op) => {
+                        let width = term_width(dst)?;
+                        let cur = &dsp[&id];
+                        next_dsp.insert(id.clone(), commit_dsp(aig, &env, instr, cur, width)?);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        state.extend(next);
+        dsp.extend(next_dsp);
+    }
+    Ok(output)
+}
@@ -0,0 +1,65 @@
+use bler::errors::Error as BlerError;
+use ir::errors::Error as IrError;
+use pat::errors::Error as PatError;
+use sim::errors::Error as SimError;
+use std::fmt;
+use xim::errors::Error as XimError;
+
+#[derive(Debug)]
+pub enum Error {
+    Ir(IrError),
+    Pat(PatError),
+    Xim(XimError),
+    Bler(BlerError),
+    Sim(SimError),
+    Equiv(String),
+}
+
+impl Error {
+    pub fn new_equiv_error(msg: &str) -> Self {
+        Error::Equiv(msg.to_string())
+    }
+}
+
+impl From<IrError> for Error {
+    fn from(e: IrError) -> Self {
+        Error::Ir(e)
+    }
+}
+
+impl From<PatError> for Error {
+    fn from(e: PatError) -> Self {
+        Error::Pat(e)
+    }
+}
+
+impl From<XimError> for Error {
+    fn from(e: XimError) -> Self {
+        Error::Xim(e)
+    }
+}
+
+impl From<BlerError> for Error {
+    fn from(e: BlerError) -> Self {
+        Error::Bler(e)
+    }
+}
+
+impl From<SimError> for Error {
+    fn from(e: SimError) -> Self {
+        Error::Sim(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Ir(msg) => write!(f, "{}", msg),
+            Error::Pat(msg) => write!(f, "{}", msg),
+            Error::Xim(msg) => write!(f, "{}", msg),
+            Error::Bler(msg) => write!(f, "{}", msg),
+            Error::Sim(msg) => write!(f, "{}", msg),
+            Error::Equiv(msg) => write!(f, "{}", msg),
+        }
+    }
+}
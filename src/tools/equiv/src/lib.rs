@@ -0,0 +1,416 @@
+pub mod aig;
+pub mod blast;
+pub mod errors;
+pub mod sat;
+
+use crate::aig::{var, Aig, Lit, FALSE};
+use crate::blast::{blast_ir_prog, blast_xir_prog, Env};
+use crate::errors::Error;
+use crate::sat::Solver;
+use ::pat::parser::Parser as PatParser;
+use ::xim::parser::Parser as XimParser;
+use asm::ast as asm;
+use bler::try_from_asm_prog_with_target;
+use generic::instance_name;
+use ir::ast as ir;
+use pat::ast as pat;
+use sim::bits::{bits_to_value, value_to_bits};
+use sim::interp::run_ir_prog;
+use sim::trace::{lane_names, lanes, signal_from_expr, term_width, Stimulus, Trace};
+use sim::xsim::run_xir_prog;
+use std::fmt;
+use std::path::Path;
+use tile::tile_from_prog_with_target;
+use xim::ast as xim;
+use xir::ast as xir;
+
+// Pairs with at most this many input bits over all cycles are simulated for
+// every input, wider ones are checked with the sat solver
+pub const EXHAUSTIVE_BITS: u64 = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    Exhaustive,
+    Sat,
+}
+
+// Inputs from reset on which the implementation differs from the pattern
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    pub stimulus: Stimulus,
+    pub cycle: usize,
+    pub signal: String,
+    pub expected: Option<i128>,
+    pub found: Option<i128>,
+}
+
+// A pattern with registers is only checked over a bounded number of cycles
+// from reset, one more than its registers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Proved(Method),
+    Bounded(usize),
+    Refuted(Counterexample),
+    Skipped(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub verdict: Verdict,
+}
+
+impl Counterexample {
+    pub fn stimulus(&self) -> &Stimulus {
+        &self.stimulus
+    }
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+    pub fn signal(&self) -> &String {
+        &self.signal
+    }
+    pub fn expected(&self) -> Option<i128> {
+        self.expected
+    }
+    pub fn found(&self) -> Option<i128> {
+        self.found
+    }
+}
+
+impl Verdict {
+    pub fn is_proved(&self) -> bool {
+        matches!(self, Verdict::Proved(_))
+    }
+    pub fn is_bounded(&self) -> bool {
+        matches!(self, Verdict::Bounded(_))
+    }
+    pub fn is_skipped(&self) -> bool {
+        matches!(self, Verdict::Skipped(_))
+    }
+    pub fn is_refuted(&self) -> bool {
+        matches!(self, Verdict::Refuted(_))
+    }
+    pub fn counterexample(&self) -> Option<&Counterexample> {
+        match self {
+            Verdict::Refuted(cex) => Some(cex),
+            _ => None,
+        }
+    }
+}
+
+impl Check {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn verdict(&self) -> &Verdict {
+        &self.verdict
+    }
+}
+
+fn show(val: Option<i128>) -> String {
+    match val {
+        Some(v) => v.to_string(),
+        None => "nothing".to_string(),
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Exhaustive => write!(f, "exhaustive simulation"),
+            Method::Sat => write!(f, "sat"),
+        }
+    }
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} is {} instead of {} at cycle {}, with inputs",
+            self.signal,
+            show(self.found),
+            show(self.expected),
+            self.cycle
+        )?;
+        write!(f, "{}", self.stimulus)
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.verdict {
+            Verdict::Proved(method) => write!(f, "{}: proved by {}", self.name, method),
+            Verdict::Bounded(depth) => write!(
+                f,
+                "{}: equivalent for {} cycles from reset",
+                self.name, depth
+            ),
+            Verdict::Refuted(cex) => write!(f, "{}: refuted, {}", self.name, cex),
+            Verdict::Skipped(reason) => write!(f, "{}: skipped, {}", self.name, reason),
+        }
+    }
+}
+
+fn prog_from_pat(input: &pat::Pat) -> ir::Prog {
+    let mut def = ir::Def::default();
+    def.set_sig(input.sig().clone());
+    def.set_body(input.body().iter().cloned().map(ir::Instr::from).collect());
+    let mut prog = ir::Prog::default();
+    prog.insert(&input.id(), def);
+    prog
+}
+
+// the pattern used once, then tiled and assembled like a selected program
+fn xir_from_pat(input: &pat::Pat, target: &xim::Target) -> Result<xir::Prog, Error> {
+    let prim = input
+        .body()
+        .iter()
+        .find_map(|instr| match instr {
+            pat::Instr::Prim(p) => Some(p.prim().clone()),
+            _ => None,
+        })
+        .unwrap_or(ir::Prim::Any);
+    let instr = asm::InstrAsm {
+        op: asm::OpAsm::from(input.id()),
+        dst: input.output().clone(),
+        arg: input.input().clone(),
+        loc: asm::Loc {
+            prim,
+            x: asm::ExprCoord::Any,
+            y: asm::ExprCoord::Any,
+        },
+        span: ir::Span::default(),
+    };
+    let mut prog = asm::Prog::default();
+    prog.set_sig(input.sig().clone());
+    prog.set_body(vec![asm::Instr::from(instr)]);
+    let prog = tile_from_prog_with_target(&prog, target);
+    let (res, _) = try_from_asm_prog_with_target(&prog, None, target)?;
+    Ok(res)
+}
+
+fn has_memory(input: &pat::Pat) -> bool {
+    input.body().iter().any(|instr| match instr {
+        pat::Instr::Prim(p) => matches!(
+            p.op(),
+            ir::OpPrim::Rom | ir::OpPrim::Ram | ir::OpPrim::Srom | ir::OpPrim::Sram
+        ),
+        _ => false,
+    })
+}
+
+// every register of the pattern can delay a value by one more cycle
+fn depth(input: &pat::Pat) -> usize {
+    1 + input.body().iter().filter(|instr| instr.is_reg()).count()
+}
+
+// proved when the unroll covers every behavior, which needs no registers
+fn no_mismatch(input: &pat::Pat, method: Method) -> Verdict {
+    match depth(input) {
+        1 => Verdict::Proved(method),
+        depth => Verdict::Bounded(depth),
+    }
+}
+
+fn input_bits(input: &pat::Pat) -> Result<u64, Error> {
+    let term: Vec<ir::ExprTerm> = input.input().clone().into();
+    let mut bits = 0;
+    for t in term {
+        bits += term_width(&t)? * lanes(t.get_ty()?) as u64;
+    }
+    Ok(bits)
+}
+
+// the first cycle and signal where the traces differ
+fn mismatch(stimulus: &Stimulus, exp: &Trace, res: &Trace) -> Option<Counterexample> {
+    for cycle in 0..exp.len().max(res.len()) {
+        for signal in exp.signal() {
+            let expected = exp.get(cycle, signal);
+            let found = res.get(cycle, signal);
+            if expected != found {
+                return Some(Counterexample {
+                    stimulus: stimulus.clone(),
+                    cycle,
+                    signal: signal.to_string(),
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    None
+}
+
+fn simulate(
+    prog: &ir::Prog,
+    xir: &xir::Prog,
+    stimulus: &Stimulus,
+) -> Result<Option<Counterexample>, Error> {
+    let exp = run_ir_prog(prog, stimulus, None)?;
+    let res = run_xir_prog(xir, stimulus, None)?;
+    Ok(mismatch(stimulus, &exp, &res))
+}
+
+// the stimulus with inputs taken from the bits of val, cycle after cycle
+fn stimulus_from_bits(input: &ir::Expr, cycles: usize, val: u128) -> Result<Stimulus, Error> {
+    let term: Vec<ir::ExprTerm> = input.clone().into();
+    let mut stimulus = Stimulus::new(&signal_from_expr(input));
+    let mut offset = 0;
+    for _ in 0..cycles {
+        let mut row: Vec<i128> = Vec::new();
+        for t in term.iter() {
+            let width = term_width(t)?;
+            let signed = t.get_ty()?.is_signed();
+            for _ in lane_names(t) {
+                let bits = value_to_bits((val >> offset) as i128, width);
+                row.push(bits_to_value(bits, width, signed));
+                offset += width;
+            }
+        }
+        stimulus.push(row)?;
+    }
+    Ok(stimulus)
+}
+
+fn check_exhaustive(
+    prog: &ir::Prog,
+    xir: &xir::Prog,
+    input: &pat::Pat,
+    bits: u64,
+) -> Result<Verdict, Error> {
+    let cycles = depth(input);
+    for val in 0..(1_u128 << bits) {
+        let stimulus = stimulus_from_bits(input.input(), cycles, val)?;
+        if let Some(cex) = simulate(prog, xir, &stimulus)? {
+            return Ok(Verdict::Refuted(cex));
+        }
+    }
+    Ok(no_mismatch(input, Method::Exhaustive))
+}
+
+// Both programs are unrolled from reset over the same symbolic inputs, and
+// the solver looks for inputs where any output bit differs
+fn check_sat(prog: &ir::Prog, xir: &xir::Prog, input: &pat::Pat) -> Result<Verdict, Error> {
+    let mut aig = Aig::default();
+    let term: Vec<ir::ExprTerm> = input.input().clone().into();
+    let mut env: Vec<Env> = Vec::new();
+    for _ in 0..depth(input) {
+        let mut cycle = Env::new();
+        for t in term.iter() {
+            let width = term_width(t)?;
+            let word = (0..lanes(t.get_ty()?)).map(|_| aig.word(width)).collect();
+            cycle.insert(t.get_id()?, word);
+        }
+        env.push(cycle);
+    }
+    let exp = blast_ir_prog(&mut aig, prog, &env)?;
+    let res = blast_xir_prog(&mut aig, xir, &env)?;
+    let mut miter = FALSE;
+    for (x, y) in exp.iter().flatten().zip(res.iter().flatten()) {
+        for (a, b) in x.iter().zip(y.iter()) {
+            let diff = aig.xor(*a, *b);
+            miter = aig.or(miter, diff);
+        }
+    }
+    if miter == FALSE {
+        return Ok(no_mismatch(input, Method::Sat));
+    }
+    let mut solver = Solver::new(aig.len());
+    for clause in aig.cnf(miter) {
+        solver.add_clause(&clause);
+    }
+    let model = match solver.solve() {
+        Some(model) => model,
+        None => return Ok(no_mismatch(input, Method::Sat)),
+    };
+    // the inputs are read back from the model in the order they were created
+    let bit = |lit: Lit| model[var(lit)];
+    let mut stimulus = Stimulus::new(&signal_from_expr(input.input()));
+    for cycle in env.iter() {
+        let mut row: Vec<i128> = Vec::new();
+        for t in term.iter() {
+            let width = term_width(t)?;
+            let signed = t.get_ty()?.is_signed();
+            for word in cycle[&t.get_id()?].iter() {
+                let bits = word
+                    .iter()
+                    .enumerate()
+                    .fold(0_u128, |acc, (i, lit)| acc | (u128::from(bit(*lit)) << i));
+                row.push(bits_to_value(bits, width, signed));
+            }
+        }
+        stimulus.push(row)?;
+    }
+    match simulate(prog, xir, &stimulus)? {
+        Some(cex) => Ok(Verdict::Refuted(cex)),
+        None => {
+            let err = format!(
+                "{} has a counterexample that simulation does not reproduce",
+                input.id()
+            );
+            Err(Error::new_equiv_error(&err))
+        }
+    }
+}
+
+// Check that the implementation of a pattern computes the same outputs from
+// reset, exhaustively for few input bits and with sat otherwise
+pub fn check_pat(input: &pat::Pat, target: &xim::Target) -> Result<Verdict, Error> {
+    if target.instance(&input.id())?.is_none() {
+        let err = format!("pattern {} does not have an implementation", input.id());
+        return Err(Error::new_equiv_error(&err));
+    }
+    if has_memory(input) {
+        return Ok(Verdict::Skipped(
+            "memory contents are not modeled".to_string(),
+        ));
+    }
+    let prog = prog_from_pat(input);
+    let xir = xir_from_pat(input, target)?;
+    let bits = input_bits(input)? * depth(input) as u64;
+    if bits <= EXHAUSTIVE_BITS {
+        check_exhaustive(&prog, &xir, input, bits)
+    } else {
+        check_sat(&prog, &xir, input)
+    }
+}
+
+// Every concrete pattern, and every generic one at the given widths that its
+// implementation covers
+pub fn check_target(
+    target_pat: &pat::Target,
+    target_imp: &xim::Target,
+    width: &[u64],
+) -> Result<Vec<Check>, Error> {
+    let mut input: Vec<pat::Pat> = target_pat.pat().values().cloned().collect();
+    let mut name: Vec<&String> = target_pat.generic().keys().collect();
+    name.sort();
+    for n in name {
+        for w in width {
+            let inst = instance_name(n, *w);
+            if target_pat.get(&inst).is_some() || target_imp.instance(&inst)?.is_none() {
+                continue;
+            }
+            if let Some(p) = target_pat.instance(&inst)? {
+                input.push(p);
+            }
+        }
+    }
+    input.sort_by_key(|p| p.id());
+    let mut res: Vec<Check> = Vec::new();
+    for p in input {
+        res.push(Check {
+            name: p.id(),
+            verdict: check_pat(&p, target_imp)?,
+        });
+    }
+    Ok(res)
+}
+
+pub fn check_files<P: AsRef<Path>>(pat: P, imp: P, width: &[u64]) -> Result<Vec<Check>, Error> {
+    let target_pat = PatParser::parse_from_file(pat)?;
+    let target_imp = XimParser::parse_from_file(imp)?;
+    check_target(&target_pat, &target_imp, width)
+}
@@ -0,0 +1,253 @@
+use crate::aig::{is_neg, neg, var, Lit};
+use std::collections::BinaryHeap;
+
+// Conflicts before the first restart, the limit grows after every restart
+const RESTART: u64 = 100;
+
+fn lit_value(value: &[Option<bool>], lit: Lit) -> Option<bool> {
+    value[var(lit)].map(|v| v != is_neg(lit))
+}
+
+// Conflict-driven clause learning over the literals of an aig, with two
+// watched literals per clause, activity-based decisions and restarts
+#[derive(Clone, Debug, Default)]
+pub struct Solver {
+    pub clause: Vec<Vec<Lit>>,
+    pub watch: Vec<Vec<usize>>,
+    pub value: Vec<Option<bool>>,
+    pub phase: Vec<bool>,
+    pub level: Vec<usize>,
+    pub reason: Vec<Option<usize>>,
+    pub trail: Vec<Lit>,
+    pub trail_lim: Vec<usize>,
+    pub head: usize,
+    pub activity: Vec<f64>,
+    pub inc: f64,
+    pub order: BinaryHeap<(u64, usize)>,
+    pub conflict: bool,
+}
+
+impl Solver {
+    pub fn new(vars: usize) -> Self {
+        Solver {
+            watch: vec![Vec::new(); vars * 2],
+            value: vec![None; vars],
+            phase: vec![false; vars],
+            level: vec![0; vars],
+            reason: vec![None; vars],
+            activity: vec![0.0; vars],
+            inc: 1.0,
+            order: (0..vars).map(|v| (0, v)).collect(),
+            ..Default::default()
+        }
+    }
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var(lit);
+        self.value[v] = Some(!is_neg(lit));
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+    // clauses are added before solving, at level 0
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        let mut c = clause.to_vec();
+        c.sort_unstable();
+        c.dedup();
+        if c.windows(2).any(|w| w[0] == neg(w[1])) {
+            return;
+        }
+        c.retain(|l| lit_value(&self.value, *l) != Some(false));
+        if c.iter().any(|l| lit_value(&self.value, *l) == Some(true)) {
+            return;
+        }
+        match c.len() {
+            0 => self.conflict = true,
+            1 => {
+                self.enqueue(c[0], None);
+                if self.propagate().is_some() {
+                    self.conflict = true;
+                }
+            }
+            _ => {
+                let index = self.clause.len();
+                self.watch[c[0] as usize].push(index);
+                self.watch[c[1] as usize].push(index);
+                self.clause.push(c);
+            }
+        }
+    }
+    // the clause that became false, if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let false_lit = neg(self.trail[self.head]);
+            self.head += 1;
+            let watch = std::mem::take(&mut self.watch[false_lit as usize]);
+            let mut keep: Vec<usize> = Vec::with_capacity(watch.len());
+            let mut conflict: Option<usize> = None;
+            for index in watch {
+                if conflict.is_some() {
+                    keep.push(index);
+                    continue;
+                }
+                let c = &mut self.clause[index];
+                if c[0] == false_lit {
+                    c.swap(0, 1);
+                }
+                if lit_value(&self.value, c[0]) == Some(true) {
+                    keep.push(index);
+                    continue;
+                }
+                let value = &self.value;
+                match (2..c.len()).find(|j| lit_value(value, c[*j]) != Some(false)) {
+                    Some(j) => {
+                        c.swap(1, j);
+                        let lit = c[1] as usize;
+                        self.watch[lit].push(index);
+                    }
+                    None => {
+                        keep.push(index);
+                        let first = c[0];
+                        match lit_value(&self.value, first) {
+                            Some(false) => conflict = Some(index),
+                            _ => self.enqueue(first, Some(index)),
+                        }
+                    }
+                }
+            }
+            self.watch[false_lit as usize] = keep;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.inc;
+        if self.activity[v] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.inc *= 1e-100;
+        }
+        self.order.push((self.activity[v].to_bits(), v));
+    }
+    // first unique implication point, with the asserting literal first and a
+    // literal of the backtrack level second
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut seen = vec![false; self.value.len()];
+        let mut learnt: Vec<Lit> = vec![0];
+        let mut count = 0;
+        let mut index = self.trail.len();
+        let mut reason = conflict;
+        let mut first = true;
+        loop {
+            let c = self.clause[reason].clone();
+            let start = if first { 0 } else { 1 };
+            first = false;
+            for q in c[start..].iter() {
+                let v = var(*q);
+                if !seen[v] && self.level[v] > 0 {
+                    seen[v] = true;
+                    self.bump(v);
+                    if self.level[v] >= self.decision_level() {
+                        count += 1;
+                    } else {
+                        learnt.push(*q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            seen[var(lit)] = false;
+            count -= 1;
+            if count == 0 {
+                learnt[0] = neg(lit);
+                break;
+            }
+            reason = self.reason[var(lit)].unwrap_or_default();
+        }
+        let mut back = 0;
+        for i in 1..learnt.len() {
+            let level = self.level[var(learnt[i])];
+            if level > back {
+                back = level;
+                learnt.swap(1, i);
+            }
+        }
+        (learnt, back)
+    }
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for lit in self.trail.drain(start..) {
+            let v = var(lit);
+            self.phase[v] = !is_neg(lit);
+            self.value[v] = None;
+            self.reason[v] = None;
+            self.order.push((self.activity[v].to_bits(), v));
+        }
+        self.trail_lim.truncate(level);
+        self.head = self.trail.len();
+    }
+    fn decide(&mut self) -> Option<usize> {
+        while let Some((_, v)) = self.order.pop() {
+            if self.value[v].is_none() {
+                return Some(v);
+            }
+        }
+        None
+    }
+    // a value for every variable, or none when the clauses are unsatisfiable
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.conflict || self.propagate().is_some() {
+            return None;
+        }
+        let mut conflicts: u64 = 0;
+        let mut limit = RESTART;
+        loop {
+            match self.propagate() {
+                Some(conflict) => {
+                    if self.decision_level() == 0 {
+                        return None;
+                    }
+                    let (learnt, back) = self.analyze(conflict);
+                    self.backtrack(back);
+                    if learnt.len() == 1 {
+                        self.enqueue(learnt[0], None);
+                    } else {
+                        let index = self.clause.len();
+                        self.watch[learnt[0] as usize].push(index);
+                        self.watch[learnt[1] as usize].push(index);
+                        self.enqueue(learnt[0], Some(index));
+                        self.clause.push(learnt);
+                    }
+                    self.inc /= 0.95;
+                    conflicts += 1;
+                    if conflicts >= limit {
+                        conflicts = 0;
+                        limit += limit / 2;
+                        self.backtrack(0);
+                    }
+                }
+                None => match self.decide() {
+                    Some(v) => {
+                        self.trail_lim.push(self.trail.len());
+                        let lit = ((v as Lit) << 1) | u32::from(!self.phase[v]);
+                        self.enqueue(lit, None);
+                    }
+                    None => return Some(self.value.iter().map(|v| v == &Some(true)).collect()),
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use equiv::aig::Aig;
+use equiv::errors::Error;
+use equiv::sat::Solver;
+use equiv::{check_files, check_target, Check, Method, Verdict};
+use pat::parser::Parser as PatParser;
+use std::fs;
+use std::path::Path;
+use xim::parser::Parser as XimParser;
+
+// widths the generic patterns are checked at
+const WIDTH: [u64; 8] = [1, 2, 3, 4, 7, 8, 12, 16];
+
+// patterns with memories, whose contents are not modeled
+const SKIPPED: [&str; 3] = ["brom_i8i8", "lrom_i8i3", "lrom_i8i8"];
+
+fn check(name: &str) -> Result<Vec<Check>, Error> {
+    let mut pat = Path::new("../../../examples/pat").join(name);
+    let mut imp = Path::new("../../../examples/xim").join(name);
+    pat.set_extension("pat");
+    imp.set_extension("xim");
+    check_files(pat, imp, &WIDTH)
+}
+
+fn check_str(pat: &str, imp: &str) -> Result<Vec<Check>, Error> {
+    let target_pat = PatParser::parse_from_str(pat)?;
+    let target_imp = XimParser::parse_from_str(imp)?;
+    check_target(&target_pat, &target_imp, &WIDTH)
+}
+
+#[test]
+fn examples() -> Result<(), Error> {
+    for entry in fs::read_dir("../../../examples/pat").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        for res in check(name)? {
+            if SKIPPED.contains(&res.name().as_str()) {
+                assert!(res.verdict().is_skipped(), "{}", res);
+            } else {
+                let verdict = res.verdict();
+                assert!(verdict.is_proved() || verdict.is_bounded(), "{}", res);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn lut() -> Result<(), Error> {
+    let res = check("lut")?;
+    assert!(res
+        .iter()
+        .all(|c| c.verdict().is_proved() || c.verdict().is_bounded()));
+    let method = |name: &str| {
+        res.iter()
+            .find(|c| c.name() == name)
+            .map(|c| c.verdict().clone())
+    };
+    assert_eq!(method("ladd_i4"), Some(Verdict::Proved(Method::Exhaustive)));
+    assert_eq!(method("ladd_i12"), Some(Verdict::Proved(Method::Sat)));
+    assert_eq!(method("lmuxrega_i16"), Some(Verdict::Bounded(2)));
+    Ok(())
+}
+
+#[test]
+fn dsp() -> Result<(), Error> {
+    let res = check("dsp")?;
+    assert_eq!(res.len(), 9);
+    assert!(res
+        .iter()
+        .all(|c| c.verdict().is_proved() || c.verdict().is_bounded()));
+    let verdict = |name: &str| {
+        res.iter()
+            .find(|c| c.name() == name)
+            .map(|c| c.verdict().clone())
+    };
+    assert_eq!(verdict("dmul_i8"), Some(Verdict::Proved(Method::Sat)));
+    assert_eq!(verdict("dmuladdrega_i8i8"), Some(Verdict::Bounded(5)));
+    Ok(())
+}
+
+#[test]
+fn refute_exhaustive() -> Result<(), Error> {
+    let res = check_str(
+        "pat lxor_i2(a:i2, b:i2) -> (y:i2) {
+            y:i2 = xor(a, b) @lut;
+        }",
+        "imp lxor_i2[1, 2](a:i2, b:i2) -> (y:i2) {
+            t0:bool = ext[0](a);
+            t1:bool = ext[0](b);
+            t2:bool = ext[1](a);
+            t3:bool = ext[1](b);
+            t4:bool = lut2[6](t0, t1) @a6(??, ??);
+            t5:bool = lut2[7](t2, t3) @b6(??, ??);
+            y:i2 = cat(t4, t5);
+        }",
+    )?;
+    let cex = res[0].verdict().counterexample().unwrap();
+    assert_eq!(cex.stimulus().get(0, "a"), Some(0));
+    assert_eq!(cex.stimulus().get(0, "b"), Some(0));
+    assert_eq!(cex.expected(), Some(0));
+    assert_eq!(cex.found(), Some(-2));
+    Ok(())
+}
+
+#[test]
+fn refute_sat() -> Result<(), Error> {
+    let res = check_str(
+        "pat land<N>(a:i{N}, b:i{N}) -> (y:i{N}) {
+            y:i{N} = and(a, b) @lut;
+        }",
+        "imp land<N: 8..=8>[1, 2](a:i{N}, b:i{N}) -> (y:i{N}) {
+            repeat i in 0..N {
+                a{i}:bool = ext[{i}](a);
+                b{i}:bool = ext[{i}](b);
+            }
+            repeat i in 0..7 {
+                x{i}:bool = lut2[8](a{i}, b{i}) @{i}6(??, ??);
+            }
+            x7:bool = lut2[14](a7, b7) @h6(??, ??);
+            y:i{N} = cat(x{0..N});
+        }",
+    )?;
+    assert_eq!(res.len(), 1);
+    let cex = res[0].verdict().counterexample().unwrap();
+    assert_eq!(cex.signal(), "y");
+    assert_ne!(cex.expected(), cex.found());
+    let a = cex.stimulus().get(0, "a").unwrap();
+    let b = cex.stimulus().get(0, "b").unwrap();
+    assert_ne!((a >> 7) & 1, (b >> 7) & 1);
+    Ok(())
+}
+
+#[test]
+fn sat() {
+    // x and not x
+    let mut aig = Aig::default();
+    let x = aig.input();
+    let root = aig.and(x, x ^ 1);
+    assert_eq!(root, 0);
+    // three pigeons in two holes
+    let mut solver = Solver::new(7);
+    let hole = |p: u32, h: u32| (1 + p * 2 + h) << 1;
+    for p in 0..3 {
+        solver.add_clause(&[hole(p, 0), hole(p, 1)]);
+    }
+    for h in 0..2 {
+        for p in 0..3 {
+            for q in (p + 1)..3 {
+                solver.add_clause(&[hole(p, h) ^ 1, hole(q, h) ^ 1]);
+            }
+        }
+    }
+    assert!(solver.solve().is_none());
+    let mut solver = Solver::new(3);
+    solver.add_clause(&[2, 4]);
+    solver.add_clause(&[3]);
+    let model = solver.solve().unwrap();
+    assert!(!model[1] && model[2]);
+}
@@ -0,0 +1,20 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Generic(String),
+}
+
+impl Error {
+    pub fn new_generic_error(msg: &str) -> Self {
+        Error::Generic(msg.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Generic(msg) => write!(f, "{}", msg),
+        }
+    }
+}
@@ -0,0 +1,363 @@
+pub mod errors;
+
+use crate::errors::Error;
+use ir::ast as ir;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+pub type Id = ir::Id;
+pub type Env = HashMap<Id, i64>;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum IndexOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+// Integer expression over the width parameter and repeat variables, written
+// between braces such as {(N + 7) / 8}
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Index {
+    Val(i64),
+    Var(Id),
+    Bin(IndexOp, Box<Index>, Box<Index>),
+}
+
+// lo..hi, or lo..=hi when inclusive
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct Range {
+    pub lo: Index,
+    pub hi: Index,
+    pub inclusive: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Part {
+    Str(String),
+    Index(Index),
+}
+
+// Identifier, type or bel with placeholders, such as a{i}, i{N} or {i}6
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash, Default)]
+pub struct Name {
+    pub part: Vec<Part>,
+}
+
+// A var with an optional type, or the list of vars a{lo..hi}
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Term {
+    Var(Name, Option<Name>),
+    List(Id, Range),
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Io {
+    Term(Term),
+    Tup(Vec<Term>),
+}
+
+// Width parameter, defined from lo up to hi, inclusive, or without bound
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub struct Param {
+    pub id: Id,
+    pub lo: u64,
+    pub hi: Option<u64>,
+}
+
+// Instructions of a generic body, repeat blocks are unrolled with the
+// variable bound to every value of the range
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Eq, Hash)]
+pub enum Stmt<T> {
+    Instr(T),
+    Repeat(Id, Range, Vec<Stmt<T>>),
+}
+
+impl IndexOp {
+    fn prec(&self) -> u8 {
+        match self {
+            IndexOp::Add | IndexOp::Sub => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl Index {
+    pub fn eval(&self, env: &Env) -> Result<i64, Error> {
+        match self {
+            Index::Val(val) => Ok(*val),
+            Index::Var(id) => env
+                .get(id)
+                .cloned()
+                .ok_or_else(|| Error::new_generic_error(&format!("{} is not defined", id))),
+            Index::Bin(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(env)?, rhs.eval(env)?);
+                match op {
+                    IndexOp::Add => Ok(lhs + rhs),
+                    IndexOp::Sub => Ok(lhs - rhs),
+                    IndexOp::Mul => Ok(lhs * rhs),
+                    _ if rhs == 0 => Err(Error::new_generic_error("division by zero")),
+                    IndexOp::Div => Ok(lhs / rhs),
+                    IndexOp::Rem => Ok(lhs % rhs),
+                }
+            }
+        }
+    }
+    // left to right, multiplicative ops before additive ones
+    pub fn from_ops(operand: Vec<Index>, op: Vec<IndexOp>) -> Index {
+        let mut operand = operand.into_iter();
+        let mut out: Vec<Index> = operand.next().into_iter().collect();
+        let mut stack: Vec<IndexOp> = Vec::new();
+        let reduce = |out: &mut Vec<Index>, op: IndexOp| {
+            let rhs = out.pop().unwrap();
+            let lhs = out.pop().unwrap();
+            out.push(Index::Bin(op, Box::new(lhs), Box::new(rhs)));
+        };
+        for (op, rhs) in op.into_iter().zip(operand) {
+            while stack.last().map_or(false, |top| top.prec() >= op.prec()) {
+                reduce(&mut out, stack.pop().unwrap());
+            }
+            stack.push(op);
+            out.push(rhs);
+        }
+        while let Some(op) = stack.pop() {
+            reduce(&mut out, op);
+        }
+        out.pop().unwrap_or(Index::Val(0))
+    }
+}
+
+impl Range {
+    // values of the range, empty when hi is not above lo
+    pub fn eval(&self, env: &Env) -> Result<std::ops::Range<i64>, Error> {
+        let lo = self.lo.eval(env)?;
+        let hi = self.hi.eval(env)?;
+        Ok(lo..if self.inclusive { hi + 1 } else { hi })
+    }
+}
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Name {
+            part: vec![Part::Str(name.to_string())],
+        }
+    }
+    pub fn part(&self) -> &Vec<Part> {
+        &self.part
+    }
+    pub fn eval(&self, env: &Env) -> Result<String, Error> {
+        let mut name = String::new();
+        for part in self.part.iter() {
+            match part {
+                Part::Str(s) => name.push_str(s),
+                Part::Index(index) => name.push_str(&index.eval(env)?.to_string()),
+            }
+        }
+        Ok(name)
+    }
+}
+
+impl Term {
+    pub fn eval(&self, env: &Env) -> Result<Vec<ir::ExprTerm>, Error> {
+        match self {
+            Term::Var(id, ty) => {
+                let ty = match ty {
+                    Some(ty) => {
+                        let ty = ty.eval(env)?;
+                        ir::Ty::from_str(&ty).map_err(|_| {
+                            Error::new_generic_error(&format!("{} is not a valid type", ty))
+                        })?
+                    }
+                    None => ir::Ty::Any,
+                };
+                Ok(vec![ir::ExprTerm::Var(id.eval(env)?, ty)])
+            }
+            Term::List(id, range) => Ok(range
+                .eval(env)?
+                .map(|i| ir::ExprTerm::Var(format!("{}{}", id, i), ir::Ty::Any))
+                .collect()),
+        }
+    }
+}
+
+impl Io {
+    pub fn eval(&self, env: &Env) -> Result<ir::Expr, Error> {
+        match self {
+            Io::Term(term @ Term::Var(..)) => Ok(ir::Expr::from(term.eval(env)?.remove(0))),
+            Io::Term(term) => Ok(ir::Expr::from(ir::ExprTup::from(term.eval(env)?))),
+            Io::Tup(term) => {
+                let mut res: Vec<ir::ExprTerm> = Vec::new();
+                for t in term {
+                    res.extend(t.eval(env)?);
+                }
+                Ok(ir::Expr::from(ir::ExprTup::from(res)))
+            }
+        }
+    }
+}
+
+impl Param {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+    pub fn is_within(&self, width: u64) -> bool {
+        width >= self.lo && self.hi.map_or(true, |hi| width <= hi)
+    }
+    // values the parameter takes at a width
+    pub fn env(&self, width: u64) -> Env {
+        let mut env = Env::new();
+        env.insert(self.id.clone(), width as i64);
+        env
+    }
+}
+
+// Unroll a generic body, calling instr on every instruction with the values
+// of the enclosing repeat variables
+pub fn unroll<T, R, E, F>(body: &[Stmt<T>], env: &Env, instr: &mut F) -> Result<Vec<R>, E>
+where
+    E: From<Error>,
+    F: FnMut(&T, &Env) -> Result<R, E>,
+{
+    let mut res: Vec<R> = Vec::new();
+    for stmt in body {
+        match stmt {
+            Stmt::Instr(i) => res.push(instr(i, env)?),
+            Stmt::Repeat(var, range, inner) => {
+                let range = range.eval(env)?;
+                let mut env = env.clone();
+                for val in range {
+                    env.insert(var.clone(), val);
+                    res.extend(unroll(inner, &env, instr)?);
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+pub fn instance_name(name: &str, width: u64) -> String {
+    format!("{}_i{}", name, width)
+}
+
+// width of an instance of the generic name, such as 12 for ladd and ladd_i12
+pub fn width_from_instance(name: &str, instance: &str) -> Option<u64> {
+    let prefix = format!("{}_i", name);
+    if !instance.starts_with(&prefix) {
+        return None;
+    }
+    let width = &instance[prefix.len()..];
+    if width.bytes().all(|c| c.is_ascii_digit()) {
+        width.parse::<u64>().ok()
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for IndexOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            IndexOp::Add => "+",
+            IndexOp::Sub => "-",
+            IndexOp::Mul => "*",
+            IndexOp::Div => "/",
+            IndexOp::Rem => "%",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for Index {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Index::Val(val) => write!(f, "{}", val),
+            Index::Var(id) => write!(f, "{}", id),
+            Index::Bin(op, lhs, rhs) => {
+                // operands bind looser than op, and on the right as tight
+                let wrap = |index: &Index, right: bool| match index {
+                    Index::Bin(inner, _, _)
+                        if inner.prec() < op.prec() || (right && inner.prec() == op.prec()) =>
+                    {
+                        format!("({})", index)
+                    }
+                    _ => index.to_string(),
+                };
+                write!(f, "{} {} {}", wrap(lhs, false), op, wrap(rhs, true))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dots = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.lo, dots, self.hi)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in self.part.iter() {
+            match part {
+                Part::Str(s) => write!(f, "{}", s)?,
+                Part::Index(index) => write!(f, "{{{}}}", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Var(id, Some(ty)) => write!(f, "{}:{}", id, ty),
+            Term::Var(id, None) => write!(f, "{}", id),
+            Term::List(id, range) => write!(f, "{}{{{}}}", id, range),
+        }
+    }
+}
+
+impl fmt::Display for Io {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Io::Term(term) => write!(f, "{}", term),
+            Io::Tup(term) => {
+                let term: Vec<String> = term.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", term.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hi {
+            None if self.lo == 1 => write!(f, "<{}>", self.id),
+            None => write!(f, "<{}: {}..>", self.id, self.lo),
+            Some(hi) => write!(f, "<{}: {}..={}>", self.id, self.lo, hi),
+        }
+    }
+}
+
+// a generic body, one instruction or repeat per line
+pub fn fmt_body<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    body: &[Stmt<T>],
+    indent: usize,
+) -> fmt::Result {
+    let pad = " ".repeat(indent);
+    for stmt in body {
+        match stmt {
+            Stmt::Instr(instr) => writeln!(f, "{}{};", pad, instr)?,
+            Stmt::Repeat(var, range, inner) => {
+                writeln!(f, "{}repeat {} in {} {{", pad, var, range)?;
+                fmt_body(f, inner, indent + 4)?;
+                writeln!(f, "{}}}", pad)?;
+            }
+        }
+    }
+    Ok(())
+}
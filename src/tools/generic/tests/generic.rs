@@ -0,0 +1,111 @@
+use generic::errors::Error;
+use generic::{unroll, width_from_instance, Env, Index, IndexOp, Name, Param, Part, Range, Stmt};
+
+fn env(param: &[(&str, i64)]) -> Env {
+    param.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+}
+
+fn var(id: &str) -> Index {
+    Index::Var(id.to_string())
+}
+
+// (N + 7) / 8
+fn slices() -> Index {
+    let sum = Index::from_ops(vec![var("N"), Index::Val(7)], vec![IndexOp::Add]);
+    Index::from_ops(vec![sum, Index::Val(8)], vec![IndexOp::Div])
+}
+
+#[test]
+fn eval_index() -> Result<(), Error> {
+    let env = env(&[("N", 12)]);
+    assert_eq!(slices().eval(&env)?, 2);
+    // N - 1 - 2 * 3 % 4
+    let index = Index::from_ops(
+        vec![
+            var("N"),
+            Index::Val(1),
+            Index::Val(2),
+            Index::Val(3),
+            Index::Val(4),
+        ],
+        vec![IndexOp::Sub, IndexOp::Sub, IndexOp::Mul, IndexOp::Rem],
+    );
+    assert_eq!(index.eval(&env)?, 9);
+    assert!(var("M").eval(&env).is_err());
+    let index = Index::from_ops(vec![var("N"), Index::Val(0)], vec![IndexOp::Div]);
+    assert!(index.eval(&env).is_err());
+    Ok(())
+}
+
+#[test]
+fn display() {
+    assert_eq!(slices().to_string(), "(N + 7) / 8");
+    let index = Index::from_ops(
+        vec![var("N"), Index::Val(1), Index::Val(2)],
+        vec![IndexOp::Sub, IndexOp::Mul],
+    );
+    assert_eq!(index.to_string(), "N - 1 * 2");
+    let name = Name {
+        part: vec![Part::Index(var("i")), Part::Str("6".to_string())],
+    };
+    assert_eq!(name.to_string(), "{i}6");
+    let param = Param {
+        id: "N".to_string(),
+        lo: 1,
+        hi: Some(8),
+    };
+    assert_eq!(param.to_string(), "<N: 1..=8>");
+}
+
+#[test]
+fn range() -> Result<(), Error> {
+    let env = env(&[("N", 2)]);
+    let range = Range {
+        lo: var("N"),
+        hi: Index::Val(3),
+        inclusive: true,
+    };
+    assert_eq!(range.eval(&env)?, 2..4);
+    let range = Range {
+        lo: Index::Val(8),
+        hi: var("N"),
+        inclusive: false,
+    };
+    assert_eq!(range.eval(&env)?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn unroll_repeat() -> Result<(), Error> {
+    let range = |hi: Index| Range {
+        lo: Index::Val(0),
+        hi,
+        inclusive: false,
+    };
+    let inner = vec![Stmt::Instr(var("j"))];
+    let body = vec![
+        Stmt::Repeat(
+            "i".to_string(),
+            range(var("N")),
+            vec![Stmt::Instr(var("i"))],
+        ),
+        Stmt::Repeat(
+            "i".to_string(),
+            range(Index::Val(2)),
+            vec![Stmt::Repeat("j".to_string(), range(var("i")), inner)],
+        ),
+    ];
+    let res = unroll(&body, &env(&[("N", 3)]), &mut |index: &Index, env: &Env| {
+        index.eval(env)
+    })?;
+    assert_eq!(res, vec![0, 1, 2, 0]);
+    Ok(())
+}
+
+#[test]
+fn instance() {
+    assert_eq!(width_from_instance("lxor", "lxor_i4"), Some(4));
+    assert_eq!(width_from_instance("lxor", "lxor_i4v2"), None);
+    assert_eq!(width_from_instance("lxor", "laddc_i8"), None);
+    assert_eq!(width_from_instance("l", "lxor_i4"), None);
+}
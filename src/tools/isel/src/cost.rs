@@ -0,0 +1,162 @@
+use crate::errors::Error;
+use ir::ast as ir;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use xim::ast as xim;
+
+// What selection minimizes, mixed weighs area and perf, e.g. mixed:2,1 counts
+// area twice as much as perf
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CostModel {
+    Area,
+    Perf,
+    Mixed(u64, u64),
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel::Perf
+    }
+}
+
+impl CostModel {
+    pub fn cost(&self, sig: &xim::Sig) -> u64 {
+        match self {
+            CostModel::Area => sig.area(),
+            CostModel::Perf => sig.perf(),
+            CostModel::Mixed(area, perf) => area * sig.area() + perf * sig.perf(),
+        }
+    }
+}
+
+impl fmt::Display for CostModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CostModel::Area => write!(f, "area"),
+            CostModel::Perf => write!(f, "perf"),
+            CostModel::Mixed(area, perf) => write!(f, "mixed:{},{}", area, perf),
+        }
+    }
+}
+
+impl FromStr for CostModel {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let err = format!(
+            "{} is not a cost model, expected area, perf or mixed:<area>,<perf>",
+            input
+        );
+        let weight = |w: &str| {
+            w.trim()
+                .parse::<u64>()
+                .map_err(|_| Error::new_isel_error(&err))
+        };
+        match input {
+            "area" => Ok(CostModel::Area),
+            "perf" => Ok(CostModel::Perf),
+            // same weight for both
+            "mixed" => Ok(CostModel::Mixed(1, 1)),
+            _ if input.starts_with("mixed:") => {
+                let mut split = input["mixed:".len()..].splitn(2, ',');
+                match (split.next(), split.next()) {
+                    (Some(area), Some(perf)) => Ok(CostModel::Mixed(weight(area)?, weight(perf)?)),
+                    _ => Err(Error::new_isel_error(&err)),
+                }
+            }
+            _ => Err(Error::new_isel_error(&err)),
+        }
+    }
+}
+
+// How selection searches for a cover: greedy per tree, dynamic programming
+// over the dataflow graph, or exact branch and bound
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Select {
+    Greedy,
+    Dp,
+    Exact,
+}
+
+impl Default for Select {
+    fn default() -> Self {
+        Select::Greedy
+    }
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Select::Greedy => write!(f, "greedy"),
+            Select::Dp => write!(f, "dp"),
+            Select::Exact => write!(f, "exact"),
+        }
+    }
+}
+
+impl FromStr for Select {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "greedy" => Ok(Select::Greedy),
+            "dp" => Ok(Select::Dp),
+            "exact" => Ok(Select::Exact),
+            _ => Err(Error::new_isel_error(&format!(
+                "{} is not a selector, expected greedy, dp or exact",
+                input
+            ))),
+        }
+    }
+}
+
+// Cost model and the maximum area that patterns of a prim can take, e.g. the
+// number of dsp slices. A tree that goes over a budget is selected again
+// without the patterns of that prim.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub model: CostModel,
+    pub budget: HashMap<ir::Prim, u64>,
+    pub select: Select,
+}
+
+impl Cost {
+    pub fn new(model: CostModel) -> Cost {
+        Cost {
+            model,
+            budget: HashMap::new(),
+            select: Select::default(),
+        }
+    }
+    pub fn model(&self) -> &CostModel {
+        &self.model
+    }
+    pub fn select(&self) -> &Select {
+        &self.select
+    }
+    pub fn set_select(&mut self, select: Select) {
+        self.select = select;
+    }
+    pub fn budget(&self, prim: &ir::Prim) -> Option<u64> {
+        self.budget.get(prim).cloned()
+    }
+    pub fn set_budget(&mut self, prim: ir::Prim, max: u64) {
+        self.budget.insert(prim, max);
+    }
+    // budgets are given as prim=max, e.g. dsp=4
+    pub fn add_budget_from_str(&mut self, input: &str) -> Result<(), Error> {
+        let err = format!("{} is not a budget, expected prim=max", input);
+        let mut split = input.splitn(2, '=');
+        match (split.next(), split.next()) {
+            (Some(prim), Some(max)) => {
+                let prim = ir::Prim::from_str(prim.trim())?;
+                let max = max
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| Error::new_isel_error(&err))?;
+                self.set_budget(prim, max);
+                Ok(())
+            }
+            _ => Err(Error::new_isel_error(&err)),
+        }
+    }
+}
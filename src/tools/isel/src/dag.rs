@@ -0,0 +1,542 @@
+use crate::cost::Cost;
+use crate::errors::Error;
+use crate::tree::helpers::{rename_arg, rename_dst};
+use crate::tree::{Node, NodeOp, Tree, TreeMap};
+use ::ir::span::diagnostic;
+use asm::ast as asm;
+use ir::ast as ir;
+use pat::ast as pat;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+
+// Dataflow graph of a def. Unlike trees, a value used by several consumers
+// is a single node, so patterns can be matched across it.
+#[derive(Clone, Debug, Default)]
+pub struct Dag {
+    pub node: Vec<Node>,
+    pub edge: Vec<Vec<usize>>,
+    pub user: Vec<usize>,
+    pub output: Vec<usize>,
+    pub index: HashMap<ir::Id, usize>,
+}
+
+// A pattern matched at a root node, leaves are the nodes bound to the
+// pattern inputs
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub pat: String,
+    pub rank: usize,
+    pub root: usize,
+    pub cost: u64,
+    pub area: u64,
+    pub prim: ir::Prim,
+    pub leaf: Vec<usize>,
+    pub cover: Vec<usize>,
+    pub input: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Choice {
+    Pat(usize),
+    Wire,
+}
+
+impl Dag {
+    pub fn node(&self, index: usize) -> &Node {
+        &self.node[index]
+    }
+    pub fn edge(&self, index: usize) -> &Vec<usize> {
+        &self.edge[index]
+    }
+    pub fn is_shared(&self, index: usize) -> bool {
+        self.user[index] > 1 || self.output.contains(&index)
+    }
+    fn add_node(&mut self, node: Node) -> usize {
+        let index = self.node.len();
+        self.index.insert(node.id(), index);
+        self.node.push(node);
+        self.edge.push(Vec::new());
+        self.user.push(0);
+        index
+    }
+}
+
+impl TryFrom<&ir::Def> for Dag {
+    type Error = Error;
+    fn try_from(def: &ir::Def) -> Result<Self, Self::Error> {
+        let mut dag = Dag::default();
+        let input: Vec<ir::ExprTerm> = def.input().clone().into();
+        for term in input {
+            dag.add_node(Node {
+                index: 0,
+                id: term.get_id()?,
+                ty: term.get_ty()?.clone(),
+                op: NodeOp::Inp,
+                attr: ir::Expr::default(),
+                prim: ir::Prim::Any,
+                cost: 0,
+                staged: false,
+                committed: false,
+                pat: None,
+                pat_prim: ir::Prim::Any,
+            });
+        }
+        for instr in def.body() {
+            dag.add_node(Node::try_from(instr.clone())?);
+        }
+        for instr in def.body() {
+            let index = dag.index[&instr.dst().get_id(0)?];
+            let arg: Vec<ir::ExprTerm> = instr.arg().clone().into();
+            for term in arg {
+                if let Some(id) = term.id() {
+                    if let Some(to) = dag.index.get(&id).cloned() {
+                        dag.edge[index].push(to);
+                        dag.user[to] += 1;
+                    }
+                }
+            }
+        }
+        let output: Vec<ir::ExprTerm> = def.output().clone().into();
+        for term in output {
+            if let Some(index) = dag.index.get(&term.get_id()?) {
+                dag.output.push(*index);
+            }
+        }
+        Ok(dag)
+    }
+}
+
+fn match_node(
+    dag: &Dag,
+    index: usize,
+    pat: &Tree,
+    pindex: u64,
+    bind: &mut HashMap<String, usize>,
+    cover: &mut Vec<usize>,
+) -> bool {
+    let dnode = dag.node(index);
+    match pat.node(pindex) {
+        Some(pnode) if pnode.ty() == dnode.ty() => {
+            if pnode.is_inp_op() {
+                // an input used twice must be bound to the same value
+                match bind.get(&pnode.id()) {
+                    Some(bound) => *bound == index,
+                    None => {
+                        bind.insert(pnode.id(), index);
+                        true
+                    }
+                }
+            } else {
+                let pedge = pat.edge(pindex).cloned().unwrap_or_default();
+                cover.push(index);
+                pnode.op() == dnode.op()
+                    && (dnode.prim().is_any() || pnode.prim() == dnode.prim())
+                    && pnode.attr() == dnode.attr()
+                    && pedge.len() == dag.edge(index).len()
+                    && pedge
+                        .iter()
+                        .zip(dag.edge(index).iter())
+                        .all(|(p, d)| match_node(dag, *d, pat, *p, bind, cover))
+            }
+        }
+        _ => false,
+    }
+}
+
+// every pattern of every map that matches at every node, earlier maps rank first
+pub fn match_dag(dag: &Dag, map: &[TreeMap], area: &HashMap<String, u64>) -> Vec<Vec<Match>> {
+    let mut res: Vec<Vec<Match>> = vec![Vec::new(); dag.node.len()];
+    for (index, dnode) in dag.node.iter().enumerate() {
+        if !dnode.is_prim_op() {
+            continue;
+        }
+        for (rank, tree_map) in map.iter().enumerate() {
+            let mut name: Vec<&String> = tree_map.keys().collect();
+            name.sort();
+            for n in name {
+                let tree = &tree_map[n];
+                let mut bind: HashMap<String, usize> = HashMap::new();
+                let mut cover: Vec<usize> = Vec::new();
+                if let Some(root) = tree.node(0) {
+                    if match_node(dag, index, tree, 0, &mut bind, &mut cover) {
+                        let mut leaf: Vec<usize> = bind.values().cloned().collect();
+                        leaf.sort_unstable();
+                        leaf.dedup();
+                        let input = bind
+                            .iter()
+                            .map(|(p, d)| (p.clone(), dag.node(*d).id()))
+                            .collect();
+                        res[index].push(Match {
+                            pat: n.clone(),
+                            rank,
+                            root: index,
+                            cost: root.cost(),
+                            area: area.get(n).cloned().unwrap_or(0),
+                            prim: root.prim().clone(),
+                            leaf,
+                            cover,
+                            input,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    res
+}
+
+fn is_required(dag: &Dag, index: usize) -> bool {
+    !dag.node(index).is_inp_op()
+}
+
+// values a choice needs from other nodes
+fn needs(dag: &Dag, matches: &[Vec<Match>], index: usize, choice: &Choice) -> Vec<usize> {
+    let leaf = match choice {
+        Choice::Pat(m) => matches[index][*m].leaf.clone(),
+        Choice::Wire => dag.edge(index).clone(),
+    };
+    leaf.into_iter().filter(|l| is_required(dag, *l)).collect()
+}
+
+fn usage_from_choice(
+    matches: &[Vec<Match>],
+    choice: &HashMap<usize, Choice>,
+) -> HashMap<ir::Prim, u64> {
+    let mut usage: HashMap<ir::Prim, u64> = HashMap::new();
+    for (index, c) in choice {
+        if let Choice::Pat(m) = c {
+            let m = &matches[*index][*m];
+            *usage.entry(m.prim.clone()).or_insert(0) += m.area;
+        }
+    }
+    usage
+}
+
+pub fn cost_from_choice(matches: &[Vec<Match>], choice: &HashMap<usize, Choice>) -> u64 {
+    let mut cost: u64 = 0;
+    for (index, c) in choice {
+        if let Choice::Pat(m) = c {
+            cost = cost.saturating_add(matches[*index][*m].cost);
+        }
+    }
+    cost
+}
+
+fn missing_node(dag: &Dag, def: &ir::Def, index: usize) -> Error {
+    let node = dag.node(index);
+    let span = def
+        .body()
+        .iter()
+        .find(|i| i.dst().get_id(0).ok() == Some(node.id()))
+        .and_then(|i| i.span());
+    let msg = format!("missing node: {}", node);
+    Error::new_isel_error(&diagnostic(span, &msg))
+}
+
+// Minimum cost of every node when shared values are computed once and for
+// free at their consumers. Patterns can still cover a shared value, which
+// duplicates it, and values nobody needs in the end are dropped.
+fn dp_choice(
+    dag: &Dag,
+    def: &ir::Def,
+    matches: &[Vec<Match>],
+    forbid: &HashSet<(usize, ir::Prim)>,
+) -> Result<HashMap<usize, Choice>, Error> {
+    let size = dag.node.len();
+    let inner = |l: &usize| is_required(dag, *l) && !dag.is_shared(*l);
+    let mut best: Vec<Option<(u64, Choice)>> = vec![None; size];
+    let mut state: Vec<u8> = vec![0; size];
+    for start in 0..size {
+        let mut stack: Vec<(usize, bool)> = vec![(start, false)];
+        while let Some((index, ready)) = stack.pop() {
+            let node = dag.node(index);
+            if !ready {
+                if state[index] != 0 {
+                    continue;
+                }
+                state[index] = 1;
+                stack.push((index, true));
+                let mut dep: Vec<usize> = dag.edge(index).clone();
+                for m in matches[index].iter() {
+                    dep.extend(m.leaf.iter());
+                }
+                for d in dep.into_iter().filter(|d| inner(d) && state[*d] == 0) {
+                    stack.push((d, false));
+                }
+            } else if state[index] == 1 {
+                state[index] = 2;
+                let cost_of = |l: &usize| best[*l].as_ref().map(|(c, _)| *c);
+                if node.is_inp_op() {
+                    best[index] = Some((0, Choice::Wire));
+                } else if node.is_wire_op() {
+                    let mut cost: Option<u64> = Some(0);
+                    for l in dag.edge(index).iter().filter(|l| inner(l)) {
+                        cost = cost.and_then(|c| cost_of(l).map(|x| c.saturating_add(x)));
+                    }
+                    best[index] = cost.map(|c| (c, Choice::Wire));
+                } else {
+                    let mut pick: Option<(u64, usize, usize, usize)> = None;
+                    for (i, m) in matches[index].iter().enumerate() {
+                        if forbid.contains(&(index, m.prim.clone())) {
+                            continue;
+                        }
+                        let mut cost: Option<u64> = Some(m.cost);
+                        for l in m.leaf.iter().filter(|l| inner(l)) {
+                            cost = cost.and_then(|c| cost_of(l).map(|x| c.saturating_add(x)));
+                        }
+                        // ties go to fewer computed inputs, then to earlier sets
+                        let need = m.leaf.iter().filter(|l| is_required(dag, **l)).count();
+                        if let Some(c) = cost {
+                            let key = (c, need, m.rank, i);
+                            if pick.map_or(true, |p| key < p) {
+                                pick = Some(key);
+                            }
+                        }
+                    }
+                    best[index] = pick.map(|(c, _, _, i)| (c, Choice::Pat(i)));
+                }
+            }
+        }
+    }
+    // keep only what the outputs need
+    let mut choice: HashMap<usize, Choice> = HashMap::new();
+    let mut work: Vec<usize> = dag.output.clone();
+    while let Some(index) = work.pop() {
+        if !is_required(dag, index) || choice.contains_key(&index) {
+            continue;
+        }
+        match &best[index] {
+            Some((_, c)) => {
+                work.extend(needs(dag, matches, index, c));
+                choice.insert(index, c.clone());
+            }
+            None => return Err(missing_node(dag, def, index)),
+        }
+    }
+    Ok(choice)
+}
+
+// dp, and once a budget is exceeded the last node using that prim in the
+// body takes patterns of other prims
+pub fn dp_select(
+    dag: &Dag,
+    def: &ir::Def,
+    matches: &[Vec<Match>],
+    cost: &Cost,
+) -> Result<HashMap<usize, Choice>, Error> {
+    let mut forbid: HashSet<(usize, ir::Prim)> = HashSet::new();
+    loop {
+        let choice = dp_choice(dag, def, matches, &forbid)?;
+        let usage = usage_from_choice(matches, &choice);
+        let over = usage
+            .iter()
+            .find(|(prim, area)| cost.budget(prim).map_or(false, |max| **area > max));
+        match over {
+            Some((prim, _)) => {
+                let last = choice
+                    .iter()
+                    .filter(|(i, c)| match c {
+                        Choice::Pat(m) => matches[**i][*m].prim == *prim,
+                        _ => false,
+                    })
+                    .map(|(i, _)| *i)
+                    .max();
+                if let Some(index) = last {
+                    forbid.insert((index, prim.clone()));
+                }
+            }
+            None => return Ok(choice),
+        }
+    }
+}
+
+// costs in the bound are scaled so they can be split among covered nodes
+const SCALE: u64 = 5040;
+
+struct Search<'a> {
+    dag: &'a Dag,
+    matches: &'a [Vec<Match>],
+    cost: &'a Cost,
+    share: Vec<u64>,
+    best: Option<HashMap<usize, Choice>>,
+    best_cost: u64,
+}
+
+impl<'a> Search<'a> {
+    // Every node between the pending values and the inputs or chosen roots is
+    // covered by some future pattern, which costs at least its cheapest share
+    fn bound(&self, acc: u64, pending: &BTreeSet<usize>, choice: &HashMap<usize, Choice>) -> u64 {
+        let mut bound = acc.saturating_mul(SCALE);
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut stack: Vec<usize> = pending.iter().cloned().collect();
+        while let Some(index) = stack.pop() {
+            if !visited.insert(index) {
+                continue;
+            }
+            bound = bound.saturating_add(self.share[index]);
+            for e in self.dag.edge(index) {
+                if is_required(self.dag, *e) && !choice.contains_key(e) {
+                    stack.push(*e);
+                }
+            }
+        }
+        bound
+    }
+    fn fits(&self, usage: &HashMap<ir::Prim, u64>) -> bool {
+        usage
+            .iter()
+            .all(|(prim, area)| self.cost.budget(prim).map_or(true, |max| *area <= max))
+    }
+    fn run(
+        &mut self,
+        choice: &mut HashMap<usize, Choice>,
+        pending: &BTreeSet<usize>,
+        acc: u64,
+        usage: &HashMap<ir::Prim, u64>,
+    ) {
+        if self.bound(acc, pending, choice) >= self.best_cost.saturating_mul(SCALE) {
+            return;
+        }
+        // consumers usually come after the values they use
+        let index = match pending.iter().next_back() {
+            Some(index) => *index,
+            None => {
+                self.best_cost = acc;
+                self.best = Some(choice.clone());
+                return;
+            }
+        };
+        let mut option: Vec<Choice> = Vec::new();
+        if self.dag.node(index).is_wire_op() {
+            option.push(Choice::Wire);
+        } else {
+            let mut order: Vec<usize> = (0..self.matches[index].len()).collect();
+            order.sort_by_key(|i| {
+                let m = &self.matches[index][*i];
+                (m.cost, m.rank, *i)
+            });
+            option.extend(order.into_iter().map(Choice::Pat));
+        }
+        for c in option {
+            let mut next_usage = usage.clone();
+            let mut next_acc = acc;
+            if let Choice::Pat(m) = &c {
+                let m = &self.matches[index][*m];
+                *next_usage.entry(m.prim.clone()).or_insert(0) += m.area;
+                next_acc = next_acc.saturating_add(m.cost);
+                if !self.fits(&next_usage) {
+                    continue;
+                }
+            }
+            let mut next = pending.clone();
+            next.remove(&index);
+            for l in needs(self.dag, self.matches, index, &c) {
+                if !choice.contains_key(&l) && l != index {
+                    next.insert(l);
+                }
+            }
+            choice.insert(index, c);
+            self.run(choice, &next, next_acc, &next_usage);
+            choice.remove(&index);
+        }
+    }
+}
+
+// Branch and bound over which pattern covers each needed value, starting
+// from the dp solution. Exact, budgets included, but exponential in the
+// worst case.
+pub fn exact_select(
+    dag: &Dag,
+    def: &ir::Def,
+    matches: &[Vec<Match>],
+    cost: &Cost,
+) -> Result<HashMap<usize, Choice>, Error> {
+    let mut share: Vec<u64> = dag
+        .node
+        .iter()
+        .map(|n| if n.is_prim_op() { u64::MAX } else { 0 })
+        .collect();
+    for m in matches.iter().flatten() {
+        let part = m.cost.saturating_mul(SCALE) / m.cover.len() as u64;
+        for index in m.cover.iter() {
+            share[*index] = share[*index].min(part);
+        }
+    }
+    let mut search = Search {
+        dag,
+        matches,
+        cost,
+        share,
+        best: None,
+        best_cost: u64::MAX,
+    };
+    let dp = dp_select(dag, def, matches, cost);
+    if let Ok(choice) = &dp {
+        if search.fits(&usage_from_choice(matches, choice)) {
+            // the search only keeps solutions that are strictly cheaper
+            search.best_cost = cost_from_choice(matches, choice).saturating_add(1);
+            search.best = Some(choice.clone());
+        }
+    }
+    let pending: BTreeSet<usize> = dag
+        .output
+        .iter()
+        .filter(|i| is_required(dag, **i))
+        .cloned()
+        .collect();
+    for index in pending.iter() {
+        if !dag.node(*index).is_wire_op() && matches[*index].is_empty() {
+            return Err(missing_node(dag, def, *index));
+        }
+    }
+    search.run(&mut HashMap::new(), &pending, 0, &HashMap::new());
+    match (search.best, dp) {
+        (Some(choice), _) => Ok(choice),
+        // nothing covers some node, with or without budgets
+        (None, Err(e)) => Err(e),
+        (None, Ok(_)) => Err(Error::new_isel_error(
+            "no selection covers the program within the budgets",
+        )),
+    }
+}
+
+// selected instructions in the order of the body
+pub fn dag_codegen(
+    dag: &Dag,
+    def: &ir::Def,
+    matches: &[Vec<Match>],
+    choice: &HashMap<usize, Choice>,
+    pat_map: &HashMap<String, pat::Pat>,
+) -> Result<Vec<asm::Instr>, Error> {
+    let mut body: Vec<asm::Instr> = Vec::new();
+    for instr in def.body() {
+        let index = dag.index[&instr.dst().get_id(0)?];
+        match choice.get(&index) {
+            Some(Choice::Pat(m)) => {
+                let m = &matches[index][*m];
+                if let Some(pat) = pat_map.get(&m.pat) {
+                    let mut output: HashMap<String, String> = HashMap::new();
+                    output.insert(pat.output().get_id(0)?, dag.node(index).id());
+                    let asm = asm::InstrAsm {
+                        op: asm::OpAsm::from(m.pat.clone()),
+                        dst: rename_dst(&output, pat.output())?,
+                        arg: rename_arg(&m.input, pat.input())?,
+                        loc: asm::Loc {
+                            prim: m.prim.clone(),
+                            x: asm::ExprCoord::Any,
+                            y: asm::ExprCoord::Any,
+                        },
+                        span: instr.span().cloned().unwrap_or_default(),
+                    };
+                    body.push(asm::Instr::from(asm));
+                }
+            }
+            Some(Choice::Wire) => {
+                let wire = asm::InstrWire::try_from(instr.clone())?;
+                body.push(asm::Instr::from(wire));
+            }
+            None => (),
+        }
+    }
+    Ok(body)
+}
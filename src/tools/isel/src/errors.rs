@@ -1,11 +1,15 @@
 use asm::errors::Error as AsmError;
 use ir::errors::Error as IrError;
+use pat::errors::Error as PatError;
 use std::fmt;
+use xim::errors::Error as XimError;
 
 #[derive(Debug)]
 pub enum Error {
     Ir(IrError),
     Asm(AsmError),
+    Pat(PatError),
+    Xim(XimError),
     ISel(String),
 }
 
@@ -27,11 +31,25 @@ impl From<AsmError> for Error {
     }
 }
 
+impl From<PatError> for Error {
+    fn from(e: PatError) -> Self {
+        Error::Pat(e)
+    }
+}
+
+impl From<XimError> for Error {
+    fn from(e: XimError) -> Self {
+        Error::Xim(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Ir(msg) => write!(f, "{}", msg),
             Error::Asm(msg) => write!(f, "{}", msg),
+            Error::Pat(msg) => write!(f, "{}", msg),
+            Error::Xim(msg) => write!(f, "{}", msg),
             Error::ISel(msg) => write!(f, "{}", msg),
         }
     }
@@ -0,0 +1,298 @@
+use crate::cost::Cost;
+use crate::errors::Error;
+use crate::library::Library;
+use crate::tree::{Id, Tree};
+use crate::{instrmap_from_prog, patterns_try_from_library, tree_select_with_cost};
+use ::ir::elab::flat_try_from_prog;
+use ::ir::span::diagnostic;
+use ir::ast as ir;
+use std::collections::VecDeque;
+use std::fmt;
+
+// candidates shown per library for every uncovered instruction
+const CLOSEST: usize = 3;
+
+// Why a pattern was not selected for an instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reject {
+    Ty,
+    Shape,
+    Prim,
+    Cost,
+    Budget,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub lib: String,
+    pub pat: String,
+    pub depth: usize,
+    pub reject: Reject,
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Uncovered {
+    pub id: Id,
+    pub instr: ir::Instr,
+    pub candidate: Vec<Candidate>,
+}
+
+impl Reject {
+    // closer rejections first
+    pub fn rank(&self) -> u8 {
+        match self {
+            Reject::Budget => 0,
+            Reject::Cost => 1,
+            Reject::Prim => 2,
+            Reject::Ty => 3,
+            Reject::Shape => 4,
+        }
+    }
+}
+
+impl Candidate {
+    pub fn lib(&self) -> &String {
+        &self.lib
+    }
+    pub fn pat(&self) -> &String {
+        &self.pat
+    }
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+    pub fn reject(&self) -> &Reject {
+        &self.reject
+    }
+    pub fn detail(&self) -> &String {
+        &self.detail
+    }
+}
+
+impl Uncovered {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+    pub fn instr(&self) -> &ir::Instr {
+        &self.instr
+    }
+    pub fn candidate(&self) -> &Vec<Candidate> {
+        &self.candidate
+    }
+}
+
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reject::Ty => write!(f, "type mismatch"),
+            Reject::Shape => write!(f, "shape mismatch"),
+            Reject::Prim => write!(f, "prim hint conflict"),
+            Reject::Cost => write!(f, "not cheaper"),
+            Reject::Budget => write!(f, "over budget"),
+        }
+    }
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({}), {}",
+            self.lib, self.pat, self.reject, self.detail
+        )
+    }
+}
+
+impl fmt::Display for Uncovered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = format!("{} is not covered by any pattern", self.instr);
+        write!(f, "{}", diagnostic(self.instr.span(), &msg))?;
+        if self.candidate.is_empty() {
+            write!(
+                f,
+                "\n    no library has a pattern rooted at this instruction"
+            )?;
+        }
+        for c in self.candidate.iter() {
+            write!(f, "\n    {}", c)?;
+        }
+        Ok(())
+    }
+}
+
+// Walk a pattern over a tree like selection does, returns the number of
+// instructions matched and the first reason the pattern does not apply
+fn reject_from_tree(block: &Tree, pat: &Tree, start: u64) -> (usize, Option<(Reject, String)>) {
+    let mut p_stack = pat.bfs(0);
+    p_stack.reverse();
+    let mut b_stack: VecDeque<u64> = VecDeque::new();
+    b_stack.push_back(start);
+    let mut depth: usize = 0;
+    let mut bcost: u64 = 0;
+    while let Some(bindex) = b_stack.pop_front() {
+        let (pnode, bnode) = match (p_stack.pop().and_then(|p| pat.node(p)), block.node(bindex)) {
+            (Some(pnode), Some(bnode)) => (pnode, bnode),
+            _ => break,
+        };
+        if pnode.is_inp_op() {
+            if pnode.ty() != bnode.ty() {
+                let detail = format!(
+                    "{} is {} but the pattern expects {}",
+                    bnode.id(),
+                    bnode.ty(),
+                    pnode.ty()
+                );
+                return (depth, Some((Reject::Ty, detail)));
+            }
+            continue;
+        }
+        if bnode.is_inp_op() {
+            let detail = format!(
+                "{} is shared or an input, the pattern expects {} on it",
+                bnode.id(),
+                pnode.op()
+            );
+            return (depth, Some((Reject::Shape, detail)));
+        }
+        if pnode.op() != bnode.op() {
+            let detail = format!(
+                "{} is {} but the pattern expects {}",
+                bnode.id(),
+                bnode.op(),
+                pnode.op()
+            );
+            return (depth, Some((Reject::Shape, detail)));
+        }
+        if pnode.attr() != bnode.attr() {
+            let detail = format!(
+                "{} has attributes {} but the pattern expects {}",
+                bnode.id(),
+                bnode.attr(),
+                pnode.attr()
+            );
+            return (depth, Some((Reject::Shape, detail)));
+        }
+        if pnode.ty() != bnode.ty() {
+            let detail = format!(
+                "{} is {} but the pattern expects {}",
+                bnode.id(),
+                bnode.ty(),
+                pnode.ty()
+            );
+            return (depth, Some((Reject::Ty, detail)));
+        }
+        if !bnode.prim().is_any() && pnode.prim() != bnode.prim() {
+            let detail = format!(
+                "{} is hinted @{} but the pattern uses @{}",
+                bnode.id(),
+                bnode.prim(),
+                pnode.prim()
+            );
+            return (depth, Some((Reject::Prim, detail)));
+        }
+        if bnode.is_committed() {
+            let detail = format!(
+                "{} is already covered by {}",
+                bnode.id(),
+                bnode.pat().map_or("another pattern", |p| p.as_str())
+            );
+            return (depth, Some((Reject::Shape, detail)));
+        }
+        depth += 1;
+        bcost = bcost.saturating_add(bnode.cost());
+        if let Some(edge) = block.edge(bindex) {
+            for e in edge {
+                b_stack.push_back(*e);
+            }
+        }
+    }
+    if !p_stack.is_empty() {
+        let detail = "the pattern has more instructions than the program".to_string();
+        return (depth, Some((Reject::Shape, detail)));
+    }
+    let pcost = pat.node(0).map_or(u64::MAX, |p| p.cost());
+    if pcost >= bcost {
+        let detail = format!("the pattern costs {} and what it covers {}", pcost, bcost);
+        return (depth, Some((Reject::Cost, detail)));
+    }
+    (depth, None)
+}
+
+// Instructions that greedy selection leaves uncovered, with the closest
+// patterns of every library and why they were rejected
+pub fn explain_ir_prog(
+    prog: &ir::Prog,
+    lib: &Library,
+    cost: &Cost,
+) -> Result<Vec<Uncovered>, Error> {
+    let prog = &flat_try_from_prog(prog)?;
+    let imap = instrmap_from_prog(prog)?;
+    let lib = &match prog.get("main") {
+        Some(main) => lib.instantiate(main)?,
+        None => lib.clone(),
+    };
+    let pats = patterns_try_from_library(lib, cost)?;
+    let blks = tree_select_with_cost(prog, &pats, cost)?;
+    let mut res: Vec<Uncovered> = Vec::new();
+    for blk in blks.iter() {
+        let mut index = blk.bfs(0);
+        index.reverse();
+        for i in index {
+            let node = match blk.node(i) {
+                Some(node) if node.is_prim_op() && node.is_free() => node,
+                _ => continue,
+            };
+            let instr = match imap.get(&node.id()) {
+                Some(instr) => instr.clone(),
+                None => continue,
+            };
+            let mut candidate: Vec<Candidate> = Vec::new();
+            for (l, map) in pats.tree.iter().enumerate() {
+                let name = lib.name(l).cloned().unwrap_or_else(|| l.to_string());
+                let mut closest: Vec<Candidate> = Vec::new();
+                for (pname, ptree) in map.iter() {
+                    let (depth, reject) = reject_from_tree(blk, ptree, i);
+                    let (reject, detail) = match reject {
+                        // patterns rooted at another operation are not candidates
+                        Some((Reject::Shape, _)) if depth == 0 => continue,
+                        Some(reject) => reject,
+                        // a pattern that applies was left out for its prim
+                        None => match pats.prim.get(pname).and_then(|p| cost.budget(p)) {
+                            Some(max) => {
+                                let detail = format!(
+                                    "the area of @{} is limited to {}",
+                                    pats.prim[pname], max
+                                );
+                                (Reject::Budget, detail)
+                            }
+                            None => continue,
+                        },
+                    };
+                    closest.push(Candidate {
+                        lib: name.clone(),
+                        pat: pname.clone(),
+                        depth,
+                        reject,
+                        detail,
+                    });
+                }
+                closest.sort_by(|a, b| {
+                    b.depth
+                        .cmp(&a.depth)
+                        .then_with(|| a.reject.rank().cmp(&b.reject.rank()))
+                        .then_with(|| a.pat.cmp(&b.pat))
+                });
+                closest.truncate(CLOSEST);
+                candidate.extend(closest);
+            }
+            res.push(Uncovered {
+                id: node.id(),
+                instr,
+                candidate,
+            });
+        }
+    }
+    // in source order
+    res.sort_by_key(|u| u.instr.span().map_or(0, |s| s.line()));
+    Ok(res)
+}
@@ -1,15 +1,25 @@
+pub mod cost;
+pub mod dag;
 pub mod errors;
+pub mod explain;
+pub mod library;
 pub mod tree;
 
+use crate::cost::{Cost, Select};
+use crate::dag::{dag_codegen, dp_select, exact_select, match_dag, Dag};
 use crate::errors::Error;
+use crate::library::Library;
 use crate::tree::helpers::{
-    tree_codegen, tree_commit, tree_select, treelist_try_from_prog, treemap_try_from_target_pair,
+    multimap_try_from_target_pair, tree_codegen, tree_commit, tree_fuse, tree_select,
+    treelist_try_from_prog, treemap_try_from_target_pair,
 };
-use crate::tree::TreeMap;
+use crate::tree::{MultiMap, Tree, TreeMap};
+use ::ir::elab::flat_try_from_prog;
 use asm::ast as asm;
 use ir::ast as ir;
 use pat::ast as pat;
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::path::Path;
 use xim::ast as xim;
 
@@ -35,40 +45,184 @@ pub fn instrmap_from_prog(prog: &ir::Prog) -> Result<ir::InstrMap, Error> {
 
 // TODO: impl try_from after refactoring done
 pub fn try_from_ir_prog(prog: &ir::Prog) -> Result<asm::Prog, Error> {
+    try_from_ir_prog_with_library(prog, &Library::builtin())
+}
+
+pub fn try_from_ir_prog_with_library(prog: &ir::Prog, lib: &Library) -> Result<asm::Prog, Error> {
+    try_from_ir_prog_with_cost(prog, lib, &Cost::default())
+}
+
+// area taken by the patterns selected in a tree, per prim
+fn usage_from_tree(
+    tree: &Tree,
+    prim_map: &HashMap<String, ir::Prim>,
+    area_map: &HashMap<String, u64>,
+) -> HashMap<ir::Prim, u64> {
+    let mut usage: HashMap<ir::Prim, u64> = HashMap::new();
+    for node in tree.node_map().values() {
+        if let Some(name) = node.pat() {
+            if let (Some(prim), Some(area)) = (prim_map.get(name), area_map.get(name)) {
+                *usage.entry(prim.clone()).or_insert(0) += area;
+            }
+        }
+    }
+    usage
+}
+
+fn is_within_budget(
+    blks: &[Tree],
+    prim_map: &HashMap<String, ir::Prim>,
+    area_map: &HashMap<String, u64>,
+    cost: &Cost,
+) -> bool {
+    let mut used: HashMap<ir::Prim, u64> = HashMap::new();
+    for blk in blks {
+        for (prim, area) in usage_from_tree(blk, prim_map, area_map) {
+            *used.entry(prim).or_insert(0) += area;
+        }
+    }
+    used.iter()
+        .all(|(prim, area)| cost.budget(prim).map_or(true, |max| *area <= max))
+}
+
+// Pattern trees of every library pair and what selection needs to know about
+// their patterns
+#[derive(Clone, Debug, Default)]
+pub struct Patterns {
+    pub tree: Vec<TreeMap>,
+    pub multi: MultiMap,
+    pub pat: HashMap<String, pat::Pat>,
+    pub prim: HashMap<String, ir::Prim>,
+    pub area: HashMap<String, u64>,
+}
+
+pub fn patterns_try_from_library(lib: &Library, cost: &Cost) -> Result<Patterns, Error> {
+    let mut res = Patterns::default();
+    for (target_pat, target_imp) in lib.pair() {
+        let map = treemap_try_from_target_pair(target_pat, target_imp, cost.model())?;
+        for (name, tree) in map.iter() {
+            if let Some(root) = tree.node(0) {
+                res.prim.insert(name.clone(), root.prim().clone());
+            }
+            if let Some(imp) = target_imp.get(name) {
+                res.area.insert(name.clone(), imp.area());
+            }
+        }
+        let multi = multimap_try_from_target_pair(target_pat, target_imp, cost.model())?;
+        for (name, trees) in multi.iter() {
+            if let Some(root) = trees.first().and_then(|t| t.node(0)) {
+                res.prim.insert(name.clone(), root.prim().clone());
+            }
+            if let Some(imp) = target_imp.get(name) {
+                res.area.insert(name.clone(), imp.area());
+            }
+        }
+        res.tree.push(map);
+        for (name, trees) in multi {
+            res.multi.entry(name).or_insert(trees);
+        }
+        res.pat.extend(target_pat.pat().clone());
+    }
+    Ok(res)
+}
+
+// Greedy selection over the trees of a flat program, returns the committed
+// trees where uncovered instructions are left free
+pub fn tree_select_with_cost(
+    prog: &ir::Prog,
+    pats: &Patterns,
+    cost: &Cost,
+) -> Result<Vec<Tree>, Error> {
+    let mut used: HashMap<ir::Prim, u64> = HashMap::new();
+    let mut blks: Vec<Tree> = Vec::new();
+    for blk in treelist_try_from_prog(prog)? {
+        let mut skip: HashSet<ir::Prim> = HashSet::new();
+        loop {
+            let mut sel = vec![blk.clone()];
+            for map in pats.tree.iter() {
+                let map: TreeMap = map
+                    .iter()
+                    .filter(|(name, _)| !skip.contains(&pats.prim[*name]))
+                    .map(|(name, tree)| (name.clone(), tree.clone()))
+                    .collect();
+                sel = tree_select(&sel, &map)?;
+            }
+            let usage = usage_from_tree(&sel[0], &pats.prim, &pats.area);
+            let over: Vec<ir::Prim> = usage
+                .iter()
+                .filter(|(prim, area)| match cost.budget(prim) {
+                    Some(max) => used.get(*prim).cloned().unwrap_or(0) + **area > max,
+                    None => false,
+                })
+                .map(|(prim, _)| prim.clone())
+                .collect();
+            if over.is_empty() {
+                for (prim, area) in usage {
+                    *used.entry(prim).or_insert(0) += area;
+                }
+                blks.extend(sel);
+                break;
+            }
+            // budget ran out, fall back to patterns of other prims
+            skip.extend(over);
+        }
+    }
+    let mut blks = tree_commit(&blks)?;
+    let mut multi_map = pats.multi.clone();
+    // roots computed by a pattern with several outputs share one instruction
+    while let Some((fused, name)) = tree_fuse(&blks, &multi_map)? {
+        if is_within_budget(&fused, &pats.prim, &pats.area, cost) {
+            blks = fused;
+        } else {
+            multi_map.remove(&name);
+        }
+    }
+    Ok(blks)
+}
+
+pub fn try_from_ir_prog_with_cost(
+    prog: &ir::Prog,
+    lib: &Library,
+    cost: &Cost,
+) -> Result<asm::Prog, Error> {
+    // calls are inlined, selection works on a flat main
+    let prog = &flat_try_from_prog(prog)?;
     if let Some(main) = prog.get("main") {
-        let lpat = deserialize_pat_from_file("lut");
-        let dpat = deserialize_pat_from_file("dsp");
-        let mpat = deserialize_pat_from_file("mem");
-        let limp = deserialize_imp_from_file("lut");
-        let dimp = deserialize_imp_from_file("dsp");
-        let mimp = deserialize_imp_from_file("mem");
-        let lmap = treemap_try_from_target_pair(&lpat, &limp)?;
-        let dmap = treemap_try_from_target_pair(&dpat, &dimp)?;
-        let mmap = treemap_try_from_target_pair(&mpat, &mimp)?;
+        let lib = &lib.instantiate(main)?;
+        let pats = patterns_try_from_library(lib, cost)?;
+        // patterns with several outputs are only fused by the greedy selector
+        if *cost.select() != Select::Greedy {
+            let dag = Dag::try_from(main)?;
+            let matches = match_dag(&dag, &pats.tree, &pats.area);
+            let choice = if *cost.select() == Select::Exact {
+                exact_select(&dag, main, &matches, cost)?
+            } else {
+                dp_select(&dag, main, &matches, cost)?
+            };
+            let body = dag_codegen(&dag, main, &matches, &choice, &pats.pat)?;
+            let mut res = asm::Prog::default();
+            res.set_sig(main.sig().clone());
+            res.set_body(body);
+            return Ok(tile::tile_from_prog_with_target(&res, &lib.imp()));
+        }
         let imap = instrmap_from_prog(prog)?;
-        let blks = treelist_try_from_prog(prog)?;
-        let blks = tree_select(&blks, &dmap)?;
-        let blks = tree_select(&blks, &lmap)?;
-        let blks = tree_select(&blks, &mmap)?;
-        let blks = tree_commit(&blks)?;
+        let blks = tree_select_with_cost(prog, &pats, cost)?;
+        let tree_map: TreeMap = pats
+            .tree
+            .iter()
+            .flatten()
+            .map(|(n, t)| (n.clone(), t.clone()))
+            .collect();
         let mut body: Vec<asm::Instr> = Vec::new();
         let mut iset: HashSet<ir::Id> = HashSet::new();
-        let tree_map: TreeMap = lmap.into_iter().chain(dmap).chain(mmap).collect();
-        let pat_map: HashMap<String, pat::Pat> = lpat
-            .pat()
-            .clone()
-            .into_iter()
-            .chain(dpat.pat().clone())
-            .chain(mpat.pat().clone())
-            .collect();
         for blk in blks {
-            body.extend(tree_codegen(&mut iset, &imap, &blk, &tree_map, &pat_map)?);
+            body.extend(tree_codegen(&mut iset, &imap, &blk, &tree_map, &pats.pat)?);
         }
         let mut res = asm::Prog::default();
         res.set_sig(main.sig().clone());
         res.set_body(body);
-        // tile operations that can be arranged in multiple slices
-        Ok(tile::tile_from_prog(&res))
+        // split wide operations onto the slices their implementations declare
+        Ok(tile::tile_from_prog_with_target(&res, &lib.imp()))
     } else {
         Err(Error::new_isel_error("main is not present"))
     }
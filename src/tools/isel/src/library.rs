@@ -0,0 +1,224 @@
+use crate::errors::Error;
+use crate::{deserialize_imp_from_file, deserialize_pat_from_file};
+use ::ir::span::diagnostic;
+use ::pat::parser::Parser as PatParser;
+use ::xim::parser::Parser as XimParser;
+use generic::instance_name;
+use ir::ast as ir;
+use pat::ast as pat;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xim::ast as xim;
+
+// built-in targets compiled by build.rs, in selection order
+const BUILTIN: [&str; 3] = ["dsp", "lut", "mem"];
+
+fn tys_from_expr(expr: &pat::Expr) -> Vec<pat::Ty> {
+    let term: Vec<pat::ExprTerm> = expr.clone().into();
+    term.iter().filter_map(|t| t.ty().cloned()).collect()
+}
+
+// widths of the integers and vector elements of a definition
+fn widths_from_def(def: &ir::Def) -> BTreeSet<u64> {
+    let mut term: Vec<ir::ExprTerm> = def.sig().input().clone().into();
+    term.extend(Vec::<ir::ExprTerm>::from(def.sig().output().clone()));
+    for instr in def.body() {
+        term.extend(Vec::<ir::ExprTerm>::from(instr.dst().clone()));
+    }
+    term.iter()
+        .filter_map(|t| t.ty())
+        .filter(|ty| !matches!(ty, ir::Ty::Bool))
+        .filter_map(|ty| ty.width())
+        .collect()
+}
+
+// every pattern needs an implementation with the same signature
+pub fn check_pair(target_pat: &pat::Target, target_imp: &xim::Target) -> Result<(), Error> {
+    let mut name: Vec<&String> = target_pat.pat().keys().collect();
+    name.sort();
+    for n in name {
+        let p = &target_pat.pat()[n];
+        let span = p.sig().span();
+        match target_imp.get(n) {
+            None => {
+                let msg = format!("pattern {} has no implementation", n);
+                return Err(Error::new_isel_error(&diagnostic(span, &msg)));
+            }
+            Some(imp) => {
+                if tys_from_expr(p.input()) != tys_from_expr(imp.input()) {
+                    let msg = format!("pattern {} and its implementation differ in inputs", n);
+                    return Err(Error::new_isel_error(&diagnostic(span, &msg)));
+                }
+                if tys_from_expr(p.output()) != tys_from_expr(imp.output()) {
+                    let msg = format!("pattern {} and its implementation differ in outputs", n);
+                    return Err(Error::new_isel_error(&diagnostic(span, &msg)));
+                }
+            }
+        }
+    }
+    check_split(target_imp)
+}
+
+// split implementations need their slices in the same target, chained slices
+// take a carry in after the inputs and give a carry out after the output
+fn check_split(target_imp: &xim::Target) -> Result<(), Error> {
+    let mut name: Vec<&String> = target_imp.imp().keys().collect();
+    name.sort();
+    for n in name {
+        let imp = &target_imp.imp()[n];
+        let span = imp.sig().span();
+        let carry = if imp.split().is_chain() { 1 } else { 0 };
+        for s in imp.split().slice() {
+            let slice = match target_imp.instance(s)? {
+                Some(slice) => slice,
+                None => {
+                    let msg = format!("implementation {} is split onto undefined {}", n, s);
+                    return Err(Error::new_isel_error(&diagnostic(span, &msg)));
+                }
+            };
+            let input: Vec<xim::ExprTerm> = slice.input().clone().into();
+            let output: Vec<xim::ExprTerm> = slice.output().clone().into();
+            let wide: Vec<xim::ExprTerm> = imp.input().clone().into();
+            if input.len() != wide.len() + carry || output.len() != 1 + carry {
+                let msg = format!("implementation {} does not fit slice {}", n, s);
+                return Err(Error::new_isel_error(&diagnostic(span, &msg)));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Pattern and implementation pairs used by selection. Pairs are tried in
+// order, so earlier pairs take precedence over later ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Library {
+    pub pair: Vec<(pat::Target, xim::Target)>,
+    pub name: Vec<String>,
+}
+
+impl Library {
+    pub fn builtin() -> Library {
+        let mut lib = Library::default();
+        for prim in BUILTIN.iter() {
+            let pat = deserialize_pat_from_file(prim);
+            let imp = deserialize_imp_from_file(prim);
+            lib.pair.push((pat, imp));
+            lib.name.push(prim.to_string());
+        }
+        lib
+    }
+    pub fn pair(&self) -> &Vec<(pat::Target, xim::Target)> {
+        &self.pair
+    }
+    // name of the i-th pair, used when reporting on selection
+    pub fn name(&self, index: usize) -> Option<&String> {
+        self.name.get(index)
+    }
+    pub fn add_pair(&mut self, pat: pat::Target, imp: xim::Target) -> Result<(), Error> {
+        let name = self.pair.len().to_string();
+        self.add_named_pair(&name, pat, imp)
+    }
+    pub fn add_named_pair(
+        &mut self,
+        name: &str,
+        pat: pat::Target,
+        imp: xim::Target,
+    ) -> Result<(), Error> {
+        check_pair(&pat, &imp)?;
+        self.pair.push((pat, imp));
+        self.name.push(name.to_string());
+        Ok(())
+    }
+    // all implementations, used by the assembler
+    pub fn imp(&self) -> xim::Target {
+        let mut target = xim::Target::default();
+        for (_, imp) in self.pair.iter() {
+            target.extend(imp.clone());
+        }
+        target
+    }
+    // Instances of generic patterns at every width used in a definition,
+    // added to their pair unless the pair already defines that width
+    pub fn instantiate(&self, def: &ir::Def) -> Result<Library, Error> {
+        let width = widths_from_def(def);
+        let mut lib = self.clone();
+        for (target_pat, target_imp) in lib.pair.iter_mut() {
+            let mut name: Vec<String> = target_pat.generic().keys().cloned().collect();
+            name.sort();
+            for n in name.iter() {
+                for w in width.iter() {
+                    let inst = instance_name(n, *w);
+                    if target_pat.get(&inst).is_some() {
+                        continue;
+                    }
+                    // widths out of the range of the implementation are left out
+                    if let (Some(pat), Some(imp)) =
+                        (target_pat.instance(&inst)?, target_imp.instance(&inst)?)
+                    {
+                        target_pat.insert(&inst, pat);
+                        if target_imp.get(&inst).is_none() {
+                            target_imp.insert(&inst, imp);
+                        }
+                    }
+                }
+            }
+            check_pair(target_pat, target_imp)?;
+        }
+        Ok(lib)
+    }
+    // the i-th pattern file goes with the i-th implementation file
+    pub fn from_files<P: AsRef<Path>>(pat: &[P], imp: &[P]) -> Result<Library, Error> {
+        if pat.len() != imp.len() {
+            return Err(Error::new_isel_error(
+                "every pattern file needs one implementation file",
+            ));
+        }
+        for path in pat.iter().chain(imp.iter()) {
+            if !path.as_ref().is_file() {
+                let msg = format!("cannot read {}", path.as_ref().display());
+                return Err(Error::new_isel_error(&msg));
+            }
+        }
+        let mut lib = Library::default();
+        for (p, i) in pat.iter().zip(imp.iter()) {
+            let target_pat = PatParser::parse_from_file(p)?;
+            let target_imp = XimParser::parse_from_file(i)?;
+            let name = p.as_ref().file_stem().map_or_else(
+                || lib.pair.len().to_string(),
+                |s| s.to_string_lossy().to_string(),
+            );
+            lib.add_named_pair(&name, target_pat, target_imp)?;
+        }
+        Ok(lib)
+    }
+    // every name.pat in the directory goes with name.xim, sorted by name
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Library, Error> {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                let msg = format!("cannot read target {}", dir.as_ref().display());
+                return Err(Error::new_isel_error(&msg));
+            }
+        };
+        let mut pat: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map_or(false, |e| e == "pat"))
+            .collect();
+        pat.sort();
+        if pat.is_empty() {
+            let msg = format!("target {} has no patterns", dir.as_ref().display());
+            return Err(Error::new_isel_error(&msg));
+        }
+        let mut imp: Vec<PathBuf> = Vec::new();
+        for p in pat.iter() {
+            let i = p.with_extension("xim");
+            if !i.is_file() {
+                let msg = format!("{} has no implementation file", p.display());
+                return Err(Error::new_isel_error(&msg));
+            }
+            imp.push(i);
+        }
+        Library::from_files(&pat, &imp)
+    }
+}
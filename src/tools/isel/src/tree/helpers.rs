@@ -1,5 +1,7 @@
+use crate::cost::CostModel;
 use crate::errors::Error;
 use crate::tree::*;
+use ::ir::span::diagnostic;
 use asm::ast as asm;
 use pat::ast as pat;
 use std::collections::BTreeMap;
@@ -99,6 +101,9 @@ impl Tree {
     pub fn edge(&self, index: u64) -> Option<&Vec<u64>> {
         self.edge.get(&index)
     }
+    pub fn fuse(&self, index: u64) -> Option<&Fuse> {
+        self.fuse.get(&index)
+    }
     pub fn dfg(&self, start: u64) -> Vec<u64> {
         let mut res: Vec<u64> = Vec::new();
         let mut stack: Vec<u64> = Vec::new();
@@ -376,7 +381,7 @@ pub fn treelist_try_from_def(def: &Def) -> Result<Vec<Tree>, Error> {
         visited.insert(r.clone());
     }
     for r in &roots {
-        let tree = create_tree(&mut visited, &instr_map, &input_map, &r, u64::MAX)?;
+        let tree = create_tree(&mut visited, &instr_map, &input_map, r, u64::MAX)?;
         res.push(tree);
     }
     Ok(res)
@@ -385,11 +390,19 @@ pub fn treelist_try_from_def(def: &Def) -> Result<Vec<Tree>, Error> {
 pub fn treemap_try_from_target_pair(
     target_pat: &pat::Target,
     target_imp: &xim::Target,
+    model: &CostModel,
 ) -> Result<TreeMap, Error> {
     let mut tree_map = TreeMap::new();
+    let mut single: usize = 0;
     for (n, p) in target_pat.pat() {
+        // patterns with several outputs are built by multimap_try_from_target_pair
+        let output: Vec<ExprTerm> = p.output().clone().into();
+        if output.len() != 1 {
+            continue;
+        }
+        single += 1;
         if let Some(imp) = target_imp.get(n) {
-            let cost = imp.perf();
+            let cost = model.cost(imp.sig());
             let instr_map = InstrMap::from(p.clone());
             let mut visited: HashSet<Id> = HashSet::new();
             let tree = tree_try_from_map(
@@ -402,13 +415,55 @@ pub fn treemap_try_from_target_pair(
             tree_map.insert(n.to_string(), tree);
         }
     }
-    if tree_map.len() == target_pat.pat().len() {
+    if tree_map.len() == single {
         Ok(tree_map)
     } else {
         Err(Error::new_isel_error("missing a pattern"))
     }
 }
 
+pub fn multimap_try_from_target_pair(
+    target_pat: &pat::Target,
+    target_imp: &xim::Target,
+    model: &CostModel,
+) -> Result<MultiMap, Error> {
+    let mut multi_map = MultiMap::new();
+    for (n, p) in target_pat.pat() {
+        let output: Vec<ExprTerm> = p.output().clone().into();
+        if output.len() < 2 {
+            continue;
+        }
+        if let Some(imp) = target_imp.get(n) {
+            let cost = model.cost(imp.sig());
+            let instr_map = InstrMap::from(p.clone());
+            let mut trees: Vec<Tree> = Vec::new();
+            let mut inner: HashSet<Id> = HashSet::new();
+            for term in output {
+                let id = term.get_id()?;
+                let mut visited: HashSet<Id> = HashSet::new();
+                let tree = tree_try_from_map(&instr_map, &mut visited, p.input(), &id, cost)?;
+                if tree.node(0).is_none() {
+                    let msg = format!("output {} of pattern {} is not computed", id, n);
+                    return Err(Error::new_isel_error(&diagnostic(p.sig().span(), &msg)));
+                }
+                // outputs are matched as separate trees, so they can only share inputs
+                for node in tree.node_map().values() {
+                    if node.is_prim_op() && !inner.insert(node.id()) {
+                        let msg =
+                            format!("outputs of pattern {} share instruction {}", n, node.id());
+                        return Err(Error::new_isel_error(&diagnostic(p.sig().span(), &msg)));
+                    }
+                }
+                trees.push(tree);
+            }
+            multi_map.insert(n.to_string(), trees);
+        } else {
+            return Err(Error::new_isel_error("missing a pattern"));
+        }
+    }
+    Ok(multi_map)
+}
+
 pub fn treelist_try_from_prog(prog: &Prog) -> Result<Vec<Tree>, Error> {
     if let Some(main) = prog.get("main") {
         Ok(treelist_try_from_def(main)?)
@@ -424,6 +479,7 @@ pub fn is_valid_change(block: &Tree, pat: &Tree, start: u64) -> (bool, u64) {
     b_stack.push_back(start);
     let mut next = b_stack.pop_front();
     let mut bcost: u64 = 0;
+    let mut bind: HashMap<Id, Id> = HashMap::new();
     if let Some(proot) = pat.node(0) {
         let mut is_match = true;
         let p_cost = proot.cost();
@@ -453,6 +509,12 @@ pub fn is_valid_change(block: &Tree, pat: &Tree, start: u64) -> (bool, u64) {
                                 }
                             }
                             next = b_stack.pop_front();
+                        } else if *bind.entry(pnode.id()).or_insert_with(|| bnode.id())
+                            != bnode.id()
+                        {
+                            // an input used twice must be bound to the same value
+                            next = None;
+                            is_match = false;
                         } else {
                             next = b_stack.pop_front();
                         }
@@ -543,9 +605,9 @@ pub fn tree_select(blocks: &[Tree], pmap: &HashMap<String, Tree>) -> Result<Vec<
         let cuts = btree.cut(0);
         for cut in cuts {
             for (pname, ptree) in pmap {
-                let (is_valid, cost) = is_valid_change(&ctree, &ptree, cut);
+                let (is_valid, cost) = is_valid_change(&ctree, ptree, cut);
                 if is_valid {
-                    ctree = tree_update(&ctree, &ptree, cut, &pname, cost);
+                    ctree = tree_update(&ctree, ptree, cut, pname, cost);
                 }
             }
         }
@@ -564,6 +626,233 @@ pub fn tree_commit(blocks: &[Tree]) -> Result<Vec<Tree>, Error> {
     Ok(res)
 }
 
+// Nodes covered by a pattern output rooted at start, where the block was
+// already selected. The cover must contain whole selected patterns, so every
+// pattern input falls on an input, a free wire, or the root of a selection.
+#[derive(Clone, Debug, Default)]
+struct Cover {
+    block: usize,
+    root: u64,
+    id: Id,
+    node: Vec<(u64, u64)>,
+    input: HashMap<Id, Id>,
+    cost: u64,
+}
+
+fn tree_match(block: &Tree, pat: &Tree, start: u64, fused: &HashSet<Id>) -> Option<Cover> {
+    block.node(start)?.pat()?;
+    let mut cover = Cover {
+        root: start,
+        id: block.node(start)?.id(),
+        ..Cover::default()
+    };
+    let mut p_stack = pat.bfs(0);
+    p_stack.reverse();
+    let mut b_stack: VecDeque<u64> = VecDeque::new();
+    b_stack.push_back(start);
+    while let Some(bindex) = b_stack.pop_front() {
+        let pnode = pat.node(p_stack.pop()?)?;
+        let bnode = block.node(bindex)?;
+        if pnode.ty() != bnode.ty() {
+            return None;
+        }
+        if pnode.is_inp_op() {
+            if !(bnode.is_inp_op()
+                || bnode.pat().is_some()
+                || bnode.is_free()
+                || fused.contains(&bnode.id()))
+            {
+                return None;
+            }
+            let id = cover.input.entry(pnode.id()).or_insert_with(|| bnode.id());
+            if *id != bnode.id() {
+                return None;
+            }
+        } else {
+            if pnode.op() != bnode.op()
+                || (!bnode.prim().is_any() && pnode.prim() != bnode.prim())
+                || pnode.attr() != bnode.attr()
+                || bnode.is_free()
+                || fused.contains(&bnode.id())
+            {
+                return None;
+            }
+            if bnode.pat().is_some() {
+                cover.cost += bnode.cost();
+            }
+            cover.node.push((bindex, pnode.index()));
+            if let Some(edge) = block.edge(bindex) {
+                for e in edge {
+                    b_stack.push_back(*e);
+                }
+            }
+        }
+    }
+    if p_stack.is_empty() {
+        Some(cover)
+    } else {
+        None
+    }
+}
+
+// program ids reachable from id, conservatively through registers too
+fn tree_reach(deps: &HashMap<Id, Vec<Id>>, id: &str) -> HashSet<Id> {
+    let mut res: HashSet<Id> = HashSet::new();
+    let mut stack: Vec<Id> = vec![id.to_string()];
+    while let Some(cur) = stack.pop() {
+        if res.insert(cur.clone()) {
+            if let Some(arg) = deps.get(&cur) {
+                stack.extend(arg.iter().cloned());
+            }
+        }
+    }
+    res
+}
+
+// pick one cover per output with consistent inputs and disjoint nodes
+fn tree_fuse_pick(
+    cand: &[Vec<Cover>],
+    pick: &mut Vec<Cover>,
+    input: &HashMap<Id, Id>,
+    best: &mut Option<(u64, Vec<Cover>)>,
+    cost: u64,
+    deps: &HashMap<Id, Vec<Id>>,
+) {
+    if pick.len() == cand.len() {
+        let old: u64 = pick.iter().map(|c| c.cost).sum();
+        let gain = old.saturating_sub(cost);
+        if gain == 0 || best.as_ref().map_or(false, |(g, _)| *g >= gain) {
+            return;
+        }
+        // an input cannot depend on an output of the same instruction
+        let root: HashSet<&Id> = pick.iter().map(|c| &c.id).collect();
+        if input
+            .values()
+            .any(|v| tree_reach(deps, v).iter().any(|r| root.contains(r)))
+        {
+            return;
+        }
+        *best = Some((gain, pick.clone()));
+        return;
+    }
+    for c in cand[pick.len()].iter() {
+        let overlap = pick.iter().any(|p| {
+            p.block == c.block
+                && p.node
+                    .iter()
+                    .any(|(b, _)| c.node.iter().any(|(n, _)| n == b))
+        });
+        let conflict = c
+            .input
+            .iter()
+            .any(|(k, v)| input.get(k).map_or(false, |w| w != v));
+        if overlap || conflict {
+            continue;
+        }
+        let mut next = input.clone();
+        next.extend(c.input.clone());
+        pick.push(c.clone());
+        tree_fuse_pick(cand, pick, &next, best, cost, deps);
+        pick.pop();
+    }
+}
+
+// Select the most profitable pattern with several outputs across the roots
+// of committed blocks, returns the new blocks and the name of the pattern
+pub fn tree_fuse(blocks: &[Tree], mmap: &MultiMap) -> Result<Option<(Vec<Tree>, String)>, Error> {
+    let mut deps: HashMap<Id, Vec<Id>> = HashMap::new();
+    let mut fused: HashSet<Id> = HashSet::new();
+    for block in blocks {
+        for (index, node) in block.node_map() {
+            if let Some(edge) = block.edge(*index) {
+                let arg: Vec<Id> = edge
+                    .iter()
+                    .filter_map(|e| block.node(*e))
+                    .map(|n| n.id())
+                    .collect();
+                deps.entry(node.id()).or_insert(arg);
+            }
+        }
+        for fuse in block.fuse.values() {
+            fused.extend(fuse.output.values().cloned());
+        }
+    }
+    let mut name: Vec<&String> = mmap.keys().collect();
+    name.sort();
+    let mut best: Option<(u64, String, Vec<Cover>)> = None;
+    for n in name {
+        let trees = &mmap[n];
+        let cost = trees[0].node(0).map_or(u64::MAX, |r| r.cost());
+        let mut cand: Vec<Vec<Cover>> = Vec::new();
+        for tree in trees {
+            let mut covers: Vec<Cover> = Vec::new();
+            for (b, block) in blocks.iter().enumerate() {
+                let mut index: Vec<u64> = block.node_map().keys().cloned().collect();
+                index.sort();
+                for i in index {
+                    if let Some(mut cover) = tree_match(block, tree, i, &fused) {
+                        cover.block = b;
+                        covers.push(cover);
+                    }
+                }
+            }
+            cand.push(covers);
+        }
+        let mut pick: Option<(u64, Vec<Cover>)> = None;
+        tree_fuse_pick(
+            &cand,
+            &mut Vec::new(),
+            &HashMap::new(),
+            &mut pick,
+            cost,
+            &deps,
+        );
+        if let Some((gain, covers)) = pick {
+            if best.as_ref().map_or(true, |(g, _, _)| gain > *g) {
+                best = Some((gain, n.to_string(), covers));
+            }
+        }
+    }
+    if let Some((_, name, covers)) = best {
+        let trees = &mmap[&name];
+        let cost = trees[0].node(0).map_or(u64::MAX, |r| r.cost());
+        let mut res = blocks.to_vec();
+        let mut fuse = Fuse::default();
+        for (cover, tree) in covers.iter().zip(trees.iter()) {
+            let block = &mut res[cover.block];
+            for (bindex, pindex) in cover.node.iter() {
+                let prim = tree.node(*pindex).map(|p| p.prim().clone());
+                if let Some(bnode) = block.node_mut(*bindex) {
+                    bnode.clear_pat();
+                    bnode.clear_commit();
+                    bnode.set_cost(0);
+                    bnode.stage();
+                    if let Some(prim) = prim {
+                        if bnode.is_prim_op() {
+                            bnode.set_pat_prim(prim);
+                        }
+                    }
+                }
+            }
+            fuse.input.extend(cover.input.clone());
+            if let Some(out) = tree.node(0) {
+                fuse.output.insert(out.id(), cover.id.clone());
+            }
+        }
+        // the instruction is emitted at the root of the first output
+        let block = &mut res[covers[0].block];
+        if let Some(bnode) = block.node_mut(covers[0].root) {
+            bnode.set_pat(&name);
+            bnode.set_cost(cost);
+            bnode.commit();
+        }
+        block.fuse.insert(covers[0].root, fuse);
+        Ok(Some((res, name)))
+    } else {
+        Ok(None)
+    }
+}
+
 pub fn rename_expr(
     map: &HashMap<String, String>,
     input: &asm::Expr,
@@ -607,15 +896,22 @@ pub fn tree_codegen(
     indices.reverse();
     let mut next = indices.pop();
     let mut uncover = String::new();
+    let mut uncover_span: Option<Span> = None;
     // bottom-up code generation
     while let Some(index) = next {
         if let Some(node) = block.node(index) {
             if node.is_committed() {
                 if let Some(name) = node.pat() {
-                    if let Some(tree) = tmap.get(name) {
+                    let bind = match (block.fuse(index), tmap.get(name)) {
+                        (Some(fuse), _) => Some((fuse.input.clone(), fuse.output.clone())),
+                        (None, Some(tree)) => Some((
+                            input_map(block, tree, index),
+                            output_map(block, tree, index),
+                        )),
+                        _ => None,
+                    };
+                    if let Some((input, output)) = bind {
                         if let Some(pat) = pmap.get(name) {
-                            let input = input_map(block, tree, index);
-                            let output = output_map(block, tree, index);
                             let dst = rename_dst(&output, pat.output())?;
                             let arg = rename_arg(&input, pat.input())?;
                             let op = asm::OpAsm::from(name.clone());
@@ -624,7 +920,19 @@ pub fn tree_codegen(
                                 x: asm::ExprCoord::Any,
                                 y: asm::ExprCoord::Any,
                             };
-                            let asm = asm::InstrAsm { op, dst, arg, loc };
+                            // selected instructions point to the root of the pattern
+                            let span = imap
+                                .get(&node.id())
+                                .and_then(|i| i.span())
+                                .cloned()
+                                .unwrap_or_default();
+                            let asm = asm::InstrAsm {
+                                op,
+                                dst,
+                                arg,
+                                loc,
+                                span,
+                            };
                             body.push(asm::Instr::from(asm));
                         }
                     }
@@ -645,6 +953,7 @@ pub fn tree_codegen(
                 } else {
                     next = None;
                     uncover = node.to_string();
+                    uncover_span = imap.get(&node.id()).and_then(|i| i.span()).cloned();
                 }
             } else {
                 next = indices.pop();
@@ -655,6 +964,9 @@ pub fn tree_codegen(
         Ok(body)
     } else {
         let msg = format!("missing node: {}", uncover);
-        Err(Error::new_isel_error(&msg))
+        Err(Error::new_isel_error(&diagnostic(
+            uncover_span.as_ref(),
+            &msg,
+        )))
     }
 }
@@ -1,4 +1,3 @@
-pub mod default;
 pub mod display;
 pub mod from;
 pub mod helpers;
@@ -21,7 +20,10 @@ pub type Instr = ir::Instr;
 pub type InstrMap = ir::InstrMap;
 pub type TermMap = ir::TermMap;
 pub type Prog = ir::Prog;
+pub type Span = ir::Span;
 pub type TreeMap = HashMap<String, Tree>;
+// patterns with several outputs, one tree per output
+pub type MultiMap = HashMap<String, Vec<Tree>>;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum NodeOp {
@@ -45,9 +47,18 @@ pub struct Node {
     pub pat_prim: Prim,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+// pattern ids bound to program ids when a pattern with several outputs
+// is selected across roots
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Fuse {
+    pub input: HashMap<Id, Id>,
+    pub output: HashMap<Id, Id>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Tree {
     pub index: u64,
     pub node: HashMap<u64, Node>,
     pub edge: HashMap<u64, Vec<u64>>,
+    pub fuse: HashMap<u64, Fuse>,
 }
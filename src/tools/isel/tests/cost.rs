@@ -0,0 +1,104 @@
+use asm::ast as asm;
+use ir::ast::Prim;
+use ir::parser::Parser as IrParser;
+use isel::cost::{Cost, CostModel};
+use isel::errors::Error;
+use isel::library::Library;
+use isel::try_from_ir_prog_with_cost;
+use pat::parser::Parser as PatParser;
+use std::str::FromStr;
+use xim::parser::Parser as XimParser;
+
+// a dsp add that is faster but larger than the lut one
+fn library() -> Result<Library, Error> {
+    let pat = PatParser::parse_from_str(
+        "pat dadd_i8(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b) @dsp;
+        }",
+    )?;
+    let imp = XimParser::parse_from_str("imp dadd_i8[2, 1](a:i8, b:i8) -> (y:i8) {}")?;
+    let mut lib = Library::default();
+    lib.add_named_pair("dadd", pat, imp)?;
+    let pat = PatParser::parse_from_file("../../../examples/pat/lut.pat")?;
+    let imp = XimParser::parse_from_file("../../../examples/xim/lut.xim")?;
+    lib.add_named_pair("lut", pat, imp)?;
+    Ok(lib)
+}
+
+fn select(lib: &Library, cost: &Cost) -> Result<Vec<String>, Error> {
+    let prog = IrParser::parse_from_str(
+        "def main(a:i8, b:i8, c:i8, d:i8) -> (y:i8, z:i8) {
+            y:i8 = add(a, b);
+            z:i8 = add(c, d);
+        }",
+    )?;
+    let res = try_from_ir_prog_with_cost(&prog, lib, cost)?;
+    let mut op: Vec<String> = res
+        .body()
+        .iter()
+        .filter_map(|i| match i {
+            asm::Instr::Asm(instr) => Some(instr.op().to_string()),
+            _ => None,
+        })
+        .collect();
+    op.sort();
+    Ok(op)
+}
+
+#[test]
+fn perf() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(&lib, &Cost::new(CostModel::Perf))?;
+    assert_eq!(res, vec!["dadd_i8", "dadd_i8"]);
+    Ok(())
+}
+
+#[test]
+fn area() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(&lib, &Cost::new(CostModel::Area))?;
+    assert_eq!(res, vec!["ladd_i8", "ladd_i8"]);
+    Ok(())
+}
+
+#[test]
+fn mixed() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(&lib, &Cost::new(CostModel::Mixed(2, 1)))?;
+    assert_eq!(res, vec!["ladd_i8", "ladd_i8"]);
+    let res = select(&lib, &Cost::new(CostModel::Mixed(1, 2)))?;
+    assert_eq!(res, vec!["dadd_i8", "dadd_i8"]);
+    Ok(())
+}
+
+#[test]
+fn budget() -> Result<(), Error> {
+    let lib = library()?;
+    let mut cost = Cost::new(CostModel::Perf);
+    cost.add_budget_from_str("dsp=2")?;
+    assert_eq!(cost.budget(&Prim::Dsp), Some(2));
+    let res = select(&lib, &cost)?;
+    assert_eq!(res, vec!["dadd_i8", "ladd_i8"]);
+    cost.set_budget(Prim::Dsp, 0);
+    let res = select(&lib, &cost)?;
+    assert_eq!(res, vec!["ladd_i8", "ladd_i8"]);
+    Ok(())
+}
+
+#[test]
+fn from_str() {
+    assert_eq!(CostModel::from_str("area").unwrap(), CostModel::Area);
+    assert_eq!(
+        CostModel::from_str("mixed").unwrap(),
+        CostModel::Mixed(1, 1)
+    );
+    assert_eq!(
+        CostModel::from_str("mixed:3,2").unwrap(),
+        CostModel::Mixed(3, 2)
+    );
+    assert_eq!(CostModel::Mixed(3, 2).to_string(), "mixed:3,2");
+    assert!(CostModel::from_str("mixed:3").is_err());
+    assert!(CostModel::from_str("mixed:a,2").is_err());
+    assert!(CostModel::from_str("speed").is_err());
+    assert!(Cost::default().add_budget_from_str("dsp").is_err());
+}
@@ -0,0 +1,115 @@
+use asm::ast as asm;
+use ir::ast::Prim;
+use ir::parser::Parser as IrParser;
+use isel::cost::{Cost, Select};
+use isel::errors::Error;
+use isel::library::Library;
+use isel::try_from_ir_prog_with_cost;
+use pat::parser::Parser as PatParser;
+use std::str::FromStr;
+use xim::parser::Parser as XimParser;
+
+// an add shared by two registers
+const SHARED: &str = "def main(a:i8, b:i8, en:bool, en2:bool) -> (y:i8, z:i8) {
+    t0:i8 = add(a, b);
+    y:i8 = reg[0](t0, en);
+    z:i8 = reg[0](t0, en2);
+}";
+
+// a lut library where fusing the add into both registers is faster but larger
+fn library() -> Result<Library, Error> {
+    let pat = PatParser::parse_from_str(
+        "pat ladd_i8(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b) @lut;
+        }
+        pat lrega_i8(a:i8, en:bool) -> (y:i8) {
+            y:i8 = reg[0](a, en) @lut;
+        }
+        pat laddrega_i8(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = add(a, b) @lut;
+            y:i8 = reg[0](t0, en) @lut;
+        }",
+    )?;
+    let imp = XimParser::parse_from_str(
+        "imp ladd_i8[1, 3](a:i8, b:i8) -> (y:i8) {}
+        imp lrega_i8[1, 1](a:i8, en:bool) -> (y:i8) {}
+        imp laddrega_i8[2, 2](a:i8, b:i8, en:bool) -> (y:i8) {}",
+    )?;
+    let mut lib = Library::default();
+    lib.add_named_pair("lut", pat, imp)?;
+    Ok(lib)
+}
+
+fn select(input: &str, lib: &Library, cost: &Cost) -> Result<Vec<String>, Error> {
+    let prog = IrParser::parse_from_str(input)?;
+    let res = try_from_ir_prog_with_cost(&prog, lib, cost)?;
+    let mut op: Vec<String> = res
+        .body()
+        .iter()
+        .filter_map(|i| match i {
+            asm::Instr::Asm(instr) => Some(instr.op().to_string()),
+            _ => None,
+        })
+        .collect();
+    op.sort();
+    Ok(op)
+}
+
+fn with_select(select: Select) -> Cost {
+    let mut cost = Cost::default();
+    cost.set_select(select);
+    cost
+}
+
+#[test]
+fn greedy() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(SHARED, &lib, &with_select(Select::Greedy))?;
+    assert_eq!(res, vec!["ladd_i8", "lrega_i8", "lrega_i8"]);
+    Ok(())
+}
+
+#[test]
+fn dp() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(SHARED, &lib, &with_select(Select::Dp))?;
+    assert_eq!(res, vec!["ladd_i8", "lrega_i8", "lrega_i8"]);
+    Ok(())
+}
+
+#[test]
+fn exact() -> Result<(), Error> {
+    let lib = library()?;
+    let res = select(SHARED, &lib, &with_select(Select::Exact))?;
+    assert_eq!(res, vec!["laddrega_i8", "laddrega_i8"]);
+    Ok(())
+}
+
+#[test]
+fn exact_budget() -> Result<(), Error> {
+    let lib = library()?;
+    let mut cost = with_select(Select::Exact);
+    cost.set_budget(Prim::Lut, 3);
+    let res = select(SHARED, &lib, &cost)?;
+    assert_eq!(res, vec!["ladd_i8", "lrega_i8", "lrega_i8"]);
+    Ok(())
+}
+
+#[test]
+fn builtin() -> Result<(), Error> {
+    let lib = Library::builtin();
+    let greedy = select(SHARED, &lib, &with_select(Select::Greedy))?;
+    let exact = select(SHARED, &lib, &with_select(Select::Exact))?;
+    assert_eq!(greedy, vec!["ladd_i8", "lrega_i8", "lrega_i8"]);
+    assert_eq!(exact, vec!["laddrega_i8", "laddrega_i8"]);
+    Ok(())
+}
+
+#[test]
+fn from_str() -> Result<(), Error> {
+    assert_eq!(Select::from_str("greedy")?, Select::Greedy);
+    assert_eq!(Select::from_str("dp")?, Select::Dp);
+    assert_eq!(Select::from_str("exact")?, Select::Exact);
+    assert!(Select::from_str("best").is_err());
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use ir::ast::Prim;
+use ir::parser::Parser as IrParser;
+use isel::cost::Cost;
+use isel::errors::Error;
+use isel::explain::{explain_ir_prog, Reject, Uncovered};
+use isel::library::Library;
+
+fn lut() -> Result<Library, Error> {
+    Library::from_files(
+        &["../../../examples/pat/lut.pat"],
+        &["../../../examples/xim/lut.xim"],
+    )
+}
+
+fn explain(input: &str, lib: &Library, cost: &Cost) -> Result<Vec<Uncovered>, Error> {
+    let prog = IrParser::parse_from_str(input)?;
+    explain_ir_prog(&prog, lib, cost)
+}
+
+// rejections of the closest candidate
+fn closest(res: &[Uncovered]) -> Vec<(String, String, Reject)> {
+    res.iter()
+        .filter_map(|u| {
+            u.candidate()
+                .first()
+                .map(|c| (u.id().clone(), c.pat().clone(), c.reject().clone()))
+        })
+        .collect()
+}
+
+#[test]
+fn covered() -> Result<(), Error> {
+    let prog = IrParser::parse_from_file("../../../examples/ir/fsm_3.ir")?;
+    let res = explain_ir_prog(&prog, &Library::builtin(), &Cost::default())?;
+    assert!(res.is_empty());
+    Ok(())
+}
+
+#[test]
+fn prim() -> Result<(), Error> {
+    let res = explain(
+        "def main(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b) @dsp;
+        }",
+        &lut()?,
+        &Cost::default(),
+    )?;
+    let exp = vec![("y".to_string(), "ladd_i8".to_string(), Reject::Prim)];
+    assert_eq!(closest(&res), exp);
+    assert_eq!(res[0].candidate()[0].lib(), "lut");
+    Ok(())
+}
+
+#[test]
+fn ty() -> Result<(), Error> {
+    let res = explain(
+        "def main(a:i8<4>, b:i8<4>) -> (y:i8<4>) {
+            y:i8<4> = add(a, b);
+        }",
+        &lut()?,
+        &Cost::default(),
+    )?;
+    assert_eq!(res.len(), 1);
+    assert!(res[0].candidate().iter().all(|c| *c.reject() == Reject::Ty));
+    assert!(!res[0].candidate().is_empty());
+    Ok(())
+}
+
+#[test]
+fn shape() -> Result<(), Error> {
+    let mut lib = lut()?;
+    let (pat, imp) = lib.pair[0].clone();
+    let mut pat = pat;
+    pat.pat.clear();
+    pat.generic.retain(|name, _| name == "laddrega");
+    lib = Library::default();
+    lib.add_named_pair("laddrega", pat, imp)?;
+    let res = explain(
+        "def main(a:i8, en:bool) -> (y:i8) {
+            y:i8 = reg[0](a, en);
+        }",
+        &lib,
+        &Cost::default(),
+    )?;
+    let exp = vec![("y".to_string(), "laddrega_i8".to_string(), Reject::Shape)];
+    assert_eq!(closest(&res), exp);
+    assert_eq!(res[0].candidate()[0].depth(), 1);
+    Ok(())
+}
+
+#[test]
+fn budget() -> Result<(), Error> {
+    let mut cost = Cost::default();
+    cost.set_budget(Prim::Lut, 0);
+    let res = explain(
+        "def main(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b);
+        }",
+        &lut()?,
+        &cost,
+    )?;
+    let exp = vec![("y".to_string(), "ladd_i8".to_string(), Reject::Budget)];
+    assert_eq!(closest(&res), exp);
+    Ok(())
+}
+
+#[test]
+fn display() -> Result<(), Error> {
+    let res = explain(
+        "def main(a:i8, b:i8) -> (y:i8) {
+            y:i8 = add(a, b) @dsp;
+        }",
+        &lut()?,
+        &Cost::default(),
+    )?;
+    let msg = res[0].to_string();
+    assert!(msg.contains("is not covered by any pattern"));
+    assert!(msg.contains("--> 2:13"));
+    assert!(msg.contains("lut: ladd_i8 (prim hint conflict)"));
+    Ok(())
+}
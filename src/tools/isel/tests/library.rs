@@ -0,0 +1,85 @@
+use ir::parser::Parser as IrParser;
+use isel::errors::Error;
+use isel::library::{check_pair, Library};
+use isel::{try_from_ir_prog, try_from_ir_prog_with_library};
+use std::fs;
+use std::path::Path;
+use xim::parser::Parser as XimParser;
+
+const PAT: &str = "../../../examples/pat";
+const XIM: &str = "../../../examples/xim";
+
+fn files(prim: &[&str]) -> (Vec<String>, Vec<String>) {
+    let pat = prim.iter().map(|p| format!("{}/{}.pat", PAT, p)).collect();
+    let xim = prim.iter().map(|p| format!("{}/{}.xim", XIM, p)).collect();
+    (pat, xim)
+}
+
+#[test]
+fn builtin() -> Result<(), Error> {
+    for (pat, imp) in Library::builtin().pair() {
+        check_pair(pat, imp)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn from_files() -> Result<(), Error> {
+    let (pat, xim) = files(&["dsp", "lut", "mem"]);
+    let lib = Library::from_files(&pat, &xim)?;
+    let prog = IrParser::parse_from_file("../../../examples/ir/fsm_3.ir")?;
+    let res = try_from_ir_prog_with_library(&prog, &lib)?;
+    let exp = try_from_ir_prog(&prog)?;
+    assert_eq!(res, exp);
+    Ok(())
+}
+
+#[test]
+fn from_dir() -> Result<(), Error> {
+    let dir = std::env::temp_dir().join("isel_library_from_dir");
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(Path::new(PAT).join("lut.pat"), dir.join("lut.pat")).unwrap();
+    fs::copy(Path::new(XIM).join("lut.xim"), dir.join("lut.xim")).unwrap();
+    let lib = Library::from_dir(&dir)?;
+    assert_eq!(lib.pair().len(), 1);
+    let prog = IrParser::parse_from_file("../../../examples/ir/add.ir")?;
+    let res = try_from_ir_prog_with_library(&prog, &lib)?;
+    assert!(res.body().iter().all(|i| i.to_string().contains("@lut")));
+    Ok(())
+}
+
+#[test]
+fn missing_imp() {
+    let pat = vec![format!("{}/lut.pat", PAT)];
+    let xim = vec![format!("{}/dsp.xim", XIM)];
+    let res = Library::from_files(&pat, &xim);
+    let msg = res.unwrap_err().to_string();
+    assert!(msg.starts_with("pattern "), "{}", msg);
+    assert!(msg.contains("has no implementation"), "{}", msg);
+}
+
+#[test]
+fn missing_file() {
+    let (_, xim) = files(&["lut"]);
+    let pat = vec![format!("{}/missing.pat", PAT)];
+    let res = Library::from_files(&pat, &xim);
+    let msg = res.unwrap_err().to_string();
+    assert_eq!(msg, format!("cannot read {}", pat[0]));
+}
+
+#[test]
+fn unpaired() {
+    let (pat, _) = files(&["lut"]);
+    assert!(Library::from_files(&pat, &[]).is_err());
+}
+
+#[test]
+fn undefined_slice() {
+    let imp = XimParser::parse_from_str(
+        "imp lxor_i16[2, 2](a:i16, b:i16) -> (y:i16) bitwise(lxor_i8) {}",
+    )
+    .unwrap();
+    let res = check_pair(&pat::ast::Target::default(), &imp);
+    let msg = res.unwrap_err().to_string();
+    assert!(msg.contains("is split onto undefined lxor_i8"), "{}", msg);
+}
@@ -1,8 +1,11 @@
 use asm::parser::Parser as AsmParser;
 use ir::parser::Parser as IrParser;
 use isel::errors::Error;
-use isel::try_from_ir_prog;
+use isel::library::Library;
+use isel::{try_from_ir_prog, try_from_ir_prog_with_library};
+use pat::parser::Parser as PatParser;
 use std::path::Path;
+use xim::parser::Parser as XimParser;
 
 fn test(name: &str) -> Result<(), Error> {
     let mut input = Path::new("../../../examples/ir").join(name);
@@ -41,6 +44,11 @@ fn fsm_9() -> Result<(), Error> {
     test("fsm_9")
 }
 
+#[test]
+fn landeq() -> Result<(), Error> {
+    test("landeq")
+}
+
 #[test]
 fn tadd() -> Result<(), Error> {
     test("tadd")
@@ -95,3 +103,129 @@ fn tdot_5_36() -> Result<(), Error> {
 fn mux128() -> Result<(), Error> {
     test("mux128")
 }
+
+#[test]
+fn span() -> Result<(), Error> {
+    let parsed = IrParser::parse_from_file("../../../examples/ir/add.ir")?;
+    let res = try_from_ir_prog(&parsed)?;
+    let line: Vec<usize> = res
+        .body()
+        .iter()
+        .map(|i| i.span().unwrap().line())
+        .collect();
+    assert_eq!(line, vec![3]);
+    Ok(())
+}
+
+// a lut computing and and eq of the same inputs, ahead of the built-in luts
+fn multi(pat: &str, imp: &str) -> Result<Library, Error> {
+    let mut lib = Library::default();
+    lib.add_named_pair(
+        "multi",
+        PatParser::parse_from_str(pat)?,
+        XimParser::parse_from_str(imp)?,
+    )?;
+    let pat = PatParser::parse_from_file("../../../examples/pat/lut.pat")?;
+    let imp = XimParser::parse_from_file("../../../examples/xim/lut.xim")?;
+    lib.add_named_pair("lut", pat, imp)?;
+    Ok(lib)
+}
+
+const LANDEQ_PAT: &str = "pat landeq_bool(a:bool, b:bool) -> (y:bool, z:bool) {
+    y:bool = and(a, b) @lut;
+    z:bool = eq(a, b) @lut;
+}";
+
+fn test_multi(lib: &Library, input: &str, output: &str) -> Result<(), Error> {
+    let parsed = IrParser::parse_from_str(input)?;
+    let exp = AsmParser::parse_from_str(output)?;
+    let res = try_from_ir_prog_with_library(&parsed, lib)?;
+    assert_eq!(res, exp);
+    Ok(())
+}
+
+#[test]
+fn multi_output() -> Result<(), Error> {
+    let imp = "imp landeq_bool[1, 1](a:bool, b:bool) -> (y:bool, z:bool) {}";
+    let lib = multi(LANDEQ_PAT, imp)?;
+    test_multi(
+        &lib,
+        "def main(a:bool, b:bool) -> (y:bool, z:bool) {
+            y:bool = and(a, b);
+            z:bool = eq(a, b);
+        }",
+        "def main(a:bool, b:bool) -> (y:bool, z:bool) {
+            (y:bool, z:bool) = landeq_bool(a, b) @lut(??, ??);
+        }",
+    )
+}
+
+#[test]
+fn multi_output_shared() -> Result<(), Error> {
+    let imp = "imp landeq_bool[1, 1](a:bool, b:bool) -> (y:bool, z:bool) {}";
+    let lib = multi(LANDEQ_PAT, imp)?;
+    test_multi(
+        &lib,
+        "def main(a:bool, b:bool) -> (y:bool) {
+            t0:bool = and(a, b);
+            t1:bool = eq(a, b);
+            y:bool = and(t0, t1);
+        }",
+        "def main(a:bool, b:bool) -> (y:bool) {
+            y:bool = land_bool(t0, t1) @lut(??, ??);
+            (t0:bool, t1:bool) = landeq_bool(a, b) @lut(??, ??);
+        }",
+    )
+}
+
+#[test]
+fn multi_output_inputs_differ() -> Result<(), Error> {
+    let imp = "imp landeq_bool[1, 1](a:bool, b:bool) -> (y:bool, z:bool) {}";
+    let lib = multi(LANDEQ_PAT, imp)?;
+    test_multi(
+        &lib,
+        "def main(a:bool, b:bool, c:bool) -> (y:bool, z:bool) {
+            y:bool = and(a, b);
+            z:bool = eq(a, c);
+        }",
+        "def main(a:bool, b:bool, c:bool) -> (y:bool, z:bool) {
+            y:bool = land_bool(a, b) @lut(??, ??);
+            z:bool = leq_bool(a, c) @lut(??, ??);
+        }",
+    )
+}
+
+#[test]
+fn multi_output_slower() -> Result<(), Error> {
+    let imp = "imp landeq_bool[1, 5](a:bool, b:bool) -> (y:bool, z:bool) {}";
+    let lib = multi(LANDEQ_PAT, imp)?;
+    test_multi(
+        &lib,
+        "def main(a:bool, b:bool) -> (y:bool, z:bool) {
+            y:bool = and(a, b);
+            z:bool = eq(a, b);
+        }",
+        "def main(a:bool, b:bool) -> (y:bool, z:bool) {
+            y:bool = land_bool(a, b) @lut(??, ??);
+            z:bool = leq_bool(a, b) @lut(??, ??);
+        }",
+    )
+}
+
+#[test]
+fn multi_output_share_instr() -> Result<(), Error> {
+    let pat = "pat landeq_bool(a:bool, b:bool) -> (y:bool, z:bool) {
+        t0:bool = and(a, b) @lut;
+        y:bool = not(t0) @lut;
+        z:bool = eq(t0, b) @lut;
+    }";
+    let imp = "imp landeq_bool[1, 1](a:bool, b:bool) -> (y:bool, z:bool) {}";
+    let lib = multi(pat, imp)?;
+    let parsed = IrParser::parse_from_str(
+        "def main(a:bool, b:bool) -> (y:bool) {
+            y:bool = and(a, b);
+        }",
+    )?;
+    assert!(try_from_ir_prog_with_library(&parsed, &lib).is_err());
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use crate::cse::output_ids;
+use crate::errors::Error;
+use crate::pass::Pass;
+use ir::ast::*;
+use std::collections::{HashMap, HashSet};
+
+// Registers a dsp can hold in front of each multiplier input
+pub const MAX_INPUT_REG: usize = 2;
+
+// Registers around a mul and add pair, in the a, b, m and p stages of a dsp
+#[derive(Clone, Debug)]
+struct Chain {
+    a: Vec<usize>,
+    b: Vec<usize>,
+    mul: usize,
+    m: Option<usize>,
+    add: usize,
+    p: usize,
+}
+
+// Instructions by destination, with the instructions reading every value
+struct Graph<'a> {
+    body: &'a [Instr],
+    driver: HashMap<Id, usize>,
+    user: HashMap<Id, Vec<usize>>,
+    output: HashSet<Id>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(def: &'a Def) -> Graph<'a> {
+        let mut driver: HashMap<Id, usize> = HashMap::new();
+        let mut user: HashMap<Id, Vec<usize>> = HashMap::new();
+        for (index, instr) in def.body().iter().enumerate() {
+            if let Ok(id) = instr.dst().get_id(0) {
+                driver.insert(id, index);
+            }
+            let arg: Vec<ExprTerm> = instr.arg().clone().into();
+            for id in arg.iter().filter_map(|t| t.id()) {
+                user.entry(id).or_default().push(index);
+            }
+        }
+        Graph {
+            body: def.body(),
+            driver,
+            user,
+            output: output_ids(def),
+        }
+    }
+    // the only instruction reading a value, which is not an output either
+    fn single_user(&self, id: &str) -> Option<usize> {
+        match self.user.get(id) {
+            Some(user) if user.len() == 1 && !self.output.contains(id) => Some(user[0]),
+            _ => None,
+        }
+    }
+    fn prim(&self, index: usize, op: OpPrim) -> Option<&InstrPrim> {
+        match self.body.get(index)? {
+            Instr::Prim(prim) if *prim.op() == op => Some(prim),
+            _ => None,
+        }
+    }
+    // register that starts at zero like the ones in a dsp, and is free to
+    // move there
+    fn reg(&self, index: usize) -> Option<&InstrPrim> {
+        let reg = self.prim(index, OpPrim::Reg)?;
+        let free = reg.prim().is_any() || *reg.prim() == Prim::Dsp;
+        if free && reg.attr().get_val(0).ok()? == 0 {
+            Some(reg)
+        } else {
+            None
+        }
+    }
+}
+
+// Registers in front of a multiplier input, closest first. When there are
+// two, both of them share the enable of the stage.
+fn input_stage(graph: &Graph, id: &str, taken: &HashSet<usize>) -> Vec<usize> {
+    let mut res: Vec<usize> = Vec::new();
+    let mut cur = id.to_string();
+    let mut en: Option<ExprTerm> = None;
+    while res.len() < MAX_INPUT_REG && graph.single_user(&cur).is_some() {
+        let index = match graph.driver.get(&cur) {
+            Some(index) if !taken.contains(index) => *index,
+            _ => break,
+        };
+        let reg = match graph.reg(index) {
+            Some(reg) => reg,
+            None => break,
+        };
+        let reg_en = reg.arg().get_term(1).ok().cloned();
+        if en.is_some() && en != reg_en {
+            break;
+        }
+        en = reg_en;
+        res.push(index);
+        cur = match reg.arg().get_id(0) {
+            Ok(id) => id,
+            Err(_) => break,
+        };
+    }
+    res
+}
+
+fn chain(graph: &Graph, mul: usize, taken: &HashSet<usize>) -> Option<Chain> {
+    let instr = graph.prim(mul, OpPrim::Mul)?;
+    if *instr.prim() != Prim::Dsp {
+        return None;
+    }
+    let mut a = input_stage(graph, &instr.arg().get_id(0).ok()?, taken);
+    let mut b = input_stage(graph, &instr.arg().get_id(1).ok()?, taken);
+    // both multiplier inputs go through the same number of registers
+    let depth = a.len().min(b.len());
+    a.truncate(depth);
+    b.truncate(depth);
+    let mut cur = instr.dst().get_id(0).ok()?;
+    let mut next = graph.single_user(&cur)?;
+    let mut m = None;
+    if !taken.contains(&next) && graph.reg(next).is_some() {
+        m = Some(next);
+        cur = graph.body[next].dst().get_id(0).ok()?;
+        next = graph.single_user(&cur)?;
+    }
+    let add = graph.prim(next, OpPrim::Add)?;
+    if !(add.prim().is_any() || *add.prim() == Prim::Dsp) {
+        return None;
+    }
+    let p = graph.single_user(&add.dst().get_id(0).ok()?)?;
+    if taken.contains(&p) || graph.reg(p).is_none() {
+        return None;
+    }
+    Some(Chain {
+        a,
+        b,
+        mul,
+        m,
+        add: next,
+        p,
+    })
+}
+
+fn bind(instr: &mut Instr) {
+    if let Instr::Prim(prim) = instr {
+        prim.set_prim(Prim::Dsp);
+    }
+}
+
+// Register absorption, registers next to a mul and add pair bound to a dsp
+// become its AREG/BREG, MREG and PREG stages. Cycles stay the same because
+// only registers that start at zero are absorbed, the values between stages
+// have no other readers, and the two registers of a stage share an enable.
+// The output of the add must be registered.
+pub fn absorb_def(def: &Def) -> Result<Def, Error> {
+    let graph = Graph::new(def);
+    let mut taken: HashSet<usize> = HashSet::new();
+    let mut found: Vec<Chain> = Vec::new();
+    for index in 0..def.body().len() {
+        if let Some(chain) = chain(&graph, index, &taken) {
+            taken.extend(chain.a.iter().chain(chain.b.iter()).cloned());
+            taken.extend(chain.m.iter().cloned());
+            taken.insert(chain.p);
+            found.push(chain);
+        }
+    }
+    let mut body = def.body().clone();
+    for chain in found {
+        let prod = match chain.m {
+            Some(m) => body[m].dst().get_id(0)?,
+            None => body[chain.mul].dst().get_id(0)?,
+        };
+        // the product comes first, as in the patterns
+        if body[chain.add].arg().get_id(1)? == prod {
+            let mut arg: Vec<ExprTerm> = body[chain.add].arg().clone().into();
+            arg.swap(0, 1);
+            body[chain.add].set_arg(Expr::from(ExprTup::from(arg)));
+        }
+        let stage = chain.a.iter().chain(chain.b.iter()).chain(chain.m.iter());
+        for index in stage.chain([chain.mul, chain.add, chain.p].iter()) {
+            bind(&mut body[*index]);
+        }
+    }
+    let mut def = def.clone();
+    def.set_body(body);
+    Ok(def)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Absorb;
+
+impl Pass<Prog> for Absorb {
+    fn name(&self) -> &str {
+        "absorb"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        let mut res = prog.clone();
+        for (name, def) in prog.def() {
+            res.insert(name, absorb_def(def)?);
+        }
+        Ok(res)
+    }
+}
@@ -1,176 +1,235 @@
 use crate::errors::Error;
+use crate::pass::Pass;
 use asm::ast::*;
-use itertools::Itertools;
 use std::collections::HashMap;
 use std::rc::Rc;
+use xim::ast::Target;
 
-type Map = HashMap<String, InstrAsm>;
-type Pair = HashMap<String, String>;
+// Cascade variants of an implementation, named with a ci, co or cio suffix
+// before the type, e.g. dmuladdregaci_i8i8 for dmuladdrega_i8i8
+#[derive(Clone, Debug)]
+pub struct Family {
+    ci: String,
+    co: String,
+    cio: String,
+    // argument driven by the cascade
+    arg: usize,
+}
 
-fn create_map_and_pair(prog: &Prog) -> Result<(Map, Pair), Error> {
-    let mut map = Map::new();
-    let mut pair = Pair::new();
-    for instr in prog.body() {
-        match instr {
-            Instr::Asm(instr) if instr.op().name().as_str() == "dmuladdrega_i8i8" => {
-                let dst = instr.dst().get_term(0)?;
-                let arg = instr.arg().get_term(2)?;
-                let dst_id = dst.get_id()?;
-                let arg_id = arg.get_id()?;
-                map.insert(dst_id.clone(), instr.clone());
-                pair.insert(arg_id, dst_id);
-            }
-            _ => (),
-        }
+impl Family {
+    pub fn ci(&self) -> &str {
+        &self.ci
     }
-    let keys: Vec<String> = pair.keys().cloned().collect();
-    for k in keys {
-        if !map.contains_key(&k) {
-            pair.remove(&k);
-        }
+    pub fn co(&self) -> &str {
+        &self.co
+    }
+    pub fn cio(&self) -> &str {
+        &self.cio
+    }
+    pub fn arg(&self) -> usize {
+        self.arg
     }
-    Ok((map, pair))
 }
 
-fn find_tail(pair: &Pair) -> String {
-    // make it ordered
-    let keys: Vec<String> = pair
-        .iter()
-        .sorted_by_key(|(x, _)| (*x).clone())
-        .map(|(x, _)| x)
-        .cloned()
-        .collect();
-    let mut tail = keys[0].clone();
-    let mut prev = String::new();
-    while tail != prev {
-        prev = tail.clone();
-        for (k, v) in pair {
-            if v == &tail {
-                tail = k.clone();
-            }
-        }
+fn variant(name: &str, suffix: &str) -> String {
+    match name.rfind('_') {
+        Some(i) => format!("{}{}{}", &name[..i], suffix, &name[i..]),
+        None => format!("{}{}", name, suffix),
     }
-    tail
 }
 
-fn find_cascade(pair: &Pair, tail: &str) -> (Vec<String>, Pair) {
-    let mut stack: Vec<String> = Vec::new();
-    stack.push(tail.to_string());
-    let mut cascade: Vec<String> = Vec::new();
-    let mut pair = pair.clone();
-    while let Some(cur) = stack.pop() {
-        cascade.push(cur.clone());
-        if let Some(next) = pair.get(&cur) {
-            stack.push(next.clone());
-            pair.remove(&cur);
+// An implementation is cascadable when the target has its three variants
+// and the cascade input of the ci variant is one of its inputs. A cascade
+// input that skips registers, as in vaddregaci, would change the latency.
+pub fn family(target: &Target, name: &str) -> Result<Option<Family>, Error> {
+    let (ci, co, cio) = (
+        variant(name, "ci"),
+        variant(name, "co"),
+        variant(name, "cio"),
+    );
+    if target.instance(&co)?.is_none() || target.instance(&cio)?.is_none() {
+        return Ok(None);
+    }
+    let imp = match target.instance(&ci)? {
+        Some(imp) => imp,
+        None => return Ok(None),
+    };
+    let input: Vec<ExprTerm> = imp.sig().input().clone().into();
+    for mach in imp.body().iter().filter_map(|i| i.mach()) {
+        if mach.op().is_cascade_unregistered() {
+            return Ok(None);
+        }
+        if let Some(index) = mach.op().cascade_arg() {
+            let id = mach.arg().get_id(index).ok();
+            if let Some(arg) = input.iter().position(|t| t.get_id().ok() == id) {
+                return Ok(Some(Family { ci, co, cio, arg }));
+            }
         }
     }
-    (cascade, pair)
+    Ok(None)
 }
 
-fn find_cascade_all(pair: &Pair) -> Vec<Vec<String>> {
-    let mut pair = pair.clone();
-    let mut res: Vec<Vec<String>> = Vec::new();
-    while !pair.is_empty() {
-        let tail = find_tail(&pair);
-        let (c, p) = find_cascade(&pair, &tail);
-        pair = p;
-        res.push(c);
-    }
-    res
+// Dsp instructions of a cascadable family, and for each one the
+// instructions whose cascaded argument it drives
+#[derive(Clone, Debug, Default)]
+struct Tree {
+    node: Vec<usize>,
+    family: HashMap<usize, Family>,
+    parent: HashMap<usize, usize>,
+    child: HashMap<usize, Vec<usize>>,
 }
 
-fn x_coord(x: u64) -> ExprCoord {
-    let var = format!("x{}", x);
-    ExprCoord::Var(var)
+impl Tree {
+    fn from_prog(prog: &Prog, target: &Target) -> Result<Tree, Error> {
+        let mut tree = Tree::default();
+        let mut cache: HashMap<String, Option<Family>> = HashMap::new();
+        let mut def: HashMap<Id, usize> = HashMap::new();
+        for (index, instr) in prog.body().iter().enumerate() {
+            if let Instr::Asm(instr) = instr {
+                let name = instr.op().name();
+                if !cache.contains_key(&name) {
+                    cache.insert(name.clone(), family(target, &name)?);
+                }
+                if let Some(family) = &cache[&name] {
+                    def.insert(instr.dst().get_id(0)?, index);
+                    tree.node.push(index);
+                    tree.family.insert(index, family.clone());
+                }
+            }
+        }
+        for n in tree.node.iter() {
+            if let Instr::Asm(instr) = &prog.body()[*n] {
+                let arg = instr.arg().get_id(tree.family[n].arg());
+                if let Some(p) = arg.ok().and_then(|id| def.get(&id)) {
+                    if p != n {
+                        tree.parent.insert(*n, *p);
+                        tree.child.entry(*p).or_default().push(*n);
+                    }
+                }
+            }
+        }
+        Ok(tree)
+    }
+    fn children(&self, node: usize) -> &[usize] {
+        self.child.get(&node).map_or(&[], |c| c.as_slice())
+    }
+    fn depth(&self, node: usize, depth: &mut HashMap<usize, usize>) -> usize {
+        if let Some(d) = depth.get(&node) {
+            return *d;
+        }
+        let mut d = 0;
+        for c in self.children(node) {
+            d = d.max(self.depth(*c, depth));
+        }
+        depth.insert(node, d + 1);
+        d + 1
+    }
+    // Split every tree into chains from a root to a leaf, following the
+    // deepest child so that the chains are as long as possible, and the
+    // other children start chains of their own
+    fn chains(&self) -> Vec<Vec<usize>> {
+        let mut depth: HashMap<usize, usize> = HashMap::new();
+        let mut res: Vec<Vec<usize>> = Vec::new();
+        let mut start: Vec<usize> = Vec::new();
+        for n in self.node.iter().rev() {
+            if !self.parent.contains_key(n) {
+                start.push(*n);
+            }
+        }
+        while let Some(mut cur) = start.pop() {
+            let mut chain = vec![cur];
+            loop {
+                let child = self.children(cur);
+                let next = child
+                    .iter()
+                    .cloned()
+                    .max_by_key(|c| (self.depth(*c, &mut depth), std::cmp::Reverse(*c)));
+                match next {
+                    Some(next) => {
+                        for c in child.iter().rev().filter(|c| **c != next) {
+                            start.push(*c);
+                        }
+                        chain.push(next);
+                        cur = next;
+                    }
+                    None => break,
+                }
+            }
+            res.push(chain);
+        }
+        res
+    }
 }
 
-fn y_coord(y: u64) -> ExprCoord {
-    let var = format!("y{}", y);
+fn x_coord(x: u64) -> ExprCoord {
+    let var = format!("x{}", x);
     ExprCoord::Var(var)
 }
 
 fn y_add_val_coord(y: u64, val: u64) -> ExprCoord {
     let var = format!("y{}", y);
-    let y = Rc::new(ExprCoord::Var(var));
-    let one = Rc::new(ExprCoord::Val(val));
-    ExprCoord::Bin(OpCoord::Add, y, one)
+    let y = ExprCoord::Var(var);
+    if val == 0 {
+        y
+    } else {
+        let one = Rc::new(ExprCoord::Val(val));
+        ExprCoord::Bin(OpCoord::Add, Rc::new(y), one)
+    }
 }
 
-#[allow(unused_assignments)]
-fn replace_map(map: &Map, cascade: &[Vec<String>]) -> Map {
-    let mut res = Map::new();
-    for (x, c) in cascade.iter().enumerate() {
-        let mut y: u64 = 1;
-        let mut cur = c.clone();
-        cur.reverse();
-        if let Some(tail) = cur.pop() {
-            if let Some(instr) = map.get(&tail) {
-                let mut instr = instr.clone();
-                let op = String::from("dmuladdregaco_i8i8");
-                let op = OpAsm::Op(op);
-                instr.set_op(op);
-                let mut loc = instr.loc().clone();
-                loc.set_x(x_coord(x as u64));
-                loc.set_y(y_coord(x as u64));
-                instr.set_loc(loc);
-                res.insert(tail.clone(), instr);
-            }
-        }
-        while cur.len() > 1 {
-            if let Some(mid) = cur.pop() {
-                if let Some(instr) = map.get(&mid) {
-                    let mut instr = instr.clone();
-                    let op = String::from("dmuladdregacio_i8i8");
-                    let op = OpAsm::Op(op);
-                    instr.set_op(op);
+// Turn chains of cascadable dsp instructions into cascades, each one
+// placed in a column no taller than height. Trees that branch are split
+// into chains, and a chain of a single instruction is left as it is
+pub fn cascader(prog: &Prog, target: &Target, height: usize) -> Result<Prog, Error> {
+    if height == 0 {
+        return Err(Error::new_opt_error("Cascade height must be at least one"));
+    }
+    let tree = Tree::from_prog(prog, target)?;
+    let mut prog = prog.clone();
+    let mut x: u64 = 0;
+    for chain in tree.chains() {
+        for column in chain.chunks(height).filter(|c| c.len() > 1) {
+            for (y, n) in column.iter().enumerate() {
+                let family = &tree.family[n];
+                let op = if y == 0 {
+                    family.co()
+                } else if y == column.len() - 1 {
+                    family.ci()
+                } else {
+                    family.cio()
+                };
+                if let Instr::Asm(instr) = &mut prog.body_mut()[*n] {
+                    instr.set_op(OpAsm::Op(op.to_string()));
                     let mut loc = instr.loc().clone();
-                    loc.set_x(x_coord(x as u64));
-                    loc.set_y(y_add_val_coord(x as u64, y));
+                    loc.set_x(x_coord(x));
+                    loc.set_y(y_add_val_coord(x, y as u64));
                     instr.set_loc(loc);
-                    res.insert(mid.clone(), instr);
-                    y += 1;
                 }
             }
-        }
-        if let Some(head) = cur.pop() {
-            if let Some(instr) = map.get(&head) {
-                let mut instr = instr.clone();
-                let op = String::from("dmuladdregaci_i8i8");
-                let op = OpAsm::Op(op);
-                instr.set_op(op);
-                let mut loc = instr.loc().clone();
-                loc.set_x(x_coord(x as u64));
-                loc.set_y(y_add_val_coord(x as u64, y));
-                instr.set_loc(loc);
-                res.insert(head.clone(), instr);
-                y += 1;
-            }
+            x += 1;
         }
     }
-    res
+    Ok(prog)
 }
 
-pub fn cascader(prog: &Prog) -> Result<Prog, Error> {
-    let (map, pair) = create_map_and_pair(&prog)?;
-    let cas = find_cascade_all(&pair);
-    let map = replace_map(&map, &cas);
-    let mut body: Vec<Instr> = Vec::new();
-    for instr in prog.body() {
-        match instr {
-            Instr::Asm(instr_asm) => {
-                let term = instr_asm.dst().get_term(0)?;
-                let id = term.get_id()?;
-                if let Some(instr_asm) = map.get(&id) {
-                    body.push(Instr::from(instr_asm.clone()));
-                } else {
-                    body.push(instr.clone());
-                }
-            }
-            _ => body.push(instr.clone()),
-        }
+// The cascader as a pass on asm programs
+#[derive(Clone, Debug)]
+pub struct Cascade {
+    target: Target,
+    height: usize,
+}
+
+impl Cascade {
+    pub fn new(target: Target, height: usize) -> Cascade {
+        Cascade { target, height }
+    }
+}
+
+impl Pass<Prog> for Cascade {
+    fn name(&self) -> &str {
+        "cascade"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        cascader(prog, &self.target, self.height)
     }
-    let mut prog = prog.clone();
-    prog.set_body(body);
-    Ok(prog)
 }
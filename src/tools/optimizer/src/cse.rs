@@ -0,0 +1,167 @@
+use crate::errors::Error;
+use crate::pass::Pass;
+use ir::ast::*;
+use std::collections::{HashMap, HashSet};
+
+// Wires and prims other than registers and memories compute their
+// outputs from their arguments alone
+pub fn is_pure(instr: &Instr) -> bool {
+    match instr {
+        Instr::Wire(_) => true,
+        Instr::Prim(instr) => !matches!(
+            instr.op(),
+            OpPrim::Reg | OpPrim::Ram | OpPrim::Rom | OpPrim::Sram | OpPrim::Srom
+        ),
+        Instr::Call(_) => false,
+    }
+}
+
+pub fn output_ids(def: &Def) -> HashSet<Id> {
+    let output: Vec<ExprTerm> = def.output().clone().into();
+    output.iter().filter_map(|t| t.id()).collect()
+}
+
+// Use the values in the map instead of the keys in the arguments of an
+// instruction, true when an argument changed
+pub fn rename_arg(instr: &mut Instr, map: &HashMap<Id, Id>) -> bool {
+    let rename = |term: &ExprTerm| match term {
+        ExprTerm::Var(id, ty) => match map.get(id) {
+            Some(new) => ExprTerm::Var(new.to_string(), ty.clone()),
+            None => term.clone(),
+        },
+        _ => term.clone(),
+    };
+    let arg = match instr.arg() {
+        Expr::Term(term) => Expr::from(rename(term)),
+        Expr::Tup(tup) => Expr::from(ExprTup::from(
+            tup.term().iter().map(rename).collect::<Vec<ExprTerm>>(),
+        )),
+    };
+    if &arg == instr.arg() {
+        false
+    } else {
+        instr.set_arg(arg);
+        true
+    }
+}
+
+// Instruction without its destination name, equal for instructions that
+// compute the same value
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Wire(OpWire, Expr, Expr, Ty),
+    Prim(OpPrim, Expr, Expr, Prim, Ty),
+}
+
+fn key(instr: &Instr) -> Option<Key> {
+    if !is_pure(instr) {
+        return None;
+    }
+    let dst: Vec<ExprTerm> = instr.dst().clone().into();
+    let ty = match dst.as_slice() {
+        [ExprTerm::Var(_, ty)] => ty.clone(),
+        _ => return None,
+    };
+    match instr {
+        Instr::Wire(w) => Some(Key::Wire(
+            w.op().clone(),
+            w.attr().clone(),
+            w.arg().clone(),
+            ty,
+        )),
+        Instr::Prim(p) => Some(Key::Prim(
+            p.op().clone(),
+            p.attr().clone(),
+            p.arg().clone(),
+            p.prim().clone(),
+            ty,
+        )),
+        Instr::Call(_) => None,
+    }
+}
+
+// an id of a value of the same type, or a mux choosing between the same
+// value, is a copy of it
+fn copy(instr: &Instr) -> Option<Id> {
+    let arg = match instr {
+        Instr::Wire(w) if *w.op() == OpWire::Id => w.arg().get_term(0).ok()?,
+        Instr::Prim(p) if *p.op() == OpPrim::Mux && p.arg().idx(1) == p.arg().idx(2) => {
+            p.arg().get_term(1).ok()?
+        }
+        _ => return None,
+    };
+    if instr.dst().get_term(0).ok()?.ty() == arg.ty() {
+        arg.id()
+    } else {
+        None
+    }
+}
+
+// Common subexpression elimination, an instruction that computes the same
+// value as an earlier one is removed and its uses read the earlier one,
+// outputs keep their instruction
+pub fn cse_def(def: &Def) -> Result<Def, Error> {
+    let output = output_ids(def);
+    let mut body = def.body().clone();
+    let mut map: HashMap<Id, Id> = HashMap::new();
+    loop {
+        let mut changed = false;
+        let mut seen: HashMap<Key, Id> = HashMap::new();
+        let mut next: Vec<Instr> = Vec::new();
+        for mut instr in body.into_iter() {
+            changed |= rename_arg(&mut instr, &map);
+            let dst = instr.dst().get_term(0).ok().and_then(|t| t.id());
+            let dst = match dst {
+                Some(dst) if !output.contains(&dst) => dst,
+                _ => {
+                    if let (Some(key), Some(dst)) = (key(&instr), instr.dst().get_id(0).ok()) {
+                        seen.entry(key).or_insert(dst);
+                    }
+                    next.push(instr);
+                    continue;
+                }
+            };
+            if let Some(src) = copy(&instr) {
+                map.insert(dst, src);
+                changed = true;
+                continue;
+            }
+            match key(&instr) {
+                Some(key) => match seen.get(&key) {
+                    Some(src) => {
+                        map.insert(dst, src.clone());
+                        changed = true;
+                    }
+                    None => {
+                        seen.insert(key, dst);
+                        next.push(instr);
+                    }
+                },
+                None => next.push(instr),
+            }
+        }
+        body = next;
+        if !changed {
+            break;
+        }
+    }
+    let mut def = def.clone();
+    def.set_body(body);
+    Ok(def)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cse;
+
+impl Pass<Prog> for Cse {
+    fn name(&self) -> &str {
+        "cse"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        let mut res = prog.clone();
+        for (name, def) in prog.def() {
+            res.insert(name, cse_def(def)?);
+        }
+        Ok(res)
+    }
+}
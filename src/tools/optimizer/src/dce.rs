@@ -0,0 +1,57 @@
+use crate::cse::output_ids;
+use crate::errors::Error;
+use crate::pass::Pass;
+use ir::ast::*;
+use std::collections::{HashMap, HashSet};
+
+// Dead code elimination, instructions are kept when the outputs depend on
+// them, including registers and memories that feed an output
+pub fn dce_def(def: &Def) -> Result<Def, Error> {
+    let mut driver: HashMap<Id, &Instr> = HashMap::new();
+    for instr in def.body() {
+        let dst: Vec<ExprTerm> = instr.dst().clone().into();
+        for id in dst.iter().filter_map(|t| t.id()) {
+            driver.insert(id, instr);
+        }
+    }
+    let mut live: HashSet<Id> = HashSet::new();
+    let mut stack: Vec<Id> = output_ids(def).into_iter().collect();
+    while let Some(id) = stack.pop() {
+        if !live.insert(id.clone()) {
+            continue;
+        }
+        if let Some(instr) = driver.get(&id) {
+            let arg: Vec<ExprTerm> = instr.arg().clone().into();
+            stack.extend(arg.iter().filter_map(|t| t.id()));
+        }
+    }
+    let body = def
+        .body()
+        .iter()
+        .filter(|instr| {
+            let dst: Vec<ExprTerm> = instr.dst().clone().into();
+            let mut id = dst.iter().filter_map(|t| t.id()).peekable();
+            id.peek().is_none() || id.any(|id| live.contains(&id))
+        })
+        .cloned()
+        .collect();
+    let mut def = def.clone();
+    def.set_body(body);
+    Ok(def)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Dce;
+
+impl Pass<Prog> for Dce {
+    fn name(&self) -> &str {
+        "dce"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        let mut res = prog.clone();
+        for (name, def) in prog.def() {
+            res.insert(name, dce_def(def)?);
+        }
+        Ok(res)
+    }
+}
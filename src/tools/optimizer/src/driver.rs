@@ -1,21 +1,33 @@
-use crate::cascader::cascader;
+use crate::absorb::Absorb;
+use crate::cascader::Cascade;
+use crate::cse::Cse;
+use crate::dce::Dce;
 use crate::errors::Error;
-use crate::opt::{Opt, Optimization};
+use crate::fold::Fold;
+use crate::lutpack::LutPack;
+use crate::opt::{Lang, Opt};
+use crate::pass::{Program, Registry};
 use asm::parser::Parser as AsmParser;
 use io::write_to_file;
+use ir::parser::Parser as IrParser;
+use isel::library::Library;
 use std::path::PathBuf;
 use structopt::StructOpt;
+use xim::ast::Target;
+use xim::parser::Parser as XimParser;
+use xir::parser::Parser as XirParser;
 
 #[derive(Clone, Debug)]
 pub struct Driver {
     pub opts: Opt,
+    pub ir_pass: Registry<ir::ast::Prog>,
+    pub asm_pass: Registry<asm::ast::Prog>,
+    pub xir_pass: Registry<xir::ast::Prog>,
 }
 
 impl Default for Driver {
     fn default() -> Self {
-        Driver {
-            opts: Opt::from_args(),
-        }
+        Driver::new(Opt::from_args())
     }
 }
 
@@ -27,23 +39,118 @@ fn write_output(path: Option<&PathBuf>, contents: &str) {
     }
 }
 
+// implementation files, otherwise the built-in library
+pub fn target(xim: &[PathBuf]) -> Result<Target, Error> {
+    if xim.is_empty() {
+        return Ok(Library::builtin().imp());
+    }
+    let mut target = Target::default();
+    for path in xim {
+        target.extend(XimParser::parse_from_file(path)?);
+    }
+    Ok(target)
+}
+
 impl Driver {
     pub fn new(opts: Opt) -> Driver {
-        Driver { opts }
+        let mut ir_pass = Registry::default();
+        ir_pass.register("fold", || Ok(Box::new(Fold)));
+        ir_pass.register("cse", || Ok(Box::new(Cse)));
+        ir_pass.register("dce", || Ok(Box::new(Dce)));
+        ir_pass.register("absorb", || Ok(Box::new(Absorb)));
+        ir_pass.set_default(&["fold", "cse", "dce"]);
+        let mut asm_pass = Registry::default();
+        let (xim, height) = (opts.xim().clone(), opts.height());
+        asm_pass.register("cascade", move || {
+            Ok(Box::new(Cascade::new(target(&xim)?, height)))
+        });
+        asm_pass.set_default(&["cascade"]);
+        let mut xir_pass = Registry::default();
+        xir_pass.register("lutpack", || Ok(Box::new(LutPack)));
+        xir_pass.set_default(&["lutpack"]);
+        Driver {
+            opts,
+            ir_pass,
+            asm_pass,
+            xir_pass,
+        }
     }
     pub fn opts(&self) -> &Opt {
         &self.opts
     }
+    pub fn ir_pass(&self) -> &Registry<ir::ast::Prog> {
+        &self.ir_pass
+    }
+    pub fn asm_pass(&self) -> &Registry<asm::ast::Prog> {
+        &self.asm_pass
+    }
+    pub fn xir_pass(&self) -> &Registry<xir::ast::Prog> {
+        &self.xir_pass
+    }
+    pub fn ir_pass_mut(&mut self) -> &mut Registry<ir::ast::Prog> {
+        &mut self.ir_pass
+    }
+    pub fn asm_pass_mut(&mut self) -> &mut Registry<asm::ast::Prog> {
+        &mut self.asm_pass
+    }
+    pub fn xir_pass_mut(&mut self) -> &mut Registry<xir::ast::Prog> {
+        &mut self.xir_pass
+    }
+    // passes to run, --opt is a deprecated alias of --passes
+    pub fn passes(&self) -> Vec<String> {
+        match self.opts().opt() {
+            Some(opt) => {
+                eprintln!("--opt is deprecated, use --passes {} instead", opt);
+                vec![opt.to_string()]
+            }
+            None => self.opts().passes().clone(),
+        }
+    }
+    // passes of another language are rejected, programs are not lowered
+    // from one language to another
+    fn check_lang(&self, pass: &[String]) -> Result<(), Error> {
+        let lang = self.opts().lang().to_string();
+        for p in pass.iter() {
+            let other = [
+                (Lang::Ir, self.ir_pass().contains(p)),
+                (Lang::Asm, self.asm_pass().contains(p)),
+                (Lang::Xir, self.xir_pass().contains(p)),
+            ];
+            if other.iter().any(|(l, c)| *c && l.to_string() == lang) {
+                continue;
+            }
+            if let Some((l, _)) = other.iter().find(|(_, c)| *c) {
+                let msg = format!(
+                    "Pass {} runs on {} programs, but the input is {} and --passes only runs passes of the --lang language",
+                    p, l, lang
+                );
+                return Err(Error::new_opt_error(&msg));
+            }
+        }
+        Ok(())
+    }
+    fn optimize<P: Program>(&self, registry: &Registry<P>, prog: &P) -> Result<(), Error> {
+        let pass = self.passes();
+        self.check_lang(&pass)?;
+        let mut manager = registry.pipeline(&pass)?;
+        if let Some(dir) = self.opts().dump() {
+            manager.set_dump(dir);
+        }
+        let (prog, report) = manager.run(prog)?;
+        if self.opts().stats() {
+            for r in report.iter() {
+                eprintln!("{}", r);
+            }
+        }
+        write_output(self.opts().output(), &prog.to_string());
+        Ok(())
+    }
     pub fn run(&self) -> Result<(), Error> {
         let input = self.opts().input();
-        let output = self.opts().output();
-        match self.opts().optimization() {
-            Optimization::Cascade => {
-                let prog = AsmParser::parse_from_file(input)?;
-                let opt = cascader(&prog)?;
-                write_output(output, &opt.to_string());
-                Ok(())
-            }
+        match self.opts().lang() {
+            Lang::Ir => self.optimize(self.ir_pass(), &IrParser::parse_from_file(input)?),
+            Lang::Asm => self.optimize(self.asm_pass(), &AsmParser::parse_from_file(input)?),
+            Lang::Xir => self.optimize(self.xir_pass(), &XirParser::parse_from_file(input)?),
         }
     }
 }
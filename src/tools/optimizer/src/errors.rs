@@ -1,11 +1,15 @@
 use asm::errors::Error as AsmError;
 use ir::errors::Error as IrError;
 use std::fmt;
+use xim::errors::Error as XimError;
+use xir::errors::Error as XirError;
 
 #[derive(Debug)]
 pub enum Error {
     Ir(IrError),
     Asm(AsmError),
+    Xim(XimError),
+    Xir(XirError),
     Driver(String),
     Opt(String),
 }
@@ -25,6 +29,18 @@ impl From<AsmError> for Error {
     }
 }
 
+impl From<XimError> for Error {
+    fn from(e: XimError) -> Self {
+        Error::Xim(e)
+    }
+}
+
+impl From<XirError> for Error {
+    fn from(e: XirError) -> Self {
+        Error::Xir(e)
+    }
+}
+
 impl From<IrError> for Error {
     fn from(e: IrError) -> Self {
         Error::Ir(e)
@@ -36,6 +52,8 @@ impl fmt::Display for Error {
         match self {
             Error::Ir(msg) => write!(f, "{}", msg),
             Error::Asm(msg) => write!(f, "{}", msg),
+            Error::Xim(msg) => write!(f, "{}", msg),
+            Error::Xir(msg) => write!(f, "{}", msg),
             Error::Driver(msg) => write!(f, "{}", msg),
             Error::Opt(msg) => write!(f, "{}", msg),
         }
@@ -0,0 +1,121 @@
+use crate::cse::is_pure;
+use crate::errors::Error;
+use crate::pass::Pass;
+use ir::ast::*;
+use sim::bits::sign_extend;
+use sim::interp::Interp;
+use sim::trace::term_width;
+use std::convert::TryFrom;
+
+// Constant with the value of the bits, if it fits in the attribute
+fn con(instr: &Instr, bits: &[u128]) -> Option<Instr> {
+    let dst = instr.dst().get_term(0).ok()?;
+    let first = *bits.first()?;
+    if bits.iter().any(|b| *b != first) {
+        return None;
+    }
+    let width = term_width(dst).ok()?;
+    let val = if dst.get_ty().ok()?.is_signed() {
+        sign_extend(first, width)
+    } else {
+        i128::try_from(first).ok()?
+    };
+    let val = i64::try_from(val).ok()?;
+    let wire = InstrWire {
+        op: OpWire::Con,
+        dst: instr.dst().clone(),
+        attr: Expr::from(ExprTup::from(vec![ExprTerm::Val(val)])),
+        arg: Expr::default(),
+        span: instr.span().cloned().unwrap_or_default(),
+    };
+    Some(Instr::from(wire))
+}
+
+// A mux with a constant selector is an id of the selected argument
+fn select(instr: &Instr, interp: &Interp) -> Option<Instr> {
+    match instr {
+        Instr::Prim(prim) if *prim.op() == OpPrim::Mux => {
+            let sel = interp.env().get(&prim.arg().get_id(0).ok()?)?;
+            let index = if sel.first()? & 1 == 1 { 1 } else { 2 };
+            let wire = InstrWire {
+                op: OpWire::Id,
+                dst: prim.dst().clone(),
+                attr: Expr::default(),
+                arg: Expr::from(prim.arg().get_term(index).ok()?.clone()),
+                span: prim.span().cloned().unwrap_or_default(),
+            };
+            Some(Instr::from(wire))
+        }
+        _ => None,
+    }
+}
+
+fn is_known(instr: &Instr, interp: &Interp) -> bool {
+    let arg: Vec<ExprTerm> = instr.arg().clone().into();
+    arg.iter().all(|t| match t.id() {
+        Some(id) => interp.env().contains_key(&id),
+        None => true,
+    })
+}
+
+fn is_con(instr: &Instr) -> bool {
+    match instr {
+        Instr::Wire(wire) => *wire.op() == OpWire::Con,
+        _ => false,
+    }
+}
+
+// Constant folding and propagation, pure instructions with constant
+// arguments are evaluated with the interpreter and become constants
+pub fn fold_def(def: &Def) -> Result<Def, Error> {
+    let mut body = def.body().clone();
+    let mut interp = Interp::default();
+    loop {
+        let mut changed = false;
+        for instr in body.iter_mut() {
+            let id = match instr.dst().get_id(0) {
+                Ok(id) if is_pure(instr) && !interp.env().contains_key(&id) => id,
+                _ => continue,
+            };
+            if !is_known(instr, &interp) {
+                if let Some(wire) = select(instr, &interp) {
+                    *instr = wire;
+                    changed = true;
+                }
+                continue;
+            }
+            // values the interpreter cannot compute stay as they are
+            if let Ok(bits) = interp.eval_instr(instr) {
+                if !is_con(instr) {
+                    if let Some(wire) = con(instr, &bits) {
+                        *instr = wire;
+                    }
+                }
+                interp.env.insert(id, bits);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut def = def.clone();
+    def.set_body(body);
+    Ok(def)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Fold;
+
+impl Pass<Prog> for Fold {
+    fn name(&self) -> &str {
+        "fold"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        let mut res = prog.clone();
+        for (name, def) in prog.def() {
+            res.insert(name, fold_def(def)?);
+        }
+        Ok(res)
+    }
+}
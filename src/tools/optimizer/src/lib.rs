@@ -1,4 +1,10 @@
+pub mod absorb;
 pub mod cascader;
+pub mod cse;
+pub mod dce;
 pub mod driver;
 pub mod errors;
+pub mod fold;
+pub mod lutpack;
 pub mod opt;
+pub mod pass;
@@ -0,0 +1,188 @@
+use crate::errors::Error;
+use crate::pass::Pass;
+use std::collections::HashSet;
+use xir::ast::*;
+
+// Inputs of a lut6_2, I5 is tied high to use both outputs
+pub const SHARED_INPUTS: usize = 5;
+
+// Placed lut with at most five inputs and a lut bel, which can share a site
+#[derive(Clone, Debug)]
+struct Small {
+    index: usize,
+    bel: BelLut,
+    x: ExprCoord,
+    y: ExprCoord,
+    arg: Vec<ExprTerm>,
+}
+
+fn ids(expr: &Expr) -> Vec<Id> {
+    let term: Vec<ExprTerm> = expr.clone().into();
+    term.iter().filter_map(|t| t.id()).collect()
+}
+
+// ids feeding a carry chain, directly or through basic instructions such as
+// cat, the luts driving them stay on the bels the carry chain reads
+fn carry_input(prog: &Prog) -> HashSet<Id> {
+    let mut res: HashSet<Id> = HashSet::new();
+    for instr in prog.body().iter().rev() {
+        let feeds = match instr {
+            Instr::Mach(mach) => matches!(mach.op(), OpMach::CarryAdd | OpMach::CarrySub),
+            Instr::Basc(basc) => ids(basc.dst()).iter().any(|id| res.contains(id)),
+        };
+        if feeds {
+            res.extend(ids(instr.arg()));
+        }
+    }
+    res
+}
+
+// a lut at ?? is not known to share a site with any other lut
+fn small(index: usize, instr: &Instr, carry: &HashSet<Id>) -> Option<Small> {
+    let instr = instr.mach()?;
+    match instr.op().lut_inputs() {
+        Some(n) if n <= SHARED_INPUTS => (),
+        _ => return None,
+    }
+    let loc = instr.loc()?;
+    let bel = match loc.bel() {
+        Bel::Lut(bel) => bel.clone(),
+        _ => return None,
+    };
+    if loc.x().is_any() || loc.y().is_any() || ids(instr.dst()).iter().any(|id| carry.contains(id))
+    {
+        return None;
+    }
+    let arg: Vec<ExprTerm> = instr.arg().clone().into();
+    if arg.iter().any(|t| t.id().is_none()) {
+        return None;
+    }
+    Some(Small {
+        index,
+        bel,
+        x: loc.x().clone(),
+        y: loc.y().clone(),
+        arg,
+    })
+}
+
+fn union(a: &[ExprTerm], b: &[ExprTerm]) -> Vec<ExprTerm> {
+    let mut res = a.to_vec();
+    for t in b {
+        if !res.contains(t) {
+            res.push(t.clone());
+        }
+    }
+    res
+}
+
+// Truth table of a lut over new inputs, which include all of its inputs
+pub fn remap_init(init: u64, arg: &[ExprTerm], input: &[ExprTerm]) -> u64 {
+    let pos: Vec<usize> = arg
+        .iter()
+        .map(|a| input.iter().position(|i| i == a).unwrap_or(0))
+        .collect();
+    let mut res = 0;
+    for index in 0..(1u64 << input.len()) {
+        let mut orig = 0;
+        for (i, p) in pos.iter().enumerate() {
+            orig |= ((index >> p) & 1) << i;
+        }
+        res |= ((init >> orig) & 1) << index;
+    }
+    res
+}
+
+fn rewrite(instr: &mut Instr, input: &[ExprTerm], bel: BelLut) -> Result<(), Error> {
+    if let Instr::Mach(mach) = instr {
+        let arg: Vec<ExprTerm> = mach.arg().clone().into();
+        let init = mach.attr().get_val(0)? as u64;
+        let init = remap_init(init, &arg, input);
+        mach.op = OpMach::lut(input.len()).unwrap_or(OpMach::Lut5);
+        mach.attr = Expr::from(ExprTup::from(vec![ExprTerm::Val(init as i64)]));
+        mach.arg = Expr::from(ExprTup::from(input.to_vec()));
+        if let Some(loc) = mach.loc.as_mut() {
+            loc.bel = Bel::Lut(bel);
+        }
+    }
+    Ok(())
+}
+
+// Pair luts with at most five inputs between them on the 5 and 6 bels of
+// the same lut, both reading the same inputs in the same order. Luts in
+// different or unknown sites are not paired, and neither are luts feeding
+// a carry chain.
+pub fn lutpack(prog: &Prog) -> Result<Prog, Error> {
+    let carry = carry_input(prog);
+    let lut: Vec<Small> = prog
+        .body()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instr)| small(i, instr, &carry))
+        .collect();
+    // lut bels taken in every site
+    let mut used: HashSet<(BelLut, ExprCoord, ExprCoord)> = HashSet::new();
+    for instr in prog.body().iter().filter_map(|i| i.mach()) {
+        if let Some(loc) = instr.loc() {
+            let bel = match loc.bel() {
+                Bel::Lut(bel) => bel.clone(),
+                Bel::Lum(BelLum::H6) => BelLut::H6,
+                _ => continue,
+            };
+            used.insert((bel, loc.x().clone(), loc.y().clone()));
+        }
+    }
+    let mut paired: HashSet<usize> = HashSet::new();
+    let mut res = prog.clone();
+    for (i, a) in lut.iter().enumerate() {
+        if paired.contains(&i) {
+            continue;
+        }
+        let five = (a.bel.lut5(), a.x.clone(), a.y.clone());
+        let six = (a.bel.lut6(), a.x.clone(), a.y.clone());
+        let other = if a.bel.is_lut5() { &six } else { &five };
+        // partner sharing the most inputs
+        let mut best: Option<(usize, Vec<ExprTerm>)> = None;
+        for (j, b) in lut.iter().enumerate().skip(i + 1) {
+            if paired.contains(&j) || a.x != b.x || a.y != b.y {
+                continue;
+            }
+            // the other output of the lut is taken by something else
+            let old = (b.bel.clone(), b.x.clone(), b.y.clone());
+            if used.contains(other) && *other != old {
+                continue;
+            }
+            let input = union(&a.arg, &b.arg);
+            let better = match &best {
+                Some((_, cur)) => input.len() < cur.len(),
+                None => true,
+            };
+            if input.len() <= SHARED_INPUTS && better {
+                best = Some((j, input));
+            }
+        }
+        if let Some((j, input)) = best {
+            let b = &lut[j];
+            paired.insert(i);
+            paired.insert(j);
+            used.remove(&(b.bel.clone(), b.x.clone(), b.y.clone()));
+            used.insert(five.clone());
+            used.insert(six.clone());
+            rewrite(&mut res.body_mut()[a.index], &input, six.0)?;
+            rewrite(&mut res.body_mut()[b.index], &input, five.0)?;
+        }
+    }
+    Ok(res)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LutPack;
+
+impl Pass<Prog> for LutPack {
+    fn name(&self) -> &str {
+        "lutpack"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        lutpack(prog)
+    }
+}
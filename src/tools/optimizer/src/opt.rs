@@ -23,9 +23,29 @@ pub struct Opt {
     #[structopt(long = "lang", default_value = "asm")]
     pub lang: Lang,
 
-    // Optimization
-    #[structopt(long = "opt", default_value = "cascade")]
-    pub optimization: Optimization,
+    // Passes of the language run in order, e.g. fold,cse,dce, the default pipeline otherwise
+    #[structopt(long = "passes", use_delimiter = true, require_delimiter = true)]
+    pub passes: Vec<String>,
+
+    // Deprecated alias of --passes with a single pass
+    #[structopt(long = "opt", conflicts_with = "passes")]
+    pub opt: Option<String>,
+
+    // Directory where the result of every pass is written
+    #[structopt(long = "dump", parse(from_os_str))]
+    pub dump: Option<PathBuf>,
+
+    // Print the number of instructions before and after every pass
+    #[structopt(long = "stats")]
+    pub stats: bool,
+
+    // Implementation files with the cascade variants, the built-in library otherwise
+    #[structopt(long = "xim", parse(from_os_str), number_of_values = 1)]
+    pub xim: Vec<PathBuf>,
+
+    // Largest number of dsps in a cascade, the height of a dsp column
+    #[structopt(long = "height", default_value = "72")]
+    pub height: usize,
 }
 
 impl Opt {
@@ -38,25 +58,39 @@ impl Opt {
     pub fn lang(&self) -> &Lang {
         &self.lang
     }
-    pub fn optimization(&self) -> &Optimization {
-        &self.optimization
+    pub fn passes(&self) -> &Vec<String> {
+        &self.passes
+    }
+    pub fn opt(&self) -> Option<&String> {
+        self.opt.as_ref()
+    }
+    pub fn dump(&self) -> Option<&PathBuf> {
+        self.dump.as_ref()
+    }
+    pub fn stats(&self) -> bool {
+        self.stats
+    }
+    pub fn xim(&self) -> &Vec<PathBuf> {
+        &self.xim
+    }
+    pub fn height(&self) -> usize {
+        self.height
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum Lang {
+    Ir,
     Asm,
-}
-
-#[derive(Clone, Debug)]
-pub enum Optimization {
-    Cascade,
+    Xir,
 }
 
 impl fmt::Display for Lang {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let lang = match self {
+            Lang::Ir => "ir",
             Lang::Asm => "asm",
+            Lang::Xir => "xir",
         };
         write!(f, "{}", lang)
     }
@@ -66,26 +100,9 @@ impl FromStr for Lang {
     type Err = Error;
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         match input {
+            "ir" => Ok(Lang::Ir),
             "asm" => Ok(Lang::Asm),
-            _ => Err(Error::new_opt_error("Unsupported language")),
-        }
-    }
-}
-
-impl fmt::Display for Optimization {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let opt = match self {
-            Optimization::Cascade => "cascade",
-        };
-        write!(f, "{}", opt)
-    }
-}
-
-impl FromStr for Optimization {
-    type Err = Error;
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        match input {
-            "cascade" => Ok(Optimization::Cascade),
+            "xir" => Ok(Lang::Xir),
             _ => Err(Error::new_opt_error("Unsupported language")),
         }
     }
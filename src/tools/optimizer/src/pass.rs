@@ -0,0 +1,254 @@
+use crate::errors::Error;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Number of instructions of every kind in a program
+pub type Stats = BTreeMap<String, usize>;
+
+// Program a pass runs on
+pub trait Program: Clone + fmt::Display {
+    // language, also the extension of dumped programs
+    fn lang() -> &'static str;
+    fn stats(&self) -> Stats;
+}
+
+pub trait Pass<P: Program> {
+    fn name(&self) -> &str;
+    fn run(&self, prog: &P) -> Result<P, Error>;
+}
+
+fn count(stats: &mut Stats, key: &str) {
+    *stats.entry(key.to_string()).or_insert(0) += 1;
+}
+
+impl Program for ir::ast::Prog {
+    fn lang() -> &'static str {
+        "ir"
+    }
+    fn stats(&self) -> Stats {
+        let mut stats = Stats::new();
+        for def in self.def().values() {
+            count(&mut stats, "def");
+            for instr in def.body() {
+                count(&mut stats, "instr");
+                match instr {
+                    ir::ast::Instr::Wire(_) => count(&mut stats, "wire"),
+                    ir::ast::Instr::Prim(instr) => count(&mut stats, &instr.prim().to_string()),
+                    ir::ast::Instr::Call(_) => count(&mut stats, "call"),
+                }
+            }
+        }
+        stats
+    }
+}
+
+impl Program for asm::ast::Prog {
+    fn lang() -> &'static str {
+        "asm"
+    }
+    fn stats(&self) -> Stats {
+        let mut stats = Stats::new();
+        for instr in self.body() {
+            count(&mut stats, "instr");
+            match instr {
+                asm::ast::Instr::Wire(_) => count(&mut stats, "wire"),
+                asm::ast::Instr::Asm(instr) => count(&mut stats, &instr.loc().prim().to_string()),
+            }
+        }
+        stats
+    }
+}
+
+impl Program for xir::ast::Prog {
+    fn lang() -> &'static str {
+        "xir"
+    }
+    fn stats(&self) -> Stats {
+        let mut stats = Stats::new();
+        for instr in self.body() {
+            count(&mut stats, "instr");
+            match instr {
+                xir::ast::Instr::Basc(_) => count(&mut stats, "basc"),
+                xir::ast::Instr::Mach(instr) if instr.op().is_dsp() => count(&mut stats, "dsp"),
+                xir::ast::Instr::Mach(_) => count(&mut stats, "mach"),
+            }
+        }
+        stats
+    }
+}
+
+// Statistics of a program before and after a pass
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub pass: String,
+    pub before: Stats,
+    pub after: Stats,
+}
+
+impl Report {
+    pub fn pass(&self) -> &str {
+        &self.pass
+    }
+    pub fn before(&self) -> &Stats {
+        &self.before
+    }
+    pub fn after(&self) -> &Stats {
+        &self.after
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key: BTreeSet<&String> = self.before.keys().chain(self.after.keys()).collect();
+        let diff: Vec<String> = key
+            .iter()
+            .map(|k| {
+                let before = self.before.get(*k).cloned().unwrap_or(0);
+                let after = self.after.get(*k).cloned().unwrap_or(0);
+                format!("{} {} -> {}", k, before, after)
+            })
+            .collect();
+        write!(f, "{}: {}", self.pass, diff.join(", "))
+    }
+}
+
+// Passes run in order, each one on the result of the previous one
+pub struct PassManager<P: Program> {
+    pass: Vec<Box<dyn Pass<P>>>,
+    dump: Option<PathBuf>,
+}
+
+impl<P: Program> Default for PassManager<P> {
+    fn default() -> Self {
+        PassManager {
+            pass: Vec::new(),
+            dump: None,
+        }
+    }
+}
+
+impl<P: Program> PassManager<P> {
+    pub fn pass(&self) -> Vec<&str> {
+        self.pass.iter().map(|p| p.name()).collect()
+    }
+    pub fn dump(&self) -> Option<&Path> {
+        self.dump.as_ref().map(|d| d.as_path())
+    }
+    pub fn add_pass(&mut self, pass: Box<dyn Pass<P>>) {
+        self.pass.push(pass);
+    }
+    // write the result of every pass to the directory, as 1_name.lang
+    pub fn set_dump<Q: AsRef<Path>>(&mut self, dir: Q) {
+        self.dump = Some(dir.as_ref().to_path_buf());
+    }
+    pub fn run(&self, prog: &P) -> Result<(P, Vec<Report>), Error> {
+        if let Some(dir) = self.dump() {
+            if fs::create_dir_all(dir).is_err() {
+                let msg = format!("cannot create dump directory {}", dir.display());
+                return Err(Error::new_opt_error(&msg));
+            }
+        }
+        let mut prog = prog.clone();
+        let mut report: Vec<Report> = Vec::new();
+        for (i, pass) in self.pass.iter().enumerate() {
+            let before = prog.stats();
+            prog = pass.run(&prog)?;
+            if let Some(dir) = self.dump() {
+                let name = format!("{}_{}.{}", i + 1, pass.name(), P::lang());
+                if fs::write(dir.join(name), prog.to_string()).is_err() {
+                    let msg = format!("cannot dump the result of {}", pass.name());
+                    return Err(Error::new_opt_error(&msg));
+                }
+            }
+            report.push(Report {
+                pass: pass.name().to_string(),
+                before,
+                after: prog.stats(),
+            });
+        }
+        Ok((prog, report))
+    }
+}
+
+pub type Factory<P> = Rc<dyn Fn() -> Result<Box<dyn Pass<P>>, Error>>;
+
+// Passes of a language by name, passes defined out of this crate are
+// registered the same way as ours
+pub struct Registry<P: Program> {
+    factory: HashMap<String, Factory<P>>,
+    default: Vec<String>,
+}
+
+impl<P: Program> Default for Registry<P> {
+    fn default() -> Self {
+        Registry {
+            factory: HashMap::new(),
+            default: Vec::new(),
+        }
+    }
+}
+
+impl<P: Program> Clone for Registry<P> {
+    fn clone(&self) -> Self {
+        Registry {
+            factory: self.factory.clone(),
+            default: self.default.clone(),
+        }
+    }
+}
+
+impl<P: Program> fmt::Debug for Registry<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Registry({})", self.names().join(", "))
+    }
+}
+
+impl<P: Program> Registry<P> {
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factory.keys().map(|n| n.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+    pub fn default_passes(&self) -> &Vec<String> {
+        &self.default
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.factory.contains_key(name)
+    }
+    // a pass registered again replaces the previous one
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Result<Box<dyn Pass<P>>, Error> + 'static,
+    {
+        self.factory.insert(name.to_string(), Rc::new(factory));
+    }
+    // pipeline used when no pass is given
+    pub fn set_default(&mut self, pass: &[&str]) {
+        self.default = pass.iter().map(|p| p.to_string()).collect();
+    }
+    pub fn create(&self, name: &str) -> Result<Box<dyn Pass<P>>, Error> {
+        match self.factory.get(name) {
+            Some(factory) => factory(),
+            None => {
+                let msg = format!(
+                    "Unknown {} pass {}, the passes are: {}",
+                    P::lang(),
+                    name,
+                    self.names().join(", ")
+                );
+                Err(Error::new_opt_error(&msg))
+            }
+        }
+    }
+    pub fn pipeline(&self, pass: &[String]) -> Result<PassManager<P>, Error> {
+        let pass = if pass.is_empty() { &self.default } else { pass };
+        let mut manager = PassManager::default();
+        for name in pass.iter() {
+            manager.add_pass(self.create(name)?);
+        }
+        Ok(manager)
+    }
+}
@@ -0,0 +1,131 @@
+use bler::try_from_asm_prog;
+use ir::ast::Prog;
+use ir::parser::Parser as IrParser;
+use isel::try_from_ir_prog;
+use optimizer::absorb::absorb_def;
+use sim::interp::run_ir_prog;
+use sim::trace::Stimulus;
+use sim::xsim::run_xir_prog;
+use std::str::FromStr;
+
+fn absorb(input: &str) -> Prog {
+    let prog = IrParser::parse_from_str(input).unwrap();
+    let mut res = prog.clone();
+    res.insert("main", absorb_def(prog.get("main").unwrap()).unwrap());
+    res
+}
+
+// implementations selected for the program
+fn select(prog: &Prog) -> Vec<String> {
+    let asm = try_from_ir_prog(prog).unwrap();
+    asm.body()
+        .iter()
+        .filter_map(|instr| {
+            let instr = instr.to_string();
+            let op = instr.split(" = ").nth(1)?;
+            Some(op.split('(').next()?.to_string())
+        })
+        .collect()
+}
+
+// the dsp computes the same outputs, cycle by cycle, as the registers did
+fn check_cycles(prog: &Prog, res: &Prog) {
+    let stim = Stimulus::from_str(
+        "a b c ena enb enm enp\n3 4 1 1 1 1 1\n-5 2 7 1 0 1 1\n6 -7 -2 0 1 1 0\n1 9 3 1 1 0 1\n-8 -3 5 1 1 1 1\n2 2 2 0 0 1 1\n7 -1 0 1 1 1 1\n0 0 0 1 1 1 1",
+    )
+    .unwrap();
+    let asm = try_from_ir_prog(res).unwrap();
+    let (xir, _) = try_from_asm_prog(&asm, None).unwrap();
+    let exp = run_ir_prog(prog, &stim, None).unwrap();
+    assert_eq!(run_ir_prog(res, &stim, None).unwrap(), exp);
+    assert_eq!(run_xir_prog(&xir, &stim, None).unwrap(), exp);
+}
+
+#[test]
+fn all_stages() {
+    let input = "def main(a:i8, b:i8, c:i8, ena:bool, enb:bool, enm:bool, enp:bool) -> (y:i8) {
+            t0:i8 = reg[0](a, ena);
+            t1:i8 = reg[0](b, enb);
+            t2:i8 = mul(t0, t1) @dsp;
+            t3:i8 = reg[0](t2, enm);
+            t4:i8 = add(c, t3);
+            y:i8 = reg[0](t4, enp);
+        }";
+    let prog = IrParser::parse_from_str(input).unwrap();
+    let res = absorb(input);
+    let body = res.get("main").unwrap().body();
+    assert!(body.iter().all(|i| i.to_string().ends_with("@dsp")));
+    assert_eq!(body[4].to_string(), "t4:i8 = add(t3, c) @dsp");
+    assert_eq!(select(&res), vec!["dmuladdrega_i8i8"]);
+    check_cycles(&prog, &res);
+}
+
+#[test]
+fn two_input_stages() {
+    let input = "def main(a:i8, b:i8, c:i8, ena:bool, enb:bool, enm:bool, enp:bool) -> (y:i8) {
+            t0:i8 = reg[0](a, ena);
+            t1:i8 = reg[0](t0, ena);
+            t2:i8 = reg[0](b, enb);
+            t3:i8 = reg[0](t2, enb);
+            t4:i8 = mul(t1, t3) @dsp;
+            t5:i8 = add(t4, c);
+            y:i8 = reg[0](t5, enp);
+        }";
+    let prog = IrParser::parse_from_str(input).unwrap();
+    let res = absorb(input);
+    assert_eq!(select(&res), vec!["dmuladdregaabbp_i8i8"]);
+    let asm = try_from_ir_prog(&res).unwrap();
+    let (xir, _) = try_from_asm_prog(&asm, None).unwrap();
+    assert!(xir.to_string().contains("muladdrega[2, 2, 0, 1]"));
+    check_cycles(&prog, &res);
+}
+
+#[test]
+fn product_stage() {
+    let input = "def main(a:i8, b:i8, c:i8, ena:bool, enb:bool, enm:bool, enp:bool) -> (y:i8) {
+            t0:i8 = mul(a, b) @dsp;
+            t1:i8 = reg[0](t0, enm);
+            t2:i8 = add(t1, c);
+            y:i8 = reg[0](t2, enp);
+        }";
+    let prog = IrParser::parse_from_str(input).unwrap();
+    let res = absorb(input);
+    assert_eq!(select(&res), vec!["dmuladdregmp_i8i8"]);
+    check_cycles(&prog, &res);
+}
+
+#[test]
+fn kept() {
+    // the a registers have different enables and the b register starts at
+    // one, so only the m and p stages are absorbed in the first dsp, and the
+    // product of the second one has another reader
+    let input =
+        "def main(a:i8, b:i8, c:i8, ena:bool, enb:bool, enm:bool, enp:bool) -> (y:i8, z:i8) {
+            t0:i8 = reg[0](a, enb);
+            t1:i8 = reg[0](t0, ena);
+            t2:i8 = reg[1](b, enb);
+            t3:i8 = mul(t1, t2) @dsp;
+            t4:i8 = reg[0](t3, enm);
+            t5:i8 = add(t4, c);
+            y:i8 = reg[0](t5, enp);
+            t6:i8 = mul(a, b) @dsp;
+            t7:i8 = add(t6, c);
+            t8:i8 = add(t6, t7);
+            z:i8 = reg[0](t8, enp);
+        }";
+    let res = absorb(input);
+    let body: Vec<String> = res
+        .get("main")
+        .unwrap()
+        .body()
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(body[0], "t0:i8 = reg[0](a, enb) @??");
+    assert_eq!(body[1], "t1:i8 = reg[0](t0, ena) @??");
+    assert_eq!(body[2], "t2:i8 = reg[1](b, enb) @??");
+    assert_eq!(body[4], "t4:i8 = reg[0](t3, enm) @dsp");
+    assert_eq!(body[6], "y:i8 = reg[0](t5, enp) @dsp");
+    assert_eq!(body[8], "t7:i8 = add(t6, c) @??");
+    assert_eq!(body[10], "z:i8 = reg[0](t8, enp) @??");
+}
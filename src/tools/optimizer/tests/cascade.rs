@@ -1,10 +1,103 @@
+use asm::ast::Prog;
 use asm::parser::Parser as AsmParser;
+use bler::try_from_asm_prog_with_target;
+use difftest::stimulus_from_sig;
 use optimizer::cascader::cascader;
+use sim::trace::Trace;
+use sim::xsim::run_xir_prog;
+use xim::ast::Target;
+use xim::parser::Parser as XimParser;
+
+fn target() -> Target {
+    XimParser::parse_from_file("../../../examples/xim/dsp.xim").unwrap()
+}
+
+// operation and location of every instruction
+fn ops(prog: &Prog) -> Vec<String> {
+    prog.body()
+        .iter()
+        .map(|instr| {
+            let instr = instr.to_string();
+            let op = instr.split(" = ").nth(1).unwrap_or_default();
+            let name = op.split('(').next().unwrap_or_default();
+            let loc = op.split(" @").nth(1).unwrap_or_default();
+            format!("{} @{}", name, loc.trim_end_matches(';'))
+        })
+        .collect()
+}
+
+// outputs for the random stimulus of difftest, so it is the same on every run
+fn run(prog: &Prog) -> Trace {
+    let (xir, _) = try_from_asm_prog_with_target(prog, None, &target()).unwrap();
+    let stim = stimulus_from_sig(xir.sig(), 16);
+    run_xir_prog(&xir, &stim, None).unwrap()
+}
 
 #[test]
 fn tdot() {
     let prog = AsmParser::parse_from_file("../../../examples/asm/tdot_5_3.asm").unwrap();
     let exp = AsmParser::parse_from_file("../../../examples/asm/tdot_5_3_opt.asm").unwrap();
-    let res = cascader(&prog).unwrap();
+    let res = cascader(&prog, &target(), 72).unwrap();
     assert_eq!(res, exp);
+    assert_eq!(run(&res), run(&prog));
+}
+
+// the cascade input of vaddregaci skips the b register, so a cascade would
+// compute the sums a cycle earlier and it is left alone
+#[test]
+fn vadd() {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8<4>, b:i8<4>, c:i8<4>, d:i8<4>, en:bool) -> (y:i8<4>) {
+            t0:i8<4> = daddrega_i8v4(a, b, en, en, en) @dsp(??, ??);
+            t1:i8<4> = daddrega_i8v4(c, t0, en, en, en) @dsp(??, ??);
+            y:i8<4> = daddrega_i8v4(d, t1, en, en, en) @dsp(??, ??);
+        }",
+    )
+    .unwrap();
+    let res = cascader(&prog, &target(), 72).unwrap();
+    assert_eq!(res, prog);
+    let cascaded = AsmParser::parse_from_str(
+        "def main(a:i8<4>, b:i8<4>, c:i8<4>, d:i8<4>, en:bool) -> (y:i8<4>) {
+            t0:i8<4> = daddregaco_i8v4(a, b, en, en, en) @dsp(x0, y0);
+            t1:i8<4> = daddregacio_i8v4(c, t0, en, en, en) @dsp(x0, y0+1);
+            y:i8<4> = daddregaci_i8v4(d, t1, en, en, en) @dsp(x0, y0+2);
+        }",
+    )
+    .unwrap();
+    assert_ne!(run(&cascaded), run(&prog));
+}
+
+#[test]
+fn split() {
+    let prog = AsmParser::parse_from_file("../../../examples/asm/tdot_5_3.asm").unwrap();
+    let res = cascader(&prog, &target(), 2).unwrap();
+    let ops = ops(&res);
+    assert_eq!(ops[2], "dmuladdregaco_i8i8 @dsp(x0, y0)");
+    assert_eq!(ops[1], "dmuladdregaci_i8i8 @dsp(x0, y0+1)");
+    assert_eq!(ops[0], "dmuladdrega_i8i8 @dsp(??, ??)");
+    assert!(cascader(&prog, &target(), 0).is_err());
+}
+
+#[test]
+fn branch() {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8, z:i8) {
+            t0:i8 = dmuladdrega_i8i8(a, b, c, en, en, en, en) @dsp(??, ??);
+            y:i8 = dmuladdrega_i8i8(a, b, t0, en, en, en, en) @dsp(??, ??);
+            t1:i8 = dmuladdrega_i8i8(a, c, t0, en, en, en, en) @dsp(??, ??);
+            z:i8 = dmuladdrega_i8i8(b, c, t1, en, en, en, en) @dsp(??, ??);
+        }",
+    )
+    .unwrap();
+    let res = cascader(&prog, &target(), 72).unwrap();
+    assert_eq!(
+        ops(&res),
+        vec![
+            "dmuladdregaco_i8i8 @dsp(x0, y0)",
+            "dmuladdrega_i8i8 @dsp(??, ??)",
+            "dmuladdregacio_i8i8 @dsp(x0, y0+1)",
+            "dmuladdregaci_i8i8 @dsp(x0, y0+2)",
+        ]
+    );
+    assert_eq!(run(&res), run(&prog));
 }
@@ -0,0 +1,142 @@
+use ir::ast::Def;
+use ir::check::check_prog;
+use ir::parser::Parser as IrParser;
+use optimizer::cse::cse_def;
+use optimizer::dce::dce_def;
+use optimizer::driver::Driver;
+use optimizer::errors::Error;
+use optimizer::fold::fold_def;
+use optimizer::opt::Opt;
+use sim::interp::run_ir_prog;
+use sim::trace::Stimulus;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+fn main_def(input: &str) -> Def {
+    IrParser::parse_from_str(input)
+        .unwrap()
+        .get("main")
+        .unwrap()
+        .clone()
+}
+
+fn body(def: &Def) -> Vec<String> {
+    def.body().iter().map(|i| i.to_string()).collect()
+}
+
+#[test]
+fn fold() {
+    let def = main_def(
+        "def main(a:i8, c:bool) -> (y:i8, z:i8) {
+            t0:i8 = const[3];
+            t1:i8 = const[-5];
+            t2:i8 = add(t0, t1);
+            t3:i16 = cat(t2, t0);
+            t4:i8 = ext[4,11](t3);
+            t5:bool = lt(t2, t0);
+            y:i8 = mux(t5, t4, a);
+            z:i8 = mul(a, t2);
+        }",
+    );
+    let res = body(&fold_def(&def).unwrap());
+    assert_eq!(res[2], "t2:i8 = const[-2]");
+    assert_eq!(res[3], "t3:i16 = const[-509]");
+    assert_eq!(res[4], "t4:i8 = const[-32]");
+    assert_eq!(res[5], "t5:bool = const[1]");
+    assert_eq!(res[6], "y:i8 = const[-32]");
+    assert_eq!(res[7], "z:i8 = mul(a, t2) @??");
+}
+
+#[test]
+fn cse() {
+    let def = main_def(
+        "def main(a:i8, b:i8) -> (y:i8, z:i8) {
+            t0:i8 = add(a, b);
+            t1:i8 = add(a, b);
+            t2:i8 = id(t1);
+            t3:i8 = add(a, b) @lut;
+            y:i8 = sub(t0, t2);
+            z:i8 = sub(t1, t3);
+        }",
+    );
+    let res = body(&cse_def(&def).unwrap());
+    assert_eq!(
+        res,
+        vec![
+            "t0:i8 = add(a, b) @??",
+            "t3:i8 = add(a, b) @lut",
+            "y:i8 = sub(t0, t0) @??",
+            "z:i8 = sub(t0, t3) @??",
+        ]
+    );
+}
+
+#[test]
+fn dce() {
+    let def = main_def(
+        "def main(a:i8, en:bool) -> (y:i8) {
+            t0:i8 = add(a, t1);
+            t1:i8 = reg[0](t0, en);
+            t2:i8 = reg[0](t3, en);
+            t3:i8 = add(t2, a);
+            t4:i8 = not(a);
+            y:i8 = id(t1);
+        }",
+    );
+    let res = body(&dce_def(&def).unwrap());
+    assert_eq!(
+        res,
+        vec![
+            "t0:i8 = add(a, t1) @??",
+            "t1:i8 = reg[0](t0, en) @??",
+            "y:i8 = id(t1)",
+        ]
+    );
+}
+
+#[test]
+fn aes_encrypt() -> Result<(), Error> {
+    let file = "../../../examples/ir/aes_encrypt.ir";
+    let opts = Opt::from_iter(&["ro", "--lang", "ir", file]);
+    let driver = Driver::new(opts);
+    let prog = IrParser::parse_from_file(file)?;
+    let manager = driver.ir_pass().pipeline(&[])?;
+    assert_eq!(manager.pass(), vec!["fold", "cse", "dce"]);
+    let (res, report) = manager.run(&prog)?;
+    assert!(check_prog(&res).is_ok());
+    let def = res.get("main").unwrap();
+    assert!(def.body().len() < prog.get("main").unwrap().body().len());
+    let body = body(def);
+    assert_eq!(body[2], "t3:i4 = const[10]");
+    assert!(body.contains(&"t9:bool = eq(t8, t3) @??".to_string()));
+    assert!(body.contains(&"t207:i4 = mux(t198, t8, t2) @??".to_string()));
+    assert!(!body.iter().any(|i| i.starts_with("t5:")));
+    assert!(report[1].after()["instr"] < report[1].before()["instr"]);
+    Ok(())
+}
+
+#[test]
+fn equivalence() -> Result<(), Error> {
+    let prog = IrParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = const[2];
+            t1:i8 = const[2];
+            t2:i8 = mul(t0, t1);
+            t3:i8 = add(a, b);
+            t4:i8 = add(a, b);
+            t5:i8 = mul(t3, t2);
+            t6:i8 = sub(t4, t1);
+            t9:i8 = mux(en, t6, t6);
+            t7:i8 = reg[0](t5, en);
+            t8:i8 = xor(t6, t6);
+            y:i8 = add(t7, t9);
+        }",
+    )?;
+    let driver = Driver::new(Opt::from_iter(&["ro", "--lang", "ir", "main.ir"]));
+    let (res, _) = driver.ir_pass().pipeline(&[])?.run(&prog)?;
+    assert_eq!(res.get("main").unwrap().body().len(), 7);
+    let stim = Stimulus::from_str("a b en\n1 2 1\n3 -4 1\n5 6 0\n-7 8 1").unwrap();
+    let exp = run_ir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(run_ir_prog(&res, &stim, None).unwrap(), exp);
+    Ok(())
+}
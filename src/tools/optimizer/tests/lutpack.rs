@@ -0,0 +1,124 @@
+use optimizer::driver::Driver;
+use optimizer::errors::Error;
+use optimizer::lutpack::{lutpack, remap_init};
+use optimizer::opt::Opt;
+use sim::trace::Stimulus;
+use sim::xsim::run_xir_prog;
+use std::str::FromStr;
+use structopt::StructOpt;
+use xir::ast::{ExprTerm, Prog, Ty};
+use xir::parser::Parser as XirParser;
+
+fn body(prog: &Prog) -> Vec<String> {
+    prog.body().iter().map(|i| i.to_string()).collect()
+}
+
+fn var(name: &str) -> ExprTerm {
+    ExprTerm::Var(name.to_string(), Ty::Bool)
+}
+
+#[test]
+fn remap() {
+    let input = vec![var("a"), var("b"), var("c"), var("d")];
+    assert_eq!(remap_init(8, &[var("a"), var("b")], &input), 0x8888);
+    assert_eq!(
+        remap_init(0x96, &[var("b"), var("c"), var("d")], &input),
+        0xc33c
+    );
+    assert_eq!(remap_init(2, &[var("b")], &[var("a"), var("b")]), 0xc);
+}
+
+#[test]
+fn pack() -> Result<(), Error> {
+    let prog = XirParser::parse_from_str(
+        "def main(a:bool, b:bool, c:bool, d:bool, e:bool) -> (y:bool, z:bool, w:bool, v:bool) {
+            y:bool = lut2[8](a, b) @a6(0, 0);
+            w:bool = lut6[1](a, b, c, d, e, a) @c6(0, 0);
+            z:bool = lut3[0x96](b, c, d) @b6(0, 0);
+            v:bool = lut3[0x80](c, d, e) @d6(0, 0);
+        }",
+    )?;
+    let res = lutpack(&prog)?;
+    assert_eq!(
+        body(&res),
+        vec![
+            "y:bool = lut4[34952](a, b, c, d) @a6(0, 0)",
+            "w:bool = lut6[1](a, b, c, d, e, a) @c6(0, 0)",
+            "z:bool = lut4[49980](a, b, c, d) @a5(0, 0)",
+            "v:bool = lut3[128](c, d, e) @d6(0, 0)",
+        ]
+    );
+    let stim = Stimulus::from_str(
+        "a b c d e
+         0 0 0 0 0
+         1 1 0 0 0
+         0 1 1 0 1
+         1 1 1 1 0
+         0 0 1 1 1
+         1 0 1 0 1",
+    )
+    .unwrap();
+    let exp = run_xir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(run_xir_prog(&res, &stim, None).unwrap(), exp);
+    Ok(())
+}
+
+#[test]
+fn placed() -> Result<(), Error> {
+    let prog = XirParser::parse_from_str(
+        "def main(a:bool, b:bool, c:bool) -> (y:bool, z:bool, w:bool, v:bool) {
+            y:bool = lut2[8](a, b) @a6(1, 2);
+            z:bool = lut2[6](a, b) @b6(3, 4);
+            w:bool = lut2[1](b, c) @c6(3, 4);
+            v:bool = lut1[1](a) @c5(1, 2);
+            u:bool = lut2[9](a, c) @a5(1, 2);
+        }",
+    )?;
+    let res = lutpack(&prog)?;
+    assert_eq!(
+        body(&res),
+        vec![
+            "y:bool = lut3[136](a, b, c) @a6(1, 2)",
+            "z:bool = lut3[102](a, b, c) @b6(3, 4)",
+            "w:bool = lut3[3](a, b, c) @b5(3, 4)",
+            "v:bool = lut1[1](a) @c5(1, 2)",
+            "u:bool = lut3[165](a, b, c) @a5(1, 2)",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn unplaced() -> Result<(), Error> {
+    let prog = XirParser::parse_from_str(
+        "def main(a:bool, b:bool) -> (y:bool, z:bool) {
+            y:bool = lut2[8](a, b) @a6(??, ??);
+            z:bool = lut2[6](a, b) @b6(??, ??);
+        }",
+    )?;
+    assert_eq!(lutpack(&prog)?, prog);
+    Ok(())
+}
+
+// the carry chain reads s from the 6 output of every lut
+#[test]
+fn carry() -> Result<(), Error> {
+    let prog = XirParser::parse_from_str(
+        "def main(a:bool, b:bool) -> (y:i2, z:bool) {
+            s0:bool = lut2[6](a, b) @a6(0, 0);
+            s1:bool = lut2[9](a, b) @b6(0, 0);
+            z:bool = lut2[8](a, b) @c6(0, 0);
+            t0:i2 = cat(s0, s1);
+            t1:i2 = cat(a, b);
+            y:i2 = carryadd(t1, t0) @c8(0, 0);
+        }",
+    )?;
+    assert_eq!(lutpack(&prog)?, prog);
+    Ok(())
+}
+
+#[test]
+fn driver() {
+    let driver = Driver::new(Opt::from_iter(&["ro", "--lang", "xir", "main.xir"]));
+    assert_eq!(driver.xir_pass().default_passes(), &vec!["lutpack"]);
+}
@@ -0,0 +1,102 @@
+use asm::ast::Prog;
+use asm::parser::Parser as AsmParser;
+use optimizer::cascader::{cascader, Cascade};
+use optimizer::driver::Driver;
+use optimizer::errors::Error;
+use optimizer::opt::Opt;
+use optimizer::pass::{Pass, PassManager, Registry};
+use std::env;
+use std::fs;
+use structopt::StructOpt;
+use xim::parser::Parser as XimParser;
+
+const TDOT: &str = "../../../examples/asm/tdot_5_3.asm";
+
+// a pass defined out of the crate, removing the first instruction
+struct Drop;
+
+impl Pass<Prog> for Drop {
+    fn name(&self) -> &str {
+        "drop"
+    }
+    fn run(&self, prog: &Prog) -> Result<Prog, Error> {
+        let mut prog = prog.clone();
+        prog.body_mut().remove(0);
+        Ok(prog)
+    }
+}
+
+fn registry() -> Registry<Prog> {
+    let mut registry = Registry::default();
+    registry.register("cascade", || {
+        let target = XimParser::parse_from_file("../../../examples/xim/dsp.xim")?;
+        Ok(Box::new(Cascade::new(target, 72)))
+    });
+    registry.register("drop", || Ok(Box::new(Drop)));
+    registry
+}
+
+#[test]
+fn pipeline() -> Result<(), Error> {
+    let prog = AsmParser::parse_from_file(TDOT)?;
+    let manager = registry().pipeline(&["cascade".to_string(), "drop".to_string()])?;
+    assert_eq!(manager.pass(), vec!["cascade", "drop"]);
+    let (res, report) = manager.run(&prog)?;
+    let target = XimParser::parse_from_file("../../../examples/xim/dsp.xim")?;
+    let mut exp = cascader(&prog, &target, 72)?;
+    exp.body_mut().remove(0);
+    assert_eq!(res, exp);
+    assert_eq!(
+        report[0].to_string(),
+        "cascade: dsp 15 -> 15, instr 15 -> 15"
+    );
+    assert_eq!(report[1].to_string(), "drop: dsp 15 -> 14, instr 15 -> 14");
+    Ok(())
+}
+
+#[test]
+fn dump() -> Result<(), Error> {
+    let dir = env::temp_dir().join("optimizer_pass_dump");
+    let _ = fs::remove_dir_all(&dir);
+    let mut manager = PassManager::default();
+    manager.add_pass(Box::new(Drop));
+    manager.add_pass(Box::new(Drop));
+    manager.set_dump(&dir);
+    let (res, _) = manager.run(&AsmParser::parse_from_file(TDOT)?)?;
+    let last = AsmParser::parse_from_file(dir.join("2_drop.asm"))?;
+    assert_eq!(last.to_string(), res.to_string());
+    let first = AsmParser::parse_from_file(dir.join("1_drop.asm"))?;
+    assert_eq!(first.body().len(), res.body().len() + 1);
+    fs::remove_dir_all(&dir).unwrap();
+    Ok(())
+}
+
+#[test]
+fn driver() -> Result<(), Error> {
+    let opts = Opt::from_iter(&["ro", "--lang", "asm", "--passes", "drop,cascade", TDOT]);
+    let mut driver = Driver::new(opts);
+    assert_eq!(driver.asm_pass().names(), vec!["cascade"]);
+    assert!(driver.asm_pass().pipeline(driver.opts().passes()).is_err());
+    driver
+        .asm_pass_mut()
+        .register("drop", || Ok(Box::new(Drop)));
+    let manager = driver.asm_pass().pipeline(driver.opts().passes())?;
+    assert_eq!(manager.pass(), vec!["drop", "cascade"]);
+    let manager = driver.asm_pass().pipeline(&[])?;
+    assert_eq!(manager.pass(), vec!["cascade"]);
+    assert!(driver.ir_pass().pipeline(&["cascade".to_string()]).is_err());
+    Ok(())
+}
+
+#[test]
+fn passes() -> Result<(), Error> {
+    let opts = Opt::from_iter(&["ro", "--lang", "xir", "--passes", "lutpack", "main.xir"]);
+    assert_eq!(opts.passes(), &vec!["lutpack"]);
+    assert_eq!(opts.input().to_str(), Some("main.xir"));
+    let driver = Driver::new(Opt::from_iter(&["ro", "--opt", "cascade", TDOT]));
+    assert_eq!(driver.passes(), vec!["cascade"]);
+    let opts = Opt::from_iter(&["ro", "--lang", "asm", "--passes", "dce,cse,cascade", TDOT]);
+    let err = Driver::new(opts).run().unwrap_err().to_string();
+    assert!(err.starts_with("Pass dce runs on ir programs"), "{}", err);
+    Ok(())
+}
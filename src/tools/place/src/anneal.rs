@@ -0,0 +1,180 @@
+use crate::errors::Error;
+use crate::netlist::Netlist;
+use crate::placement::{group_sites, Anchor, Placement};
+use device::Device;
+use sim::trace::Rng;
+use std::collections::BTreeSet;
+
+// lcg seed, so a program is always placed the same way
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// Temperature is lowered by this factor after every round of moves
+const COOLING: f64 = 0.9;
+
+// Moves per movable group and round
+const MOVES: usize = 20;
+
+const MIN_TEMP: f64 = 0.05;
+
+fn below(rng: &mut Rng, n: usize) -> usize {
+    (rng.next_u32() as usize) % n
+}
+
+fn unit(rng: &mut Rng) -> f64 {
+    f64::from(rng.next_u32()) / (1u64 << 32) as f64
+}
+
+// value within window of v, not below zero
+fn near(rng: &mut Rng, v: Option<u64>, window: u64) -> Option<u64> {
+    v.map(|v| {
+        let d = u64::from(rng.next_u32()) % (2 * window + 1);
+        (v + d).saturating_sub(window)
+    })
+}
+
+fn group_nets(netlist: &Netlist, group: usize) -> BTreeSet<usize> {
+    netlist.group()[group]
+        .cell()
+        .iter()
+        .flat_map(|c| netlist.cell_net(*c).iter().cloned())
+        .collect()
+}
+
+fn nets_cost(netlist: &Netlist, placement: &Placement, nets: &BTreeSet<usize>) -> u64 {
+    nets.iter().map(|n| placement.net_cost(netlist, *n)).sum()
+}
+
+// Move a group to a random anchor near its current one, either into free
+// room or by swapping places with a single cell. Returns every group that
+// moves with its current and new anchor.
+fn propose(
+    netlist: &Netlist,
+    device: &Device,
+    placement: &Placement,
+    rng: &mut Rng,
+    group: usize,
+    window: u64,
+) -> Option<Vec<(usize, Anchor, Anchor)>> {
+    let old = *placement.anchor(group)?;
+    let anchor = (near(rng, old.0, window), near(rng, old.1, window));
+    if anchor == old {
+        return None;
+    }
+    let site = group_sites(netlist, device, group, &anchor)?;
+    let cell = netlist.group()[group].cell();
+    let mut other: Vec<usize> = Vec::new();
+    for (c, s) in cell.iter().zip(site.iter()) {
+        for o in placement.clashes(netlist, *c, s) {
+            let g = netlist.cell()[o].group();
+            if !other.contains(&g) {
+                other.push(g);
+            }
+        }
+    }
+    match other.as_slice() {
+        [] => Some(vec![(group, old, anchor)]),
+        [g] if cell.len() == 1 && netlist.group()[*g].cell().len() == 1 => {
+            let oc = &netlist.cell()[netlist.group()[*g].cell()[0]];
+            if netlist.group()[*g].is_fixed() || netlist.cell()[cell[0]].prim() != oc.prim() {
+                return None;
+            }
+            let (x, y) = *placement.site(cell[0])?;
+            let swap = (oc.x().solve(x)?, oc.y().solve(y)?);
+            let other_old = *placement.anchor(*g)?;
+            Some(vec![(group, old, anchor), (*g, other_old, swap)])
+        }
+        _ => None,
+    }
+}
+
+// Returns false when a group does not fit at its anchor, such as a swapped
+// cell overlapping with another cell at its new site, so the move is rejected
+fn apply(
+    netlist: &Netlist,
+    device: &Device,
+    placement: &mut Placement,
+    moves: &[(usize, Anchor, Anchor)],
+    new: bool,
+) -> bool {
+    for (g, _, _) in moves {
+        placement.remove(netlist, *g);
+    }
+    for (g, old, anchor) in moves {
+        let anchor = if new { anchor } else { old };
+        match group_sites(netlist, device, *g, anchor) {
+            Some(site) if placement.is_free(netlist, *g, &site) => {
+                placement.insert(netlist, *g, *anchor, &site)
+            }
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn undo(
+    netlist: &Netlist,
+    device: &Device,
+    placement: &mut Placement,
+    moves: &[(usize, Anchor, Anchor)],
+) -> Result<(), Error> {
+    if apply(netlist, device, placement, moves, false) {
+        Ok(())
+    } else {
+        let msg = "Annealing failed to restore a placement it moved away from";
+        Err(Error::new_place_error(msg))
+    }
+}
+
+// Simulated annealing on the wirelength, starting from a legal placement.
+// Moves are local, the window shrinks with the temperature, and the best
+// placement seen is returned, so it is never worse than the initial one.
+pub fn anneal(netlist: &Netlist, device: &Device, init: &Placement) -> Result<Placement, Error> {
+    let movable: Vec<usize> = (0..netlist.group().len())
+        .filter(|g| !netlist.group()[*g].is_fixed())
+        .collect();
+    let mut placement = init.clone();
+    let mut cost = placement.cost(netlist);
+    if movable.is_empty() || netlist.net().is_empty() {
+        return Ok(placement);
+    }
+    let mut best = placement.clone();
+    let mut best_cost = cost;
+    let mut rng = Rng::new(SEED);
+    let (x, y) = device.extent();
+    let span = x.max(y).max(1);
+    // an average net is expected to get worse at first
+    let start = (cost as f64 / netlist.net().len() as f64).max(1.0);
+    let mut temp = start;
+    while temp > MIN_TEMP && best_cost > 0 {
+        let window = ((span as f64 * temp / start) as u64).max(1);
+        for _ in 0..MOVES * movable.len() {
+            let group = movable[below(&mut rng, movable.len())];
+            let moves = match propose(netlist, device, &placement, &mut rng, group, window) {
+                Some(moves) => moves,
+                None => continue,
+            };
+            let mut nets: BTreeSet<usize> = BTreeSet::new();
+            for (g, _, _) in moves.iter() {
+                nets.extend(group_nets(netlist, *g));
+            }
+            let prev = nets_cost(netlist, &placement, &nets);
+            if !apply(netlist, device, &mut placement, &moves, true) {
+                undo(netlist, device, &mut placement, &moves)?;
+                continue;
+            }
+            let next = nets_cost(netlist, &placement, &nets);
+            let delta = next as f64 - prev as f64;
+            if delta <= 0.0 || unit(&mut rng) < (-delta / temp).exp() {
+                cost = cost + next - prev;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = placement.clone();
+                }
+            } else {
+                undo(netlist, device, &mut placement, &moves)?;
+            }
+        }
+        temp *= COOLING;
+    }
+    Ok(best)
+}
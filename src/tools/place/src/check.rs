@@ -0,0 +1,342 @@
+use crate::errors::Error;
+use crate::netlist::{overlap, shared_bel, site_bel, Coord, Vars};
+use crate::solve::{bind_prog, is_solved};
+use asm::ast as asm;
+use device::{site_prim, Device};
+use std::collections::HashMap;
+use std::fmt;
+use xim::ast as xim;
+use xir::ast as xir;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    // no site of any prim at the coordinates
+    Bounds {
+        instr: asm::Id,
+        prim: asm::Prim,
+        x: u64,
+        y: u64,
+    },
+    // the coordinates belong to sites of other prims
+    Prim {
+        instr: asm::Id,
+        prim: asm::Prim,
+        x: u64,
+        y: u64,
+        site: Vec<asm::Prim>,
+    },
+    // the implementation is for other sites than the location says
+    Loc {
+        instr: asm::Id,
+        prim: asm::Prim,
+        loc: asm::Prim,
+    },
+    // two instructions on the same bel, or on the same site when the bels
+    // of one of them are unknown
+    Overlap {
+        instr: asm::Id,
+        other: asm::Id,
+        bel: Option<xir::Bel>,
+        x: asm::ExprCoord,
+        y: asm::ExprCoord,
+    },
+    // a cascade input that is not right above its driver
+    Cascade {
+        instr: asm::Id,
+        driver: Option<asm::Id>,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Bounds { instr, prim, x, y } => write!(
+                f,
+                "{} is placed at ({}, {}), outside of the {} sites of the device",
+                instr, x, y, prim
+            ),
+            Violation::Prim {
+                instr,
+                prim,
+                x,
+                y,
+                site,
+            } => {
+                let site: Vec<String> = site.iter().map(|p| p.to_string()).collect();
+                write!(
+                    f,
+                    "{} is a {} instruction placed at ({}, {}), which is a {} site",
+                    instr,
+                    prim,
+                    x,
+                    y,
+                    site.join(" and ")
+                )
+            }
+            Violation::Loc { instr, prim, loc } => write!(
+                f,
+                "{} is a {} instruction with a {} location",
+                instr, prim, loc
+            ),
+            Violation::Overlap {
+                instr,
+                other,
+                bel: Some(bel),
+                x,
+                y,
+            } => write!(
+                f,
+                "{} and {} are both placed on bel {} at ({}, {})",
+                other, instr, bel, x, y
+            ),
+            Violation::Overlap {
+                instr, other, x, y, ..
+            } => write!(
+                f,
+                "{} and {} are both placed on site ({}, {})",
+                other, instr, x, y
+            ),
+            Violation::Cascade {
+                instr,
+                driver: Some(driver),
+            } => write!(
+                f,
+                "{} cascades from {}, so it must be placed right above it",
+                instr, driver
+            ),
+            Violation::Cascade {
+                instr,
+                driver: None,
+            } => write!(
+                f,
+                "The cascade input of {} is not driven by a cascade output",
+                instr
+            ),
+        }
+    }
+}
+
+impl Violation {
+    pub fn instr(&self) -> &str {
+        match self {
+            Violation::Bounds { instr, .. } => instr,
+            Violation::Prim { instr, .. } => instr,
+            Violation::Loc { instr, .. } => instr,
+            Violation::Overlap { instr, .. } => instr,
+            Violation::Cascade { instr, .. } => instr,
+        }
+    }
+}
+
+// Instruction resolved to a site and the bels it takes there
+#[derive(Clone, Debug)]
+struct Item {
+    name: asm::Id,
+    prim: asm::Prim,
+    loc: (asm::ExprCoord, asm::ExprCoord),
+    x: Coord,
+    y: Coord,
+    bel: Vec<xir::Bel>,
+    // id feeding the cascade input, and whether there is a cascade output
+    cascade_in: Option<asm::Id>,
+    cascade_out: bool,
+}
+
+fn bel_prim(bel: &xir::Bel) -> asm::Prim {
+    match bel {
+        xir::Bel::Dsp(_) => asm::Prim::Dsp,
+        xir::Bel::Block(xir::BelBlock::L) => asm::Prim::Bram,
+        xir::Bel::Block(xir::BelBlock::U) => asm::Prim::Uram,
+        _ => asm::Prim::Lut,
+    }
+}
+
+fn is_any(coord: &asm::ExprCoord) -> bool {
+    matches!(coord, asm::ExprCoord::Any)
+}
+
+fn check_site(item: &Item, device: &Device) -> Option<Violation> {
+    let (x, y) = (item.x.eval(None)?, item.y.eval(None)?);
+    if item.prim == asm::Prim::Any || device.contains(&item.prim, x, y) {
+        return None;
+    }
+    let site: Vec<asm::Prim> = device
+        .prims()
+        .into_iter()
+        .filter(|p| device.contains(p, x, y))
+        .collect();
+    if site.is_empty() {
+        Some(Violation::Bounds {
+            instr: item.name.clone(),
+            prim: item.prim.clone(),
+            x,
+            y,
+        })
+    } else {
+        Some(Violation::Prim {
+            instr: item.name.clone(),
+            prim: item.prim.clone(),
+            x,
+            y,
+            site,
+        })
+    }
+}
+
+fn check_item(item: &[Item], device: &Device) -> Vec<Violation> {
+    let mut res: Vec<Violation> = Vec::new();
+    let mut site: HashMap<(asm::Prim, Coord, Coord), Vec<usize>> = HashMap::new();
+    let mut driver: HashMap<&str, usize> = HashMap::new();
+    for (i, it) in item.iter().enumerate() {
+        driver.insert(&it.name, i);
+        if let Some(v) = check_site(it, device) {
+            res.push(v);
+        }
+        // a ?? is never on the same site as anything else
+        if is_any(&it.loc.0) || is_any(&it.loc.1) {
+            continue;
+        }
+        let key = (site_prim(&it.prim), it.x.clone(), it.y.clone());
+        let placed = site.entry(key).or_default();
+        for o in placed.iter() {
+            let other = &item[*o];
+            if overlap(&it.bel, &other.bel) {
+                let bel = shared_bel(&it.bel, &other.bel);
+                res.push(Violation::Overlap {
+                    instr: it.name.clone(),
+                    other: other.name.clone(),
+                    bel: bel.cloned(),
+                    x: it.loc.0.clone(),
+                    y: it.loc.1.clone(),
+                });
+            }
+        }
+        placed.push(i);
+    }
+    for it in item.iter() {
+        let id = match &it.cascade_in {
+            Some(id) => id,
+            None => continue,
+        };
+        let from = match driver.get(id.as_str()).map(|d| &item[*d]) {
+            Some(from) if from.cascade_out => from,
+            _ => {
+                res.push(Violation::Cascade {
+                    instr: it.name.clone(),
+                    driver: None,
+                });
+                continue;
+            }
+        };
+        if [&it.loc, &from.loc]
+            .iter()
+            .any(|(x, y)| is_any(x) || is_any(y))
+        {
+            continue;
+        }
+        let above = match &from.y {
+            Coord::Val(y) => Coord::Val(y + 1),
+            Coord::Var(v, offset) => Coord::Var(*v, offset + 1),
+        };
+        if it.x != from.x || it.y != above {
+            res.push(Violation::Cascade {
+                instr: it.name.clone(),
+                driver: Some(from.name.clone()),
+            });
+        }
+    }
+    res
+}
+
+fn expr_id(expr: &xir::Expr, index: usize) -> Option<asm::Id> {
+    expr.get_id(index).ok()
+}
+
+// Check the locations of an asm program against a device, the bels and
+// cascades of every instruction come from its implementation in the target,
+// and coordinates other than offsets are solved first
+pub fn check_asm_prog(
+    prog: &asm::Prog,
+    device: &Device,
+    target: &xim::Target,
+) -> Result<Vec<Violation>, Error> {
+    let solved;
+    let prog = if is_solved(prog) {
+        prog
+    } else {
+        solved = bind_prog(prog, device)?;
+        &solved
+    };
+    let mut vars = Vars::default();
+    let mut item: Vec<Item> = Vec::new();
+    let mut loc_error: Vec<Violation> = Vec::new();
+    for instr in prog.body() {
+        if let asm::Instr::Asm(instr) = instr {
+            let loc = instr.loc();
+            let mut it = Item {
+                name: instr.dst().get_id(0).unwrap_or_default(),
+                prim: loc.prim().clone(),
+                loc: (loc.x().clone(), loc.y().clone()),
+                x: vars.coord(loc.x(), 'x')?,
+                y: vars.coord(loc.y(), 'y')?,
+                bel: Vec::new(),
+                cascade_in: None,
+                cascade_out: false,
+            };
+            if let Some(imp) = target.instance(&instr.op().to_string())? {
+                let input: Vec<asm::ExprTerm> = imp.sig().input().clone().into();
+                for mach in imp.body().iter().filter_map(|i| i.mach()) {
+                    if let Some(loc) = mach.loc() {
+                        it.bel.push(site_bel(loc.bel()));
+                    }
+                    it.cascade_out |= mach.op().is_cascade_out();
+                    if let Some(arg) = mach.op().cascade_arg() {
+                        // the cascaded operand of the implementation is an
+                        // input, and the argument at its position is cascaded
+                        let id = expr_id(mach.arg(), arg);
+                        let pos = input.iter().position(|t| t.get_id().ok() == id);
+                        it.cascade_in = pos.and_then(|p| expr_id(instr.arg(), p));
+                    }
+                }
+            }
+            let prim = it.bel.first().map(bel_prim);
+            match prim {
+                Some(prim) if loc.prim() != &asm::Prim::Any && prim != site_prim(loc.prim()) => {
+                    loc_error.push(Violation::Loc {
+                        instr: it.name.clone(),
+                        prim,
+                        loc: loc.prim().clone(),
+                    })
+                }
+                _ => (),
+            }
+            item.push(it);
+        }
+    }
+    loc_error.extend(check_item(&item, device));
+    Ok(loc_error)
+}
+
+// Check the locations of an xir program against a device
+pub fn check_xir_prog(prog: &xir::Prog, device: &Device) -> Result<Vec<Violation>, Error> {
+    let mut vars = Vars::default();
+    let mut item: Vec<Item> = Vec::new();
+    for mach in prog.body().iter().filter_map(|i| i.mach()) {
+        if let Some(loc) = mach.loc() {
+            item.push(Item {
+                name: mach.dst().get_id(0).unwrap_or_default(),
+                prim: bel_prim(loc.bel()),
+                loc: (loc.x().clone(), loc.y().clone()),
+                x: vars.coord(loc.x(), 'x')?,
+                y: vars.coord(loc.y(), 'y')?,
+                bel: vec![site_bel(loc.bel())],
+                cascade_in: mach
+                    .op()
+                    .cascade_arg()
+                    .and_then(|arg| expr_id(mach.arg(), arg)),
+                cascade_out: mach.op().is_cascade_out(),
+            });
+        }
+    }
+    Ok(check_item(&item, device))
+}
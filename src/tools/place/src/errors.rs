@@ -0,0 +1,56 @@
+use asm::errors::Error as AsmError;
+use device::errors::Error as DeviceError;
+use ir::errors::Error as IrError;
+use std::fmt;
+use xim::errors::Error as XimError;
+
+#[derive(Debug)]
+pub enum Error {
+    Ir(IrError),
+    Asm(AsmError),
+    Xim(XimError),
+    Device(DeviceError),
+    Place(String),
+}
+
+impl Error {
+    pub fn new_place_error(msg: &str) -> Self {
+        Error::Place(msg.to_string())
+    }
+}
+
+impl From<IrError> for Error {
+    fn from(e: IrError) -> Self {
+        Error::Ir(e)
+    }
+}
+
+impl From<AsmError> for Error {
+    fn from(e: AsmError) -> Self {
+        Error::Asm(e)
+    }
+}
+
+impl From<XimError> for Error {
+    fn from(e: XimError) -> Self {
+        Error::Xim(e)
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(e: DeviceError) -> Self {
+        Error::Device(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Ir(msg) => write!(f, "{}", msg),
+            Error::Asm(msg) => write!(f, "{}", msg),
+            Error::Xim(msg) => write!(f, "{}", msg),
+            Error::Device(msg) => write!(f, "{}", msg),
+            Error::Place(msg) => write!(f, "{}", msg),
+        }
+    }
+}
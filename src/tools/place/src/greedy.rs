@@ -0,0 +1,115 @@
+use crate::errors::Error;
+use crate::netlist::{Coord, Netlist};
+use crate::placement::{candidates, group_sites, hpwl, Anchor, Placement, Site};
+use asm::ast::Prim;
+use device::Device;
+use std::collections::HashMap;
+
+// groups with the same prims and offsets have the same candidates
+type Shape = Vec<(Prim, Option<u64>, Option<u64>, u64, u64)>;
+
+fn shape(netlist: &Netlist, group: usize) -> Shape {
+    let split = |coord: &Coord| match coord {
+        Coord::Val(v) => (Some(*v), 0),
+        Coord::Var(_, offset) => (None, *offset),
+    };
+    netlist.group()[group]
+        .cell()
+        .iter()
+        .map(|c| {
+            let cell = &netlist.cell()[*c];
+            let (x, dx) = split(cell.x());
+            let (y, dy) = split(cell.y());
+            (cell.prim().clone(), x, y, dx, dy)
+        })
+        .collect()
+}
+
+fn fixed_error(netlist: &Netlist, group: usize) -> Error {
+    let cell = &netlist.cell()[netlist.group()[group].cell()[0]];
+    let msg = format!(
+        "Instruction {} is fixed at ({}, {}), which is not a free {} site",
+        cell.name(),
+        cell.x(),
+        cell.y(),
+        cell.prim()
+    );
+    Error::new_place_error(&msg)
+}
+
+// Groups are placed one at a time, fixed ones first and then the largest,
+// each at the free anchor closest to the cells it connects to
+pub fn greedy(netlist: &Netlist, device: &Device) -> Result<Placement, Error> {
+    let mut placement = Placement::new(netlist);
+    let mut order: Vec<usize> = (0..netlist.group().len()).collect();
+    order.sort_by_key(|g| {
+        let group = &netlist.group()[*g];
+        (!group.is_fixed(), std::cmp::Reverse(group.cell().len()), *g)
+    });
+    let mut cache: HashMap<Shape, Vec<Anchor>> = HashMap::new();
+    for g in order {
+        let group = &netlist.group()[g];
+        if group.is_fixed() {
+            let anchor = (None, None);
+            match group_sites(netlist, device, g, &anchor) {
+                Some(site) if placement.is_free(netlist, g, &site) => {
+                    placement.insert(netlist, g, anchor, &site)
+                }
+                _ => return Err(fixed_error(netlist, g)),
+            }
+            continue;
+        }
+        let cand = cache
+            .entry(shape(netlist, g))
+            .or_insert_with(|| candidates(netlist, device, g));
+        // placed cells of every net the group is on, and the group cells in it
+        let mut mine: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, c) in group.cell().iter().enumerate() {
+            for n in netlist.cell_net(*c) {
+                mine.entry(*n).or_default().push(i);
+            }
+        }
+        let nets: Vec<(Vec<Option<Site>>, Vec<usize>)> = mine
+            .into_iter()
+            .map(|(n, mine)| {
+                let other: Vec<Option<Site>> = netlist.net()[n]
+                    .iter()
+                    .filter(|o| netlist.cell()[**o].group() != g)
+                    .map(|o| placement.site(*o).cloned())
+                    .collect();
+                (other, mine)
+            })
+            .collect();
+        let mut best: Option<(u64, Anchor, Vec<Site>)> = None;
+        for anchor in cand.iter() {
+            let site = match group_sites(netlist, device, g, anchor) {
+                Some(site) if placement.is_free(netlist, g, &site) => site,
+                _ => continue,
+            };
+            let cost: u64 = nets
+                .iter()
+                .map(|(other, mine)| {
+                    let mut all = other.clone();
+                    all.extend(mine.iter().map(|i| Some(site[*i])));
+                    hpwl(&all)
+                })
+                .sum();
+            if best.as_ref().map_or(true, |(b, _, _)| cost < *b) {
+                best = Some((cost, *anchor, site));
+            }
+        }
+        match best {
+            Some((_, anchor, site)) => placement.insert(netlist, g, anchor, &site),
+            None => {
+                let cell = &netlist.cell()[group.cell()[0]];
+                let msg = format!(
+                    "No free {} site left for instruction {}",
+                    cell.prim(),
+                    cell.name()
+                );
+                return Err(Error::new_place_error(&msg));
+            }
+        }
+    }
+    Ok(placement)
+}
@@ -0,0 +1,116 @@
+pub mod anneal;
+pub mod check;
+pub mod errors;
+pub mod greedy;
+pub mod netlist;
+pub mod placement;
+pub mod solve;
+
+use crate::anneal::anneal;
+use crate::errors::Error;
+use crate::greedy::greedy;
+use crate::netlist::Netlist;
+use crate::placement::Placement;
+use crate::solve::{bind_prog, is_solved};
+use asm::ast::{ExprCoord, Instr, Prog};
+use device::Device;
+use std::fmt;
+use std::str::FromStr;
+use xim::ast::Target;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Greedy,
+    Anneal,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match self {
+            Mode::Greedy => "greedy",
+            Mode::Anneal => "anneal",
+        };
+        write!(f, "{}", mode)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "greedy" => Ok(Mode::Greedy),
+            "anneal" => Ok(Mode::Anneal),
+            _ => Err(Error::new_place_error("Unsupported placement mode")),
+        }
+    }
+}
+
+// Assign a site of the device to every instruction, keeping fixed locations
+// and the offsets between instructions that share coordinate variables,
+// other coordinate expressions are solved first. Every instruction takes a
+// whole site.
+pub fn place_prog(prog: &Prog, device: &Device, mode: &Mode) -> Result<Prog, Error> {
+    place(prog, device, mode, None)
+}
+
+// Same as place_prog, but instructions take the bels of their implementation
+// in the target, so the ones on different bels can share a site
+pub fn place_prog_with_target(
+    prog: &Prog,
+    device: &Device,
+    mode: &Mode,
+    target: &Target,
+) -> Result<Prog, Error> {
+    place(prog, device, mode, Some(target))
+}
+
+fn place(
+    prog: &Prog,
+    device: &Device,
+    mode: &Mode,
+    target: Option<&Target>,
+) -> Result<Prog, Error> {
+    let solved;
+    let prog = if is_solved(prog) {
+        prog
+    } else {
+        solved = bind_prog(prog, device)?;
+        &solved
+    };
+    let netlist = match target {
+        Some(target) => Netlist::from_prog_with_target(prog, target)?,
+        None => Netlist::from_prog(prog)?,
+    };
+    let mut placement = greedy(&netlist, device)?;
+    if *mode == Mode::Anneal {
+        placement = anneal(&netlist, device, &placement)?;
+    }
+    let mut prog = prog.clone();
+    for (c, cell) in netlist.cell().iter().enumerate() {
+        if let (Instr::Asm(instr), Some((x, y))) =
+            (&mut prog.body_mut()[cell.index()], placement.site(c))
+        {
+            let mut loc = instr.loc().clone();
+            loc.set_x(ExprCoord::Val(*x));
+            loc.set_y(ExprCoord::Val(*y));
+            instr.set_loc(loc);
+        }
+    }
+    Ok(prog)
+}
+
+// Sum of the half-perimeter wirelength of every net of a placed program
+pub fn wirelength(prog: &Prog) -> Result<u64, Error> {
+    let netlist = Netlist::from_prog(prog)?;
+    let mut placement = Placement::new(&netlist);
+    for (c, cell) in netlist.cell().iter().enumerate() {
+        match (cell.x().eval(None), cell.y().eval(None)) {
+            (Some(x), Some(y)) => placement.site[c] = Some((x, y)),
+            _ => {
+                let msg = format!("Instruction {} is not placed", cell.name());
+                return Err(Error::new_place_error(&msg));
+            }
+        }
+    }
+    Ok(placement.cost(&netlist))
+}
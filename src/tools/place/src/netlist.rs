@@ -0,0 +1,362 @@
+use crate::errors::Error;
+use asm::ast::*;
+use std::collections::HashMap;
+use std::fmt;
+use xim::ast as xim;
+use xir::ast as xir;
+
+// A coordinate is either fixed or a variable plus an offset
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Coord {
+    Val(u64),
+    Var(usize, u64),
+}
+
+// Instruction occupying bels of a site, or the whole site without bels
+#[derive(Clone, Debug)]
+pub struct Cell {
+    pub index: usize,
+    pub name: Id,
+    pub prim: Prim,
+    pub x: Coord,
+    pub y: Coord,
+    pub bel: Vec<xir::Bel>,
+    pub group: usize,
+}
+
+// Cells sharing coordinate variables move together, such as a dsp cascade
+// at x0 and y0, y0+1, y0+2. A group without variables is fixed.
+#[derive(Clone, Debug, Default)]
+pub struct Group {
+    pub cell: Vec<usize>,
+    pub x: Option<usize>,
+    pub y: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Netlist {
+    pub cell: Vec<Cell>,
+    pub group: Vec<Group>,
+    pub net: Vec<Vec<usize>>,
+    pub cell_net: Vec<Vec<usize>>,
+    pub vars: usize,
+}
+
+impl Coord {
+    pub fn var(&self) -> Option<usize> {
+        match self {
+            Coord::Var(v, _) => Some(*v),
+            Coord::Val(_) => None,
+        }
+    }
+    pub fn eval(&self, var: Option<u64>) -> Option<u64> {
+        match self {
+            Coord::Val(v) => Some(*v),
+            Coord::Var(_, offset) => var.map(|v| v + offset),
+        }
+    }
+    // variable value putting the coordinate at pos, if it can get there
+    pub fn solve(&self, pos: u64) -> Option<Option<u64>> {
+        match self {
+            Coord::Val(v) if *v == pos => Some(None),
+            Coord::Var(_, offset) if pos >= *offset => Some(Some(pos - offset)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Coord::Val(v) => write!(f, "{}", v),
+            Coord::Var(v, 0) => write!(f, "v{}", v),
+            Coord::Var(v, offset) => write!(f, "v{}+{}", v, offset),
+        }
+    }
+}
+
+impl Cell {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn prim(&self) -> &Prim {
+        &self.prim
+    }
+    pub fn x(&self) -> &Coord {
+        &self.x
+    }
+    pub fn y(&self) -> &Coord {
+        &self.y
+    }
+    pub fn bel(&self) -> &Vec<xir::Bel> {
+        &self.bel
+    }
+    pub fn group(&self) -> usize {
+        self.group
+    }
+}
+
+impl Group {
+    pub fn cell(&self) -> &Vec<usize> {
+        &self.cell
+    }
+    pub fn x(&self) -> Option<usize> {
+        self.x
+    }
+    pub fn y(&self) -> Option<usize> {
+        self.y
+    }
+    pub fn is_fixed(&self) -> bool {
+        self.x.is_none() && self.y.is_none()
+    }
+}
+
+// Coordinate variables by name, checked to be used on a single axis
+#[derive(Clone, Debug, Default)]
+pub struct Vars {
+    name: HashMap<Id, usize>,
+    axis: Vec<char>,
+}
+
+impl Vars {
+    fn fresh(&mut self, axis: char) -> usize {
+        self.axis.push(axis);
+        self.axis.len() - 1
+    }
+    fn named(&mut self, name: &str, axis: char) -> Result<usize, Error> {
+        if let Some(v) = self.name.get(name) {
+            if self.axis[*v] != axis {
+                let msg = format!("Coordinate {} is used both as x and y", name);
+                return Err(Error::new_place_error(&msg));
+            }
+            Ok(*v)
+        } else {
+            let v = self.fresh(axis);
+            self.name.insert(name.to_string(), v);
+            Ok(v)
+        }
+    }
+    // every ?? is a variable of its own
+    pub fn coord(&mut self, expr: &ExprCoord, axis: char) -> Result<Coord, Error> {
+        match expr {
+            ExprCoord::Any => Ok(Coord::Var(self.fresh(axis), 0)),
+            ExprCoord::Val(v) => Ok(Coord::Val(*v)),
+            ExprCoord::Var(n) => Ok(Coord::Var(self.named(n, axis)?, 0)),
+            ExprCoord::Bin(OpCoord::Add, a, b) => {
+                match (self.coord(a, axis)?, self.coord(b, axis)?) {
+                    (Coord::Val(a), Coord::Val(b)) => Ok(Coord::Val(a + b)),
+                    (Coord::Var(v, a), Coord::Val(b)) | (Coord::Val(b), Coord::Var(v, a)) => {
+                        Ok(Coord::Var(v, a + b))
+                    }
+                    _ => Err(Error::new_place_error(&format!(
+                        "Unsupported coordinate {}, it adds two variables",
+                        expr
+                    ))),
+                }
+            }
+            // solved before placement
+            ExprCoord::Bin(_, _, _) => Err(Error::new_place_error(&format!(
+                "Unsupported coordinate {}, it must be solved first",
+                expr
+            ))),
+        }
+    }
+}
+
+// the h6 lut of a lut ram is the h6 lut of the slice
+pub fn site_bel(bel: &xir::Bel) -> xir::Bel {
+    match bel {
+        xir::Bel::Lum(xir::BelLum::H6) => xir::Bel::Lut(xir::BelLut::H6),
+        _ => bel.clone(),
+    }
+}
+
+// bel used by both instructions on a site
+pub fn shared_bel<'a>(bel: &'a [xir::Bel], other: &[xir::Bel]) -> Option<&'a xir::Bel> {
+    bel.iter().find(|b| other.contains(b))
+}
+
+// instructions on a site overlap when they share a bel, or when the bels of
+// one of them are unknown, so it takes the whole site
+pub fn overlap(bel: &[xir::Bel], other: &[xir::Bel]) -> bool {
+    bel.is_empty() || other.is_empty() || shared_bel(bel, other).is_some()
+}
+
+// bels of the implementation of an instruction in the target, if any
+pub fn instr_bel(instr: &InstrAsm, target: &xim::Target) -> Result<Vec<xir::Bel>, Error> {
+    let mut bel: Vec<xir::Bel> = Vec::new();
+    if let Some(imp) = target.instance(&instr.op().to_string())? {
+        for mach in imp.body().iter().filter_map(|i| i.mach()) {
+            if let Some(loc) = mach.loc() {
+                bel.push(site_bel(loc.bel()));
+            }
+        }
+    }
+    Ok(bel)
+}
+
+fn ids(expr: &Expr) -> Vec<Id> {
+    let term: Vec<ExprTerm> = expr.clone().into();
+    term.iter().filter_map(|t| t.get_id().ok()).collect()
+}
+
+fn find(parent: &mut [usize], a: usize) -> usize {
+    let mut root = a;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[a] = root;
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        parent[b] = a;
+    }
+}
+
+// a group moves with one x and one y variable
+fn join(var: &mut Option<usize>, coord: &Coord, name: &str) -> Result<(), Error> {
+    match (*var, coord.var()) {
+        (Some(u), Some(v)) if u != v => {
+            let msg = format!(
+                "Instructions placed with {} need a single x and y coordinate",
+                name
+            );
+            Err(Error::new_place_error(&msg))
+        }
+        (None, Some(v)) => {
+            *var = Some(v);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Netlist {
+    pub fn cell(&self) -> &Vec<Cell> {
+        &self.cell
+    }
+    pub fn group(&self) -> &Vec<Group> {
+        &self.group
+    }
+    pub fn net(&self) -> &Vec<Vec<usize>> {
+        &self.net
+    }
+    pub fn cell_net(&self, cell: usize) -> &Vec<usize> {
+        &self.cell_net[cell]
+    }
+    pub fn vars(&self) -> usize {
+        self.vars
+    }
+    // every cell takes a whole site
+    pub fn from_prog(prog: &Prog) -> Result<Netlist, Error> {
+        Netlist::from_prog_bel(prog, None)
+    }
+    // cells take the bels of their implementation in the target
+    pub fn from_prog_with_target(prog: &Prog, target: &xim::Target) -> Result<Netlist, Error> {
+        Netlist::from_prog_bel(prog, Some(target))
+    }
+    fn from_prog_bel(prog: &Prog, target: Option<&xim::Target>) -> Result<Netlist, Error> {
+        let mut vars = Vars::default();
+        let mut cell: Vec<Cell> = Vec::new();
+        // signals joined by wire instructions form a single net
+        let mut signal: HashMap<Id, usize> = HashMap::new();
+        let mut parent: Vec<usize> = Vec::new();
+        let mut index = |id: Id, parent: &mut Vec<usize>| {
+            let len = signal.len();
+            let s = *signal.entry(id).or_insert(len);
+            if s == parent.len() {
+                parent.push(s);
+            }
+            s
+        };
+        let mut pin: Vec<Vec<usize>> = Vec::new();
+        for (i, instr) in prog.body().iter().enumerate() {
+            match instr {
+                Instr::Wire(instr) => {
+                    let mut s: Vec<usize> = Vec::new();
+                    for id in ids(instr.dst()).into_iter().chain(ids(instr.arg())) {
+                        s.push(index(id, &mut parent));
+                    }
+                    for w in s.windows(2) {
+                        union(&mut parent, w[0], w[1]);
+                    }
+                }
+                Instr::Asm(instr) => {
+                    let loc = instr.loc();
+                    let mut s: Vec<usize> = Vec::new();
+                    for id in ids(instr.dst()).into_iter().chain(ids(instr.arg())) {
+                        s.push(index(id, &mut parent));
+                    }
+                    pin.push(s);
+                    cell.push(Cell {
+                        index: i,
+                        name: instr.dst().get_id(0).unwrap_or_default(),
+                        prim: loc.prim().clone(),
+                        x: vars.coord(loc.x(), 'x')?,
+                        y: vars.coord(loc.y(), 'y')?,
+                        bel: match target {
+                            Some(target) => instr_bel(instr, target)?,
+                            None => Vec::new(),
+                        },
+                        group: 0,
+                    });
+                }
+            }
+        }
+        let mut net_of: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (c, s) in pin.iter().enumerate() {
+            for p in s {
+                let root = find(&mut parent, *p);
+                let net = net_of.entry(root).or_default();
+                if !net.contains(&c) {
+                    net.push(c);
+                }
+            }
+        }
+        let mut net: Vec<Vec<usize>> = net_of.into_iter().map(|(_, n)| n).collect();
+        net.retain(|n| n.len() > 1);
+        net.sort();
+        let mut cell_net: Vec<Vec<usize>> = vec![Vec::new(); cell.len()];
+        for (n, cells) in net.iter().enumerate() {
+            for c in cells {
+                cell_net[*c].push(n);
+            }
+        }
+        // cells sharing a variable belong to the same group
+        let mut parent: Vec<usize> = (0..cell.len()).collect();
+        let mut first: HashMap<usize, usize> = HashMap::new();
+        for (c, cl) in cell.iter().enumerate() {
+            for v in cl.x.var().into_iter().chain(cl.y.var()) {
+                let f = *first.entry(v).or_insert(c);
+                union(&mut parent, f, c);
+            }
+        }
+        let mut group: Vec<Group> = Vec::new();
+        let mut group_of: HashMap<usize, usize> = HashMap::new();
+        for (c, cl) in cell.iter_mut().enumerate() {
+            let root = find(&mut parent, c);
+            let len = group.len();
+            let g = *group_of.entry(root).or_insert(len);
+            if g == group.len() {
+                group.push(Group::default());
+            }
+            cl.group = g;
+            group[g].cell.push(c);
+            join(&mut group[g].x, &cl.x, &cl.name)?;
+            join(&mut group[g].y, &cl.y, &cl.name)?;
+        }
+        Ok(Netlist {
+            cell,
+            group,
+            net,
+            cell_net,
+            vars: vars.axis.len(),
+        })
+    }
+}
@@ -0,0 +1,166 @@
+use crate::netlist::{overlap, Netlist};
+use asm::ast::Prim;
+use device::{site_prim, Device};
+use std::collections::{BTreeSet, HashMap};
+
+// Values of the x and y variables of a group
+pub type Anchor = (Option<u64>, Option<u64>);
+
+pub type Site = (u64, u64);
+
+#[derive(Clone, Debug, Default)]
+pub struct Placement {
+    pub anchor: Vec<Option<Anchor>>,
+    pub site: Vec<Option<Site>>,
+    pub used: HashMap<(Prim, Site), Vec<usize>>,
+}
+
+// half-perimeter of the bounding box of the placed cells of a net
+pub fn hpwl(site: &[Option<Site>]) -> u64 {
+    let mut bbox: Option<(u64, u64, u64, u64)> = None;
+    for (x, y) in site.iter().flatten() {
+        bbox = Some(match bbox {
+            None => (*x, *x, *y, *y),
+            Some((x0, x1, y0, y1)) => (x0.min(*x), x1.max(*x), y0.min(*y), y1.max(*y)),
+        });
+    }
+    bbox.map_or(0, |(x0, x1, y0, y1)| (x1 - x0) + (y1 - y0))
+}
+
+// sites of the cells of a group at an anchor, if they all exist on the device
+// and cells of the group sharing a site do not overlap
+pub fn group_sites(
+    netlist: &Netlist,
+    device: &Device,
+    group: usize,
+    anchor: &Anchor,
+) -> Option<Vec<Site>> {
+    let cell = netlist.group()[group].cell();
+    let mut res: Vec<Site> = Vec::new();
+    for (i, c) in cell.iter().enumerate() {
+        let cl = &netlist.cell()[*c];
+        let site = (cl.x().eval(anchor.0)?, cl.y().eval(anchor.1)?);
+        if !device.contains(cl.prim(), site.0, site.1) {
+            return None;
+        }
+        let clash = cell[..i].iter().zip(res.iter()).any(|(o, s)| {
+            let other = &netlist.cell()[*o];
+            *s == site
+                && site_prim(other.prim()) == site_prim(cl.prim())
+                && overlap(cl.bel(), other.bel())
+        });
+        if clash {
+            return None;
+        }
+        res.push(site);
+    }
+    Some(res)
+}
+
+// anchors putting every cell of a group on a site of its prim, lowest first
+pub fn candidates(netlist: &Netlist, device: &Device, group: usize) -> Vec<Anchor> {
+    let g = &netlist.group()[group];
+    let mut xs: BTreeSet<u64> = BTreeSet::new();
+    let mut ys: BTreeSet<u64> = BTreeSet::new();
+    for c in g.cell() {
+        let cell = &netlist.cell()[*c];
+        for x in device.columns(cell.prim()) {
+            if let Some(Some(v)) = cell.x().solve(x) {
+                xs.insert(v);
+            }
+        }
+        for y in device.rows(cell.prim()) {
+            if let Some(Some(v)) = cell.y().solve(y) {
+                ys.insert(v);
+            }
+        }
+    }
+    let xs: Vec<Option<u64>> = match g.x() {
+        Some(_) => xs.into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let ys: Vec<Option<u64>> = match g.y() {
+        Some(_) => ys.into_iter().map(Some).collect(),
+        None => vec![None],
+    };
+    let mut res: Vec<Anchor> = Vec::new();
+    for x in xs.iter() {
+        for y in ys.iter() {
+            let anchor = (*x, *y);
+            if group_sites(netlist, device, group, &anchor).is_some() {
+                res.push(anchor);
+            }
+        }
+    }
+    res
+}
+
+impl Placement {
+    pub fn new(netlist: &Netlist) -> Self {
+        Placement {
+            anchor: vec![None; netlist.group().len()],
+            site: vec![None; netlist.cell().len()],
+            used: HashMap::new(),
+        }
+    }
+    pub fn anchor(&self, group: usize) -> Option<&Anchor> {
+        self.anchor[group].as_ref()
+    }
+    pub fn site(&self, cell: usize) -> Option<&Site> {
+        self.site[cell].as_ref()
+    }
+    // cells on a site
+    pub fn occupants(&self, prim: &Prim, site: &Site) -> &[usize] {
+        self.used
+            .get(&(site_prim(prim), *site))
+            .map_or(&[], |o| o.as_slice())
+    }
+    // cells of other groups a cell would overlap with on a site
+    pub fn clashes(&self, netlist: &Netlist, cell: usize, site: &Site) -> Vec<usize> {
+        let cl = &netlist.cell()[cell];
+        self.occupants(cl.prim(), site)
+            .iter()
+            .filter(|o| {
+                let other = &netlist.cell()[**o];
+                other.group() != cl.group() && overlap(cl.bel(), other.bel())
+            })
+            .cloned()
+            .collect()
+    }
+    // no cell of the group overlaps with a cell of another group
+    pub fn is_free(&self, netlist: &Netlist, group: usize, site: &[Site]) -> bool {
+        netlist.group()[group]
+            .cell()
+            .iter()
+            .zip(site.iter())
+            .all(|(c, s)| self.clashes(netlist, *c, s).is_empty())
+    }
+    pub fn insert(&mut self, netlist: &Netlist, group: usize, anchor: Anchor, site: &[Site]) {
+        self.anchor[group] = Some(anchor);
+        for (c, s) in netlist.group()[group].cell().iter().zip(site.iter()) {
+            let prim = site_prim(netlist.cell()[*c].prim());
+            self.site[*c] = Some(*s);
+            self.used.entry((prim, *s)).or_default().push(*c);
+        }
+    }
+    pub fn remove(&mut self, netlist: &Netlist, group: usize) {
+        self.anchor[group] = None;
+        for c in netlist.group()[group].cell() {
+            if let Some(s) = self.site[*c].take() {
+                let prim = site_prim(netlist.cell()[*c].prim());
+                if let Some(o) = self.used.get_mut(&(prim, s)) {
+                    o.retain(|o| o != c);
+                }
+            }
+        }
+    }
+    pub fn net_cost(&self, netlist: &Netlist, net: usize) -> u64 {
+        let site: Vec<Option<Site>> = netlist.net()[net].iter().map(|c| self.site[*c]).collect();
+        hpwl(&site)
+    }
+    pub fn cost(&self, netlist: &Netlist) -> u64 {
+        (0..netlist.net().len())
+            .map(|n| self.net_cost(netlist, n))
+            .sum()
+    }
+}
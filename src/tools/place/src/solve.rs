@@ -0,0 +1,432 @@
+use crate::errors::Error;
+use asm::ast::*;
+use device::{site_prim, Device};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+// Values tried before the search gives up
+const BUDGET: u64 = 1_000_000;
+
+// Constraint on the locations of a program, as written in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Constraint {
+    // the instruction is on a site of its prim
+    Site {
+        instr: Id,
+        prim: Prim,
+        x: ExprCoord,
+        y: ExprCoord,
+    },
+    // two instructions are on different sites
+    Distinct {
+        instr: Id,
+        other: Id,
+    },
+    // a coordinate from lo to hi, lo..hi
+    Range {
+        instr: Id,
+        expr: ExprCoord,
+    },
+    // a coordinate that is a multiple of k, a%k
+    Align {
+        instr: Id,
+        expr: ExprCoord,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Solution {
+    // every coordinate except ?? bound to a value
+    Sat(Prog),
+    // constraints that cannot hold together
+    Unsat(Vec<Constraint>),
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Site { instr, prim, x, y } => {
+                write!(f, "{} is on a {} site at ({}, {})", instr, prim, x, y)
+            }
+            Constraint::Distinct { instr, other } => {
+                write!(f, "{} and {} are on different sites", instr, other)
+            }
+            Constraint::Range {
+                instr,
+                expr: ExprCoord::Bin(_, lo, hi),
+            } => write!(f, "{} has a coordinate from {} to {}", instr, lo, hi),
+            Constraint::Align {
+                instr,
+                expr: ExprCoord::Bin(_, a, k),
+            } => write!(f, "{} has coordinate {} aligned to {}", instr, a, k),
+            Constraint::Range { instr, expr } | Constraint::Align { instr, expr } => {
+                write!(f, "{} has coordinate {}", instr, expr)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Term {
+    Val(u64),
+    Var(usize),
+    Add(Box<Term>, Box<Term>),
+    Sub(Box<Term>, Box<Term>),
+    Mul(Box<Term>, Box<Term>),
+}
+
+impl Term {
+    // none when the value is out of the coordinates, such as 0-1
+    fn eval(&self, value: &[Option<u64>]) -> Option<u64> {
+        match self {
+            Term::Val(v) => Some(*v),
+            Term::Var(v) => value[*v],
+            Term::Add(a, b) => a.eval(value)?.checked_add(b.eval(value)?),
+            Term::Sub(a, b) => a.eval(value)?.checked_sub(b.eval(value)?),
+            Term::Mul(a, b) => a.eval(value)?.checked_mul(b.eval(value)?),
+        }
+    }
+    fn vars(&self, var: &mut BTreeSet<usize>) {
+        match self {
+            Term::Val(_) => (),
+            Term::Var(v) => {
+                var.insert(*v);
+            }
+            Term::Add(a, b) | Term::Sub(a, b) | Term::Mul(a, b) => {
+                a.vars(var);
+                b.vars(var);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Site(Prim, Term, Term),
+    Distinct((Term, Term), (Term, Term)),
+    Range(Term, Term, Term),
+    Align(Term, Term),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    kind: Kind,
+    var: BTreeSet<usize>,
+    constraint: Constraint,
+}
+
+impl Rule {
+    fn new(kind: Kind, constraint: Constraint) -> Rule {
+        let mut var = BTreeSet::new();
+        match &kind {
+            Kind::Site(_, x, y) => {
+                x.vars(&mut var);
+                y.vars(&mut var);
+            }
+            Kind::Distinct((ax, ay), (bx, by)) => {
+                for t in [ax, ay, bx, by].iter() {
+                    t.vars(&mut var);
+                }
+            }
+            Kind::Range(t, lo, hi) => {
+                for t in [t, lo, hi].iter() {
+                    t.vars(&mut var);
+                }
+            }
+            Kind::Align(t, k) => {
+                t.vars(&mut var);
+                k.vars(&mut var);
+            }
+        }
+        Rule {
+            kind,
+            var,
+            constraint,
+        }
+    }
+    fn holds(&self, device: &Device, value: &[Option<u64>]) -> bool {
+        let eval = |t: &Term| t.eval(value);
+        match &self.kind {
+            Kind::Site(prim, x, y) => match (eval(x), eval(y)) {
+                (Some(x), Some(y)) => device.contains(prim, x, y),
+                _ => false,
+            },
+            Kind::Distinct((ax, ay), (bx, by)) => (eval(ax), eval(ay)) != (eval(bx), eval(by)),
+            Kind::Range(t, lo, hi) => match (eval(t), eval(lo), eval(hi)) {
+                (Some(t), Some(lo), Some(hi)) => lo <= t && t <= hi,
+                _ => false,
+            },
+            Kind::Align(t, k) => match (eval(t), eval(k)) {
+                (Some(t), Some(k)) => k > 0 && t % k == 0,
+                _ => false,
+            },
+        }
+    }
+}
+
+// Why a search failed, the positions of the variables involved in the
+// failure, or running out of budget
+enum Fail {
+    Conflict(BTreeSet<usize>),
+    Budget,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Problem {
+    name: HashMap<Id, usize>,
+    // largest value of every variable
+    domain: Vec<u64>,
+    rule: Vec<Rule>,
+    // coordinates of every asm instruction, none for ??
+    loc: Vec<(usize, Option<Term>, Option<Term>)>,
+}
+
+impl Problem {
+    fn fresh(&mut self, max: u64) -> usize {
+        self.domain.push(max);
+        self.domain.len() - 1
+    }
+    fn term(&mut self, expr: &ExprCoord, max: u64, instr: &str) -> Term {
+        match expr {
+            ExprCoord::Any => Term::Var(self.fresh(max)),
+            ExprCoord::Val(v) => Term::Val(*v),
+            ExprCoord::Var(n) => {
+                let v = match self.name.get(n) {
+                    Some(v) => *v,
+                    None => {
+                        let v = self.fresh(max);
+                        self.name.insert(n.to_string(), v);
+                        v
+                    }
+                };
+                self.domain[v] = self.domain[v].max(max);
+                Term::Var(v)
+            }
+            ExprCoord::Bin(op, a, b) => {
+                let (ta, tb) = (self.term(a, max, instr), self.term(b, max, instr));
+                match op {
+                    OpCoord::Add => Term::Add(Box::new(ta), Box::new(tb)),
+                    OpCoord::Sub => Term::Sub(Box::new(ta), Box::new(tb)),
+                    OpCoord::Mul => Term::Mul(Box::new(ta), Box::new(tb)),
+                    OpCoord::Range => {
+                        let t = Term::Var(self.fresh(max));
+                        let constraint = Constraint::Range {
+                            instr: instr.to_string(),
+                            expr: expr.clone(),
+                        };
+                        let kind = Kind::Range(t.clone(), ta, tb);
+                        self.rule.push(Rule::new(kind, constraint));
+                        t
+                    }
+                    OpCoord::Align => {
+                        let constraint = Constraint::Align {
+                            instr: instr.to_string(),
+                            expr: expr.clone(),
+                        };
+                        let kind = Kind::Align(ta.clone(), tb);
+                        self.rule.push(Rule::new(kind, constraint));
+                        ta
+                    }
+                }
+            }
+        }
+    }
+    fn from_prog(prog: &Prog, device: &Device) -> Problem {
+        let mut problem = Problem::default();
+        let (max_x, max_y) = device.extent();
+        // cells on the same kind of site, with their names and coordinates
+        let mut cell: Vec<(Prim, Id, Term, Term)> = Vec::new();
+        for (index, instr) in prog.body().iter().enumerate() {
+            if let Instr::Asm(instr) = instr {
+                let name = instr.dst().get_id(0).unwrap_or_default();
+                let loc = instr.loc();
+                let x = problem.term(loc.x(), max_x, &name);
+                let y = problem.term(loc.y(), max_y, &name);
+                if loc.prim() != &Prim::Any {
+                    let constraint = Constraint::Site {
+                        instr: name.clone(),
+                        prim: loc.prim().clone(),
+                        x: loc.x().clone(),
+                        y: loc.y().clone(),
+                    };
+                    let kind = Kind::Site(loc.prim().clone(), x.clone(), y.clone());
+                    problem.rule.push(Rule::new(kind, constraint));
+                }
+                // a ?? is left to the placer, which keeps sites distinct
+                let (any_x, any_y) = (loc.x().is_any(), loc.y().is_any());
+                if !any_x && !any_y {
+                    let prim = site_prim(loc.prim());
+                    for (p, other, ox, oy) in cell.iter() {
+                        if *p == prim {
+                            let constraint = Constraint::Distinct {
+                                instr: other.clone(),
+                                other: name.clone(),
+                            };
+                            let kind =
+                                Kind::Distinct((ox.clone(), oy.clone()), (x.clone(), y.clone()));
+                            problem.rule.push(Rule::new(kind, constraint));
+                        }
+                    }
+                    cell.push((prim, name.clone(), x.clone(), y.clone()));
+                }
+                let x = if any_x { None } else { Some(x) };
+                let y = if any_y { None } else { Some(y) };
+                problem.loc.push((index, x, y));
+            }
+        }
+        problem
+    }
+    // Conflict-directed backjumping over the variables in order, where each
+    // rule is checked once its last variable has a value
+    fn search(
+        &self,
+        device: &Device,
+        check: &[Vec<usize>],
+        value: &mut Vec<Option<u64>>,
+        pos: usize,
+        count: &mut u64,
+    ) -> Result<(), Fail> {
+        if pos == self.domain.len() {
+            return Ok(());
+        }
+        let mut conflict: BTreeSet<usize> = BTreeSet::new();
+        for val in 0..=self.domain[pos] {
+            *count += 1;
+            if *count > BUDGET {
+                return Err(Fail::Budget);
+            }
+            value[pos] = Some(val);
+            if let Some(r) = check[pos]
+                .iter()
+                .find(|r| !self.rule[**r].holds(device, value))
+            {
+                conflict.extend(self.rule[*r].var.iter().filter(|v| **v != pos));
+                continue;
+            }
+            match self.search(device, check, value, pos + 1, count) {
+                Ok(()) => return Ok(()),
+                Err(Fail::Conflict(c)) if c.contains(&pos) => {
+                    conflict.extend(c.into_iter().filter(|v| *v != pos));
+                }
+                Err(fail) => {
+                    value[pos] = None;
+                    return Err(fail);
+                }
+            }
+        }
+        value[pos] = None;
+        Err(Fail::Conflict(conflict))
+    }
+    // values of the variables when the active rules can hold, none when
+    // they cannot, or an error when the search runs out of budget
+    fn solve(&self, device: &Device, active: &[usize]) -> Result<Option<Vec<u64>>, Error> {
+        let mut value: Vec<Option<u64>> = vec![None; self.domain.len()];
+        let mut check: Vec<Vec<usize>> = vec![Vec::new(); self.domain.len()];
+        for r in active {
+            match self.rule[*r].var.iter().next_back() {
+                Some(last) => check[*last].push(*r),
+                None if !self.rule[*r].holds(device, &value) => return Ok(None),
+                None => (),
+            }
+        }
+        let mut count = 0;
+        match self.search(device, &check, &mut value, 0, &mut count) {
+            Ok(()) => Ok(Some(value.into_iter().map(|v| v.unwrap_or(0)).collect())),
+            Err(Fail::Conflict(_)) => Ok(None),
+            Err(Fail::Budget) => Err(Error::new_place_error(
+                "Coordinate constraints are too hard to solve",
+            )),
+        }
+    }
+    fn is_unsat(&self, device: &Device, active: &[usize]) -> bool {
+        matches!(self.solve(device, active), Ok(None))
+    }
+    // Drop chunks of rules while the rest still cannot hold, halving the
+    // chunks down to single rules
+    fn unsat_core(&self, device: &Device, active: &[usize]) -> Vec<usize> {
+        let mut core = active.to_vec();
+        let mut chunk = (core.len() / 2).max(1);
+        loop {
+            let mut i = 0;
+            while i < core.len() {
+                let end = (i + chunk).min(core.len());
+                let trial: Vec<usize> = core[..i].iter().chain(&core[end..]).cloned().collect();
+                if self.is_unsat(device, &trial) {
+                    core = trial;
+                } else {
+                    i = end;
+                }
+            }
+            if chunk == 1 {
+                return core;
+            }
+            chunk = (chunk / 2).max(1);
+        }
+    }
+}
+
+// Collect the coordinates of an asm program as constraints and bind every
+// variable, range and alignment to values that fit the device
+pub fn solve_prog(prog: &Prog, device: &Device) -> Result<Solution, Error> {
+    let problem = Problem::from_prog(prog, device);
+    let all: Vec<usize> = (0..problem.rule.len()).collect();
+    let value = match problem.solve(device, &all)? {
+        Some(value) => value,
+        None => {
+            let core = problem.unsat_core(device, &all);
+            let core = core
+                .iter()
+                .map(|r| problem.rule[*r].constraint.clone())
+                .collect();
+            return Ok(Solution::Unsat(core));
+        }
+    };
+    let value: Vec<Option<u64>> = value.into_iter().map(Some).collect();
+    let mut prog = prog.clone();
+    for (index, x, y) in problem.loc.iter() {
+        if let Instr::Asm(instr) = &mut prog.body_mut()[*index] {
+            let mut loc = instr.loc().clone();
+            if let Some(x) = x.as_ref().and_then(|x| x.eval(&value)) {
+                loc.set_x(ExprCoord::Val(x));
+            }
+            if let Some(y) = y.as_ref().and_then(|y| y.eval(&value)) {
+                loc.set_y(ExprCoord::Val(y));
+            }
+            instr.set_loc(loc);
+        }
+    }
+    Ok(Solution::Sat(prog))
+}
+
+// Program with bound coordinates, or an error listing the constraints that
+// cannot hold together
+pub fn bind_prog(prog: &Prog, device: &Device) -> Result<Prog, Error> {
+    match solve_prog(prog, device)? {
+        Solution::Sat(prog) => Ok(prog),
+        Solution::Unsat(core) => {
+            let core: Vec<String> = core.iter().map(|c| format!("    {}", c)).collect();
+            let msg = format!("Unsatisfiable coordinate constraints:\n{}", core.join("\n"));
+            Err(Error::new_place_error(&msg))
+        }
+    }
+}
+
+// a value, a variable, ?? or one of them plus a value, which the placer
+// handles without solving
+pub fn is_offset(expr: &ExprCoord) -> bool {
+    match expr {
+        ExprCoord::Bin(OpCoord::Add, a, b) => {
+            is_offset(a) && is_offset(b) && (a.vars().is_empty() || b.vars().is_empty())
+        }
+        ExprCoord::Bin(_, _, _) => false,
+        _ => true,
+    }
+}
+
+// every location is made of offsets, so there is nothing to solve
+pub fn is_solved(prog: &Prog) -> bool {
+    prog.body().iter().all(|instr| match instr {
+        Instr::Asm(instr) => is_offset(instr.loc().x()) && is_offset(instr.loc().y()),
+        _ => true,
+    })
+}
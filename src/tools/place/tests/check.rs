@@ -0,0 +1,172 @@
+use asm::parser::Parser as AsmParser;
+use device::Device;
+use ir::ast::Prim;
+use place::check::{check_asm_prog, check_xir_prog, Violation};
+use place::errors::Error;
+use place::{place_prog, place_prog_with_target, Mode};
+use std::fs;
+use xim::ast::Target;
+use xim::parser::Parser as XimParser;
+use xir::parser::Parser as XirParser;
+
+fn device() -> Device {
+    Device::from_file("../../../dev/xczu3eg-sbva484.csv").unwrap()
+}
+
+fn target() -> Target {
+    let mut target = Target::default();
+    for name in ["lut", "dsp", "mem"].iter() {
+        let path = format!("../../../examples/xim/{}.xim", name);
+        target.extend(XimParser::parse_from_file(path).unwrap());
+    }
+    target
+}
+
+fn check_asm(prog: &str) -> Result<Vec<Violation>, Error> {
+    let prog = AsmParser::parse_from_str(prog)?;
+    check_asm_prog(&prog, &device(), &target())
+}
+
+#[test]
+fn examples() -> Result<(), Error> {
+    let (device, target) = (device(), target());
+    for entry in fs::read_dir("../../../examples/asm").unwrap() {
+        let prog = AsmParser::parse_from_file(entry.unwrap().path())?;
+        assert_eq!(check_asm_prog(&prog, &device, &target)?, vec![]);
+        let placed = place_prog(&prog, &device, &Mode::Greedy)?;
+        assert_eq!(check_asm_prog(&placed, &device, &target)?, vec![]);
+        for mode in [Mode::Greedy, Mode::Anneal].iter() {
+            let placed = place_prog_with_target(&prog, &device, mode, &target)?;
+            assert_eq!(check_asm_prog(&placed, &device, &target)?, vec![]);
+        }
+    }
+    let prog = XirParser::parse_from_file("../../../examples/xir/addreduce_placed.xir").unwrap();
+    assert_eq!(check_xir_prog(&prog, &device)?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn overlap() -> Result<(), Error> {
+    let res = check_asm(
+        "def main(a:bool, b:bool, c:i8, d:i8, en:bool) -> (y:bool, z:i8) {
+            t0:bool = land_bool(a, b) @lut(3, 4);
+            y:bool = lnot_bool(t0) @lut(3, 4);
+            z:i8 = laddrega_i8(c, d, en) @lut(3, 5);
+        }",
+    )?;
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].instr(), "y");
+    assert_eq!(
+        res[0].to_string(),
+        "t0 and y are both placed on bel a6 at (3, 4)"
+    );
+    let res = check_xir_prog(
+        &XirParser::parse_from_str(
+            "def main(a:bool, b:bool) -> (y:bool) {
+                t0:bool = lut2[8](a, b) @a6(x0, y0);
+                t1:bool = lut2[6](a, b) @b6(x0, y0);
+                y:bool = lut2[6](t0, t1) @a6(x0, y0);
+            }",
+        )
+        .unwrap(),
+        &device(),
+    )?;
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].instr(), "y");
+    Ok(())
+}
+
+// the placer shares a slice only between instructions on different bels
+#[test]
+fn share() -> Result<(), Error> {
+    let (device, target) = (device(), target());
+    let prog = AsmParser::parse_from_str(
+        "def main(a:bool, b:bool, c:i2, en:bool) -> (y:bool, z:bool, w:i2) {
+            y:bool = land_bool(a, b) @lut(??, ??);
+            z:bool = land_bool(y, b) @lut(??, ??);
+            w:i2 = lrega_i2(c, en) @lut(??, ??);
+        }",
+    )?;
+    for mode in [Mode::Greedy, Mode::Anneal].iter() {
+        let placed = place_prog_with_target(&prog, &device, mode, &target)?;
+        assert_eq!(check_asm_prog(&placed, &device, &target)?, vec![]);
+        let loc: Vec<String> = placed.body().iter().map(|i| i.to_string()).collect();
+        let site = |i: usize| loc[i].rsplit('@').next().unwrap().to_string();
+        assert_ne!(site(0), site(1));
+        if *mode == Mode::Greedy {
+            assert_eq!(site(0), site(2));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn site() -> Result<(), Error> {
+    let res = check_asm(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8, z:i8) {
+            y:i8 = dmuladdrega_i8i8(a, b, c, en, en, en, en) @dsp(6, 10);
+            z:i8 = laddrega_i8(a, b, en) @lut(0, 500);
+            t0:bool = land_bool(en, en) @dsp(0, 0);
+        }",
+    )?;
+    assert_eq!(
+        res[0],
+        Violation::Loc {
+            instr: "t0".to_string(),
+            prim: Prim::Lut,
+            loc: Prim::Dsp,
+        }
+    );
+    assert_eq!(
+        res[1],
+        Violation::Prim {
+            instr: "y".to_string(),
+            prim: Prim::Dsp,
+            x: 6,
+            y: 10,
+            site: vec![Prim::Lut],
+        }
+    );
+    assert_eq!(
+        res[2],
+        Violation::Bounds {
+            instr: "z".to_string(),
+            prim: Prim::Lut,
+            x: 0,
+            y: 500,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn cascade() -> Result<(), Error> {
+    let res = check_asm(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8) {
+            y:i8 = dmuladdregaci_i8i8(a, b, t1, en, en, en, en) @dsp(x0, y0+3);
+            t1:i8 = dmuladdregacio_i8i8(a, b, t0, en, en, en, en) @dsp(x0, y0+1);
+            t0:i8 = dmuladdregaco_i8i8(a, b, c, en, en, en, en) @dsp(x0, y0);
+        }",
+    )?;
+    assert_eq!(
+        res,
+        vec![Violation::Cascade {
+            instr: "y".to_string(),
+            driver: Some("t1".to_string()),
+        }]
+    );
+    let res = check_asm(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8) {
+            y:i8 = dmuladdregaci_i8i8(a, b, t0, en, en, en, en) @dsp(1, 5);
+            t0:i8 = dmuladdrega_i8i8(a, b, c, en, en, en, en) @dsp(1, 4);
+        }",
+    )?;
+    assert_eq!(
+        res,
+        vec![Violation::Cascade {
+            instr: "y".to_string(),
+            driver: None,
+        }]
+    );
+    Ok(())
+}
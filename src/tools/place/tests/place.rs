@@ -0,0 +1,123 @@
+use asm::ast::{ExprCoord, Instr, Prog};
+use asm::parser::Parser as AsmParser;
+use device::{site_prim, Device};
+use place::errors::Error;
+use place::{place_prog, wirelength, Mode};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::str::FromStr;
+
+fn device() -> Device {
+    Device::from_file("../../../dev/xczu3eg-sbva484.csv").unwrap()
+}
+
+fn parse(name: &str) -> Prog {
+    AsmParser::parse_from_file(format!("../../../examples/asm/{}.asm", name)).unwrap()
+}
+
+fn val(coord: &ExprCoord) -> u64 {
+    match coord {
+        ExprCoord::Val(v) => *v,
+        _ => panic!("{} is not placed", coord),
+    }
+}
+
+// sites of the instructions, checked to be distinct sites of their prim
+fn sites(prog: &Prog, device: &Device) -> HashMap<String, (u64, u64)> {
+    let mut used = HashSet::new();
+    let mut res = HashMap::new();
+    for instr in prog.body() {
+        if let Instr::Asm(instr) = instr {
+            let loc = instr.loc();
+            let site = (val(loc.x()), val(loc.y()));
+            assert!(device.contains(loc.prim(), site.0, site.1), "{}", instr);
+            assert!(used.insert((site_prim(loc.prim()), site)), "{}", instr);
+            res.insert(instr.dst().get_id(0).unwrap(), site);
+        }
+    }
+    res
+}
+
+#[test]
+fn examples() {
+    let device = device();
+    for entry in fs::read_dir("../../../examples/asm").unwrap() {
+        let path = entry.unwrap().path();
+        let prog = AsmParser::parse_from_file(&path).unwrap();
+        let res = place_prog(&prog, &device, &Mode::Greedy).unwrap();
+        assert_eq!(res.body().len(), prog.body().len());
+        sites(&res, &device);
+    }
+}
+
+#[test]
+fn fixed() -> Result<(), Error> {
+    let prog = parse("addreduce_placed");
+    let res = place_prog(&prog, &device(), &Mode::Anneal)?;
+    assert_eq!(res, prog);
+    Ok(())
+}
+
+#[test]
+fn cascade() -> Result<(), Error> {
+    let device = device();
+    let prog = parse("tdot_5_3_opt");
+    for mode in [Mode::Greedy, Mode::Anneal].iter() {
+        let site = sites(&place_prog(&prog, &device, mode)?, &device);
+        for chain in [["t5", "t11", "v"], ["t77", "t83", "z"]].iter() {
+            let (x, y) = site[chain[0]];
+            assert_eq!(site[chain[1]], (x, y + 1));
+            assert_eq!(site[chain[2]], (x, y + 2));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn anneal() -> Result<(), Error> {
+    let device = device();
+    for name in ["fsm_9", "mux128", "tdot_5_9"].iter() {
+        let prog = parse(name);
+        let greedy = wirelength(&place_prog(&prog, &device, &Mode::Greedy)?)?;
+        let anneal = wirelength(&place_prog(&prog, &device, &Mode::Anneal)?)?;
+        assert!(anneal <= greedy, "{}: {} > {}", name, anneal, greedy);
+    }
+    let prog = parse("fsm_9");
+    let greedy = wirelength(&place_prog(&prog, &device, &Mode::Greedy)?)?;
+    let anneal = wirelength(&place_prog(&prog, &device, &Mode::Anneal)?)?;
+    assert!(anneal < greedy);
+    Ok(())
+}
+
+#[test]
+fn partial() -> Result<(), Error> {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = laddrega_i8(a, b, en) @lut(2, ??);
+            y:i8 = laddrega_i8(t0, b, en) @lut(??, 5);
+        }",
+    )?;
+    let site = sites(&place_prog(&prog, &device(), &Mode::Greedy)?, &device());
+    assert_eq!(site["t0"].0, 2);
+    assert_eq!(site["y"].1, 5);
+    Ok(())
+}
+
+#[test]
+fn error() -> Result<(), Error> {
+    let device = Device::from_str("x,y,prim,raw\n0,0,LUT,SLICE_X0Y0\n0,0,DSP,DSP48E2_X0Y0\n")?;
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = laddrega_i8(a, b, en) @lut(??, ??);
+            y:i8 = laddrega_i8(t0, b, en) @lut(??, ??);
+        }",
+    )?;
+    assert!(place_prog(&prog, &device, &Mode::Greedy).is_err());
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            y:i8 = laddrega_i8(a, b, en) @lut(1, 0);
+        }",
+    )?;
+    assert!(place_prog(&prog, &device, &Mode::Greedy).is_err());
+    Ok(())
+}
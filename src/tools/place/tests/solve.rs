@@ -0,0 +1,113 @@
+use asm::ast::{ExprCoord, Instr, Prog};
+use asm::parser::Parser as AsmParser;
+use device::Device;
+use place::errors::Error;
+use place::solve::{bind_prog, solve_prog, Constraint, Solution};
+use place::{place_prog, Mode};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// four lut columns with eight rows each, dsp sites on two of them
+fn device() -> Device {
+    let mut csv = String::from("x,y,prim\n");
+    for x in 0..4 {
+        for y in 0..8 {
+            csv.push_str(&format!("{},{},LUT\n", x, y));
+        }
+    }
+    for y in 0..4 {
+        csv.push_str(&format!("1,{},DSP\n3,{},DSP\n", y, y));
+    }
+    Device::from_str(&csv).unwrap()
+}
+
+fn locs(prog: &Prog) -> HashMap<String, (ExprCoord, ExprCoord)> {
+    let mut res = HashMap::new();
+    for instr in prog.body() {
+        if let Instr::Asm(instr) = instr {
+            let loc = instr.loc();
+            let coord = (loc.x().clone(), loc.y().clone());
+            res.insert(instr.dst().get_id(0).unwrap(), coord);
+        }
+    }
+    res
+}
+
+fn site(coord: &(ExprCoord, ExprCoord)) -> (u64, u64) {
+    match coord {
+        (ExprCoord::Val(x), ExprCoord::Val(y)) => (*x, *y),
+        _ => panic!("({}, {}) is not solved", coord.0, coord.1),
+    }
+}
+
+#[test]
+fn print() -> Result<(), Error> {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = laddrega_i8(a, b, en) @lut(x*2-1, (y+1)%2);
+            y:i8 = laddrega_i8(t0, b, en) @lut(x-(1+1), 2..y+4);
+            z:i8 = laddrega_i8(t0, y, en) @lut(x+y%2*2, y);
+        }",
+    )?;
+    let loc = locs(&prog);
+    assert_eq!(loc["t0"].0.to_string(), "x*2-1");
+    assert_eq!(loc["t0"].1.to_string(), "(y+1)%2");
+    assert_eq!(loc["y"].0.to_string(), "x-(1+1)");
+    assert_eq!(loc["y"].1.to_string(), "2..y+4");
+    assert_eq!(loc["z"].0.to_string(), "x+y%2*2");
+    assert_eq!(AsmParser::parse_from_str(&prog.to_string())?, prog);
+    Ok(())
+}
+
+#[test]
+fn sat() -> Result<(), Error> {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = laddrega_i8(a, b, en) @lut(x*3, y%4);
+            t1:i8 = laddrega_i8(t0, b, en) @lut(x, y+2..7);
+            t2:i8 = laddrega_i8(t1, b, en) @dsp(x+2-2, 3-y);
+            y:i8 = laddrega_i8(t2, b, en) @lut(??, 6);
+        }",
+    )?;
+    let res = bind_prog(&prog, &device())?;
+    let loc = locs(&res);
+    let (x0, y) = site(&loc["t0"]);
+    assert_eq!(x0 % 3, 0);
+    assert_eq!(y % 4, 0);
+    let x = x0 / 3;
+    let (x1, y1) = site(&loc["t1"]);
+    assert_eq!(x1, x);
+    assert!(y + 2 <= y1 && y1 <= 7);
+    assert_eq!(site(&loc["t2"]), (x, 3 - y));
+    assert_eq!(loc["y"], (ExprCoord::Any, ExprCoord::Val(6)));
+    let res = place_prog(&prog, &device(), &Mode::Greedy)?;
+    assert_eq!(locs(&res)["t1"], loc["t1"]);
+    Ok(())
+}
+
+#[test]
+fn unsat() -> Result<(), Error> {
+    let prog = AsmParser::parse_from_str(
+        "def main(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = laddrega_i8(a, b, en) @lut(0, 0..3);
+            t1:i8 = laddrega_i8(t0, b, en) @dsp(x%2, 0);
+            t2:i8 = laddrega_i8(t1, b, en) @lut(0, 5..7);
+            y:i8 = laddrega_i8(t2, b, en) @lut(0, y*2-1);
+        }",
+    )?;
+    let core = match solve_prog(&prog, &device())? {
+        Solution::Unsat(core) => core,
+        Solution::Sat(prog) => panic!("{}", prog),
+    };
+    let instr: Vec<&str> = core
+        .iter()
+        .map(|c| match c {
+            Constraint::Site { instr, .. } | Constraint::Align { instr, .. } => instr.as_str(),
+            _ => "",
+        })
+        .collect();
+    assert_eq!(instr, vec!["t1", "t1"]);
+    assert!(bind_prog(&prog, &device()).is_err());
+    assert!(place_prog(&prog, &device(), &Mode::Greedy).is_err());
+    Ok(())
+}
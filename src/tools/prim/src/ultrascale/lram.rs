@@ -14,6 +14,12 @@ pub struct Lram(Prim<ParamValue>);
 #[derive(Clone, Debug, Default)]
 struct LramPrim;
 
+#[derive(Clone, Debug, Deref, DerefMut)]
+pub struct Lram256(Prim<ParamValue>);
+
+#[derive(Clone, Debug, Default)]
+struct Lram256Prim;
+
 impl PartialEq for ParamValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -82,3 +88,41 @@ impl Default for Lram {
         Lram(ram.to_prim())
     }
 }
+
+impl ToPrim<ParamValue> for Lram256Prim {
+    fn to_name(&self) -> String {
+        String::from("RAM256X1S")
+    }
+    fn to_param(&self) -> ParamSet<ParamValue> {
+        let mut param = ParamSet::new();
+        param.insert(Param {
+            name: "INIT".into(),
+            value: (256, vec![0; 32]).into(),
+        });
+        param.insert(Param {
+            name: "IS_WCLK_INVERTED".into(),
+            value: false.into(),
+        });
+        param
+    }
+    fn to_input(&self) -> PortSet {
+        let mut port = PortSet::new();
+        port.insert(Port::new("D", 1));
+        port.insert(Port::new("A", 8));
+        port.insert(Port::new("WE", 1));
+        port.insert(Port::new("WCLK", 1));
+        port
+    }
+    fn to_output(&self) -> PortSet {
+        let mut port = PortSet::new();
+        port.insert(Port::new("O", 1));
+        port
+    }
+}
+
+impl Default for Lram256 {
+    fn default() -> Self {
+        let ram = Lram256Prim;
+        Lram256(ram.to_prim())
+    }
+}
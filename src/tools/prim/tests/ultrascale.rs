@@ -354,6 +354,40 @@ mod test_lram {
         ];
         test_output(&prim, &output);
     }
+
+    #[test]
+    fn name_256() {
+        let prim = Lram256::default();
+        test_name(&prim, "RAM256X1S");
+    }
+
+    #[test]
+    fn param_256() {
+        let prim = Lram256::default();
+        let mut param = ParamSet::<ParamValue>::new();
+        param.insert(Param {
+            name: "INIT".into(),
+            value: (256, vec![0; 32]).into(),
+        });
+        param.insert(Param {
+            name: "IS_WCLK_INVERTED".into(),
+            value: false.into(),
+        });
+        test_param(&prim, &param);
+    }
+
+    #[test]
+    fn input_256() {
+        let prim = Lram256::default();
+        let input = [("D", 1), ("A", 8), ("WE", 1), ("WCLK", 1)];
+        test_input(&prim, &input);
+    }
+
+    #[test]
+    fn output_256() {
+        let prim = Lram256::default();
+        test_output(&prim, &[("O", 1)]);
+    }
 }
 
 mod test_uram {
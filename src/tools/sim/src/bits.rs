@@ -0,0 +1,57 @@
+// Values are kept as raw bits in a u128, truncated to the width of their type
+pub const MAX_WIDTH: u64 = 128;
+
+pub fn mask(width: u64) -> u128 {
+    if width >= MAX_WIDTH {
+        !0
+    } else {
+        (1 << width) - 1
+    }
+}
+
+pub fn trunc(bits: u128, width: u64) -> u128 {
+    bits & mask(width)
+}
+
+pub fn sign_extend(bits: u128, width: u64) -> i128 {
+    if width == 0 {
+        0
+    } else if width >= MAX_WIDTH {
+        bits as i128
+    } else {
+        let shift = MAX_WIDTH - width;
+        ((bits << shift) as i128) >> shift
+    }
+}
+
+pub fn bits_to_value(bits: u128, width: u64, signed: bool) -> i128 {
+    if signed {
+        sign_extend(bits, width)
+    } else {
+        trunc(bits, width) as i128
+    }
+}
+
+pub fn value_to_bits(value: i128, width: u64) -> u128 {
+    trunc(value as u128, width)
+}
+
+pub fn shift_left(bits: u128, amount: u64, width: u64) -> u128 {
+    if amount >= MAX_WIDTH {
+        0
+    } else {
+        trunc(bits << amount, width)
+    }
+}
+
+pub fn shift_right(bits: u128, amount: u64) -> u128 {
+    if amount >= MAX_WIDTH {
+        0
+    } else {
+        bits >> amount
+    }
+}
+
+pub fn extract(bits: u128, lo: u64, hi: u64) -> u128 {
+    trunc(shift_right(bits, lo), hi - lo + 1)
+}
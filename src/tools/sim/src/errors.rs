@@ -0,0 +1,47 @@
+use ir::errors::Error as IrError;
+use std::fmt;
+use std::num::ParseIntError;
+use xir::errors::Error as XirError;
+
+#[derive(Debug)]
+pub enum Error {
+    Ir(IrError),
+    Xir(XirError),
+    ParseInt(ParseIntError),
+    Sim(String),
+}
+
+impl Error {
+    pub fn new_sim_error(msg: &str) -> Self {
+        Error::Sim(msg.to_string())
+    }
+}
+
+impl From<IrError> for Error {
+    fn from(e: IrError) -> Self {
+        Error::Ir(e)
+    }
+}
+
+impl From<XirError> for Error {
+    fn from(e: XirError) -> Self {
+        Error::Xir(e)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(e: ParseIntError) -> Self {
+        Error::ParseInt(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Ir(msg) => write!(f, "{}", msg),
+            Error::Xir(msg) => write!(f, "{}", msg),
+            Error::ParseInt(msg) => write!(f, "{}", msg),
+            Error::Sim(msg) => write!(f, "{}", msg),
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use crate::bits::*;
+use crate::errors::Error;
+use crate::trace::{env_from_stimulus, lanes, sample_from_env, signal_from_expr, term_width};
+use crate::trace::{Env, Stimulus, Trace};
+use crate::vcd::{Scope, Vcd};
+use ir::ast::*;
+use ir::elab::flat_try_from_prog_with_instances;
+use mmap::Mmap;
+use std::cmp::Ordering;
+
+// memories are modeled as flat arrays, so keep the address space reasonable
+const MAX_ADDR_WIDTH: u64 = 20;
+
+fn check_arity(instr: &InstrPrim, arity: usize) -> Result<(), Error> {
+    let terms: Vec<ExprTerm> = instr.arg().clone().into();
+    if terms.len() == arity {
+        Ok(())
+    } else {
+        let err = format!(
+            "{} expects {} arguments, found {}",
+            instr.op(),
+            arity,
+            terms.len()
+        );
+        Err(Error::new_sim_error(&err))
+    }
+}
+
+fn check_lanes(instr: &InstrPrim, lhs: &[u128], rhs: &[u128]) -> Result<(), Error> {
+    if lhs.len() == rhs.len() {
+        Ok(())
+    } else {
+        let err = format!("{} arguments have different number of lanes", instr.op());
+        Err(Error::new_sim_error(&err))
+    }
+}
+
+pub fn top_def(prog: &Prog) -> Result<&Def, Error> {
+    if let Some(def) = prog.get("main") {
+        Ok(def)
+    } else if let (1, Some(def)) = (prog.def().len(), prog.def().values().next()) {
+        Ok(def)
+    } else {
+        Err(Error::new_sim_error("main def not found"))
+    }
+}
+
+fn mem_try_from_instr(instr: &InstrPrim, mmap: Option<&Mmap>) -> Result<Vec<u128>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let id = dst.get_id()?;
+    let width = term_width(dst)?;
+    let addr_width = term_width(instr.arg().get_term(0)?)?;
+    if addr_width > MAX_ADDR_WIDTH {
+        let err = format!("{} address is wider than {} bits", id, MAX_ADDR_WIDTH);
+        return Err(Error::new_sim_error(&err));
+    }
+    let mut words: Vec<u128> = vec![0; 1 << addr_width];
+    let bytes = ((width + 7) / 8) as usize;
+    match mmap.and_then(|m| m.get(&id)) {
+        Some(mem) => {
+            for (index, chunk) in mem.values().chunks(bytes).enumerate() {
+                let addr = mem.offset() as usize + index;
+                if let Some(word) = words.get_mut(addr) {
+                    let bits = chunk.iter().rev().fold(0, |acc, b| (acc << 8) | *b as u128);
+                    *word = trunc(bits, width);
+                }
+            }
+            Ok(words)
+        }
+        None if matches!(instr.op(), OpPrim::Rom | OpPrim::Srom) => {
+            let err = format!("missing memory contents for {}", id);
+            Err(Error::new_sim_error(&err))
+        }
+        None => Ok(words),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Interp {
+    pub def: Def,
+    pub scope: Scope,
+    pub env: Env,
+    pub state: Env,
+    pub mem: Env,
+}
+
+impl Interp {
+    pub fn new(prog: &Prog, mmap: Option<&Mmap>) -> Result<Self, Error> {
+        let top = top_def(prog)?;
+        let (flat, mut instances) = flat_try_from_prog_with_instances(prog)?;
+        let instance = instances.remove(&top.id()).unwrap_or_default();
+        let scope = Scope::from_def(prog, top, &instance);
+        let mut def = top_def(&flat)?.clone();
+        // registers break cycles, so values can be used before they are defined
+        if def.sort_body().is_err() {
+            let err = format!(
+                "{} has a combinational loop or uses an undefined value",
+                def.id()
+            );
+            return Err(Error::new_sim_error(&err));
+        }
+        let mut interp = Interp {
+            def,
+            scope,
+            ..Default::default()
+        };
+        interp.init(mmap)?;
+        Ok(interp)
+    }
+    pub fn def(&self) -> &Def {
+        &self.def
+    }
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+    pub fn state(&self) -> &Env {
+        &self.state
+    }
+    fn init(&mut self, mmap: Option<&Mmap>) -> Result<(), Error> {
+        let input: Vec<ExprTerm> = self.def.input().clone().into();
+        for term in input {
+            term_width(&term)?;
+        }
+        for instr in self.def.body() {
+            let dst: Vec<ExprTerm> = instr.dst().clone().into();
+            let arg: Vec<ExprTerm> = instr.arg().clone().into();
+            for term in dst.iter().chain(arg.iter()) {
+                term_width(term)?;
+            }
+            if let Instr::Prim(instr) = instr {
+                let dst = instr.dst().get_term(0)?;
+                let id = dst.get_id()?;
+                let len = lanes(dst.get_ty()?);
+                match instr.op() {
+                    OpPrim::Reg => {
+                        let init = match instr.attr().get_val(0) {
+                            Ok(val) => val,
+                            Err(_) => {
+                                let err = format!("reg {} is missing init value", id);
+                                return Err(Error::new_sim_error(&err));
+                            }
+                        };
+                        let bits = value_to_bits(i128::from(init), term_width(dst)?);
+                        self.state.insert(id, vec![bits; len]);
+                    }
+                    OpPrim::Rom | OpPrim::Ram | OpPrim::Srom | OpPrim::Sram => {
+                        if matches!(instr.op(), OpPrim::Srom | OpPrim::Sram) {
+                            self.state.insert(id.clone(), vec![0; len]);
+                        }
+                        self.mem.insert(id, mem_try_from_instr(instr, mmap)?);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+    fn arg<'a>(&'a self, arg: &'a Expr, index: usize) -> Result<(&'a Ty, &'a Vec<u128>), Error> {
+        let term = arg.get_term(index)?;
+        let id = term.get_id()?;
+        match self.env.get(&id) {
+            Some(bits) => Ok((term.get_ty()?, bits)),
+            None => {
+                let err = format!("{} is not defined", id);
+                Err(Error::new_sim_error(&err))
+            }
+        }
+    }
+    fn read(&self, id: &str, addr: u128) -> u128 {
+        match self.mem.get(id) {
+            Some(mem) => mem.get(addr as usize).cloned().unwrap_or(0),
+            None => 0,
+        }
+    }
+    fn eval_wire(&self, instr: &InstrWire) -> Result<Vec<u128>, Error> {
+        let dst = instr.dst().get_term(0)?;
+        let width = term_width(dst)?;
+        let len = lanes(dst.get_ty()?);
+        match instr.op() {
+            OpWire::Id => {
+                let (_, a) = self.arg(instr.arg(), 0)?;
+                Ok(a.iter().map(|x| trunc(*x, width)).collect())
+            }
+            OpWire::Con => {
+                let val = instr.attr().get_val(0)?;
+                Ok(vec![value_to_bits(i128::from(val), width); len])
+            }
+            OpWire::Sll | OpWire::Srl | OpWire::Sra => {
+                let amount = instr.attr().get_val(0)?;
+                if amount < 0 {
+                    let err = format!("{} has a negative shift amount", instr);
+                    return Err(Error::new_sim_error(&err));
+                }
+                let amount = amount as u64;
+                let (ty, a) = self.arg(instr.arg(), 0)?;
+                let wa = ty.width().unwrap_or(0);
+                let res = a.iter().map(|x| match instr.op() {
+                    OpWire::Sll => shift_left(*x, amount, width),
+                    OpWire::Srl => trunc(shift_right(*x, amount), width),
+                    _ => {
+                        let amount = amount.min(MAX_WIDTH - 1);
+                        value_to_bits(sign_extend(*x, wa) >> amount, width)
+                    }
+                });
+                Ok(res.collect())
+            }
+            OpWire::Ext => {
+                let lo = instr.attr().get_val(0)?;
+                let hi = instr.attr().get_val(1)?;
+                let (ty, a) = self.arg(instr.arg(), 0)?;
+                let wa = ty.width().unwrap_or(0) as i64;
+                if lo < 0 || lo > hi || hi >= wa {
+                    let err = format!("{} is out of range", instr);
+                    return Err(Error::new_sim_error(&err));
+                }
+                let res = a
+                    .iter()
+                    .map(|x| trunc(extract(*x, lo as u64, hi as u64), width));
+                Ok(res.collect())
+            }
+            OpWire::Cat => {
+                let terms: Vec<ExprTerm> = instr.arg().clone().into();
+                let mut acc: u128 = 0;
+                // the first argument ends up in the most significant bits
+                for index in 0..terms.len() {
+                    let (ty, a) = self.arg(instr.arg(), index)?;
+                    if a.len() != 1 || len != 1 {
+                        let err = format!("{} only supports scalar values", instr);
+                        return Err(Error::new_sim_error(&err));
+                    }
+                    let wa = ty.width().unwrap_or(0);
+                    acc = shift_left(acc, wa, MAX_WIDTH) | trunc(a[0], wa);
+                }
+                Ok(vec![trunc(acc, width)])
+            }
+        }
+    }
+    fn eval_prim(&self, instr: &InstrPrim) -> Result<Vec<u128>, Error> {
+        let dst = instr.dst().get_term(0)?;
+        let id = dst.get_id()?;
+        let width = term_width(dst)?;
+        match instr.op() {
+            OpPrim::Reg | OpPrim::Srom | OpPrim::Sram => match self.state.get(&id) {
+                Some(bits) => Ok(bits.clone()),
+                None => {
+                    let err = format!("{} does not have a state", id);
+                    Err(Error::new_sim_error(&err))
+                }
+            },
+            OpPrim::Rom | OpPrim::Ram => {
+                let (_, addr) = self.arg(instr.arg(), 0)?;
+                Ok(vec![self.read(&id, addr[0])])
+            }
+            OpPrim::Not => {
+                check_arity(instr, 1)?;
+                let (_, a) = self.arg(instr.arg(), 0)?;
+                Ok(a.iter().map(|x| trunc(!x, width)).collect())
+            }
+            OpPrim::Add | OpPrim::Sub | OpPrim::Mul | OpPrim::And | OpPrim::Or | OpPrim::Xor => {
+                check_arity(instr, 2)?;
+                let (ta, a) = self.arg(instr.arg(), 0)?;
+                let (tb, b) = self.arg(instr.arg(), 1)?;
+                check_lanes(instr, a, b)?;
+                let wa = ta.width().unwrap_or(0);
+                let wb = tb.width().unwrap_or(0);
+                let mut res: Vec<u128> = Vec::new();
+                for (x, y) in a.iter().zip(b.iter()) {
+                    let x = bits_to_value(*x, wa, ta.is_signed());
+                    let y = bits_to_value(*y, wb, tb.is_signed());
+                    let val = match instr.op() {
+                        OpPrim::Add => x.wrapping_add(y),
+                        OpPrim::Sub => x.wrapping_sub(y),
+                        OpPrim::Mul => x.wrapping_mul(y),
+                        OpPrim::And => x & y,
+                        OpPrim::Or => x | y,
+                        _ => x ^ y,
+                    };
+                    res.push(value_to_bits(val, width));
+                }
+                Ok(res)
+            }
+            OpPrim::Mux => {
+                check_arity(instr, 3)?;
+                let (_, sel) = self.arg(instr.arg(), 0)?;
+                let (_, a) = self.arg(instr.arg(), 1)?;
+                let (_, b) = self.arg(instr.arg(), 2)?;
+                check_lanes(instr, a, b)?;
+                let res = if sel[0] & 1 == 1 { a } else { b };
+                Ok(res.iter().map(|x| trunc(*x, width)).collect())
+            }
+            OpPrim::Eql | OpPrim::Neql | OpPrim::Gt | OpPrim::Lt | OpPrim::Ge | OpPrim::Le => {
+                check_arity(instr, 2)?;
+                let (ta, a) = self.arg(instr.arg(), 0)?;
+                let (tb, b) = self.arg(instr.arg(), 1)?;
+                check_lanes(instr, a, b)?;
+                let wa = ta.width().unwrap_or(0);
+                let wb = tb.width().unwrap_or(0);
+                let mut res: Vec<u128> = Vec::new();
+                for (x, y) in a.iter().zip(b.iter()) {
+                    let ord = if ta.is_signed() {
+                        sign_extend(*x, wa).cmp(&sign_extend(*y, wb))
+                    } else {
+                        trunc(*x, wa).cmp(&trunc(*y, wb))
+                    };
+                    let val = match instr.op() {
+                        OpPrim::Eql => ord == Ordering::Equal,
+                        OpPrim::Neql => ord != Ordering::Equal,
+                        OpPrim::Gt => ord == Ordering::Greater,
+                        OpPrim::Lt => ord == Ordering::Less,
+                        OpPrim::Ge => ord != Ordering::Less,
+                        _ => ord != Ordering::Greater,
+                    };
+                    res.push(u128::from(val));
+                }
+                Ok(res)
+            }
+        }
+    }
+    pub fn eval_instr(&self, instr: &Instr) -> Result<Vec<u128>, Error> {
+        match instr {
+            Instr::Wire(instr) => self.eval_wire(instr),
+            Instr::Prim(instr) => self.eval_prim(instr),
+            Instr::Call(instr) => {
+                let err = format!("call to {} is not supported", instr.op());
+                Err(Error::new_sim_error(&err))
+            }
+        }
+    }
+    pub fn eval(&mut self, stim: &Stimulus, cycle: usize) -> Result<(), Error> {
+        self.env = env_from_stimulus(self.def.input(), stim, cycle)?;
+        for instr in self.def.body() {
+            let bits = self.eval_instr(instr)?;
+            self.env.insert(instr.dst().get_id(0)?, bits);
+        }
+        Ok(())
+    }
+    pub fn commit(&mut self) -> Result<(), Error> {
+        let mut state = Env::new();
+        let mut write: Vec<(Id, usize, u128)> = Vec::new();
+        for instr in self.def.body() {
+            if let Instr::Prim(instr) = instr {
+                let dst = instr.dst().get_term(0)?;
+                let id = dst.get_id()?;
+                let width = term_width(dst)?;
+                match instr.op() {
+                    OpPrim::Reg => {
+                        check_arity(instr, 2)?;
+                        let (_, d) = self.arg(instr.arg(), 0)?;
+                        let (_, en) = self.arg(instr.arg(), 1)?;
+                        if en[0] & 1 == 1 {
+                            state.insert(id, d.iter().map(|x| trunc(*x, width)).collect());
+                        }
+                    }
+                    OpPrim::Srom => {
+                        check_arity(instr, 1)?;
+                        let (_, addr) = self.arg(instr.arg(), 0)?;
+                        state.insert(id.clone(), vec![self.read(&id, addr[0])]);
+                    }
+                    // read-first on synchronous memories
+                    OpPrim::Ram | OpPrim::Sram => {
+                        check_arity(instr, 3)?;
+                        let (_, addr) = self.arg(instr.arg(), 0)?;
+                        let (_, data) = self.arg(instr.arg(), 1)?;
+                        let (_, we) = self.arg(instr.arg(), 2)?;
+                        if matches!(instr.op(), OpPrim::Sram) {
+                            state.insert(id.clone(), vec![self.read(&id, addr[0])]);
+                        }
+                        if we[0] & 1 == 1 {
+                            write.push((id, addr[0] as usize, trunc(data[0], width)));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.state.extend(state);
+        for (id, addr, bits) in write {
+            if let Some(word) = self.mem.get_mut(&id).and_then(|m| m.get_mut(addr)) {
+                *word = bits;
+            }
+        }
+        Ok(())
+    }
+    pub fn sample(&self, expr: &Expr) -> Result<Vec<i128>, Error> {
+        sample_from_env(&self.env, expr)
+    }
+    // vcd with every signal of the program, or only the selected ones
+    pub fn vcd(&self, select: &[String]) -> Result<Vcd, Error> {
+        let mut vcd = Vcd::new(self.scope.clone());
+        vcd.add_expr(self.def.input(), select)?;
+        for instr in self.def.body() {
+            vcd.add_expr(instr.dst(), select)?;
+        }
+        vcd.check_select(select)?;
+        Ok(vcd)
+    }
+    fn run_with(&mut self, stim: &Stimulus, mut vcd: Option<&mut Vcd>) -> Result<Trace, Error> {
+        let output = self.def.output().clone();
+        let mut trace = Trace::new(&signal_from_expr(&output));
+        for cycle in 0..stim.len() {
+            self.eval(stim, cycle)?;
+            trace.push(self.sample(&output)?)?;
+            if let Some(vcd) = vcd.as_mut() {
+                vcd.sample(&self.env);
+            }
+            self.commit()?;
+        }
+        Ok(trace)
+    }
+    pub fn run(&mut self, stim: &Stimulus) -> Result<Trace, Error> {
+        self.run_with(stim, None)
+    }
+    pub fn run_with_vcd(&mut self, stim: &Stimulus, vcd: &mut Vcd) -> Result<Trace, Error> {
+        self.run_with(stim, Some(vcd))
+    }
+}
+
+pub fn run_ir_prog(prog: &Prog, stim: &Stimulus, mmap: Option<&Mmap>) -> Result<Trace, Error> {
+    let mut interp = Interp::new(prog, mmap)?;
+    interp.run(stim)
+}
+
+pub fn run_ir_prog_with_vcd(
+    prog: &Prog,
+    stim: &Stimulus,
+    mmap: Option<&Mmap>,
+    select: &[String],
+) -> Result<(Trace, Vcd), Error> {
+    let mut interp = Interp::new(prog, mmap)?;
+    let mut vcd = interp.vcd(select)?;
+    let trace = interp.run_with_vcd(stim, &mut vcd)?;
+    Ok((trace, vcd))
+}
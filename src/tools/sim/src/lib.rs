@@ -0,0 +1,6 @@
+pub mod bits;
+pub mod errors;
+pub mod interp;
+pub mod trace;
+pub mod vcd;
+pub mod xsim;
@@ -0,0 +1,252 @@
+use crate::bits::*;
+use crate::errors::Error;
+use io::read_to_string;
+use ir::ast::{Expr, ExprTerm, Id, Ty};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+// A table of signal values, one row per cycle. The first line names the
+// signals and every following line holds the values for one cycle. Vector
+// signals are split in lanes named id_n, the same way bline names them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub signal: Vec<String>,
+    pub value: Vec<Vec<i128>>,
+}
+
+// Stimulus files use the same format as traces
+pub type Stimulus = Trace;
+
+// Seeded linear congruential generator, so random values are the same on
+// every run
+#[derive(Clone, Debug)]
+pub struct Rng {
+    pub state: u64,
+}
+
+// Signal values during a cycle, kept as raw bits per lane
+pub type Env = HashMap<Id, Vec<u128>>;
+
+pub fn lanes(ty: &Ty) -> usize {
+    match ty.length() {
+        Some(len) => len as usize,
+        None => 1,
+    }
+}
+
+pub fn lane_names(term: &ExprTerm) -> Vec<String> {
+    match (term.id(), term.ty()) {
+        (Some(id), Some(ty)) if ty.is_vector() => {
+            (0..lanes(ty)).map(|n| format!("{}_{}", id, n)).collect()
+        }
+        (Some(id), _) => vec![id],
+        _ => Vec::new(),
+    }
+}
+
+pub fn signal_from_expr(expr: &Expr) -> Vec<String> {
+    let terms: Vec<ExprTerm> = expr.clone().into();
+    terms.iter().flat_map(lane_names).collect()
+}
+
+pub fn term_width(term: &ExprTerm) -> Result<u64, Error> {
+    match term.width() {
+        Some(w) if w <= MAX_WIDTH => Ok(w),
+        Some(w) => {
+            let err = format!("{} has width {}, wider than {} bits", term, w, MAX_WIDTH);
+            Err(Error::new_sim_error(&err))
+        }
+        None => {
+            let err = format!("{} does not have a width", term);
+            Err(Error::new_sim_error(&err))
+        }
+    }
+}
+
+pub fn env_from_stimulus(input: &Expr, stim: &Stimulus, cycle: usize) -> Result<Env, Error> {
+    let mut env = Env::new();
+    let terms: Vec<ExprTerm> = input.clone().into();
+    for term in terms {
+        let width = term_width(&term)?;
+        let mut bits: Vec<u128> = Vec::new();
+        for name in lane_names(&term) {
+            match stim.get(cycle, &name) {
+                Some(val) => bits.push(value_to_bits(val, width)),
+                None => {
+                    let err = format!("stimulus is missing {} at cycle {}", name, cycle);
+                    return Err(Error::new_sim_error(&err));
+                }
+            }
+        }
+        env.insert(term.get_id()?, bits);
+    }
+    Ok(env)
+}
+
+pub fn sample_from_env(env: &Env, expr: &Expr) -> Result<Vec<i128>, Error> {
+    let terms: Vec<ExprTerm> = expr.clone().into();
+    let mut res: Vec<i128> = Vec::new();
+    for term in terms {
+        let id = term.get_id()?;
+        let width = term_width(&term)?;
+        let signed = term.get_ty()?.is_signed();
+        match env.get(&id) {
+            Some(bits) => res.extend(bits.iter().map(|x| bits_to_value(*x, width, signed))),
+            None => {
+                let err = format!("{} is never assigned", id);
+                return Err(Error::new_sim_error(&err));
+            }
+        }
+    }
+    Ok(res)
+}
+
+pub fn parse_value(input: &str) -> Result<i128, Error> {
+    let (neg, num) = match input.chars().next() {
+        Some('-') => (true, &input[1..]),
+        Some('+') => (false, &input[1..]),
+        _ => (false, input),
+    };
+    let val = if num.starts_with("0b") {
+        u128::from_str_radix(&num[2..], 2)? as i128
+    } else if num.starts_with("0x") {
+        u128::from_str_radix(&num[2..], 16)? as i128
+    } else {
+        num.parse::<u128>()? as i128
+    };
+    if neg {
+        Ok(val.wrapping_neg())
+    } else {
+        Ok(val)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+    // the high bits of the state, the low ones have short periods
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self
+            .state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.state >> 32) as u32
+    }
+}
+
+impl Trace {
+    pub fn new(signal: &[String]) -> Self {
+        Trace {
+            signal: signal.to_vec(),
+            value: Vec::new(),
+        }
+    }
+    // cycles of random values for every signal, the same for a given seed
+    pub fn random(signal: &[String], cycles: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut trace = Trace::new(signal);
+        for _ in 0..cycles {
+            let mut row: Vec<i128> = Vec::new();
+            for _ in signal {
+                let mut val: u128 = 0;
+                for _ in 0..4 {
+                    val = (val << 32) | u128::from(rng.next_u32());
+                }
+                row.push(val as i128);
+            }
+            trace.value.push(row);
+        }
+        trace
+    }
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = read_to_string(path);
+        Trace::from_str(&content)
+    }
+    pub fn signal(&self) -> &Vec<String> {
+        &self.signal
+    }
+    pub fn value(&self) -> &Vec<Vec<i128>> {
+        &self.value
+    }
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.signal.iter().position(|s| s == name)
+    }
+    pub fn row(&self, cycle: usize) -> Option<&Vec<i128>> {
+        self.value.get(cycle)
+    }
+    pub fn get(&self, cycle: usize, name: &str) -> Option<i128> {
+        let index = self.index(name)?;
+        let row = self.row(cycle)?;
+        row.get(index).cloned()
+    }
+    pub fn push(&mut self, row: Vec<i128>) -> Result<(), Error> {
+        if row.len() == self.signal.len() {
+            self.value.push(row);
+            Ok(())
+        } else {
+            let err = format!(
+                "expected {} values per cycle, found {}",
+                self.signal.len(),
+                row.len()
+            );
+            Err(Error::new_sim_error(&err))
+        }
+    }
+}
+
+impl FromStr for Trace {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut trace: Option<Trace> = None;
+        for (num, line) in input.lines().enumerate() {
+            let line = match line.find("//") {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            if let Some(trace) = trace.as_mut() {
+                let mut row: Vec<i128> = Vec::new();
+                for token in tokens {
+                    match parse_value(token) {
+                        Ok(val) => row.push(val),
+                        Err(_) => {
+                            let err = format!("line {}: {} is not a valid value", num + 1, token);
+                            return Err(Error::new_sim_error(&err));
+                        }
+                    }
+                }
+                if let Err(e) = trace.push(row) {
+                    let err = format!("line {}: {}", num + 1, e);
+                    return Err(Error::new_sim_error(&err));
+                }
+            } else {
+                let signal: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+                trace = Some(Trace::new(&signal));
+            }
+        }
+        Ok(trace.unwrap_or_default())
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<String> = vec![self.signal.join(" ")];
+        for row in &self.value {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            lines.push(row.join(" "));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,245 @@
+use crate::errors::Error;
+use crate::trace::{lane_names, term_width, Env};
+use ir::ast::{Def, Expr, ExprTerm, Id, Prog};
+use ir::elab::{call_prefixes, calls_from_def, InstanceMap};
+use std::collections::HashSet;
+use std::fmt;
+
+const CLOCK: &str = "clock";
+// values change on the rising edge, at the start of every period
+const PERIOD: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Var {
+    pub id: Id,
+    pub lane: usize,
+    pub name: String,
+    pub width: u64,
+}
+
+// Module scope of a def or of a call instance. Calls are inlined before they
+// are simulated, value holds the inlined values of the instance itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    pub name: String,
+    pub prefix: String,
+    pub value: HashSet<Id>,
+    pub child: Vec<Scope>,
+}
+
+// Value change dump of a simulation. Every var is a lane of a signal, named
+// id_n for vectors, and it goes in the innermost scope of the signal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Vcd {
+    pub scope: Scope,
+    pub var: Vec<Var>,
+    pub value: Vec<Vec<Option<u128>>>,
+}
+
+// identifier codes use the printable characters from ! to ~
+fn code(index: usize) -> String {
+    let mut code = String::new();
+    let mut index = index;
+    loop {
+        code.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    code
+}
+
+fn value_change(bits: Option<u128>, width: u64, code: &str) -> String {
+    match bits {
+        Some(bits) if width == 1 => format!("{}{}", bits & 1, code),
+        Some(bits) => format!("b{:b} {}", bits, code),
+        None if width == 1 => format!("x{}", code),
+        None => format!("bx {}", code),
+    }
+}
+
+impl Scope {
+    pub fn new(name: &str) -> Self {
+        Scope {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+    // scope of def and of the calls inside it, instances are named after
+    // their prefix such as addreg_0. The values go in the instance they are
+    // inlined from.
+    pub fn from_def(prog: &Prog, def: &Def, instance: &InstanceMap) -> Self {
+        let mut scope = Scope::from_instance(prog, def, &def.id(), "");
+        for (id, prefix) in instance {
+            if let Some(inner) = scope.get_mut(prefix) {
+                inner.value.insert(id.clone());
+            }
+        }
+        scope
+    }
+    fn from_instance(prog: &Prog, def: &Def, name: &str, prefix: &str) -> Self {
+        let mut scope = Scope::new(name);
+        scope.prefix = prefix.to_string();
+        for (call, inner) in calls_from_def(def).iter().zip(call_prefixes(def)) {
+            if let Some(callee) = prog.get(&call.op().op()) {
+                let name = inner.trim_end_matches('_');
+                let prefix = format!("{}{}", prefix, inner);
+                scope
+                    .child
+                    .push(Scope::from_instance(prog, callee, name, &prefix));
+            }
+        }
+        scope
+    }
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn prefix(&self) -> &String {
+        &self.prefix
+    }
+    pub fn child(&self) -> &Vec<Scope> {
+        &self.child
+    }
+    pub fn value(&self) -> &HashSet<Id> {
+        &self.value
+    }
+    fn get_mut(&mut self, prefix: &str) -> Option<&mut Scope> {
+        if self.prefix == prefix {
+            return Some(self);
+        }
+        self.child.iter_mut().find_map(|c| c.get_mut(prefix))
+    }
+    fn holds(&self, id: &str) -> bool {
+        self.value.contains(id) || self.child.iter().any(|c| c.holds(id))
+    }
+    // innermost scope holding the value id
+    pub fn find(&self, id: &str) -> &Scope {
+        match self.child.iter().find(|c| c.holds(id)) {
+            Some(child) => child.find(id),
+            None => self,
+        }
+    }
+}
+
+impl Vcd {
+    pub fn new(scope: Scope) -> Self {
+        Vcd {
+            scope,
+            ..Default::default()
+        }
+    }
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+    pub fn var(&self) -> &Vec<Var> {
+        &self.var
+    }
+    pub fn value(&self) -> &Vec<Vec<Option<u128>>> {
+        &self.value
+    }
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.var.iter().any(|v| v.name == name || v.id == name)
+    }
+    // add the lanes of term, all of them when nothing is selected
+    pub fn add_term(&mut self, term: &ExprTerm, select: &[String]) -> Result<(), Error> {
+        let id = term.get_id()?;
+        let width = term_width(term)?;
+        for (lane, name) in lane_names(term).into_iter().enumerate() {
+            let selected = select.is_empty() || select.contains(&id) || select.contains(&name);
+            if selected && !self.contains(&name) {
+                self.var.push(Var {
+                    id: id.clone(),
+                    lane,
+                    name,
+                    width,
+                });
+            }
+        }
+        Ok(())
+    }
+    pub fn add_expr(&mut self, expr: &Expr, select: &[String]) -> Result<(), Error> {
+        let terms: Vec<ExprTerm> = expr.clone().into();
+        for term in terms {
+            self.add_term(&term, select)?;
+        }
+        Ok(())
+    }
+    pub fn check_select(&self, select: &[String]) -> Result<(), Error> {
+        match select.iter().find(|name| !self.contains(name)) {
+            Some(name) => {
+                let err = format!("{} is not a signal of {}", name, self.scope.name);
+                Err(Error::new_sim_error(&err))
+            }
+            None => Ok(()),
+        }
+    }
+    pub fn sample(&mut self, env: &Env) {
+        let row = self
+            .var
+            .iter()
+            .map(|v| env.get(&v.id).and_then(|bits| bits.get(v.lane)).cloned())
+            .collect();
+        self.value.push(row);
+    }
+    // declarations of scope, its vars without the prefix and then its
+    // instances, the clock goes in the outermost one
+    fn scope_lines(&self, scope: &Scope, lines: &mut Vec<String>) {
+        lines.push(format!("$scope module {} $end", scope.name));
+        if scope.prefix.is_empty() {
+            lines.push(format!("$var wire 1 {} {} $end", code(0), CLOCK));
+        }
+        for (index, var) in self.var.iter().enumerate() {
+            if self.scope.find(&var.id).prefix == scope.prefix {
+                lines.push(format!(
+                    "$var wire {} {} {} $end",
+                    var.width,
+                    code(index + 1),
+                    &var.name[scope.prefix.len()..]
+                ));
+            }
+        }
+        for child in scope.child.iter() {
+            self.scope_lines(child, lines);
+        }
+        lines.push("$upscope $end".to_string());
+    }
+}
+
+impl fmt::Display for Vcd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<String> = vec!["$timescale 1ns $end".to_string()];
+        self.scope_lines(&self.scope, &mut lines);
+        lines.push("$enddefinitions $end".to_string());
+        let mut prev: Option<&Vec<Option<u128>>> = None;
+        for (cycle, row) in self.value.iter().enumerate() {
+            lines.push(format!("#{}", cycle * PERIOD));
+            if prev.is_none() {
+                lines.push("$dumpvars".to_string());
+            }
+            lines.push(format!("1{}", code(0)));
+            for (index, (var, bits)) in self.var.iter().zip(row.iter()).enumerate() {
+                if prev.map_or(true, |p| p.get(index) != Some(bits)) {
+                    lines.push(value_change(*bits, var.width, &code(index + 1)));
+                }
+            }
+            if prev.is_none() {
+                lines.push("$end".to_string());
+            }
+            lines.push(format!("#{}", cycle * PERIOD + PERIOD / 2));
+            lines.push(format!("0{}", code(0)));
+            prev = Some(row);
+        }
+        if !self.value.is_empty() {
+            lines.push(format!("#{}", self.value.len() * PERIOD));
+        }
+        writeln!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,572 @@
+use crate::bits::*;
+use crate::errors::Error;
+use crate::trace::{env_from_stimulus, lanes, sample_from_env, signal_from_expr, term_width};
+use crate::trace::{Env, Stimulus, Trace};
+use crate::vcd::{Scope, Vcd};
+use mmap::Mmap;
+use std::collections::{HashMap, HashSet};
+use xir::ast::*;
+
+// memories are modeled as flat arrays, so keep the address space reasonable
+const MAX_ADDR_WIDTH: u64 = 20;
+
+// pipeline registers inside a dsp, each one holds a value per lane. The vector
+// adds only use the a, b and p registers.
+#[derive(Clone, Debug, Default)]
+pub struct Dsp {
+    pub a1: Vec<u128>,
+    pub b1: Vec<u128>,
+    pub a: Vec<u128>,
+    pub b: Vec<u128>,
+    pub m: Vec<u128>,
+    pub p: Vec<u128>,
+}
+
+impl Dsp {
+    pub fn new(len: usize) -> Self {
+        Dsp {
+            a1: vec![0; len],
+            b1: vec![0; len],
+            a: vec![0; len],
+            b: vec![0; len],
+            m: vec![0; len],
+            p: vec![0; len],
+        }
+    }
+}
+
+pub fn is_seq(op: &OpMach) -> bool {
+    matches!(
+        op,
+        OpMach::Fdre
+            | OpMach::Fdse
+            | OpMach::VecAddRegA
+            | OpMach::VecAddRegACi
+            | OpMach::VecAddRegACo
+            | OpMach::VecAddRegACio
+            | OpMach::MulAddRegA
+            | OpMach::MulAddRegACi
+            | OpMach::MulAddRegACo
+            | OpMach::MulAddRegACio
+            | OpMach::Bram
+            | OpMach::Brom
+    )
+}
+
+fn check_arity(instr: &InstrMach, arity: usize) -> Result<(), Error> {
+    let terms: Vec<ExprTerm> = instr.arg().clone().into();
+    if terms.len() == arity {
+        Ok(())
+    } else {
+        let err = format!(
+            "{} expects {} arguments, found {}",
+            instr.op(),
+            arity,
+            terms.len()
+        );
+        Err(Error::new_sim_error(&err))
+    }
+}
+
+// registers in the a, b, m and p stages of a muladd, up to two in the a and
+// b stages and the output is always registered
+pub fn dsp_reg(instr: &InstrMach) -> Result<Vec<i64>, Error> {
+    let reg = instr.dsp_reg()?;
+    let max = [2, 2, 1, 1];
+    if reg.len() == max.len()
+        && reg.iter().zip(max.iter()).all(|(r, m)| 0 <= *r && r <= m)
+        && reg[3] == 1
+    {
+        Ok(reg)
+    } else {
+        let err = format!("{} has invalid register stages {:?}", instr.op(), reg);
+        Err(Error::new_sim_error(&err))
+    }
+}
+
+fn instr_is_ready(env: &HashSet<Id>, instr: &Instr) -> bool {
+    if let Some(mach) = instr.mach() {
+        if is_seq(mach.op()) {
+            return true;
+        }
+    }
+    let arg: Vec<ExprTerm> = instr.arg().clone().into();
+    arg.iter()
+        .all(|t| t.id().map_or(false, |id| env.contains(&id)))
+}
+
+// sequential instructions are always ready, so values can be used before they are defined
+pub fn sort_body(prog: &Prog) -> Result<Vec<Instr>, Error> {
+    let mut env: HashSet<Id> = HashSet::new();
+    let input: Vec<ExprTerm> = prog.sig().input().clone().into();
+    for term in input {
+        env.insert(term.get_id()?);
+    }
+    let mut body: Vec<Instr> = Vec::new();
+    let mut queue: Vec<Instr> = prog.body().clone();
+    while !queue.is_empty() {
+        let mut rest: Vec<Instr> = Vec::new();
+        for instr in queue.iter() {
+            if instr_is_ready(&env, instr) {
+                body.push(instr.clone());
+                env.insert(instr.dst().get_id(0)?);
+            } else {
+                rest.push(instr.clone());
+            }
+        }
+        if rest.len() == queue.len() {
+            let err = format!(
+                "{} has a combinational loop or uses an undefined value",
+                prog.sig().id()
+            );
+            return Err(Error::new_sim_error(&err));
+        }
+        queue = rest;
+    }
+    Ok(body)
+}
+
+// xpand maps every memory entry to a byte, so the same is done here
+fn mem_try_from_instr(instr: &InstrMach, mmap: Option<&Mmap>) -> Result<Vec<u128>, Error> {
+    let dst = instr.dst().get_term(0)?;
+    let id = dst.get_id()?;
+    let width = term_width(dst)?;
+    let addr_width = term_width(instr.arg().get_term(0)?)?;
+    if addr_width > MAX_ADDR_WIDTH {
+        let err = format!("{} address is wider than {} bits", id, MAX_ADDR_WIDTH);
+        return Err(Error::new_sim_error(&err));
+    }
+    let mut words: Vec<u128> = vec![0; 1 << addr_width];
+    match instr.mem().or_else(|| mmap.and_then(|m| m.get(&id))) {
+        Some(mem) => {
+            for (index, value) in mem.values().iter().enumerate() {
+                let addr = mem.offset() as usize + index;
+                if let Some(word) = words.get_mut(addr) {
+                    *word = trunc(u128::from(*value), width);
+                }
+            }
+            Ok(words)
+        }
+        None if matches!(instr.op(), OpMach::Lrom | OpMach::Brom) => {
+            let err = format!("missing memory contents for {}", id);
+            Err(Error::new_sim_error(&err))
+        }
+        None => Ok(words),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Sim {
+    pub sig: Sig,
+    pub body: Vec<Instr>,
+    pub env: Env,
+    pub state: Env,
+    pub dsp: HashMap<Id, Dsp>,
+    pub mem: Env,
+}
+
+impl Sim {
+    pub fn new(prog: &Prog, mmap: Option<&Mmap>) -> Result<Self, Error> {
+        let body = sort_body(prog)?;
+        let mut sim = Sim {
+            sig: prog.sig().clone(),
+            body,
+            ..Default::default()
+        };
+        sim.init(mmap)?;
+        Ok(sim)
+    }
+    pub fn sig(&self) -> &Sig {
+        &self.sig
+    }
+    pub fn body(&self) -> &Vec<Instr> {
+        &self.body
+    }
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+    pub fn state(&self) -> &Env {
+        &self.state
+    }
+    fn init(&mut self, mmap: Option<&Mmap>) -> Result<(), Error> {
+        let input: Vec<ExprTerm> = self.sig.input().clone().into();
+        for term in input {
+            term_width(&term)?;
+        }
+        for instr in self.body.iter() {
+            let dst: Vec<ExprTerm> = instr.dst().clone().into();
+            let arg: Vec<ExprTerm> = instr.arg().clone().into();
+            for term in dst.iter().chain(arg.iter()) {
+                term_width(term)?;
+            }
+            if let Instr::Mach(instr) = instr {
+                let dst = instr.dst().get_term(0)?;
+                let id = dst.get_id()?;
+                let len = lanes(dst.get_ty()?);
+                match instr.op() {
+                    // the state after reset, fdse is set and fdre is cleared
+                    OpMach::Fdre => {
+                        self.state.insert(id, vec![0; len]);
+                    }
+                    OpMach::Fdse => {
+                        self.state.insert(id, vec![1; len]);
+                    }
+                    OpMach::Brom | OpMach::Bram => {
+                        self.state.insert(id.clone(), vec![0; len]);
+                        self.mem.insert(id, mem_try_from_instr(instr, mmap)?);
+                    }
+                    OpMach::Lrom | OpMach::Lram => {
+                        self.mem.insert(id, mem_try_from_instr(instr, mmap)?);
+                    }
+                    op if is_seq(op) => {
+                        self.dsp.insert(id, Dsp::new(len));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+    fn arg<'a>(&'a self, arg: &'a Expr, index: usize) -> Result<(&'a Ty, &'a Vec<u128>), Error> {
+        let term = arg.get_term(index)?;
+        let id = term.get_id()?;
+        match self.env.get(&id) {
+            Some(bits) => Ok((term.get_ty()?, bits)),
+            None => {
+                let err = format!("{} is not defined", id);
+                Err(Error::new_sim_error(&err))
+            }
+        }
+    }
+    fn read(&self, id: &str, addr: u128) -> u128 {
+        match self.mem.get(id) {
+            Some(mem) => mem.get(addr as usize).cloned().unwrap_or(0),
+            None => 0,
+        }
+    }
+    fn eval_basc(&self, instr: &InstrBasc) -> Result<Vec<u128>, Error> {
+        let dst = instr.dst().get_term(0)?;
+        let width = term_width(dst)?;
+        let len = lanes(dst.get_ty()?);
+        match instr.op() {
+            OpBasc::Id => {
+                let (_, a) = self.arg(instr.arg(), 0)?;
+                Ok(a.iter().map(|x| trunc(*x, width)).collect())
+            }
+            OpBasc::Gnd => Ok(vec![0; len]),
+            OpBasc::Vcc => Ok(vec![mask(width); len]),
+            OpBasc::Ext => {
+                let lo = instr.attr().get_val(0)?;
+                let hi = instr.attr().get_val(1).unwrap_or(lo);
+                let (ty, a) = self.arg(instr.arg(), 0)?;
+                let wa = ty.width().unwrap_or(0) as i64;
+                if lo < 0 || lo > hi || hi >= wa {
+                    let err = format!("{} is out of range", instr);
+                    return Err(Error::new_sim_error(&err));
+                }
+                let res = a
+                    .iter()
+                    .map(|x| trunc(extract(*x, lo as u64, hi as u64), width));
+                Ok(res.collect())
+            }
+            OpBasc::Cat => {
+                let terms: Vec<ExprTerm> = instr.arg().clone().into();
+                let mut acc: u128 = 0;
+                let mut offset: u64 = 0;
+                // unlike ir, the first argument ends up in the least significant bits
+                for index in 0..terms.len() {
+                    let (ty, a) = self.arg(instr.arg(), index)?;
+                    if a.len() != 1 || len != 1 {
+                        let err = format!("{} only supports scalar values", instr);
+                        return Err(Error::new_sim_error(&err));
+                    }
+                    let wa = ty.width().unwrap_or(0);
+                    acc |= shift_left(trunc(a[0], wa), offset, MAX_WIDTH);
+                    offset += wa;
+                }
+                Ok(vec![trunc(acc, width)])
+            }
+        }
+    }
+    fn eval_lane(&self, instr: &InstrMach, width: u64) -> Result<Vec<u128>, Error> {
+        let terms: Vec<ExprTerm> = instr.arg().clone().into();
+        let mut arg: Vec<&Vec<u128>> = Vec::new();
+        for index in 0..terms.len() {
+            let (_, a) = self.arg(instr.arg(), index)?;
+            if let Some(first) = arg.first() {
+                if first.len() != a.len() {
+                    let err = format!("{} arguments have different number of lanes", instr.op());
+                    return Err(Error::new_sim_error(&err));
+                }
+            }
+            arg.push(a);
+        }
+        let mut res: Vec<u128> = Vec::new();
+        for (lane, a) in arg[0].iter().enumerate() {
+            let val = match instr.op() {
+                OpMach::VecAdd => a.wrapping_add(arg[1][lane]),
+                OpMach::VecSub => a.wrapping_sub(arg[1][lane]),
+                OpMach::MulAdd => a.wrapping_mul(arg[1][lane]).wrapping_add(arg[2][lane]),
+                _ => a.wrapping_mul(arg[1][lane]),
+            };
+            res.push(trunc(val, width));
+        }
+        Ok(res)
+    }
+    fn eval_mach(&self, instr: &InstrMach) -> Result<Vec<u128>, Error> {
+        let dst = instr.dst().get_term(0)?;
+        let id = dst.get_id()?;
+        let width = term_width(dst)?;
+        // lut output is the INIT bit indexed by the inputs, I0 being the lsb
+        if let Some(inputs) = instr.op().lut_inputs() {
+            check_arity(instr, inputs)?;
+            let init = instr.attr().get_val(0)? as u64;
+            let mut index: u64 = 0;
+            for input in 0..inputs {
+                let (_, a) = self.arg(instr.arg(), input)?;
+                index |= ((a[0] & 1) as u64) << input;
+            }
+            return Ok(vec![u128::from((init >> index) & 1)]);
+        }
+        match instr.op() {
+            OpMach::Fdre | OpMach::Fdse | OpMach::Brom | OpMach::Bram => {
+                match self.state.get(&id) {
+                    Some(bits) => Ok(bits.clone()),
+                    None => {
+                        let err = format!("{} does not have a state", id);
+                        Err(Error::new_sim_error(&err))
+                    }
+                }
+            }
+            op if is_seq(op) => match self.dsp.get(&id) {
+                Some(dsp) => Ok(dsp.p.iter().map(|x| trunc(*x, width)).collect()),
+                None => {
+                    let err = format!("{} does not have a state", id);
+                    Err(Error::new_sim_error(&err))
+                }
+            },
+            // carry chain where DI is the first argument and S the second one
+            OpMach::CarryAdd | OpMach::CarrySub => {
+                check_arity(instr, 2)?;
+                let (_, di) = self.arg(instr.arg(), 0)?;
+                let (_, s) = self.arg(instr.arg(), 1)?;
+                let mut carry = u128::from(matches!(instr.op(), OpMach::CarrySub));
+                let mut res: u128 = 0;
+                for bit in 0..width {
+                    let s = (s[0] >> bit) & 1;
+                    let di = (di[0] >> bit) & 1;
+                    res |= (s ^ carry) << bit;
+                    carry = if s == 1 { carry } else { di };
+                }
+                Ok(vec![res])
+            }
+            OpMach::VecAdd | OpMach::VecSub | OpMach::VecMul | OpMach::Mul => {
+                check_arity(instr, 2)?;
+                self.eval_lane(instr, width)
+            }
+            OpMach::MulAdd => {
+                check_arity(instr, 3)?;
+                self.eval_lane(instr, width)
+            }
+            OpMach::Lrom | OpMach::Lram => {
+                let (_, addr) = self.arg(instr.arg(), 0)?;
+                Ok(vec![self.read(&id, addr[0])])
+            }
+            _ => {
+                let err = format!("{} is not supported", instr.op());
+                Err(Error::new_sim_error(&err))
+            }
+        }
+    }
+    pub fn eval(&mut self, stim: &Stimulus, cycle: usize) -> Result<(), Error> {
+        self.env = env_from_stimulus(self.sig.input(), stim, cycle)?;
+        for instr in self.body.iter() {
+            let bits = match instr {
+                Instr::Basc(instr) => self.eval_basc(instr)?,
+                Instr::Mach(instr) => self.eval_mach(instr)?,
+            };
+            self.env.insert(instr.dst().get_id(0)?, bits);
+        }
+        Ok(())
+    }
+    fn commit_dsp(&self, instr: &InstrMach, dsp: &Dsp, width: u64) -> Result<Dsp, Error> {
+        let mut next = dsp.clone();
+        match instr.op() {
+            OpMach::VecAddRegA
+            | OpMach::VecAddRegACi
+            | OpMach::VecAddRegACo
+            | OpMach::VecAddRegACio => {
+                check_arity(instr, 5)?;
+                let (_, a) = self.arg(instr.arg(), 0)?;
+                let (_, b) = self.arg(instr.arg(), 1)?;
+                let (_, ena) = self.arg(instr.arg(), 2)?;
+                let (_, enb) = self.arg(instr.arg(), 3)?;
+                let (_, enp) = self.arg(instr.arg(), 4)?;
+                // the cascade input bypasses the b register
+                let y = if instr.op().is_cascade_unregistered() {
+                    b
+                } else {
+                    &dsp.b
+                };
+                if enp[0] & 1 == 1 {
+                    let sum = dsp.a.iter().zip(y.iter());
+                    next.p = sum.map(|(x, y)| trunc(x.wrapping_add(*y), width)).collect();
+                }
+                if ena[0] & 1 == 1 {
+                    next.a = a.clone();
+                }
+                if enb[0] & 1 == 1 {
+                    next.b = b.clone();
+                }
+            }
+            _ => {
+                // the cascade variants compute the same value, only the routing changes
+                check_arity(instr, 7)?;
+                let reg = dsp_reg(instr)?;
+                let (_, a) = self.arg(instr.arg(), 0)?;
+                let (_, b) = self.arg(instr.arg(), 1)?;
+                let (_, c) = self.arg(instr.arg(), 2)?;
+                let (_, ena) = self.arg(instr.arg(), 3)?;
+                let (_, enb) = self.arg(instr.arg(), 4)?;
+                let (_, enm) = self.arg(instr.arg(), 5)?;
+                let (_, enp) = self.arg(instr.arg(), 6)?;
+                // stages without registers pass their input through
+                let x = if reg[0] == 0 { a } else { &dsp.a };
+                let y = if reg[1] == 0 { b } else { &dsp.b };
+                let mul: Vec<u128> = x
+                    .iter()
+                    .zip(y.iter())
+                    .map(|(x, y)| trunc(x.wrapping_mul(*y), width))
+                    .collect();
+                let m = if reg[2] == 0 { &mul } else { &dsp.m };
+                if enp[0] & 1 == 1 {
+                    let sum = m.iter().zip(c.iter());
+                    next.p = sum.map(|(x, y)| trunc(x.wrapping_add(*y), width)).collect();
+                }
+                if enm[0] & 1 == 1 && reg[2] == 1 {
+                    next.m = mul.clone();
+                }
+                if ena[0] & 1 == 1 {
+                    next.a1 = a.clone();
+                    next.a = if reg[0] == 2 {
+                        dsp.a1.clone()
+                    } else {
+                        a.clone()
+                    };
+                }
+                if enb[0] & 1 == 1 {
+                    next.b1 = b.clone();
+                    next.b = if reg[1] == 2 {
+                        dsp.b1.clone()
+                    } else {
+                        b.clone()
+                    };
+                }
+            }
+        }
+        Ok(next)
+    }
+    pub fn commit(&mut self) -> Result<(), Error> {
+        let mut state = Env::new();
+        let mut dsp: HashMap<Id, Dsp> = HashMap::new();
+        let mut write: Vec<(Id, usize, u128)> = Vec::new();
+        for instr in self.body.iter() {
+            if let Instr::Mach(instr) = instr {
+                let dst = instr.dst().get_term(0)?;
+                let id = dst.get_id()?;
+                let width = term_width(dst)?;
+                match instr.op() {
+                    OpMach::Fdre | OpMach::Fdse => {
+                        check_arity(instr, 2)?;
+                        let (_, d) = self.arg(instr.arg(), 0)?;
+                        let (_, ce) = self.arg(instr.arg(), 1)?;
+                        if ce[0] & 1 == 1 {
+                            state.insert(id, d.iter().map(|x| trunc(*x, width)).collect());
+                        }
+                    }
+                    OpMach::Brom => {
+                        check_arity(instr, 1)?;
+                        let (_, addr) = self.arg(instr.arg(), 0)?;
+                        state.insert(id.clone(), vec![self.read(&id, addr[0])]);
+                    }
+                    // read-first on synchronous memories
+                    OpMach::Lram | OpMach::Bram => {
+                        check_arity(instr, 3)?;
+                        let (_, addr) = self.arg(instr.arg(), 0)?;
+                        let (_, data) = self.arg(instr.arg(), 1)?;
+                        let (_, we) = self.arg(instr.arg(), 2)?;
+                        if matches!(instr.op(), OpMach::Bram) {
+                            state.insert(id.clone(), vec![self.read(&id, addr[0])]);
+                        }
+                        if we[0] & 1 == 1 {
+                            write.push((id, addr[0] as usize, trunc(data[0], width)));
+                        }
+                    }
+                    op if is_seq(op) => {
+                        if let Some(cur) = self.dsp.get(&id) {
+                            dsp.insert(id, self.commit_dsp(instr, cur, width)?);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        self.state.extend(state);
+        self.dsp.extend(dsp);
+        for (id, addr, bits) in write {
+            if let Some(word) = self.mem.get_mut(&id).and_then(|m| m.get_mut(addr)) {
+                *word = bits;
+            }
+        }
+        Ok(())
+    }
+    pub fn sample(&self, expr: &Expr) -> Result<Vec<i128>, Error> {
+        sample_from_env(&self.env, expr)
+    }
+    // vcd with every signal of the program, or only the selected ones
+    pub fn vcd(&self, select: &[String]) -> Result<Vcd, Error> {
+        let mut vcd = Vcd::new(Scope::new(&self.sig.id()));
+        vcd.add_expr(self.sig.input(), select)?;
+        for instr in self.body.iter() {
+            vcd.add_expr(instr.dst(), select)?;
+        }
+        vcd.check_select(select)?;
+        Ok(vcd)
+    }
+    fn run_with(&mut self, stim: &Stimulus, mut vcd: Option<&mut Vcd>) -> Result<Trace, Error> {
+        let output = self.sig.output().clone();
+        let mut trace = Trace::new(&signal_from_expr(&output));
+        for cycle in 0..stim.len() {
+            self.eval(stim, cycle)?;
+            trace.push(self.sample(&output)?)?;
+            if let Some(vcd) = vcd.as_mut() {
+                vcd.sample(&self.env);
+            }
+            self.commit()?;
+        }
+        Ok(trace)
+    }
+    pub fn run(&mut self, stim: &Stimulus) -> Result<Trace, Error> {
+        self.run_with(stim, None)
+    }
+    pub fn run_with_vcd(&mut self, stim: &Stimulus, vcd: &mut Vcd) -> Result<Trace, Error> {
+        self.run_with(stim, Some(vcd))
+    }
+}
+
+pub fn run_xir_prog(prog: &Prog, stim: &Stimulus, mmap: Option<&Mmap>) -> Result<Trace, Error> {
+    let mut sim = Sim::new(prog, mmap)?;
+    sim.run(stim)
+}
+
+pub fn run_xir_prog_with_vcd(
+    prog: &Prog,
+    stim: &Stimulus,
+    mmap: Option<&Mmap>,
+    select: &[String],
+) -> Result<(Trace, Vcd), Error> {
+    let mut sim = Sim::new(prog, mmap)?;
+    let mut vcd = sim.vcd(select)?;
+    let trace = sim.run_with_vcd(stim, &mut vcd)?;
+    Ok((trace, vcd))
+}
@@ -0,0 +1,99 @@
+use ir::parser::Parser as IrParser;
+use mmap::Mmap;
+use sim::interp::run_ir_prog;
+use sim::trace::{parse_value, Stimulus, Trace};
+use std::str::FromStr;
+
+fn test(prog: &str, stim: &str, exp: &str) {
+    let prog = IrParser::parse_from_str(prog).unwrap();
+    let stim = Stimulus::from_str(stim).unwrap();
+    let exp = Trace::from_str(exp).unwrap();
+    let res = run_ir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn add() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/add.ir").unwrap();
+    let stim = Stimulus::from_file("../../../examples/stim/add.stim").unwrap();
+    let exp = Trace::from_str("y\n0\n3\n7\n127\n127").unwrap();
+    let res = run_ir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn fsm_3() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/fsm_3.ir").unwrap();
+    let stim = Stimulus::from_file("../../../examples/stim/fsm_3.stim").unwrap();
+    let exp = Trace::from_str("y\n0\n1\n2\n2\n0").unwrap();
+    let res = run_ir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn rom_8x8() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/rom_8x8.ir").unwrap();
+    let mmap = Mmap::from_file("../../../examples/mmap/lrom_8x8.json");
+    let stim = Stimulus::from_str("a\n0\n1\n7").unwrap();
+    let exp = Trace::from_str("y\n-86\n-69\n34").unwrap();
+    let res = run_ir_prog(&prog, &stim, Some(&mmap)).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn aes_encrypt() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/aes_encrypt.ir").unwrap();
+    let mmap = Mmap::from_file("../../../examples/mmap/aes.json");
+    let stim = Stimulus::from_file("../../../examples/stim/aes_encrypt.stim").unwrap();
+    let res = run_ir_prog(&prog, &stim, Some(&mmap)).unwrap();
+    let exp = parse_value("0x69c4e0d86a7b0430d8cdb78070b4c55a").unwrap();
+    assert_eq!(res.get(11, "aes_ciphertext"), Some(exp));
+    assert_eq!(res.get(11, "ready"), Some(1));
+    assert_eq!(res.get(10, "ready"), Some(0));
+}
+
+#[test]
+fn tadd() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/tadd.ir").unwrap();
+    let stim = Stimulus::from_str(
+        "en a_0 a_1 a_2 a_3 b_0 b_1 b_2 b_3
+         1  1   2   3   4   10  20  30  127
+         1  0   0   0   0   0   0   0   0
+         1  0   0   0   0   0   0   0   0",
+    )
+    .unwrap();
+    let exp = Trace::from_str("y_0 y_1 y_2 y_3\n0 0 0 0\n0 0 0 0\n11 22 33 -125").unwrap();
+    let res = run_ir_prog(&prog, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn wire() {
+    test(
+        "def main(a:i8, b:i4) -> (y0:i8, y1:i8, y2:u8, y3:i4, y4:i12, y5:i8) {
+            y0:i8 = sll[2](a);
+            y1:i8 = sra[2](a);
+            y2:u8 = srl[2](a);
+            y3:i4 = ext[4, 7](a);
+            y4:i12 = cat(b, a);
+            y5:i8 = const[-3];
+        }",
+        "a b\n0x90 0b0101",
+        "y0 y1 y2 y3 y4 y5\n64 -28 36 -7 1424 -3",
+    );
+}
+
+#[test]
+fn compare() {
+    test(
+        "def main(a:i8, b:i8, c:u8, d:u8) -> (y0:bool, y1:bool, y2:bool, y3:i8) {
+            y0:bool = lt(a, b);
+            y1:bool = lt(c, d);
+            y2:bool = neq(a, b);
+            t0:bool = ge(a, b);
+            y3:i8 = mux(t0, a, b);
+        }",
+        "a b c d\n-1 1 255 1\n3 3 0 0",
+        "y0 y1 y2 y3\n1 0 1 1\n0 0 0 3",
+    );
+}
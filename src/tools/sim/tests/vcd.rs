@@ -0,0 +1,136 @@
+use ir::parser::Parser as IrParser;
+use sim::interp::{run_ir_prog, run_ir_prog_with_vcd};
+use sim::trace::Stimulus;
+use sim::xsim::{run_xir_prog, run_xir_prog_with_vcd};
+use std::str::FromStr;
+use xir::parser::Parser as XirParser;
+
+#[test]
+fn add() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/add.ir").unwrap();
+    let stim = Stimulus::from_file("../../../examples/stim/add.stim").unwrap();
+    let select = vec!["y".to_string()];
+    let (_, vcd) = run_ir_prog_with_vcd(&prog, &stim, None, &select).unwrap();
+    let exp = "$timescale 1ns $end
+$scope module main $end
+$var wire 1 ! clock $end
+$var wire 8 \" y $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+1!
+b0 \"
+$end
+#5
+0!
+#10
+1!
+b11 \"
+#15
+0!
+#20
+1!
+b111 \"
+#25
+0!
+#30
+1!
+b1111111 \"
+#35
+0!
+#40
+1!
+#45
+0!
+#50
+";
+    assert_eq!(vcd.to_string(), exp);
+}
+
+#[test]
+fn tadd() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/tadd.ir").unwrap();
+    let stim = Stimulus::from_str("en a_0 a_1 a_2 a_3 b_0 b_1 b_2 b_3\n1 1 2 3 4 5 6 7 8").unwrap();
+    let (_, vcd) = run_ir_prog_with_vcd(&prog, &stim, None, &[]).unwrap();
+    assert_eq!(vcd.scope().name(), "main");
+    assert!(vcd.contains("t2_3"));
+    assert!(vcd.contains("y_0"));
+    let select = vec!["a_1".to_string(), "y".to_string()];
+    let (_, vcd) = run_ir_prog_with_vcd(&prog, &stim, None, &select).unwrap();
+    let names: Vec<&str> = vcd.var().iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(names, vec!["a_1", "y_0", "y_1", "y_2", "y_3"]);
+    assert_eq!(vcd.value()[0][0], Some(2));
+}
+
+#[test]
+fn call() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/call.ir").unwrap();
+    let stim = Stimulus::from_str("a b c en\n1 2 3 1\n4 5 6 1").unwrap();
+    let (trace, vcd) = run_ir_prog_with_vcd(&prog, &stim, None, &[]).unwrap();
+    assert_eq!(trace, run_ir_prog(&prog, &stim, None).unwrap());
+    let text = vcd.to_string();
+    let scope: Vec<&str> = text
+        .lines()
+        .take_while(|l| !l.starts_with("$enddefinitions"))
+        .filter(|l| l.starts_with("$scope") || l.starts_with("$upscope"))
+        .collect();
+    let exp = vec![
+        "$scope module main $end",
+        "$scope module addreg_0 $end",
+        "$upscope $end",
+        "$scope module addreg_1 $end",
+        "$upscope $end",
+        "$upscope $end",
+    ];
+    assert_eq!(scope, exp);
+    let child = &vcd.scope().child()[1];
+    assert_eq!(vcd.scope().find("addreg_1_t0"), child);
+    assert_eq!(vcd.scope().find("t0"), vcd.scope());
+    assert!(text.contains("$scope module addreg_1 $end\n$var wire 8 "));
+    assert!(text.lines().any(|l| l.ends_with(" t0 $end")));
+}
+
+#[test]
+fn unknown() {
+    let prog = IrParser::parse_from_file("../../../examples/ir/add.ir").unwrap();
+    let stim = Stimulus::from_file("../../../examples/stim/add.stim").unwrap();
+    let select = vec!["z".to_string()];
+    assert!(run_ir_prog_with_vcd(&prog, &stim, None, &select).is_err());
+}
+
+#[test]
+fn fsm_3() {
+    let prog = XirParser::parse_from_file("../../../examples/xir/fsm_3.xir").unwrap();
+    let stim = Stimulus::from_file("../../../examples/stim/fsm_3.stim").unwrap();
+    let (trace, vcd) = run_xir_prog_with_vcd(&prog, &stim, None, &[]).unwrap();
+    assert_eq!(trace, run_xir_prog(&prog, &stim, None).unwrap());
+    assert_eq!(vcd.scope().name(), "main");
+    assert_eq!(vcd.len(), stim.len());
+    assert!(vcd.contains("y"));
+    let ir = IrParser::parse_from_file("../../../examples/ir/fsm_3.ir").unwrap();
+    assert_eq!(trace, run_ir_prog(&ir, &stim, None).unwrap());
+}
+
+// a value of main named like a value of an instance stays in main
+#[test]
+fn call_name() {
+    let prog = IrParser::parse_from_str(
+        "def addreg(a:i8, b:i8, en:bool) -> (y:i8) {
+            t0:i8 = add(a, b);
+            y:i8 = reg[0](t0, en);
+        }
+        def main(a:i8, b:i8, en:bool) -> (y:i8, addreg_1_t0:i8) {
+            addreg_1_t0:i8 = add(a, b);
+            y:i8 = addreg(a, b, en);
+        }",
+    )
+    .unwrap();
+    let stim = Stimulus::from_str("a b en\n1 2 1").unwrap();
+    let (_, vcd) = run_ir_prog_with_vcd(&prog, &stim, None, &[]).unwrap();
+    let scope = vcd.scope();
+    assert_eq!(scope.child().len(), 1);
+    assert_eq!(scope.child()[0].name(), "addreg_0");
+    assert_eq!(scope.find("addreg_1_t0"), scope);
+    assert_eq!(scope.find("addreg_0_t0"), &scope.child()[0]);
+}
@@ -0,0 +1,132 @@
+use difftest::stimulus_from_ir_prog;
+use ir::parser::Parser as IrParser;
+use mmap::Mmap;
+use sim::interp::run_ir_prog;
+use sim::trace::{Stimulus, Trace};
+use sim::xsim::{dsp_reg, run_xir_prog};
+use std::str::FromStr;
+use xir::ast::{Expr, ExprTerm, ExprTup, Ty};
+use xir::parser::Parser as XirParser;
+
+fn test(name: &str) {
+    let ir_file = format!("../../../examples/ir/{}.ir", name);
+    let xir_file = format!("../../../examples/xir/{}.xir", name);
+    let ir = IrParser::parse_from_file(ir_file).unwrap();
+    let xir = XirParser::parse_from_file(xir_file).unwrap();
+    let stim = stimulus_from_ir_prog(&ir, 64).unwrap();
+    let exp = run_ir_prog(&ir, &stim, None).unwrap();
+    let res = run_xir_prog(&xir, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn add() {
+    test("add");
+}
+
+#[test]
+fn fsm_3() {
+    test("fsm_3");
+}
+
+#[test]
+fn fsm_5() {
+    test("fsm_5");
+}
+
+#[test]
+fn fsm_7() {
+    test("fsm_7");
+}
+
+#[test]
+fn fsm_9() {
+    test("fsm_9");
+}
+
+#[test]
+fn mux128() {
+    test("mux128");
+}
+
+#[test]
+fn tadd() {
+    test("tadd");
+}
+
+#[test]
+fn tadd_64() {
+    test("tadd_64");
+}
+
+#[test]
+fn tdot() {
+    test("tdot");
+}
+
+#[test]
+fn tdot_5_3() {
+    test("tdot_5_3");
+}
+
+#[test]
+fn lrom_8x8() {
+    let ir = IrParser::parse_from_file("../../../examples/ir/rom_8x8.ir").unwrap();
+    let xir = XirParser::parse_from_file("../../../examples/xir/lrom_8x8.xir").unwrap();
+    let mmap = Mmap::from_file("../../../examples/mmap/lrom_8x8.json");
+    let stim = Stimulus::from_str("a addr\n0 0\n3 3\n7 7\n5 5").unwrap();
+    let exp = run_ir_prog(&ir, &stim, Some(&mmap)).unwrap();
+    let res = run_xir_prog(&xir, &stim, Some(&mmap)).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn brom_8x256() {
+    let xir = XirParser::parse_from_file("../../../examples/xir/brom_8x256.xir").unwrap();
+    let mmap = Mmap::from_file("../../../examples/mmap/brom_8x256.json");
+    let stim = Stimulus::from_str("addr\n0\n1\n2\n0").unwrap();
+    let exp = Trace::from_str("y\n0\n-86\n-69\n-52").unwrap();
+    let res = run_xir_prog(&xir, &stim, Some(&mmap)).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn carrysub() {
+    let xir = XirParser::parse_from_str(
+        "def main(a:i2, b:i2) -> (y:i2) {
+            t0:bool = ext[0](a);
+            t1:bool = ext[1](a);
+            t2:bool = ext[0](b);
+            t3:bool = ext[1](b);
+            t4:bool = lut2[9](t0, t2);
+            t5:bool = lut2[9](t1, t3);
+            t6:i2 = cat(t4, t5);
+            y:i2 = carrysub(a, t6);
+        }",
+    )
+    .unwrap();
+    let stim = Stimulus::from_str("a b\n1 -2\n1 1\n-2 1\n0 1").unwrap();
+    let exp = Trace::from_str("y\n-1\n0\n1\n-1").unwrap();
+    let res = run_xir_prog(&xir, &stim, None).unwrap();
+    assert_eq!(res, exp);
+}
+
+#[test]
+fn dsp_reg_var() {
+    let xir = XirParser::parse_from_str(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8) {
+            y:i8 = muladdrega[0, 0, 0, 1](a, b, c, en, en, en, en);
+        }",
+    )
+    .unwrap();
+    let mut instr = xir.body()[0].mach().unwrap().clone();
+    assert_eq!(dsp_reg(&instr).unwrap(), vec![0, 0, 0, 1]);
+    let stage = vec![
+        ExprTerm::Val(0),
+        ExprTerm::Var("x".into(), Ty::Bool),
+        ExprTerm::Val(0),
+        ExprTerm::Val(1),
+    ];
+    instr.attr = Expr::from(ExprTup::from(stage));
+    assert!(dsp_reg(&instr).is_err());
+}
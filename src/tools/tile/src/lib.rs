@@ -1,7 +1,12 @@
-use asm::ast::{Expr, ExprTerm, ExprTup, Instr, InstrAsm, InstrWire, Loc, OpAsm, OpWire, Prog, Ty};
+use ::xim::parser::Parser as XimParser;
+use asm::ast::{
+    Expr, ExprTerm, ExprTup, Instr, InstrAsm, InstrWire, Loc, OpAsm, OpWire, Prog, Span, Ty,
+};
 use asm::parser::Parser;
 use regex::Regex;
+use std::cmp::Reverse;
 use std::path::Path;
+use xim::ast as xim;
 
 #[derive(Clone, Debug)]
 pub struct Namer {
@@ -81,79 +86,168 @@ fn get_max(body: &[Instr]) -> i64 {
     val
 }
 
-fn emit_term(name: &str, width: u64) -> ExprTerm {
-    ExprTerm::Var(name.to_string(), Ty::SInt(width))
-}
-
-fn emit_term_bool(name: &str) -> ExprTerm {
-    ExprTerm::Var(name.to_string(), Ty::Bool)
-}
-
-fn emit_expr(name: &str, width: u64) -> Expr {
-    Expr::Term(emit_term(name, width))
+// same kind of type with another width
+fn resize(ty: &Ty, width: u64) -> Ty {
+    match ty {
+        Ty::UInt(_) => Ty::UInt(width),
+        Ty::SInt(_) => Ty::SInt(width),
+        _ => ty.clone(),
+    }
 }
 
-fn emit_ext(instr: &InstrAsm, dst: Expr, arg: usize, start: i64, end: i64) -> Instr {
-    let attr_term = vec![ExprTerm::Val(start), ExprTerm::Val(end)];
-    let attr = Expr::from(ExprTup { term: attr_term });
-    let arg_term = vec![instr.arg().get_term(arg).unwrap().clone()];
-    let arg = Expr::from(ExprTup { term: arg_term });
-    Instr::from(InstrWire {
-        op: OpWire::Ext,
-        dst,
-        attr,
-        arg,
-    })
+fn emit_term(name: &str, ty: &Ty) -> ExprTerm {
+    ExprTerm::Var(name.to_string(), ty.clone())
 }
 
-fn emit_cat(dst: Expr, arg: &[String]) -> Instr {
-    let mut arg_term: Vec<ExprTerm> = Vec::new();
-    for a in arg {
-        arg_term.push(emit_term(a, 8));
-    }
+fn emit_wire(op: OpWire, dst: Expr, attr: &[i64], arg: Vec<ExprTerm>) -> Instr {
+    let attr_term: Vec<ExprTerm> = attr.iter().map(|v| ExprTerm::Val(*v)).collect();
     Instr::from(InstrWire {
-        op: OpWire::Cat,
+        op,
         dst,
-        attr: Expr::default(),
-        arg: Expr::from(ExprTup::from(arg_term)),
+        attr: Expr::from(ExprTup::from(attr_term)),
+        arg: Expr::from(ExprTup::from(arg)),
+        span: Span::default(),
     })
 }
 
-fn emit_op(op: &str, dst: &str, arg: &[String], loc: &Loc) -> Instr {
+fn emit_op(op: &str, dst: Vec<ExprTerm>, arg: Vec<ExprTerm>, loc: &Loc) -> Instr {
     let op_asm: OpAsm = op.to_string().into();
-    let dst = emit_expr(dst, 8);
-    let mut arg_term: Vec<ExprTerm> = Vec::new();
-    for a in arg {
-        arg_term.push(emit_term(a, 8));
-    }
+    let dst = if dst.len() == 1 {
+        Expr::Term(dst[0].clone())
+    } else {
+        Expr::from(ExprTup::from(dst))
+    };
     Instr::from(InstrAsm {
         op: op_asm,
         dst,
-        arg: Expr::from(ExprTup::from(arg_term)),
+        arg: Expr::from(ExprTup::from(arg)),
         loc: loc.clone(),
+        span: Span::default(),
     })
 }
 
-fn emit_op_mux(op: &str, dst: &str, arg: &[String], loc: &Loc) -> Instr {
-    let op_asm: OpAsm = op.to_string().into();
-    let dst = emit_expr(dst, 8);
-    let mut arg_term: Vec<ExprTerm> = Vec::new();
-    for (i, a) in arg.iter().enumerate() {
-        if i == 1 || i == 2 {
-            arg_term.push(emit_term(a, 8));
+// slices of a split implementation with their width, widest first
+fn slice_from_imp(imp: &xim::Imp, target: &xim::Target) -> Vec<(xim::Imp, u64)> {
+    let mut slice: Vec<(xim::Imp, u64)> = imp
+        .split()
+        .slice()
+        .iter()
+        .filter_map(|name| target.instance(name).ok().flatten())
+        .filter_map(|s| {
+            let width = s.output().get_ty(0).ok().and_then(|ty| ty.width());
+            width.map(|w| (s, w))
+        })
+        .collect();
+    slice.sort_by_key(|s| Reverse(s.1));
+    slice
+}
+
+// Split a wide instruction onto the slices of its implementation. Each slice
+// takes the widest implementation that fits in the bits left, the last one is
+// zero-padded when none fits. Bool arguments go to every slice and chained
+// slices pass a carry from one to the next.
+fn split_instr(
+    asm: &InstrAsm,
+    imp: &xim::Imp,
+    target: &xim::Target,
+    namer: &mut Namer,
+) -> Option<Vec<Instr>> {
+    let slice = slice_from_imp(imp, target);
+    let width = asm.dst().get_ty(0).ok()?.width()?;
+    let input: Vec<ExprTerm> = imp.input().clone().into();
+    let mut body: Vec<Instr> = Vec::new();
+    let mut cat: Vec<ExprTerm> = Vec::new();
+    let mut carry: Option<ExprTerm> = None;
+    if imp.split().is_chain() {
+        let ci = emit_term(&namer.next_name(), &Ty::Bool);
+        body.push(emit_wire(OpWire::Con, Expr::from(ci.clone()), &[0], vec![]));
+        carry = Some(ci);
+    }
+    let mut lo: u64 = 0;
+    while lo < width {
+        let rem = width - lo;
+        let (s, w) = slice
+            .iter()
+            .find(|(_, w)| *w <= rem)
+            .or_else(|| slice.last())
+            .cloned()?;
+        let used = w.min(rem);
+        let s_input: Vec<ExprTerm> = s.input().clone().into();
+        let mut arg: Vec<ExprTerm> = Vec::new();
+        for (i, term) in input.iter().enumerate() {
+            let a = asm.arg().get_term(i).ok()?.clone();
+            if term.ty() == Some(&Ty::Bool) {
+                arg.push(a);
+                continue;
+            }
+            let ty = s_input.get(i)?.ty()?;
+            let part = emit_term(&namer.next_name(), &resize(ty, used));
+            let end = (lo + used - 1) as i64;
+            body.push(emit_wire(
+                OpWire::Ext,
+                Expr::from(part.clone()),
+                &[lo as i64, end],
+                vec![a],
+            ));
+            if used < w {
+                let zero = emit_term(&namer.next_name(), &resize(ty, w - used));
+                body.push(emit_wire(
+                    OpWire::Con,
+                    Expr::from(zero.clone()),
+                    &[0],
+                    vec![],
+                ));
+                let pad = emit_term(&namer.next_name(), ty);
+                body.push(emit_wire(
+                    OpWire::Cat,
+                    Expr::from(pad.clone()),
+                    &[],
+                    vec![zero, part],
+                ));
+                arg.push(pad);
+            } else {
+                arg.push(part);
+            }
+        }
+        let out_ty = s.output().get_ty(0).ok()?;
+        let out = emit_term(&namer.next_name(), out_ty);
+        let mut dst = vec![out.clone()];
+        if let Some(ci) = carry.take() {
+            arg.push(ci);
+            let co = emit_term(&namer.next_name(), &Ty::Bool);
+            dst.push(co.clone());
+            carry = Some(co);
+        }
+        body.push(emit_op(&s.id(), dst, arg, asm.loc()));
+        if used < w {
+            let part = emit_term(&namer.next_name(), &resize(out_ty, used));
+            let end = (used - 1) as i64;
+            body.push(emit_wire(
+                OpWire::Ext,
+                Expr::from(part.clone()),
+                &[0, end],
+                vec![out],
+            ));
+            cat.push(part);
         } else {
-            arg_term.push(emit_term_bool(a));
+            cat.push(out);
         }
+        lo += used;
     }
-    Instr::from(InstrAsm {
-        op: op_asm,
-        dst,
-        arg: Expr::from(ExprTup::from(arg_term)),
-        loc: loc.clone(),
-    })
+    // cat takes the most significant slice first
+    cat.reverse();
+    body.push(emit_wire(OpWire::Cat, asm.dst().clone(), &[], cat));
+    Some(body)
 }
 
+// wide operations are split with the implementations in lut.xim
 pub fn tile_from_prog(input: &Prog) -> Prog {
+    let target = XimParser::parse_from_str(include_str!("../../../../examples/xim/lut.xim"))
+        .expect("Error: cannot parse lut.xim");
+    tile_from_prog_with_target(input, &target)
+}
+
+pub fn tile_from_prog_with_target(input: &Prog, target: &xim::Target) -> Prog {
     let mut body: Vec<Instr> = Vec::new();
     let max = get_max(input.body());
     let init = if max < 0 { 0_u64 } else { (max + 1) as u64 };
@@ -162,75 +256,23 @@ pub fn tile_from_prog(input: &Prog) -> Prog {
         match instr {
             Instr::Wire(_) => body.push(instr.clone()),
             Instr::Asm(asm) => {
-                match asm.op().to_string().as_str() {
-                    "lxor_i128" | "lxor_i32" => {
-                        let num = (asm.dst().get_ty(0).unwrap().width().unwrap() / 8) as i64;
-                        let mut cat: Vec<String> = Vec::new();
-                        for i in 0..num {
-                            let mut arg: Vec<String> = Vec::new();
-                            for a in 0..2 {
-                                let name = namer.next_name();
-                                arg.push(name.clone());
-                                let dst = emit_expr(&name, 8);
-                                let new = emit_ext(asm, dst, a, i * 8, (i + 1) * 8 - 1);
-                                body.push(new);
-                            }
-                            let name = namer.next_name();
-                            cat.push(name.clone());
-                            let new = emit_op("lxor_i8", &name, &arg, asm.loc());
-                            body.push(new);
-                        }
-                        let new = emit_cat(asm.dst().clone(), &cat);
-                        body.push(new);
-                    }
-                    "lmux_i128" => {
-                        let num = (asm.dst().get_ty(0).unwrap().width().unwrap() / 8) as i64;
-                        let mut cat: Vec<String> = Vec::new();
-                        for i in 0..num {
-                            let c = asm.arg().get_id(0).unwrap();
-                            let mut arg: Vec<String> = Vec::new();
-                            arg.push(c);
-                            for a in 1..3 {
-                                let name = namer.next_name();
-                                arg.push(name.clone());
-                                let dst = emit_expr(&name, 8);
-                                let new = emit_ext(asm, dst, a, i * 8, (i + 1) * 8 - 1);
-                                body.push(new);
-                            }
-                            let name = namer.next_name();
-                            cat.push(name.clone());
-                            let new = emit_op_mux("lmux_i8", &name, &arg, asm.loc());
-                            body.push(new);
-                        }
-                        let new = emit_cat(asm.dst().clone(), &cat);
-                        body.push(new);
-                    }
-                    "lmuxrega_i128" => {
-                        let num = (asm.dst().get_ty(0).unwrap().width().unwrap() / 8) as i64;
-                        let mut cat: Vec<String> = Vec::new();
-                        for i in 0..num {
-                            let c = asm.arg().get_id(0).unwrap();
-                            let mut arg: Vec<String> = Vec::new();
-                            arg.push(c);
-                            for a in 1..3 {
-                                let name = namer.next_name();
-                                arg.push(name.clone());
-                                let dst = emit_expr(&name, 8);
-                                let new = emit_ext(asm, dst, a, i * 8, (i + 1) * 8 - 1);
-                                body.push(new);
-                            }
-                            let e = asm.arg().get_id(3).unwrap();
-                            arg.push(e);
-                            let name = namer.next_name();
-                            cat.push(name.clone());
-                            let new = emit_op_mux("lmuxrega_i8", &name, &arg, asm.loc());
-                            body.push(new);
-                        }
-                        let new = emit_cat(asm.dst().clone(), &cat);
-                        body.push(new);
+                let start = body.len();
+                let split = target
+                    .instance(&asm.op().to_string())
+                    .ok()
+                    .flatten()
+                    .filter(|imp| !imp.split().is_none())
+                    .and_then(|imp| split_instr(asm, &imp, target, &mut namer));
+                match split {
+                    Some(tiles) => body.extend(tiles),
+                    None => body.push(instr.clone()),
+                }
+                // tiles keep the location of the instruction they come from
+                if let Some(span) = asm.span() {
+                    for new in body[start..].iter_mut() {
+                        new.set_span(span.clone());
                     }
-                    _ => body.push(instr.clone()),
-                };
+                }
             }
         }
     }
@@ -1,7 +1,8 @@
 use asm::errors::Error;
 use asm::parser::Parser;
 use pretty_assertions::assert_eq;
-use tile::tile_from_prog;
+use tile::{tile_from_prog, tile_from_prog_with_target};
+use xim::parser::Parser as XimParser;
 
 fn test(input: &str, output: &str) -> Result<(), Error> {
     let prog = Parser::parse_from_str(input)?;
@@ -11,6 +12,15 @@ fn test(input: &str, output: &str) -> Result<(), Error> {
     Ok(())
 }
 
+fn test_with_target(input: &str, output: &str, target: &str) -> Result<(), Error> {
+    let prog = Parser::parse_from_str(input)?;
+    let target = XimParser::parse_from_str(target).unwrap();
+    let res = tile_from_prog_with_target(&prog, &target);
+    let exp = Parser::parse_from_str(output)?;
+    assert_eq!(res, exp);
+    Ok(())
+}
+
 #[test]
 fn tile_xor128() -> Result<(), Error> {
     let input = r#"def main(a:i128, b:i128) -> (y:i128) {
@@ -65,7 +75,7 @@ fn tile_xor128() -> Result<(), Error> {
     t45:i8 = ext[120, 127](a);
     t46:i8 = ext[120, 127](b);
     t47:i8 = lxor_i8(t45, t46) @lut(??, ??);
-    y:i128 = cat(t2, t5, t8, t11, t14, t17, t20, t23, t26, t29, t32, t35, t38, t41, t44, t47);
+    y:i128 = cat(t47, t44, t41, t38, t35, t32, t29, t26, t23, t20, t17, t14, t11, t8, t5, t2);
 }"#;
     Ok(test(input, output)?)
 }
@@ -88,7 +98,7 @@ fn tile_xor32() -> Result<(), Error> {
     t9:i8 = ext[24, 31](a);
     t10:i8 = ext[24, 31](b);
     t11:i8 = lxor_i8(t9, t10) @lut(??, ??);
-    y:i32 = cat(t2, t5, t8, t11);
+    y:i32 = cat(t11, t8, t5, t2);
 }"#;
     Ok(test(input, output)?)
 }
@@ -147,7 +157,7 @@ fn tile_mux128() -> Result<(), Error> {
     t45:i8 = ext[120, 127](a);
     t46:i8 = ext[120, 127](b);
     t47:i8 = lmux_i8(c, t45, t46) @lut(??, ??);
-    y:i128 = cat(t2, t5, t8, t11, t14, t17, t20, t23, t26, t29, t32, t35, t38, t41, t44, t47);
+    y:i128 = cat(t47, t44, t41, t38, t35, t32, t29, t26, t23, t20, t17, t14, t11, t8, t5, t2);
 }"#;
     Ok(test(input, output)?)
 }
@@ -206,7 +216,72 @@ fn tile_muxreg128() -> Result<(), Error> {
     t45:i8 = ext[120, 127](a);
     t46:i8 = ext[120, 127](b);
     t47:i8 = lmuxrega_i8(c, t45, t46, en) @lut(??, ??);
-    y:i128 = cat(t2, t5, t8, t11, t14, t17, t20, t23, t26, t29, t32, t35, t38, t41, t44, t47);
+    y:i128 = cat(t47, t44, t41, t38, t35, t32, t29, t26, t23, t20, t17, t14, t11, t8, t5, t2);
+}"#;
+    Ok(test(input, output)?)
+}
+
+#[test]
+fn tile_xor20() -> Result<(), Error> {
+    let target = r#"imp lxor_i8[1, 2](a:i8, b:i8) -> (y:i8) {}
+imp lxor_i4[1, 2](a:i4, b:i4) -> (y:i4) {}
+imp lxor_i20[3, 2](a:i20, b:i20) -> (y:i20) bitwise(lxor_i4, lxor_i8) {}"#;
+    let input = r#"def main(a:i20, b:i20) -> (y:i20) {
+    y:i20 = lxor_i20(a, b) @lut(??, ??);
+}"#;
+    let output = r#"def main(a:i20, b:i20) -> (y:i20) {
+    t0:i8 = ext[0, 7](a);
+    t1:i8 = ext[0, 7](b);
+    t2:i8 = lxor_i8(t0, t1) @lut(??, ??);
+    t3:i8 = ext[8, 15](a);
+    t4:i8 = ext[8, 15](b);
+    t5:i8 = lxor_i8(t3, t4) @lut(??, ??);
+    t6:i4 = ext[16, 19](a);
+    t7:i4 = ext[16, 19](b);
+    t8:i4 = lxor_i4(t6, t7) @lut(??, ??);
+    y:i20 = cat(t8, t5, t2);
+}"#;
+    Ok(test_with_target(input, output, target)?)
+}
+
+#[test]
+fn tile_xor12_pad() -> Result<(), Error> {
+    let target = r#"imp lxor_i8[1, 2](a:i8, b:i8) -> (y:i8) {}
+imp lxor_i12[2, 2](a:i12, b:i12) -> (y:i12) bitwise(lxor_i8) {}"#;
+    let input = r#"def main(a:i12, b:i12) -> (y:i12) {
+    y:i12 = lxor_i12(a, b) @lut(??, ??);
+}"#;
+    let output = r#"def main(a:i12, b:i12) -> (y:i12) {
+    t0:i8 = ext[0, 7](a);
+    t1:i8 = ext[0, 7](b);
+    t2:i8 = lxor_i8(t0, t1) @lut(??, ??);
+    t3:i4 = ext[8, 11](a);
+    t4:i4 = const[0];
+    t5:i8 = cat(t4, t3);
+    t6:i4 = ext[8, 11](b);
+    t7:i4 = const[0];
+    t8:i8 = cat(t7, t6);
+    t9:i8 = lxor_i8(t5, t8) @lut(??, ??);
+    t10:i4 = ext[0, 3](t9);
+    y:i12 = cat(t10, t2);
+}"#;
+    Ok(test_with_target(input, output, target)?)
+}
+
+#[test]
+fn tile_add16_chain() -> Result<(), Error> {
+    let input = r#"def main(a:i16, b:i16) -> (y:i16) {
+    y:i16 = ladd_i16(a, b) @lut(??, ??);
+}"#;
+    let output = r#"def main(a:i16, b:i16) -> (y:i16) {
+    t0:bool = const[0];
+    t1:i8 = ext[0, 7](a);
+    t2:i8 = ext[0, 7](b);
+    (t3:i8, t4:bool) = laddc_i8(t1, t2, t0) @lut(??, ??);
+    t5:i8 = ext[8, 15](a);
+    t6:i8 = ext[8, 15](b);
+    (t7:i8, t8:bool) = laddc_i8(t5, t6, t4) @lut(??, ??);
+    y:i16 = cat(t7, t3);
 }"#;
     Ok(test(input, output)?)
 }
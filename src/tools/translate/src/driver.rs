@@ -1,14 +1,26 @@
 use crate::errors::Error;
 use crate::opt::{Lang, Opt};
 use asm::parser::Parser as AsmParser;
-use bler::try_from_asm_prog as asm_try_into_xir;
-use bline::try_from_ir_prog as ir_try_into_behav;
+use bler::try_from_asm_prog_with_target as asm_try_into_xir;
+use bline::try_from_ir_prog_with_mmap as ir_try_into_behav;
+use device::Device;
+use difftest::{run_diff_test_with_library, stimulus_from_ir_prog, CYCLES};
 use io::write_to_file;
+use ir::check::check_prog;
 use ir::parser::Parser as IrParser;
-use isel::try_from_ir_prog as ir_try_into_asm;
-use std::path::PathBuf;
+use isel::cost::Cost;
+use isel::explain::explain_ir_prog;
+use isel::library::Library;
+use isel::try_from_ir_prog_with_cost as ir_try_into_asm;
+use place::check::{check_asm_prog, check_xir_prog};
+use place::place_prog_with_target;
+use sim::interp::{run_ir_prog, run_ir_prog_with_vcd};
+use sim::trace::Stimulus;
+use sim::xsim::{run_xir_prog, run_xir_prog_with_vcd};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use xir::parser::Parser as XirParser;
+use xpand::hier::try_from_ir_prog_with_library as ir_try_into_struct;
 use xpand::try_from_xir_prog as xir_try_into_struct;
 
 #[derive(Clone, Debug)]
@@ -24,6 +36,13 @@ impl Default for Driver {
     }
 }
 
+// programs are type checked before any backend sees them
+fn ir_from_file(path: &Path) -> Result<ir::ast::Prog, Error> {
+    let prog = IrParser::parse_from_file(path)?;
+    check_prog(&prog)?;
+    Ok(prog)
+}
+
 fn write_output(path: Option<&PathBuf>, contents: &str) {
     if let Some(output) = path {
         write_to_file(output, contents);
@@ -39,48 +58,156 @@ impl Driver {
     pub fn opts(&self) -> &Opt {
         &self.opts
     }
+    pub fn stimulus(&self) -> Result<Stimulus, Error> {
+        match self.opts().stimulus() {
+            Some(path) => Ok(Stimulus::from_file(path)?),
+            None => Err(Error::new_driver_error("Simulation needs a stimulus file")),
+        }
+    }
+    // target directory first, then pattern files, otherwise the built-in library
+    pub fn library(&self) -> Result<Library, Error> {
+        if let Some(dir) = self.opts().target() {
+            Ok(Library::from_dir(dir)?)
+        } else if !self.opts().pat().is_empty() || !self.opts().xim().is_empty() {
+            Ok(Library::from_files(self.opts().pat(), self.opts().xim())?)
+        } else {
+            Ok(Library::builtin())
+        }
+    }
+    pub fn cost(&self) -> Result<Cost, Error> {
+        let mut cost = Cost::new(self.opts().cost().clone());
+        cost.set_select(self.opts().select().clone());
+        for budget in self.opts().budget() {
+            cost.add_budget_from_str(budget)?;
+        }
+        Ok(cost)
+    }
+    // asm is placed only when a device is given
+    pub fn place(&self, prog: asm::ast::Prog, lib: &Library) -> Result<asm::ast::Prog, Error> {
+        if let Some(path) = self.opts().device() {
+            let device = Device::from_file(path)?;
+            let mode = self.opts().place();
+            Ok(place_prog_with_target(&prog, &device, mode, &lib.imp())?)
+        } else {
+            Ok(prog)
+        }
+    }
+    pub fn check_place(
+        &self,
+        input: &Path,
+        output: Option<&PathBuf>,
+        lib: &Library,
+    ) -> Result<(), Error> {
+        let device = match self.opts().device() {
+            Some(path) => Device::from_file(path)?,
+            None => {
+                return Err(Error::new_driver_error(
+                    "Placement checks need a device file",
+                ))
+            }
+        };
+        let violation = match self.opts().from() {
+            Lang::Asm => {
+                let prog = AsmParser::parse_from_file(input)?;
+                check_asm_prog(&prog, &device, &lib.imp())?
+            }
+            Lang::Xir => {
+                let prog = XirParser::parse_from_file(input)?;
+                check_xir_prog(&prog, &device)?
+            }
+            _ => {
+                return Err(Error::new_driver_error(
+                    "Placement can only be checked for asm and xir programs",
+                ))
+            }
+        };
+        if violation.is_empty() {
+            write_output(output, "placement is legal");
+            Ok(())
+        } else {
+            let report: Vec<String> = violation.iter().map(|v| v.to_string()).collect();
+            write_output(output, &report.join("\n"));
+            Err(Error::new_driver_error("Placement is not legal"))
+        }
+    }
+    pub fn explain(
+        &self,
+        input: &Path,
+        output: Option<&PathBuf>,
+        lib: &Library,
+        cost: &Cost,
+    ) -> Result<(), Error> {
+        if !matches!(self.opts().from(), Lang::Ir) {
+            return Err(Error::new_driver_error(
+                "Selection can only be explained for ir programs",
+            ));
+        }
+        let ir = ir_from_file(input)?;
+        let uncovered = explain_ir_prog(&ir, lib, cost)?;
+        if uncovered.is_empty() {
+            write_output(output, "every instruction is covered");
+        } else {
+            let report: Vec<String> = uncovered.iter().map(|u| u.to_string()).collect();
+            write_output(output, &report.join("\n\n"));
+        }
+        Ok(())
+    }
     pub fn run(&self) -> Result<(), Error> {
+        let lib = self.library()?;
+        let cost = self.cost()?;
         let input = self.opts().input();
         let output = self.opts().output();
+        if self.opts().explain_isel() {
+            return self.explain(input, output, &lib, &cost);
+        }
+        if self.opts().check_place() {
+            return self.check_place(input, output, &lib);
+        }
         match (self.opts().from(), self.opts().to(), self.opts().mmap()) {
             (Lang::Ir, Lang::Asm, _) => {
-                let ir = IrParser::parse_from_file(input)?;
-                let asm = ir_try_into_asm(&ir)?;
+                let ir = ir_from_file(input)?;
+                let asm = self.place(ir_try_into_asm(&ir, &lib, &cost)?, &lib)?;
                 write_output(output, &asm.to_string());
                 Ok(())
             }
             (Lang::Ir, Lang::Xir, _) => {
-                let ir = IrParser::parse_from_file(input)?;
-                let asm = ir_try_into_asm(&ir)?;
-                let (xir, _) = asm_try_into_xir(&asm, None)?;
+                let ir = ir_from_file(input)?;
+                let asm = self.place(ir_try_into_asm(&ir, &lib, &cost)?, &lib)?;
+                let (xir, _) = asm_try_into_xir(&asm, None, &lib.imp())?;
                 write_output(output, &xir.to_string());
                 Ok(())
             }
-            (Lang::Ir, Lang::Behav, _) => {
-                let ir = IrParser::parse_from_file(input)?;
-                let behav_prog = ir_try_into_behav(&ir)?;
+            (Lang::Ir, Lang::Behav, mmap) => {
+                let ir = ir_from_file(input)?;
+                let mmap = mmap.map(mmap::Mmap::from_file);
+                let behav_prog = ir_try_into_behav(&ir, mmap.as_ref())?;
                 write_output(output, &behav_prog.to_string());
                 Ok(())
             }
+            (Lang::Asm, Lang::Asm, _) => {
+                let prog = self.place(AsmParser::parse_from_file(input)?, &lib)?;
+                write_output(output, &prog.to_string());
+                Ok(())
+            }
             (Lang::Asm, Lang::Xir, _) => {
-                let prog = AsmParser::parse_from_file(input)?;
-                let (xir, _) = asm_try_into_xir(&prog, None)?;
+                let prog = self.place(AsmParser::parse_from_file(input)?, &lib)?;
+                let (xir, _) = asm_try_into_xir(&prog, None, &lib.imp())?;
                 write_output(output, &xir.to_string());
                 Ok(())
             }
             (Lang::Asm, Lang::Struct, _) => {
-                let prog = AsmParser::parse_from_file(input)?;
-                let (xir, _) = asm_try_into_xir(&prog, None)?;
+                let prog = self.place(AsmParser::parse_from_file(input)?, &lib)?;
+                let (xir, _) = asm_try_into_xir(&prog, None, &lib.imp())?;
                 let sct = xir_try_into_struct(&xir, None)?;
                 write_output(output, &sct.to_string());
                 Ok(())
             }
-            (Lang::Ir, Lang::Struct, None) => {
-                let ir = IrParser::parse_from_file(input)?;
-                let asm = ir_try_into_asm(&ir)?;
-                let (xir, _) = asm_try_into_xir(&asm, None)?;
-                let sct = xir_try_into_struct(&xir, None)?;
-                write_output(output, &sct.to_string());
+            (Lang::Ir, Lang::Struct, mmap) => {
+                let ir = ir_from_file(input)?;
+                let mmap = mmap.map(mmap::Mmap::from_file);
+                let sct = ir_try_into_struct(&ir, mmap.as_ref(), &lib, &cost)?;
+                let sct: Vec<String> = sct.iter().map(|m| m.to_string()).collect();
+                write_output(output, &sct.join("\n"));
                 Ok(())
             }
             (Lang::Xir, Lang::Struct, None) => {
@@ -89,15 +216,6 @@ impl Driver {
                 write_output(output, &sct.to_string());
                 Ok(())
             }
-            (Lang::Ir, Lang::Struct, Some(path)) => {
-                let ir = IrParser::parse_from_file(input)?;
-                let asm = ir_try_into_asm(&ir)?;
-                let mmap = Some(mmap::Mmap::from_file(path));
-                let (xir, mmap) = asm_try_into_xir(&asm, mmap.as_ref())?;
-                let sct = xir_try_into_struct(&xir, mmap.as_ref())?;
-                write_output(output, &sct.to_string());
-                Ok(())
-            }
             (Lang::Xir, Lang::Struct, Some(path)) => {
                 let prog = XirParser::parse_from_file(input)?;
                 let mmap = Some(mmap::Mmap::from_file(path));
@@ -105,6 +223,58 @@ impl Driver {
                 write_output(output, &sct.to_string());
                 Ok(())
             }
+            (Lang::Ir, Lang::Sim, mmap) => {
+                let ir = ir_from_file(input)?;
+                let stimulus = self.stimulus()?;
+                let mmap = mmap.map(mmap::Mmap::from_file);
+                let trace = if let Some(path) = self.opts().vcd() {
+                    let probe = self.opts().probe();
+                    let (trace, vcd) = run_ir_prog_with_vcd(&ir, &stimulus, mmap.as_ref(), probe)?;
+                    write_to_file(path, &vcd.to_string());
+                    trace
+                } else {
+                    run_ir_prog(&ir, &stimulus, mmap.as_ref())?
+                };
+                write_output(output, &trace.to_string());
+                Ok(())
+            }
+            (Lang::Xir, Lang::Sim, mmap) => {
+                let prog = XirParser::parse_from_file(input)?;
+                let stimulus = self.stimulus()?;
+                let mmap = mmap.map(mmap::Mmap::from_file);
+                let trace = if let Some(path) = self.opts().vcd() {
+                    let probe = self.opts().probe();
+                    let (trace, vcd) =
+                        run_xir_prog_with_vcd(&prog, &stimulus, mmap.as_ref(), probe)?;
+                    write_to_file(path, &vcd.to_string());
+                    trace
+                } else {
+                    run_xir_prog(&prog, &stimulus, mmap.as_ref())?
+                };
+                write_output(output, &trace.to_string());
+                Ok(())
+            }
+            (Lang::Ir, Lang::Diff, mmap) => {
+                let ir = ir_from_file(input)?;
+                let stimulus = match self.opts().stimulus() {
+                    Some(path) => Stimulus::from_file(path)?,
+                    None => stimulus_from_ir_prog(&ir, CYCLES)?,
+                };
+                let mmap = mmap.map(mmap::Mmap::from_file);
+                let report =
+                    run_diff_test_with_library(&ir, &stimulus, mmap.as_ref(), &lib, &cost)?;
+                write_output(output, &report.to_string());
+                let pass = if self.opts().allow_skip() {
+                    report.is_pass_or_skip()
+                } else {
+                    report.is_pass()
+                };
+                if pass {
+                    Ok(())
+                } else {
+                    Err(Error::new_driver_error("Differential test failed"))
+                }
+            }
             (_, _, _) => Err(Error::new_driver_error("Unsupported conversion")),
         }
     }
@@ -1,8 +1,12 @@
 use asm::errors::Error as AsmError;
 use bler::errors::Error as BlerError;
 use bline::errors::Error as BlineError;
+use device::errors::Error as DeviceError;
+use difftest::errors::Error as DiffTestError;
 use ir::errors::Error as IrError;
 use isel::errors::Error as ISelError;
+use place::errors::Error as PlaceError;
+use sim::errors::Error as SimError;
 use std::fmt;
 use xir::errors::Error as XirError;
 use xpand::errors::Error as XpandError;
@@ -18,6 +22,10 @@ pub enum Error {
     Bler(BlerError),
     Bline(BlineError),
     Xpand(XpandError),
+    Sim(SimError),
+    Device(DeviceError),
+    Place(PlaceError),
+    DiffTest(DiffTestError),
 }
 
 impl Error {
@@ -71,6 +79,30 @@ impl From<XpandError> for Error {
     }
 }
 
+impl From<SimError> for Error {
+    fn from(e: SimError) -> Self {
+        Error::Sim(e)
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(e: DeviceError) -> Self {
+        Error::Device(e)
+    }
+}
+
+impl From<PlaceError> for Error {
+    fn from(e: PlaceError) -> Self {
+        Error::Place(e)
+    }
+}
+
+impl From<DiffTestError> for Error {
+    fn from(e: DiffTestError) -> Self {
+        Error::DiffTest(e)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -83,6 +115,10 @@ impl fmt::Display for Error {
             Error::Opt(msg) => write!(f, "{}", msg),
             Error::Driver(msg) => write!(f, "{}", msg),
             Error::Xpand(msg) => write!(f, "{}", msg),
+            Error::Sim(msg) => write!(f, "{}", msg),
+            Error::Device(msg) => write!(f, "{}", msg),
+            Error::Place(msg) => write!(f, "{}", msg),
+            Error::DiffTest(msg) => write!(f, "{}", msg),
         }
     }
 }
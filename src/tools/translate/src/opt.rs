@@ -1,4 +1,6 @@
 use crate::errors::Error;
+use isel::cost::{CostModel, Select};
+use place::Mode;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -19,6 +21,62 @@ pub struct Opt {
     #[structopt(long = "mmap", parse(from_os_str))]
     pub mmap: Option<PathBuf>,
 
+    // Stimulus file
+    #[structopt(long = "stimulus", parse(from_os_str))]
+    pub stimulus: Option<PathBuf>,
+
+    // Let the differential test pass when a backend does not support the program
+    #[structopt(long = "allow-skip")]
+    pub allow_skip: bool,
+
+    // Waveform file written by the simulation
+    #[structopt(long = "vcd", parse(from_os_str))]
+    pub vcd: Option<PathBuf>,
+
+    // Signals in the waveform file, all of them if none is given
+    #[structopt(long = "probe", number_of_values = 1)]
+    pub probe: Vec<String>,
+
+    // Target directory with pattern (.pat) and implementation (.xim) files
+    #[structopt(long = "target", parse(from_os_str))]
+    pub target: Option<PathBuf>,
+
+    // Pattern files, each paired with the implementation file at the same position
+    #[structopt(long = "pat", parse(from_os_str), number_of_values = 1)]
+    pub pat: Vec<PathBuf>,
+
+    // Implementation files
+    #[structopt(long = "xim", parse(from_os_str), number_of_values = 1)]
+    pub xim: Vec<PathBuf>,
+
+    // Cost minimized by instruction selection: area, perf or mixed:<area>,<perf>
+    #[structopt(long = "cost", default_value = "perf")]
+    pub cost: CostModel,
+
+    // Maximum area of a prim, e.g. dsp=4, patterns of other prims are used past it
+    #[structopt(long = "budget", number_of_values = 1)]
+    pub budget: Vec<String>,
+
+    // Selector: greedy per tree, dp or exact over the dataflow graph
+    #[structopt(long = "select", default_value = "greedy")]
+    pub select: Select,
+
+    // List the instructions selection leaves uncovered and the closest patterns
+    #[structopt(long = "explain-isel")]
+    pub explain_isel: bool,
+
+    // Device file with the sites of every prim, asm instructions are placed on it
+    #[structopt(long = "device", parse(from_os_str))]
+    pub device: Option<PathBuf>,
+
+    // Placer: greedy, or anneal to minimize wirelength
+    #[structopt(long = "place", default_value = "greedy")]
+    pub place: Mode,
+
+    // Check the locations of an asm or xir program against the device
+    #[structopt(long = "check-place")]
+    pub check_place: bool,
+
     // Output file
     #[structopt(short = "o", long = "output", parse(from_os_str))]
     pub output: Option<PathBuf>,
@@ -39,6 +97,48 @@ impl Opt {
     pub fn mmap(&self) -> Option<&PathBuf> {
         self.mmap.as_ref()
     }
+    pub fn stimulus(&self) -> Option<&PathBuf> {
+        self.stimulus.as_ref()
+    }
+    pub fn allow_skip(&self) -> bool {
+        self.allow_skip
+    }
+    pub fn vcd(&self) -> Option<&PathBuf> {
+        self.vcd.as_ref()
+    }
+    pub fn probe(&self) -> &Vec<String> {
+        &self.probe
+    }
+    pub fn target(&self) -> Option<&PathBuf> {
+        self.target.as_ref()
+    }
+    pub fn pat(&self) -> &Vec<PathBuf> {
+        &self.pat
+    }
+    pub fn xim(&self) -> &Vec<PathBuf> {
+        &self.xim
+    }
+    pub fn cost(&self) -> &CostModel {
+        &self.cost
+    }
+    pub fn budget(&self) -> &Vec<String> {
+        &self.budget
+    }
+    pub fn select(&self) -> &Select {
+        &self.select
+    }
+    pub fn explain_isel(&self) -> bool {
+        self.explain_isel
+    }
+    pub fn device(&self) -> Option<&PathBuf> {
+        self.device.as_ref()
+    }
+    pub fn place(&self) -> &Mode {
+        &self.place
+    }
+    pub fn check_place(&self) -> bool {
+        self.check_place
+    }
     pub fn output(&self) -> Option<&PathBuf> {
         self.output.as_ref()
     }
@@ -57,6 +157,8 @@ pub enum Lang {
     Xir,
     Behav,
     Struct,
+    Sim,
+    Diff,
 }
 
 impl fmt::Display for Lang {
@@ -67,6 +169,8 @@ impl fmt::Display for Lang {
             Lang::Xir => "xir",
             Lang::Behav => "behav",
             Lang::Struct => "struct",
+            Lang::Sim => "sim",
+            Lang::Diff => "diff",
         };
         write!(f, "{}", backend)
     }
@@ -81,6 +185,8 @@ impl FromStr for Lang {
             "xir" => Ok(Lang::Xir),
             "behav" => Ok(Lang::Behav),
             "struct" => Ok(Lang::Struct),
+            "sim" => Ok(Lang::Sim),
+            "diff" => Ok(Lang::Diff),
             _ => Err(Error::new_opt_error("Unsupported language")),
         }
     }
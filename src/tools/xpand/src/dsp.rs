@@ -6,8 +6,8 @@ use crate::loc::{Bel, BelDsp, ExprCoord, Loc};
 use crate::param::Param;
 use crate::port::{ConnectionMap, DefaultPort, Port, WidthMap};
 use crate::{
-    create_literal, inst_name_try_from_instr, tmp_name_try_from_term, vec_expr_try_from_expr,
-    vec_expr_try_from_term,
+    create_literal, inst_name_try_from_instr, pcout_name_try_from_term, tmp_name_try_from_term,
+    vec_expr_try_from_expr, vec_expr_try_from_term,
 };
 use crate::{CLOCK, RESET};
 use prim::ultrascale::gnd::GND;
@@ -240,6 +240,35 @@ impl Default for NumRegAB {
     }
 }
 
+impl TryFrom<i64> for NumReg {
+    type Error = Error;
+    fn try_from(input: i64) -> Result<Self, Self::Error> {
+        match input {
+            0 => Ok(NumReg::Zero),
+            1 => Ok(NumReg::One),
+            _ => {
+                let err = format!("{} is not a valid NumReg", input);
+                Err(Error::new_xpand_error(&err))
+            }
+        }
+    }
+}
+
+impl TryFrom<i64> for NumRegAB {
+    type Error = Error;
+    fn try_from(input: i64) -> Result<Self, Self::Error> {
+        match input {
+            0 => Ok(NumRegAB::Zero),
+            1 => Ok(NumRegAB::One),
+            2 => Ok(NumRegAB::Two),
+            _ => {
+                let err = format!("{} is not a valid NumRegAB", input);
+                Err(Error::new_xpand_error(&err))
+            }
+        }
+    }
+}
+
 impl DefaultPort for Dsp {
     fn default_input_port() -> Port {
         let mut reset: HashSet<String> = HashSet::new();
@@ -819,6 +848,20 @@ fn vl_expr_try_from_term(
     }
 }
 
+// PCOUT of a cascade output drives PCIN of the dsp right above, and the
+// cascaded argument is the dsp driving it
+fn set_cascade(prim: &mut Dsp, instr: &xir::InstrMach) -> Result<(), Error> {
+    if instr.op().is_cascade_out() {
+        let name = pcout_name_try_from_term(instr.dst().get_term(0)?)?;
+        prim.set_output("PCOUT", vl::Expr::new_ref(&name))?;
+    }
+    if let Some(arg) = instr.op().cascade_arg() {
+        let name = pcout_name_try_from_term(instr.arg().get_term(arg)?)?;
+        prim.set_input("PCIN", vl::Expr::new_ref(&name))?;
+    }
+    Ok(())
+}
+
 pub fn vaddrega_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Error> {
     let mut prim = Dsp::default();
     let mut stmt: Vec<vl::Stmt> = Vec::new();
@@ -839,24 +882,32 @@ pub fn vaddrega_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Error
     prim.set_param("ACASCREG", ParamValue::from(NumRegAB::One))?;
     prim.set_param("BCASCREG", ParamValue::from(NumRegAB::One))?;
     prim.set_param("PREG", ParamValue::from(NumReg::One))?;
-    // opcode
-    prim.set_input("OPMODE", create_literal(9, 51))?;
+    // opcode, C plus A:B or C plus PCIN, which skips the A and B registers
+    let cascade_in = instr.op().cascade_arg().is_some();
+    if cascade_in {
+        prim.set_input("OPMODE", create_literal(9, 28))?;
+    } else {
+        prim.set_input("OPMODE", create_literal(9, 51))?;
+    }
+    set_cascade(&mut prim, instr)?;
     // input
     let left_term = instr.arg().get_term(0)?;
     let c_msb = prim.get_input_width("C").unwrap() - 1;
     let c_expr = vl_expr_try_from_term(left_term, 0, c_msb as usize)?;
     prim.set_input("C", c_expr)?;
-    let right_term = instr.arg().get_term(1)?;
-    let b_width = *prim.get_input_width("B").unwrap();
-    let b_expr = vl_expr_try_from_term(right_term, 0, (b_width - 1) as usize)?;
-    prim.set_input("B", b_expr)?;
-    let a_width = prim.get_input_width("A").unwrap();
-    let a_expr = vl_expr_try_from_term(
-        right_term,
-        b_width as usize,
-        (b_width + a_width - 1) as usize,
-    )?;
-    prim.set_input("A", a_expr)?;
+    if !cascade_in {
+        let right_term = instr.arg().get_term(1)?;
+        let b_width = *prim.get_input_width("B").unwrap();
+        let b_expr = vl_expr_try_from_term(right_term, 0, (b_width - 1) as usize)?;
+        prim.set_input("B", b_expr)?;
+        let a_width = prim.get_input_width("A").unwrap();
+        let a_expr = vl_expr_try_from_term(
+            right_term,
+            b_width as usize,
+            (b_width + a_width - 1) as usize,
+        )?;
+        prim.set_input("A", a_expr)?;
+    }
     let l_en_term = instr.arg().get_term(2)?;
     let r_en_term = instr.arg().get_term(3)?;
     let o_en_term = instr.arg().get_term(4)?;
@@ -904,15 +955,26 @@ pub fn muladdrega_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Err
     }
     // multiply
     prim.set_param("USE_MULT", ParamValue::from(UseMult::Multiply))?;
-    // registers
-    prim.set_param("AREG", ParamValue::from(NumRegAB::One))?;
-    prim.set_param("BREG", ParamValue::from(NumRegAB::One))?;
-    prim.set_param("ACASCREG", ParamValue::from(NumRegAB::One))?;
-    prim.set_param("BCASCREG", ParamValue::from(NumRegAB::One))?;
-    prim.set_param("MREG", ParamValue::from(NumReg::One))?;
-    prim.set_param("PREG", ParamValue::from(NumReg::One))?;
-    // opcode
-    prim.set_input("OPMODE", create_literal(9, 53))?;
+    // registers, as many as the stages of the instruction
+    let reg = instr.dsp_reg()?;
+    if reg.len() != 4 {
+        let err = format!("{} expects four register stages", instr.op());
+        return Err(Error::new_xpand_error(&err));
+    }
+    prim.set_param("AREG", ParamValue::from(NumRegAB::try_from(reg[0])?))?;
+    prim.set_param("BREG", ParamValue::from(NumRegAB::try_from(reg[1])?))?;
+    prim.set_param("ACASCREG", ParamValue::from(NumRegAB::try_from(reg[0])?))?;
+    prim.set_param("BCASCREG", ParamValue::from(NumRegAB::try_from(reg[1])?))?;
+    prim.set_param("MREG", ParamValue::from(NumReg::try_from(reg[2])?))?;
+    prim.set_param("PREG", ParamValue::from(NumReg::try_from(reg[3])?))?;
+    // opcode, M plus C or M plus PCIN
+    let cascade_in = instr.op().cascade_arg().is_some();
+    if cascade_in {
+        prim.set_input("OPMODE", create_literal(9, 21))?;
+    } else {
+        prim.set_input("OPMODE", create_literal(9, 53))?;
+    }
+    set_cascade(&mut prim, instr)?;
     // input
     let a_term = instr.arg().get_term(0)?;
     let a_width = *prim.get_input_width("A").unwrap();
@@ -922,10 +984,12 @@ pub fn muladdrega_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Err
     let b_width = *prim.get_input_width("B").unwrap();
     let b_expr = vl_expr_try_from_term(b_term, 0, (b_width - 1) as usize)?;
     prim.set_input("B", b_expr)?;
-    let c_term = instr.arg().get_term(2)?;
-    let c_width = *prim.get_input_width("C").unwrap();
-    let c_expr = vl_expr_try_from_term(c_term, 0, (c_width - 1) as usize)?;
-    prim.set_input("C", c_expr)?;
+    if !cascade_in {
+        let c_term = instr.arg().get_term(2)?;
+        let c_width = *prim.get_input_width("C").unwrap();
+        let c_expr = vl_expr_try_from_term(c_term, 0, (c_width - 1) as usize)?;
+        prim.set_input("C", c_expr)?;
+    }
     let a_en_term = instr.arg().get_term(3)?;
     let b_en_term = instr.arg().get_term(4)?;
     let m_en_term = instr.arg().get_term(5)?;
@@ -963,3 +1027,59 @@ pub fn muladdrega_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Err
     }
     Ok(stmt)
 }
+
+// mul and muladd, M or M plus C, keep the default of no register
+pub fn mul_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Error> {
+    let mut prim = Dsp::default();
+    let mut stmt: Vec<vl::Stmt> = Vec::new();
+    let name = inst_name_try_from_instr(instr)?;
+    prim.set_name(&name);
+    // loc
+    if let Some(loc) = instr.loc() {
+        prim.set_loc(loc.clone());
+    }
+    // multiply
+    prim.set_param("USE_MULT", ParamValue::from(UseMult::Multiply))?;
+    // opcode, M or M plus C
+    let add = matches!(instr.op(), xir::OpMach::MulAdd);
+    if add {
+        prim.set_input("OPMODE", create_literal(9, 53))?;
+    } else {
+        prim.set_input("OPMODE", create_literal(9, 5))?;
+    }
+    // input
+    let a_term = instr.arg().get_term(0)?;
+    let a_width = *prim.get_input_width("A").unwrap();
+    let a_expr = vl_expr_try_from_term(a_term, 0, (a_width - 1) as usize)?;
+    prim.set_input("A", a_expr)?;
+    let b_term = instr.arg().get_term(1)?;
+    let b_width = *prim.get_input_width("B").unwrap();
+    let b_expr = vl_expr_try_from_term(b_term, 0, (b_width - 1) as usize)?;
+    prim.set_input("B", b_expr)?;
+    if add {
+        let c_term = instr.arg().get_term(2)?;
+        let c_width = *prim.get_input_width("C").unwrap();
+        let c_expr = vl_expr_try_from_term(c_term, 0, (c_width - 1) as usize)?;
+        prim.set_input("C", c_expr)?;
+    }
+    // output
+    let dst_term = instr.dst().get_term(0)?;
+    if dst_term.length().map_or(false, |length| length > 1) {
+        let err = format!("{} expects a scalar", instr.op());
+        return Err(Error::new_xpand_error(&err));
+    }
+    let output = tmp_name_try_from_term(dst_term)?;
+    prim.set_output("P", vl::Expr::new_ref(&output))?;
+    stmt.push(prim.to_stmt());
+    if let Some(width) = dst_term.width() {
+        if let Ok(ebits) = i32::try_from(width) {
+            let dst = vl::Expr::new_ref(&String::try_from(dst_term.clone())?);
+            let assign = vl::Parallel::Assign(
+                dst,
+                vl::Expr::new_slice(&output, vl::Expr::new_int(ebits - 1), vl::Expr::new_int(0)),
+            );
+            stmt.push(vl::Stmt::from(assign));
+        }
+    }
+    Ok(stmt)
+}
@@ -1,5 +1,7 @@
+use bler::errors::Error as BlerError;
 use bline::errors::Error as BlineError;
 use ir::errors::Error as IrError;
+use isel::errors::Error as ISelError;
 use std::fmt;
 use std::num::TryFromIntError;
 use xir::errors::Error as XirError;
@@ -9,6 +11,8 @@ pub enum Error {
     Ir(IrError),
     Xir(XirError),
     Bline(BlineError),
+    ISel(ISelError),
+    Bler(BlerError),
     Xpand(String),
     TryFromInt(TryFromIntError),
 }
@@ -37,6 +41,18 @@ impl From<BlineError> for Error {
     }
 }
 
+impl From<ISelError> for Error {
+    fn from(e: ISelError) -> Self {
+        Error::ISel(e)
+    }
+}
+
+impl From<BlerError> for Error {
+    fn from(e: BlerError) -> Self {
+        Error::Bler(e)
+    }
+}
+
 impl From<TryFromIntError> for Error {
     fn from(e: TryFromIntError) -> Self {
         Error::TryFromInt(e)
@@ -50,6 +66,8 @@ impl fmt::Display for Error {
             Error::Xir(msg) => write!(f, "{}", msg),
             Error::Xpand(msg) => write!(f, "{}", msg),
             Error::Bline(msg) => write!(f, "{}", msg),
+            Error::ISel(msg) => write!(f, "{}", msg),
+            Error::Bler(msg) => write!(f, "{}", msg),
             Error::TryFromInt(msg) => write!(f, "{}", msg),
         }
     }
@@ -0,0 +1,86 @@
+use crate::errors::Error;
+use crate::module_try_from_xir_prog;
+use crate::{CLOCK, RESET};
+use ::ir::elab::{call_order, calls_from_def, detach_calls};
+use bler::try_from_asm_prog_with_target as asm_try_into_xir;
+use bline::vec_expr_try_from_expr;
+use ir::ast as ir;
+use isel::cost::Cost;
+use isel::library::Library;
+use isel::try_from_ir_prog_with_cost as ir_try_into_asm;
+use verilog::ast as vl;
+
+fn connect_try_from_expr(
+    inst: &mut vl::Instance,
+    port: &ir::Expr,
+    expr: &ir::Expr,
+) -> Result<(), Error> {
+    let port = vec_expr_try_from_expr(port)?;
+    let expr = vec_expr_try_from_expr(expr)?;
+    for (p, e) in port.iter().zip(expr.into_iter()) {
+        inst.connect(&p.id(), e);
+    }
+    Ok(())
+}
+
+pub fn instance_try_from_call(
+    call: &ir::InstrCall,
+    callee: &ir::Def,
+    name: &str,
+) -> Result<vl::Instance, Error> {
+    let mut inst = vl::Instance::new(name, &callee.id());
+    inst.connect(CLOCK, vl::Expr::new_ref(CLOCK));
+    inst.connect(RESET, vl::Expr::new_ref(RESET));
+    connect_try_from_expr(&mut inst, callee.input(), call.arg())?;
+    connect_try_from_expr(&mut inst, callee.output(), call.dst())?;
+    Ok(inst)
+}
+
+// every def is compiled on its own, with its calls replaced by instances
+pub fn module_try_from_ir_def(
+    prog: &ir::Prog,
+    def: &ir::Def,
+    mmap: Option<&mmap::Mmap>,
+    lib: &Library,
+    cost: &Cost,
+) -> Result<vl::Module, Error> {
+    let mut top = detach_calls(def)?;
+    top.set_id("main");
+    let mut single = ir::Prog::default();
+    single.insert("main", top);
+    let asm = ir_try_into_asm(&single, lib, cost)?;
+    let (xir, mmap) = asm_try_into_xir(&asm, mmap, &lib.imp())?;
+    let mut module = module_try_from_xir_prog(&xir, def.sig(), mmap.as_ref())?;
+    for (index, call) in calls_from_def(def).into_iter().enumerate() {
+        let op = call.op().op();
+        if let Some(callee) = prog.get(&op) {
+            let name = format!("__{}_{}", op, index);
+            let inst = instance_try_from_call(call, callee, &name)?;
+            module.add_stmt(vl::Stmt::from(inst));
+        }
+    }
+    Ok(module)
+}
+
+pub fn try_from_ir_prog(
+    prog: &ir::Prog,
+    mmap: Option<&mmap::Mmap>,
+) -> Result<Vec<vl::Module>, Error> {
+    try_from_ir_prog_with_library(prog, mmap, &Library::builtin(), &Cost::default())
+}
+
+// one module per def, callees come before their callers
+pub fn try_from_ir_prog_with_library(
+    prog: &ir::Prog,
+    mmap: Option<&mmap::Mmap>,
+    lib: &Library,
+    cost: &Cost,
+) -> Result<Vec<vl::Module>, Error> {
+    let mut module: Vec<vl::Module> = Vec::new();
+    for id in call_order(prog)? {
+        if let Some(def) = prog.get(&id) {
+            module.push(module_try_from_ir_def(prog, def, mmap, lib, cost)?);
+        }
+    }
+    Ok(module)
+}
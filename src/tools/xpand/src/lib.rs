@@ -10,6 +10,7 @@ pub mod ext;
 pub mod fdre;
 pub mod fdse;
 pub mod gnd;
+pub mod hier;
 pub mod id;
 pub mod instance;
 pub mod loc;
@@ -32,7 +33,7 @@ use bline::{
 use prim::ultrascale::clock::CLOCK;
 use prim::ultrascale::gnd::Gnd;
 use prim::ultrascale::reset::RESET;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use verilog::ast as vl;
 use xir::ast as xir;
@@ -103,6 +104,12 @@ fn tmp_name_try_from_term(term: &xir::ExprTerm) -> Result<xir::Id, Error> {
     Ok(format!("_{}", dst))
 }
 
+// wire from the cascade output of a dsp to the one above it
+fn pcout_name_try_from_term(term: &xir::ExprTerm) -> Result<xir::Id, Error> {
+    let dst: xir::Id = term.clone().try_into()?;
+    Ok(format!("_{}_pcout", dst))
+}
+
 fn inst_name_try_from_instr(instr: &xir::InstrMach) -> Result<vl::Id, Error> {
     let dst: Vec<vl::Id> = instr.dst().clone().try_into()?;
     Ok(format!("__{}", dst[0]))
@@ -118,8 +125,15 @@ fn stmt_from_mach(instr: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Error> {
         xir::OpMach::Lut6 => lut::lut6_from_mach(instr),
         xir::OpMach::Fdre => fdre::fdre_from_mach(instr),
         xir::OpMach::CarryAdd => carry::carryadd_from_mach(instr),
-        xir::OpMach::VecAddRegA => dsp::vaddrega_from_mach(instr),
-        xir::OpMach::MulAddRegA => dsp::muladdrega_from_mach(instr),
+        xir::OpMach::VecAddRegA
+        | xir::OpMach::VecAddRegACi
+        | xir::OpMach::VecAddRegACo
+        | xir::OpMach::VecAddRegACio => dsp::vaddrega_from_mach(instr),
+        xir::OpMach::MulAddRegA
+        | xir::OpMach::MulAddRegACi
+        | xir::OpMach::MulAddRegACo
+        | xir::OpMach::MulAddRegACio => dsp::muladdrega_from_mach(instr),
+        xir::OpMach::Mul | xir::OpMach::MulAdd => dsp::mul_from_mach(instr),
         xir::OpMach::Lrom => lram::rom_from_mach(instr),
         xir::OpMach::Brom => bram::rom_from_mach(instr),
         _ => {
@@ -139,10 +153,16 @@ fn stmt_from_basc(instr: &xir::InstrBasc) -> Result<Vec<vl::Stmt>, Error> {
     }
 }
 
-pub fn try_from_xir_prog(prog: &xir::Prog, mmap: Option<&mmap::Mmap>) -> Result<vl::Module, Error> {
-    let id = prog.sig().id();
+// module with the ports of sig and the body of prog. Inputs of prog that are
+// not ports of sig become wires, these are driven by instances added later.
+pub fn module_try_from_xir_prog(
+    prog: &xir::Prog,
+    sig: &xir::Sig,
+    mmap: Option<&mmap::Mmap>,
+) -> Result<vl::Module, Error> {
+    let id = sig.id();
     let mut module = vl::Module::new(&id);
-    let input = input_try_from_sig(prog.sig())?;
+    let input = input_try_from_sig(sig)?;
     for i in input {
         module.add_port(i.clone());
     }
@@ -158,10 +178,22 @@ pub fn try_from_xir_prog(prog: &xir::Prog, mmap: Option<&mmap::Mmap>) -> Result<
                 if let Some(width) = dsp_outputs.get_width("P") {
                     decl.push(vl::Decl::new_wire(&name, u64::from(*width)));
                 }
+                if instr.op().is_cascade_out() {
+                    let name = pcout_name_try_from_term(term)?;
+                    if let Some(width) = dsp_outputs.get_width("PCOUT") {
+                        decl.push(vl::Decl::new_wire(&name, u64::from(*width)));
+                    }
+                }
             }
         }
     }
-    let output: Vec<vl::Decl> = wire_try_from_expr(prog.sig().output())?;
+    let sig_input: HashSet<vl::Decl> = wire_try_from_expr(sig.input())?.into_iter().collect();
+    for d in wire_try_from_expr(prog.sig().input())? {
+        if !sig_input.contains(&d) {
+            decl.push(d);
+        }
+    }
+    let output: Vec<vl::Decl> = wire_try_from_expr(sig.output())?;
     for o in output.iter() {
         module.add_port(vl::Port::Output(o.clone()));
     }
@@ -178,9 +210,18 @@ pub fn try_from_xir_prog(prog: &xir::Prog, mmap: Option<&mmap::Mmap>) -> Result<
     }
     module.add_stmt(gnd.to_stmt());
     module.add_stmt(vcc.to_stmt());
-    for instr in prog.body() {
+    let pair: HashMap<usize, usize> = lut::lut6_2_pairs(prog.body()).into_iter().collect();
+    let five: HashSet<usize> = pair.values().cloned().collect();
+    for (i, instr) in prog.body().iter().enumerate() {
+        if five.contains(&i) {
+            continue;
+        }
         let stmt = match instr {
             xir::Instr::Basc(basc) => stmt_from_basc(basc)?,
+            xir::Instr::Mach(mach) if pair.contains_key(&i) => {
+                let o5 = prog.body()[pair[&i]].mach().unwrap_or(mach);
+                lut::lut6_2_from_mach(mach, o5)?
+            }
             xir::Instr::Mach(mach) => {
                 let id = mach.dst().get_id(0)?;
                 match mmap {
@@ -203,3 +244,7 @@ pub fn try_from_xir_prog(prog: &xir::Prog, mmap: Option<&mmap::Mmap>) -> Result<
     }
     Ok(module)
 }
+
+pub fn try_from_xir_prog(prog: &xir::Prog, mmap: Option<&mmap::Mmap>) -> Result<vl::Module, Error> {
+    module_try_from_xir_prog(prog, prog.sig(), mmap)
+}
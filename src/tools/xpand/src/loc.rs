@@ -111,3 +111,10 @@ pub fn attr_from_loc(loc: &Loc) -> vl::Attribute {
     attr.add_attr(bel);
     attr
 }
+
+// location without the bel, for primitives spanning several bels
+pub fn site_attr_from_loc(loc: &Loc) -> vl::Attribute {
+    let mut attr = vl::Attribute::default();
+    attr.add_attr(attr_stmt_from_loc(loc));
+    attr
+}
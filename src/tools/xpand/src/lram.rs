@@ -4,7 +4,7 @@ use crate::loc::Loc;
 use crate::to_verilog::{ToVerilogExpr, ToVerilogInstance, VerilogExprMap};
 use prim::ultrascale::clock::CLOCK;
 use prim::ultrascale::gnd::GND;
-use prim::ultrascale::lram::{Lram, ParamValue};
+use prim::ultrascale::lram::{Lram, Lram256, ParamValue};
 use prim::{ParamSet, PortSet};
 use verilog::ast as vl;
 use xir::ast::InstrMach;
@@ -164,8 +164,116 @@ impl ToVerilogInstance<ParamValue> for Rom {
     }
 }
 
-// TODO: check for valid memory shapes, support only 8x8 now (data:i8, addr:i3)
+// one RAM256X1S for every data bit of a rom
+#[derive(Clone, Debug)]
+struct Rom256 {
+    pub prim: Lram256,
+    pub instr: InstrMach,
+    pub bit: u64,
+}
+
+impl Rom256 {
+    pub fn new(instr: InstrMach, bit: u64) -> Self {
+        Rom256 {
+            prim: Lram256::default(),
+            instr,
+            bit,
+        }
+    }
+    pub fn instr(&self) -> &InstrMach {
+        &self.instr
+    }
+}
+
+impl ToVerilogInstance<ParamValue> for Rom256 {
+    fn to_name(&self) -> String {
+        let name = inst_name_try_from_instr(&self.instr).unwrap();
+        format!("{}_{}", name, self.bit)
+    }
+    fn to_prim(&self) -> String {
+        self.prim.name()
+    }
+    fn to_param_set(&self) -> &ParamSet<ParamValue> {
+        self.prim.param()
+    }
+    fn to_input_set(&self) -> &PortSet {
+        self.prim.input()
+    }
+    fn to_output_set(&self) -> &PortSet {
+        self.prim.output()
+    }
+    fn to_param_map(&self) -> VerilogExprMap {
+        let mut map = VerilogExprMap::new();
+        for p in self.to_param_set().iter() {
+            map.insert(p.name(), p.value().to_expr());
+        }
+        if let Some(mem) = self.instr().mem() {
+            let mut value: Vec<u8> = vec![0; 32];
+            for (i, v) in mem.values().iter().enumerate() {
+                let addr = mem.offset() as usize + i;
+                if addr < 256 {
+                    value[addr / 8] |= ((v >> self.bit) & 1) << (addr % 8);
+                }
+            }
+            map.insert("INIT".to_string(), ParamValue::Bytes(256, value).to_expr());
+        }
+        map
+    }
+    // address bits first, the least significant one leading
+    fn to_input_map(&self) -> VerilogExprMap {
+        let mut map = VerilogExprMap::new();
+        let id = self.instr().arg().get_id(0).unwrap();
+        let ty = self.instr().arg().get_ty(0).unwrap();
+        if let Some(width) = ty.width() {
+            let mut concat = vl::ExprConcat::default();
+            for i in 0..8 {
+                if i < width {
+                    concat.add_expr(vl::Expr::new_index_bit(&id, i as i32));
+                } else {
+                    concat.add_expr(vl::Expr::new_ref(GND));
+                }
+            }
+            for p in self.prim.input().iter() {
+                let name = p.name();
+                match name.as_str() {
+                    "A" => map.insert(name, vl::Expr::from(concat.clone())),
+                    "WCLK" => map.insert(name, vl::Expr::new_ref(CLOCK)),
+                    _ => map.insert(name, vl::Expr::new_ref(GND)),
+                };
+            }
+        }
+        map
+    }
+    fn to_output_map(&self) -> VerilogExprMap {
+        let mut map = VerilogExprMap::new();
+        let dst = self.instr().dst().get_id(0).unwrap();
+        map.insert(
+            "O".to_string(),
+            vl::Expr::new_index_bit(&dst, self.bit as i32),
+        );
+        map
+    }
+    fn to_loc(&self) -> Option<&Loc> {
+        self.instr.loc()
+    }
+}
+
+// roms with up to six address bits and eight data bits fit in a RAM64M8,
+// the ones with up to eight address bits take a RAM256X1S per data bit
 pub fn rom_from_mach(instr: &InstrMach) -> Result<Vec<vl::Stmt>, Error> {
-    let rom = Rom::new(instr.clone());
-    Ok(rom.to_block())
+    let addr = instr.arg().get_ty(0)?.width().unwrap_or(0);
+    let data = instr.dst().get_ty(0)?.width().unwrap_or(0);
+    if addr <= 6 && data <= 8 {
+        let rom = Rom::new(instr.clone());
+        Ok(rom.to_block())
+    } else if addr <= 8 {
+        let mut stmt: Vec<vl::Stmt> = Vec::new();
+        for bit in 0..data {
+            stmt.extend(Rom256::new(instr.clone(), bit).to_block());
+        }
+        Ok(stmt)
+    } else {
+        let err = format!("{} with {} address bits is not supported", instr.op(), addr);
+        Err(Error::new_xpand_error(&err))
+    }
 }
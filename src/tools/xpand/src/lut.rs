@@ -1,7 +1,7 @@
 use crate::errors::Error;
 use crate::expr::ToExpr;
 use crate::instance::ToInstance;
-use crate::loc::attr_from_loc;
+use crate::loc::{attr_from_loc, site_attr_from_loc};
 use crate::loc::{Bel, BelLut, ExprCoord, Loc};
 use crate::param::Param;
 use crate::port::{ConnectionMap, DefaultPort, Port, WidthMap};
@@ -217,3 +217,231 @@ lut!(
     64,
     ["I0", "I1", "I2", "I3", "I4", "I5"]
 );
+
+#[derive(Clone, Debug)]
+pub enum Lut62ParamVal {
+    Init(u64),
+}
+
+// Lut with two outputs, O6 is a function of I0 to I5 and O5 is a function
+// of I0 to I4. Both are used when I5 is tied high.
+#[derive(Clone, Debug)]
+pub struct Lut62 {
+    pub name: String,
+    pub prim: String,
+    pub loc: Loc,
+    pub param: Param<Lut62ParamVal>,
+    pub input: Port,
+    pub output: Port,
+}
+
+impl PartialEq for Lut62ParamVal {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl ToExpr for Lut62ParamVal {
+    fn to_expr(&self) -> vl::Expr {
+        match self {
+            Lut62ParamVal::Init(v) => {
+                let s = format!("{:x}", *v);
+                vl::Expr::new_ulit_hex(64, &s)
+            }
+        }
+    }
+}
+
+impl From<u64> for Lut62ParamVal {
+    fn from(input: u64) -> Self {
+        Lut62ParamVal::Init(input)
+    }
+}
+
+impl Default for Param<Lut62ParamVal> {
+    fn default() -> Self {
+        let mut param = Param::<Lut62ParamVal>::new();
+        param.insert("INIT".to_string(), Lut62ParamVal::from(0));
+        param
+    }
+}
+
+impl DefaultPort for Lut62 {
+    fn default_input_port() -> Port {
+        let mut width = WidthMap::new();
+        for i in ["I0", "I1", "I2", "I3", "I4", "I5"].iter() {
+            width.insert(i.to_string(), 1);
+        }
+        let mut connection = ConnectionMap::new();
+        for (k, v) in width.iter() {
+            connection.insert(k.clone(), create_literal(*v as u64, 0));
+        }
+        Port { width, connection }
+    }
+    fn default_output_port() -> Port {
+        let mut width = WidthMap::new();
+        width.insert("O5".to_string(), 1);
+        width.insert("O6".to_string(), 1);
+        let mut connection = ConnectionMap::new();
+        for k in width.keys() {
+            connection.insert(k.clone(), vl::Expr::new_ref(""));
+        }
+        Port { width, connection }
+    }
+}
+
+impl Default for Lut62 {
+    fn default() -> Self {
+        let loc = Loc {
+            bel: Bel::Lut(BelLut::A6),
+            x: ExprCoord::default(),
+            y: ExprCoord::default(),
+        };
+        Lut62 {
+            name: String::new(),
+            prim: "LUT6_2".to_string(),
+            loc,
+            param: Param::<Lut62ParamVal>::default(),
+            input: Lut62::default_input_port(),
+            output: Lut62::default_output_port(),
+        }
+    }
+}
+
+impl ToInstance<Lut62ParamVal> for Lut62 {
+    fn param(&self) -> &Param<Lut62ParamVal> {
+        &self.param
+    }
+    fn to_instance(&self) -> vl::Instance {
+        let mut inst = vl::Instance::new(&self.name, &self.prim);
+        for (k, v) in self.param.param() {
+            let expr: vl::Expr = v.clone().to_expr();
+            inst.add_param(k, expr);
+        }
+        for (k, v) in self.input.connection.iter() {
+            inst.connect(&k, v.clone());
+        }
+        for (k, v) in self.output.connection.iter() {
+            inst.connect(&k, v.clone());
+        }
+        // the bels are those of the two luts inside, so only the site is set
+        if self.loc.is_placed() {
+            inst.set_attr(site_attr_from_loc(&self.loc));
+        }
+        inst
+    }
+    fn to_stmt(&self) -> vl::Stmt {
+        vl::Stmt::from(self.to_instance())
+    }
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+    fn set_input(&mut self, port: &str, expr: vl::Expr) -> Result<(), Error> {
+        if let Some(p) = self.input.connection.get_mut(port) {
+            *p = expr;
+            Ok(())
+        } else {
+            let err = format!("input {} do not exist", port);
+            Err(Error::new_xpand_error(&err))
+        }
+    }
+    fn set_output(&mut self, port: &str, expr: vl::Expr) -> Result<(), Error> {
+        if let Some(p) = self.output.connection.get_mut(port) {
+            *p = expr;
+            Ok(())
+        } else {
+            let err = format!("output {} do not exist", port);
+            Err(Error::new_xpand_error(&err))
+        }
+    }
+}
+
+impl Lut62 {
+    pub fn set_loc(&mut self, loc: Loc) {
+        self.loc = loc;
+    }
+    pub fn set_param<P>(&mut self, name: &str, value: P) -> Result<(), Error>
+    where
+        P: Into<Lut62ParamVal>,
+    {
+        self.param.set_param(name, value.into())?;
+        Ok(())
+    }
+}
+
+// truth table of a lut repeated over the inputs it does not use
+fn init32(instr: &xir::InstrMach) -> Result<u64, Error> {
+    let init = instr.attr().get_val(0)? as u64;
+    let inputs = instr.op().lut_inputs().unwrap_or(5);
+    let mut res = 0;
+    for i in 0..32 {
+        res |= ((init >> (i % (1 << inputs))) & 1) << i;
+    }
+    Ok(res)
+}
+
+// Luts on the 6 and 5 bels of the same lut and the same site, reading the
+// same inputs, become one lut6_2. Luts at ?? are not known to share a site.
+pub fn lut6_2_pairs(body: &[xir::Instr]) -> Vec<(usize, usize)> {
+    let key = |instr: &xir::Instr| {
+        let instr = instr.mach()?;
+        let inputs = instr.op().lut_inputs()?;
+        let loc = instr.loc()?;
+        if loc.x().is_any() || loc.y().is_any() {
+            return None;
+        }
+        match loc.bel() {
+            Bel::Lut(bel) if inputs <= 5 => Some((
+                bel.lut6(),
+                loc.x().clone(),
+                loc.y().clone(),
+                instr.arg().clone(),
+            )),
+            _ => None,
+        }
+    };
+    let is_lut5 = |instr: &xir::Instr| match instr.mach().and_then(|i| i.loc()) {
+        Some(loc) => matches!(loc.bel(), Bel::Lut(bel) if bel.is_lut5()),
+        None => false,
+    };
+    let mut five: Vec<(usize, _)> = body
+        .iter()
+        .enumerate()
+        .filter(|(_, instr)| is_lut5(instr))
+        .filter_map(|(i, instr)| key(instr).map(|k| (i, k)))
+        .collect();
+    let mut res = Vec::new();
+    for (i, instr) in body.iter().enumerate() {
+        if is_lut5(instr) {
+            continue;
+        }
+        if let Some(k) = key(instr) {
+            if let Some(pos) = five.iter().position(|(_, f)| *f == k) {
+                res.push((i, five.remove(pos).0));
+            }
+        }
+    }
+    res
+}
+
+pub fn lut6_2_from_mach(o6: &xir::InstrMach, o5: &xir::InstrMach) -> Result<Vec<vl::Stmt>, Error> {
+    let mut lut = Lut62::default();
+    let name = inst_name_try_from_instr(o6)?;
+    lut.set_name(&name);
+    if let Some(loc) = o6.loc() {
+        lut.set_loc(loc.clone());
+    }
+    let init = (init32(o6)? << 32) | init32(o5)?;
+    lut.set_param("INIT", init)?;
+    let arg: Vec<vl::Expr> = vec_expr_try_from_expr(o6.arg())?;
+    for (i, e) in ["I0", "I1", "I2", "I3", "I4"].iter().zip(arg) {
+        lut.set_input(i, e)?;
+    }
+    lut.set_input("I5", create_literal(1, 1))?;
+    let o6: Vec<vl::Expr> = vec_expr_try_from_expr(o6.dst())?;
+    let o5: Vec<vl::Expr> = vec_expr_try_from_expr(o5.dst())?;
+    for (o, e) in ["O6", "O5"].iter().zip(o6.into_iter().chain(o5)) {
+        lut.set_output(o, e)?;
+    }
+    Ok(vec![lut.to_stmt()])
+}
@@ -8,7 +8,7 @@ use xpand::dsp::Dsp;
 use xpand::fdre::Fdre;
 use xpand::fdse::Fdse;
 use xpand::instance::ToInstance;
-use xpand::lut::{Lut1, Lut2, Lut3, Lut4, Lut5, Lut6};
+use xpand::lut::{Lut1, Lut2, Lut3, Lut4, Lut5, Lut6, Lut62};
 use xpand::to_verilog::ToVerilogInstance;
 use xpand::vcc::Vcc;
 
@@ -159,6 +159,24 @@ fn test_lut6() {
     test(res.to_instance(), exp)
 }
 
+#[test]
+fn test_lut6_2() {
+    let res = Lut62::default();
+    let exp = r#"LUT6_2 # (
+    .INIT(64'h0)
+)  (
+    .I0(gnd),
+    .I1(gnd),
+    .I2(gnd),
+    .I3(gnd),
+    .I4(gnd),
+    .I5(gnd),
+    .O5(),
+    .O6()
+);"#;
+    test(res.to_instance(), exp)
+}
+
 #[test]
 fn test_carry() {
     let res = Carry::default();
@@ -1,8 +1,10 @@
 use io::read_to_string;
+use ir::parser::Parser as IrParser;
 use pretty_assertions::assert_eq;
 use std::path::Path;
 use xir::parser::Parser;
 use xpand::errors::Error;
+use xpand::hier::try_from_ir_prog;
 use xpand::try_from_xir_prog;
 
 fn test(name: &str) -> Result<(), Error> {
@@ -123,6 +125,11 @@ fn brom_8x256() -> Result<(), Error> {
     test("brom_8x256")
 }
 
+#[test]
+fn lut6_2() -> Result<(), Error> {
+    test("lut6_2")
+}
+
 #[test]
 fn mux128() -> Result<(), Error> {
     test("mux128")
@@ -137,3 +144,35 @@ fn lrom_8x8_mmap() -> Result<(), Error> {
 fn brom_8x256_mmap() -> Result<(), Error> {
     test_with_mmap("brom_8x256")
 }
+
+#[test]
+fn call() -> Result<(), Error> {
+    let prog = IrParser::parse_from_file("../../../examples/ir/call.ir")?;
+    let res = try_from_ir_prog(&prog, None)?;
+    assert_eq!(res.len(), 2);
+    let callee = res[0].to_string();
+    let main = res[1].to_string();
+    assert!(callee.contains("module addreg"));
+    assert!(main.contains("module main"));
+    assert!(main.contains("__addreg_0"));
+    assert!(main.contains("__addreg_1"));
+    assert!(!main.contains("FDRE"));
+    Ok(())
+}
+
+#[test]
+fn muladdrega_stages() -> Result<(), Error> {
+    let prog = Parser::parse_from_str(
+        "def main(a:i8, b:i8, c:i8, en:bool) -> (y:i8, z:i8) {
+            y:i8 = muladdrega(a, b, c, en, en, en, en) @alu(??, ??);
+            z:i8 = muladdrega[2, 2, 0, 1](a, b, c, en, en, en, en) @alu(??, ??);
+        }",
+    )?;
+    let res = try_from_xir_prog(&prog, None)?.to_string();
+    assert_eq!(res.matches(".AREG(1)").count(), 1);
+    assert_eq!(res.matches(".AREG(2)").count(), 1);
+    assert_eq!(res.matches(".BCASCREG(2)").count(), 1);
+    assert_eq!(res.matches(".MREG(0)").count(), 1);
+    assert_eq!(res.matches(".PREG(1)").count(), 2);
+    Ok(())
+}